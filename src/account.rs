@@ -0,0 +1,315 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::OpAccount;
+#[cfg(feature = "process")]
+use super::{ErrorKind, OpSession, Result};
+
+/// Identifies a 1Password account.
+///
+/// Anything that takes an account accepts `Into<AccountSelector>`, so a plain
+/// `&str` can be used and is interpreted by `AccountSelector::parse`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AccountSelector {
+    /// Account UUID, as shown by `op account list` in `op` 2.x.
+    Uuid(String),
+    /// Shorthand of the account, which defaults to its subdomain.
+    Shorthand(String),
+    /// Sign-in address, e.g. `https://my.1password.com` or `my.1password.com`.
+    Url(String),
+}
+
+impl AccountSelector {
+    /// Interpret `s` as a URL if it contains a `.` or `://`, as a UUID if it is
+    /// 26 upper case letters and digits, and otherwise as a shorthand.
+    pub fn parse(s: &str) -> AccountSelector {
+        if s.contains("://") || s.contains('.') {
+            AccountSelector::Url(s.to_owned())
+        } else if s.len() == 26 && s.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) {
+            AccountSelector::Uuid(s.to_owned())
+        } else {
+            AccountSelector::Shorthand(s.to_owned())
+        }
+    }
+
+    /// Value to pass to `op --account` or as the sign-in address to `op signin`.
+    pub fn account_arg(&self) -> String {
+        match *self {
+            AccountSelector::Uuid(ref s) | AccountSelector::Shorthand(ref s) => s.clone(),
+            AccountSelector::Url(ref url) => host(url).to_owned(),
+        }
+    }
+
+    /// Whether this selects `account`.
+    pub fn matches(&self, account: &OpAccount) -> bool {
+        match *self {
+            AccountSelector::Uuid(ref uuid) => account.account_uuid.as_ref() == Some(uuid),
+            AccountSelector::Shorthand(ref s) => account.shorthand == *s,
+            AccountSelector::Url(ref url) => host(&account.url).eq_ignore_ascii_case(host(url)),
+        }
+    }
+
+    /// Names of the environment variables a session token for this account may be
+    /// stored in, most likely first.
+    ///
+    /// `op` 1.x names the variable after the shorthand, while `op` 2.x uses the
+    /// account UUID, so `accounts` from `Op::configured_accounts` are used to map
+    /// between the two.
+    pub fn session_var_names(&self, accounts: &[OpAccount]) -> Vec<String> {
+        let mut ids = Vec::new();
+        match *self {
+            AccountSelector::Uuid(ref uuid) => ids.push(uuid.clone()),
+            AccountSelector::Shorthand(ref s) => ids.push(s.clone()),
+            AccountSelector::Url(ref url) => {
+                if let Some(subdomain) = host(url).split('.').next() {
+                    ids.push(subdomain.to_owned());
+                }
+            },
+        }
+        for account in accounts.iter().filter(|a| self.matches(a)) {
+            if let Some(ref uuid) = account.account_uuid {
+                ids.push(uuid.clone());
+            }
+            ids.push(account.shorthand.clone());
+        }
+        let mut names : Vec<String> = Vec::new();
+        for id in ids {
+            let name = format!("OP_SESSION_{}", id);
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+}
+
+fn host(url: &str) -> &str {
+    let url = match url.find("://") {
+        Some(index) => &url[index + 3..],
+        None => url,
+    };
+    url.trim_end_matches('/')
+}
+
+impl fmt::Display for AccountSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.account_arg())
+    }
+}
+
+impl<'a> From<&'a str> for AccountSelector {
+    fn from(s: &'a str) -> AccountSelector {
+        AccountSelector::parse(s)
+    }
+}
+
+impl From<String> for AccountSelector {
+    fn from(s: String) -> AccountSelector {
+        AccountSelector::parse(&s)
+    }
+}
+
+impl<'a> From<&'a OpAccount> for AccountSelector {
+    fn from(account: &'a OpAccount) -> AccountSelector {
+        match account.account_uuid {
+            Some(ref uuid) => AccountSelector::Uuid(uuid.clone()),
+            None => AccountSelector::Shorthand(account.shorthand.clone()),
+        }
+    }
+}
+
+/// The plan of an account.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AccountType {
+    Individual,
+    Family,
+    Team,
+    Business,
+    /// A type this crate doesn't know, as reported by `op`.
+    Other(String),
+}
+
+impl AccountType {
+    fn parse(s: &str) -> AccountType {
+        match s {
+            "INDIVIDUAL" | "I" => AccountType::Individual,
+            "FAMILY" | "F" => AccountType::Family,
+            "TEAM" | "T" => AccountType::Team,
+            "BUSINESS" | "B" => AccountType::Business,
+            other => AccountType::Other(other.to_owned()),
+        }
+    }
+
+    /// The type in `op` 2.x spelling, e.g. `BUSINESS`.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            AccountType::Individual => "INDIVIDUAL",
+            AccountType::Family => "FAMILY",
+            AccountType::Team => "TEAM",
+            AccountType::Business => "BUSINESS",
+            AccountType::Other(ref other) => other,
+        }
+    }
+
+    /// Whether the account has teams features such as groups and custom vault
+    /// permissions.
+    pub fn is_team(&self) -> bool {
+        *self == AccountType::Team || *self == AccountType::Business
+    }
+}
+
+impl fmt::Display for AccountType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for AccountType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for AccountType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<AccountType, D::Error> {
+        Ok(AccountType::parse(&String::deserialize(deserializer)?))
+    }
+}
+
+/// The account a session is signed in to, as returned by `OpSession::get_account`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AccountDetails {
+    pub id: String,
+    pub name: String,
+    /// Sign-in address, e.g. `acme.1password.com`.
+    pub domain: String,
+    pub account_type: AccountType,
+    /// `ACTIVE` for accounts in good standing. `op` 1.x abbreviates it, e.g. `A`.
+    pub state: String,
+    pub created_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct V2Account {
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    domain: String,
+    #[serde(rename = "type")]
+    account_type: AccountType,
+    #[serde(default)]
+    state: String,
+    created_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct V1Account {
+    uuid: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    domain: String,
+    #[serde(rename = "type")]
+    account_type: AccountType,
+    #[serde(default)]
+    state: String,
+    created_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawAccount {
+    V2(V2Account),
+    V1(V1Account),
+}
+
+impl From<RawAccount> for AccountDetails {
+    fn from(raw: RawAccount) -> AccountDetails {
+        match raw {
+            RawAccount::V2(a) => AccountDetails {
+                id: a.id, name: a.name, domain: a.domain, account_type: a.account_type,
+                state: a.state, created_at: a.created_at,
+            },
+            RawAccount::V1(a) => AccountDetails {
+                id: a.uuid, name: a.name, domain: a.domain, account_type: a.account_type,
+                state: a.state, created_at: a.created_at,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+impl OpSession {
+    /// Get the details of the account this session is signed in to.
+    ///
+    /// This calls `op account get --format json`, or `op get account` with `op` 1.x.
+    pub fn get_account(&self) -> Result<AccountDetails> {
+        let subcommand : &[&str] = if self.config.cli_version()?.is_v2() {
+            &["account", "get"]
+        } else {
+            &["get", "account"]
+        };
+        let raw : RawAccount = self.command(subcommand)
+            .json()?
+            .run_json(ErrorKind::CommandFailed)?;
+        Ok(raw.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    fn account() -> OpAccount {
+        OpAccount {
+            shorthand: "acme".to_owned(),
+            url: "https://acme.1password.com".to_owned(),
+            email: "user@acme.com".to_owned(),
+            account_uuid: Some("ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_owned()),
+            user_uuid: None,
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(AccountSelector::from("my"), AccountSelector::Shorthand("my".to_owned()));
+        assert_eq!(AccountSelector::from("https://my.1password.com/"),
+            AccountSelector::Url("https://my.1password.com/".to_owned()));
+        assert_eq!(AccountSelector::from("ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+            AccountSelector::Uuid("ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_owned()));
+        assert_eq!(AccountSelector::from("https://my.1password.com/").account_arg(), "my.1password.com");
+    }
+
+    #[test]
+    fn session_var_names() {
+        let accounts = vec![account()];
+        assert_eq!(AccountSelector::from("acme").session_var_names(&accounts), vec![
+            "OP_SESSION_acme", "OP_SESSION_ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+        ]);
+        assert_eq!(AccountSelector::from("acme.1password.com").session_var_names(&accounts), vec![
+            "OP_SESSION_acme", "OP_SESSION_ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+        ]);
+        assert_eq!(AccountSelector::from("other").session_var_names(&accounts), vec!["OP_SESSION_other"]);
+    }
+
+    #[test]
+    fn parses_account_details() {
+        let v2 : RawAccount = serde_json::from_str(r#"{"id": "A1", "name": "Acme", "domain": "acme.1password.com",
+            "type": "BUSINESS", "state": "ACTIVE", "created_at": "2021-02-26T00:22:36Z"}"#).unwrap();
+        let v2 = AccountDetails::from(v2);
+        assert!(v2.account_type.is_team());
+        assert_eq!(v2.created_at, Some("2021-02-26T00:22:36Z".to_owned()));
+
+        let v1 : RawAccount = serde_json::from_str(r#"{"uuid": "A1", "name": "Jane", "avatar": "",
+            "domain": "my.1password.com", "type": "I", "state": "A", "createdAt": "2018-09-24T10:00:00Z"}"#).unwrap();
+        let v1 = AccountDetails::from(v1);
+        assert_eq!(v1.account_type, AccountType::Individual);
+        assert!(!v1.account_type.is_team());
+        assert_eq!(v1.id, "A1");
+    }
+}
@@ -0,0 +1,335 @@
+use std::fmt;
+
+use serde_json::Value as Json;
+
+use command::OpCommand;
+use recipe::PasswordRecipe;
+use super::{ErrorKind, FieldPath, Result, Secret};
+
+/// Type of a field, as written in brackets in an assignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Text,
+    /// A concealed field, such as a password or PIN.
+    Password,
+    Email,
+    Url,
+    /// A date as `YYYY-MM-DD`.
+    Date,
+    /// A month as `YYYYMM` or `YYYY/MM`.
+    MonthYear,
+    Phone,
+    /// A one-time password, given as an `otpauth://` URI.
+    Otp,
+}
+
+impl FieldType {
+    /// The name `op` uses for the type.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FieldType::Text => "text",
+            FieldType::Password => "password",
+            FieldType::Email => "email",
+            FieldType::Url => "url",
+            FieldType::Date => "date",
+            FieldType::MonthYear => "monthYear",
+            FieldType::Phone => "phone",
+            FieldType::Otp => "otp",
+        }
+    }
+
+    /// The name of the type in `op` 2.x item JSON.
+    fn json_type(self) -> &'static str {
+        match self {
+            FieldType::Text => "STRING",
+            FieldType::Password => "CONCEALED",
+            FieldType::Email => "EMAIL",
+            FieldType::Url => "URL",
+            FieldType::Date => "DATE",
+            FieldType::MonthYear => "MONTH_YEAR",
+            FieldType::Phone => "PHONE",
+            FieldType::Otp => "OTP",
+        }
+    }
+}
+
+/// Escape `.`, `=` and `\` in a section or field name, which `op` would
+/// otherwise read as separators.
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c == '.' || c == '=' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Value {
+    Missing,
+    Set(Secret<String>),
+    Generated(PasswordRecipe),
+    Delete,
+}
+
+/// Start assigning to the field `field`, keeping its type or, for new
+/// fields, letting `op` pick one.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use one_password::{assign, conceal, ItemEdit, Op, PasswordRecipe};
+///
+/// let session = Op::which().unwrap().env_session().unwrap();
+/// let edit = ItemEdit::new()
+///     .set(assign("username").to("admin"))
+///     .set(conceal("password").generated(PasswordRecipe::new(40).symbols(false)))
+///     .set(assign("host").in_section("db.primary").to("10.0.0.1"));
+/// session.edit_item("database", &edit).unwrap();
+/// ```
+pub fn assign(field: &str) -> FieldAssignment {
+    FieldAssignment {
+        section: None,
+        field: field.to_owned(),
+        field_type: None,
+        value: Value::Missing,
+    }
+}
+
+/// Start assigning to the concealed field `field`.
+pub fn conceal(field: &str) -> FieldAssignment {
+    assign(field).of_type(FieldType::Password)
+}
+
+impl FieldPath {
+    /// Start assigning to the field at this path, like `assign`, e.g.
+    /// `"db/host".parse::<FieldPath>()?.assign().to("10.0.0.1")`.
+    pub fn assign(&self) -> FieldAssignment {
+        FieldAssignment { section: self.section.clone(), ..assign(&self.field) }
+    }
+
+    /// Start assigning to the concealed field at this path.
+    pub fn conceal(&self) -> FieldAssignment {
+        self.assign().of_type(FieldType::Password)
+    }
+}
+
+/// An assignment of a value to a field, for `ItemBuilder` and `ItemEdit`.
+///
+/// Values are written into the item JSON piped into `op item create` and
+/// `op item edit`, and are never logged. Deletions and generated passwords
+/// are compiled to the `[section.]field[type]` syntax of their arguments,
+/// with section and field names escaped, so they can contain any character.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct FieldAssignment {
+    section: Option<String>,
+    field: String,
+    field_type: Option<FieldType>,
+    value: Value,
+}
+
+impl FieldAssignment {
+    /// Put the field in `section`.
+    pub fn in_section(mut self, section: &str) -> FieldAssignment {
+        self.section = Some(section.to_owned());
+        self
+    }
+
+    pub fn of_type(mut self, field_type: FieldType) -> FieldAssignment {
+        self.field_type = Some(field_type);
+        self
+    }
+
+    /// Assign `value`.
+    pub fn to(mut self, value: &str) -> FieldAssignment {
+        self.value = Value::Set(Secret::new(value.to_owned()));
+        self
+    }
+
+    /// Let 1Password generate the value following `recipe`.
+    ///
+    /// `op` can only generate the built-in password field, so this fails when
+    /// applied to any other field.
+    pub fn generated(mut self, recipe: PasswordRecipe) -> FieldAssignment {
+        self.value = Value::Generated(recipe);
+        self
+    }
+
+    /// Remove the field instead of assigning to it.
+    pub fn delete(mut self) -> FieldAssignment {
+        self.value = Value::Delete;
+        self
+    }
+
+    /// The left-hand side of the assignment, e.g. `db\.primary.host[text]`.
+    pub fn name(&self) -> String {
+        let mut name = match self.section {
+            Some(ref section) => format!("{}.{}", escape(section), escape(&self.field)),
+            None => escape(&self.field),
+        };
+        match (&self.value, self.field_type) {
+            (&Value::Delete, _) => name.push_str("[delete]"),
+            (_, Some(field_type)) => name.push_str(&format!("[{}]", field_type.as_str())),
+            (_, None) => (),
+        }
+        name
+    }
+
+    /// Whether the assignment sets a value, which goes into the item JSON.
+    pub(crate) fn sets_value(&self) -> bool {
+        matches!(self.value, Value::Set(_))
+    }
+
+    /// Add the assignment to `command`, or for a value to `item`, the `op`
+    /// 2.x item JSON piped into it.
+    pub(crate) fn apply(&self, command: &mut OpCommand, item: &mut Json) -> Result<()> {
+        match self.value {
+            Value::Missing => bail!(ErrorKind::InvalidAssignment(self.name(), "no value was given")),
+            Value::Set(ref value) => self.set_in(item, value.expose()),
+            Value::Generated(recipe) if self.section.is_none() && self.field == "password" => {
+                command.arg(format!("--generate-password={}", recipe));
+            },
+            Value::Generated(_) => {
+                bail!(ErrorKind::InvalidAssignment(self.name(), "only the password field can be generated"))
+            },
+            Value::Delete => {
+                command.arg(self.name());
+            },
+        };
+        Ok(())
+    }
+
+    /// Set the field in `item` to `value`, adding the field and its section
+    /// if they don't exist.
+    fn set_in(&self, item: &mut Json, value: &str) {
+        let named = |json: &Json, name: &str| {
+            json.get("label").and_then(Json::as_str) == Some(name) || json.get("id").and_then(Json::as_str) == Some(name)
+        };
+        let section = self.section.as_ref().map(|section| {
+            let sections = array(item, "sections");
+            match sections.iter().find(|s| named(s, section)) {
+                Some(existing) => existing.get("id").cloned().unwrap_or_else(|| json!(section)),
+                None => {
+                    sections.push(json!({"id": section, "label": section}));
+                    json!(section)
+                },
+            }
+        });
+        let fields = array(item, "fields");
+        let existing = fields.iter().position(|f| {
+            named(f, &self.field) && f.get("section").and_then(|s| s.get("id")) == section.as_ref()
+        });
+        let field = match existing {
+            Some(index) => &mut fields[index],
+            None => {
+                let mut field = json!({"label": self.field, "type": "STRING"});
+                if let Some(ref id) = section {
+                    field["section"] = json!({"id": id});
+                } else if let Some(purpose) = purpose(&self.field) {
+                    field["id"] = json!(self.field);
+                    field["purpose"] = json!(purpose);
+                    if purpose == "PASSWORD" {
+                        field["type"] = json!("CONCEALED");
+                    }
+                }
+                fields.push(field);
+                fields.last_mut().expect("just pushed")
+            },
+        };
+        if let Some(field_type) = self.field_type {
+            field["type"] = json!(field_type.json_type());
+        }
+        field["value"] = json!(value);
+    }
+}
+
+/// The array `key` of `item`, added if missing.
+fn array<'a>(item: &'a mut Json, key: &str) -> &'a mut Vec<Json> {
+    if !item[key].is_array() {
+        item[key] = json!([]);
+    }
+    item[key].as_array_mut().expect("just made an array")
+}
+
+/// Purpose of a built-in field outside any section.
+fn purpose(field: &str) -> Option<&'static str> {
+    match field {
+        "username" => Some("USERNAME"),
+        "password" => Some("PASSWORD"),
+        "notesPlain" => Some("NOTES"),
+        _ => None,
+    }
+}
+
+impl fmt::Debug for FieldAssignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            Value::Set(ref value) => write!(f, "{}={:?}", self.name(), value),
+            Value::Generated(recipe) => write!(f, "{} generated with {}", self.name(), recipe),
+            Value::Delete | Value::Missing => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Op;
+
+    #[test]
+    fn compiles_assignments() {
+        assert_eq!(assign("username").to("admin").name(), "username");
+        assert_eq!(assign("host").in_section("db.primary").of_type(FieldType::Url).to("x").name(),
+            r"db\.primary.host[url]");
+        assert_eq!(conceal(r"a=b\c").to("x").name(), r"a\=b\\c[password]");
+        assert_eq!(assign("old").delete().name(), "old[delete]");
+        let path : FieldPath = "db.primary/password".parse().unwrap();
+        assert_eq!(path.conceal().to("x").name(), r"db\.primary.password[password]");
+        assert!(!format!("{:?}", conceal("pin").to("1234")).contains("1234"));
+    }
+
+    #[test]
+    fn only_generates_password() {
+        let op = Op::new("op");
+        let mut command = OpCommand::new(&op, &["item", "edit"]);
+        let mut item = json!({});
+        let recipe = PasswordRecipe::new(20).symbols(false);
+        assert_eq!(recipe.to_string(), "letters,digits,20");
+        conceal("password").generated(recipe).apply(&mut command, &mut item).unwrap();
+        assert_eq!(command.redacted_args(), vec!["--generate-password=letters,digits,20"]);
+        assert!(conceal("pin").generated(recipe).apply(&mut command, &mut item).is_err());
+        assert!(assign("username").apply(&mut command, &mut item).is_err());
+    }
+
+    #[test]
+    fn sets_values_in_json() {
+        let op = Op::new("op");
+        let mut command = OpCommand::new(&op, &["item", "edit"]);
+        let mut item = json!({
+            "sections": [{"id": "s1", "label": "db"}],
+            "fields": [
+                {"id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password", "value": "old"},
+                {"id": "h", "type": "STRING", "label": "host", "value": "old", "section": {"id": "s1"}},
+            ],
+        });
+        assign("password").to("new").apply(&mut command, &mut item).unwrap();
+        assign("host").in_section("db").of_type(FieldType::Url).to("10.0.0.1").apply(&mut command, &mut item).unwrap();
+        assign("host").to("top").apply(&mut command, &mut item).unwrap();
+        conceal("pin").in_section("extra").to("1234").apply(&mut command, &mut item).unwrap();
+        assign("username").to("admin").apply(&mut command, &mut item).unwrap();
+        assert!(command.redacted_args().is_empty());
+        assert_eq!(item, json!({
+            "sections": [{"id": "s1", "label": "db"}, {"id": "extra", "label": "extra"}],
+            "fields": [
+                {"id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password", "value": "new"},
+                {"id": "h", "type": "URL", "label": "host", "value": "10.0.0.1", "section": {"id": "s1"}},
+                {"label": "host", "type": "STRING", "value": "top"},
+                {"label": "pin", "type": "CONCEALED", "value": "1234", "section": {"id": "extra"}},
+                {"id": "username", "label": "username", "type": "STRING", "purpose": "USERNAME", "value": "admin"},
+            ],
+        }));
+    }
+}
@@ -0,0 +1,131 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json;
+
+use super::{CommandInfo, CommandOutcome, Observer};
+
+/// A single line of the audit log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// Seconds since the Unix epoch when the command finished.
+    pub timestamp: u64,
+    pub subcommand: String,
+    /// UUIDs or names of the items, vaults, etc. the command was run against.
+    pub targets: Vec<String>,
+    /// Reason given with `OpSession::with_reason`.
+    pub reason: Option<String>,
+    pub outcome: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+}
+
+/// Observer that appends an `AuditRecord` as a line of JSON for every `op` invocation.
+///
+/// Only the subcommand and its targets are recorded, never session tokens or
+/// other argument values. Because observers can't fail a command, errors writing
+/// the log are ignored.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use one_password::{AuditLog, Op};
+///
+/// let op = Op::which().unwrap()
+///     .with_observer(AuditLog::open("/var/log/op-audit.jsonl").unwrap());
+/// let session = op.env_session().unwrap().with_reason("nightly backup");
+/// ```
+#[derive(Debug)]
+pub struct AuditLog<W> {
+    writer: Mutex<W>,
+}
+
+impl AuditLog<File> {
+    /// Append audit records to the file at `path`, creating it if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<AuditLog<File>> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog::new(file))
+    }
+}
+
+impl<W: Write> AuditLog<W> {
+    pub fn new(writer: W) -> AuditLog<W> {
+        AuditLog {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Return the underlying writer.
+    pub fn into_inner(self) -> W {
+        match self.writer.into_inner() {
+            Ok(w) => w,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn write(&self, record: &AuditRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut writer = match self.writer.lock() {
+            Ok(w) => w,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        writer.write_all(&line)?;
+        writer.flush()
+    }
+}
+
+impl<W: Write + Send> Observer for AuditLog<W> {
+    fn on_command_finish(&self, command: &CommandInfo, duration: Duration, outcome: CommandOutcome) {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let exit_code = match outcome {
+            CommandOutcome::Success => Some(0),
+            CommandOutcome::Failed(_, code) => code,
+            CommandOutcome::TimedOut | CommandOutcome::SpawnFailed => None,
+        };
+        let _ = self.write(&AuditRecord {
+            timestamp,
+            subcommand: command.subcommand.clone(),
+            targets: command.targets.clone(),
+            reason: command.reason.clone(),
+            outcome: outcome.as_str().to_owned(),
+            exit_code,
+            duration_ms: duration.as_millis() as u64,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ErrorCategory;
+
+    #[test]
+    fn writes_json_lines() {
+        let log = AuditLog::new(Vec::new());
+        let info = CommandInfo {
+            subcommand: "get item".to_owned(),
+            args: vec!["--session=⟨redacted⟩".to_owned(), "abc".to_owned()],
+            targets: vec!["abc".to_owned()],
+            reason: Some("deploy".to_owned()),
+        };
+        log.on_command_finish(&info, Duration::from_millis(5), CommandOutcome::Success);
+        log.on_command_finish(&info, Duration::from_millis(7), CommandOutcome::Failed(ErrorCategory::Unknown, Some(1)));
+        let out = String::from_utf8(log.into_inner()).unwrap();
+        let records : Vec<AuditRecord> = out.lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].targets, vec!["abc"]);
+        assert_eq!(records[0].reason, Some("deploy".to_owned()));
+        assert_eq!(records[0].outcome, "success");
+        assert_eq!(records[1].exit_code, Some(1));
+        assert!(!out.contains("session"));
+    }
+}
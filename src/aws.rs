@@ -0,0 +1,151 @@
+//! AWS SDK credentials read from an item.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use one_password::Op;
+//! use one_password::aws::CredentialsProvider;
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let provider = CredentialsProvider::new(session, "AWS Access Key").vault("Infrastructure");
+//! // aws_config::from_env().credentials_provider(provider).load().await
+//! ```
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use aws_credential_types::Credentials;
+use aws_credential_types::provider::{self, future, ProvideCredentials};
+use aws_credential_types::provider::error::CredentialsError;
+
+use diagnostics::not_found;
+use super::{OpSession, Result};
+
+/// Name reported to the AWS SDK as the source of the credentials.
+pub const PROVIDER_NAME: &str = "1Password";
+
+/// How long credentials are used before they are read again, unless changed
+/// with `CredentialsProvider::refresh_after`.
+pub const DEFAULT_REFRESH: Duration = Duration::from_secs(15 * 60);
+
+/// Implements the AWS SDK `ProvideCredentials` trait by reading an access key
+/// ID, secret access key and optional session token from fields of an item.
+///
+/// Credentials are cached and read again after `refresh_after`. Reading runs
+/// `op` and blocks the calling thread, which only happens on a cache miss.
+pub struct CredentialsProvider {
+    session: OpSession,
+    item: String,
+    vault: Option<String>,
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+    refresh: Duration,
+    cached: Mutex<Option<(Credentials, Instant)>>,
+}
+
+impl fmt::Debug for CredentialsProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CredentialsProvider")
+            .field("item", &self.item)
+            .field("vault", &self.vault)
+            .field("refresh", &self.refresh)
+            .finish()
+    }
+}
+
+impl CredentialsProvider {
+    /// Read credentials from the fields `access key id`, `secret access key`
+    /// and, if present, `session token` of `item`.
+    pub fn new(session: OpSession, item: &str) -> CredentialsProvider {
+        CredentialsProvider {
+            session,
+            item: item.to_owned(),
+            vault: None,
+            access_key_id: "access key id".to_owned(),
+            secret_access_key: "secret access key".to_owned(),
+            session_token: "session token".to_owned(),
+            refresh: DEFAULT_REFRESH,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Look up the item in `vault` instead of the default vault of the session.
+    pub fn vault(mut self, vault: &str) -> CredentialsProvider {
+        self.vault = Some(vault.to_owned());
+        self
+    }
+
+    /// Use other field labels than the defaults.
+    pub fn fields(mut self, access_key_id: &str, secret_access_key: &str, session_token: &str) -> CredentialsProvider {
+        self.access_key_id = access_key_id.to_owned();
+        self.secret_access_key = secret_access_key.to_owned();
+        self.session_token = session_token.to_owned();
+        self
+    }
+
+    /// Read the item again when cached credentials are older than `refresh`.
+    pub fn refresh_after(mut self, refresh: Duration) -> CredentialsProvider {
+        self.refresh = refresh;
+        self
+    }
+
+    fn read(&self) -> Result<Credentials> {
+        let vault = self.vault.as_ref().map(|v| &v[..]);
+        let access_key_id = self.session.get_field_in(&self.item, &self.access_key_id, vault)?;
+        let secret_access_key = self.session.get_field_revealed_in(&self.item, &self.secret_access_key, vault)?;
+        let session_token = not_found(self.session.get_field_revealed_in(&self.item, &self.session_token, vault))?
+            .map(|t| t.into_inner())
+            .filter(|t| !t.is_empty());
+        Ok(Credentials::new(
+            access_key_id.into_inner(),
+            secret_access_key.into_inner(),
+            session_token,
+            Some(SystemTime::now() + self.refresh),
+            PROVIDER_NAME))
+    }
+
+    /// Cached credentials, or freshly read ones if they are too old.
+    pub fn credentials(&self) -> provider::Result {
+        let mut cached = self.cached.lock().unwrap_or_else(|p| p.into_inner());
+        if let Some((ref credentials, read_at)) = *cached {
+            if read_at.elapsed() < self.refresh {
+                return Ok(credentials.clone());
+            }
+        }
+        let credentials = self.read().map_err(|err| CredentialsError::provider_error(err.to_string()))?;
+        *cached = Some((credentials.clone(), Instant::now()));
+        Ok(credentials)
+    }
+}
+
+impl ProvideCredentials for CredentialsProvider {
+    fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a> where Self: 'a {
+        future::ProvideCredentials::ready(self.credentials())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{interaction, replay_op};
+
+    #[test]
+    fn caches_credentials() {
+        let op = replay_op(vec![
+            interaction("item get", &["--session=⟨redacted⟩", "AWS", "--fields", "label=access key id"], "AKIA\n"),
+            interaction("item get", &["--session=⟨redacted⟩", "AWS", "--fields", "label=secret access key", "--reveal"],
+                "secret\n"),
+            interaction("item get", &["--session=⟨redacted⟩", "AWS", "--fields", "label=session token", "--reveal"], "")
+                .failing("[ERROR] field not found", 1),
+        ]);
+        let provider = CredentialsProvider::new(op.session("token"), "AWS");
+        let credentials = provider.credentials().unwrap();
+        assert_eq!(credentials.access_key_id(), "AKIA");
+        assert_eq!(credentials.secret_access_key(), "secret");
+        assert_eq!(credentials.session_token(), None);
+        // Served from the cache, the cassette has nothing left to replay.
+        assert_eq!(provider.credentials().unwrap().access_key_id(), "AKIA");
+    }
+}
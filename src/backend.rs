@@ -0,0 +1,457 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use super::{CommandInfo, Op, Secret};
+
+/// Customizes the local `op` process before and after it is spawned, e.g. to
+/// sandbox it.
+///
+/// Register with `Op::with_spawn_hook`. Hooks are called by backends that run
+/// `op` locally through `Invocation::spawn`, such as `ProcessBackend`, and
+/// ignored by others such as `Replay`. Closures taking a `&mut Command` are
+/// hooks too.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// # #[cfg(unix)]
+/// # fn main() {
+/// use std::io;
+/// use std::os::unix::process::CommandExt;
+/// use std::process::Command;
+/// use one_password::Op;
+///
+/// let op = Op::which().unwrap().with_spawn_hook(|command: &mut Command| -> io::Result<()> {
+///     // Run op as an unprivileged user.
+///     command.uid(65534).gid(65534);
+///     Ok(())
+/// });
+/// # }
+/// # #[cfg(not(unix))]
+/// # fn main() {}
+/// ```
+pub trait SpawnHook: Send + Sync {
+    /// Called right before `command` is spawned, e.g. to drop privileges or
+    /// install a seccomp filter with `CommandExt::pre_exec` on Unix. An error
+    /// aborts the invocation.
+    fn before_spawn(&self, command: &mut Command) -> io::Result<()>;
+
+    /// Called right after `child` was spawned, e.g. to assign it to a job
+    /// object on Windows. An error kills the child and aborts the invocation.
+    fn after_spawn(&self, _child: &Child) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<F> SpawnHook for F where F: Fn(&mut Command) -> io::Result<()> + Send + Sync {
+    fn before_spawn(&self, command: &mut Command) -> io::Result<()> {
+        self(command)
+    }
+}
+
+/// Which environment variables of this process `op` is spawned with, set
+/// with `OpBuilder::env_policy`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum EnvPolicy {
+    /// All of them.
+    #[default]
+    Inherit,
+    /// Only `OP_*` variables, those in `ESSENTIAL_VARS` that `op` needs to
+    /// find its configuration and talk to the desktop app, and the named ones.
+    Minimal(Vec<String>),
+}
+
+/// Variables kept by `EnvPolicy::Minimal`, besides `OP_*`.
+pub const ESSENTIAL_VARS: &[&str] = &[
+    "PATH", "HOME", "USER", "LOGNAME", "TMPDIR", "XDG_CONFIG_HOME", "XDG_RUNTIME_DIR",
+    "SystemRoot", "USERPROFILE", "APPDATA", "LOCALAPPDATA", "TEMP", "TMP",
+];
+
+impl EnvPolicy {
+    /// Whether `op` gets the variable `name`.
+    pub fn keeps(&self, name: &str) -> bool {
+        match *self {
+            EnvPolicy::Inherit => true,
+            EnvPolicy::Minimal(ref names) => {
+                name.starts_with("OP_") || ESSENTIAL_VARS.contains(&name) || names.iter().any(|n| n == name)
+            },
+        }
+    }
+}
+
+/// A fully prepared `op` invocation handed to a `Backend`.
+pub struct Invocation<'a> {
+    op: &'a Op,
+    args: Vec<OsString>,
+    stdin: Option<&'a [u8]>,
+    info: &'a CommandInfo,
+    session: Option<(String, Secret<String>)>,
+}
+
+impl<'a> Invocation<'a> {
+    pub(crate) fn new(op: &'a Op, args: Vec<OsString>, stdin: Option<&'a [u8]>, info: &'a CommandInfo) -> Invocation<'a> {
+        Invocation { op, args, stdin, info, session: None }
+    }
+
+    /// Pass `token` to `op` in the environment variable `name`.
+    pub(crate) fn with_session(mut self, name: &str, token: Secret<String>) -> Invocation<'a> {
+        self.session = Some((name.to_owned(), token));
+        self
+    }
+
+    /// Path to the `op` binary.
+    pub fn program(&self) -> &Path {
+        &self.op.command
+    }
+
+    /// All arguments, including the subcommand and any secrets passed as
+    /// arguments, such as the session token of a session that doesn't know
+    /// its `OP_SESSION_*` variable.
+    pub fn args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    /// Data to write to the stdin of `op`, such as a master password.
+    pub fn stdin(&self) -> Option<&[u8]> {
+        self.stdin
+    }
+
+    /// Redacted description of the invocation that is safe to log or store.
+    pub fn info(&self) -> &CommandInfo {
+        self.info
+    }
+
+    /// `OP_SESSION_*` variable `op` should read the session token from, and
+    /// the token.
+    ///
+    /// Backends running `op` elsewhere should pass it on in the environment
+    /// of `op`, never as an argument.
+    pub fn session_var(&self) -> Option<(&str, &Secret<String>)> {
+        self.session.as_ref().map(|(name, token)| (&name[..], token))
+    }
+
+    /// Locale `op` should run with, set with `Op::with_locale`.
+    ///
+    /// Backends running `op` elsewhere should set `LC_ALL` and `LANG` to it,
+    /// so its messages are in English.
+    pub fn locale(&self) -> Option<&OsStr> {
+        self.op.locale.as_deref()
+    }
+
+    /// Account `op` should use when none is given, set with `OpBuilder::account`.
+    ///
+    /// Backends running `op` elsewhere should pass it on in `OP_ACCOUNT`.
+    pub fn account(&self) -> Option<&str> {
+        self.op.account.as_deref()
+    }
+
+    /// Which environment variables of this process `op` gets.
+    pub fn env_policy(&self) -> &EnvPolicy {
+        &self.op.env_policy
+    }
+
+    /// How long `op` may run before it is killed, set with `OpBuilder::timeout`.
+    ///
+    /// Backends should fail with `io::ErrorKind::TimedOut` when it runs longer.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.op.timeout
+    }
+
+    /// Build a `Command` that runs this invocation locally.
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(self.program());
+        command.args(&self.args);
+        if *self.env_policy() != EnvPolicy::Inherit {
+            command.env_clear();
+            for (name, value) in env::vars_os() {
+                if name.to_str().is_some_and(|n| self.env_policy().keeps(n)) {
+                    command.env(name, value);
+                }
+            }
+        }
+        if let Some(locale) = self.locale() {
+            command.env("LC_ALL", locale).env("LANG", locale);
+        }
+        if let Some(account) = self.account() {
+            command.env("OP_ACCOUNT", account);
+        }
+        if let Some((name, token)) = self.session_var() {
+            // Another account's token could make `op` pick the wrong account.
+            for (other, _) in env::vars_os() {
+                if other.to_str().is_some_and(|o| o.starts_with("OP_SESSION_") && o != name) {
+                    command.env_remove(other);
+                }
+            }
+            command.env(name, token.expose());
+        }
+        command
+    }
+
+    /// Spawn `command`, usually built with `command`, calling the spawn hooks
+    /// registered on the `Op` around it.
+    pub fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+        for hook in &self.op.spawn_hooks {
+            hook.before_spawn(command)?;
+        }
+        let mut child = command.spawn()?;
+        for hook in &self.op.spawn_hooks {
+            if let Err(err) = hook.after_spawn(&child) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err);
+            }
+        }
+        Ok(child)
+    }
+}
+
+impl<'a> fmt::Debug for Invocation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Invocation")
+            .field("program", &self.op.command)
+            .field("stdin", &self.stdin.map(|s| s.len()))
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+/// Executes `op` invocations.
+///
+/// The default, `ProcessBackend`, runs the `op` binary as a child process.
+/// Use `Op::with_backend` to replace it, e.g. with a `Replay` in tests.
+pub trait Backend: Send + Sync {
+    /// Run the invocation to completion, capturing stdout and stderr.
+    fn run(&self, invocation: &Invocation) -> io::Result<Output>;
+
+    /// Run the invocation with stdin and stderr connected to the terminal so
+    /// `op` can prompt the user, capturing only stdout.
+    ///
+    /// Defaults to running `op` as a local child process.
+    fn run_interactive(&self, invocation: &Invocation) -> io::Result<Output> {
+        let mut command = invocation.command();
+        command.stdin(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+        invocation.spawn(&mut command)?.wait_with_output()
+    }
+
+    /// Run the invocation, copying stdout into `stdout` as it is produced
+    /// instead of buffering it. The returned `Output` has an empty stdout.
+    ///
+    /// Defaults to buffering with `run` and copying stdout afterwards if `op`
+    /// succeeded, so backends that can't stream still work.
+    fn run_to(&self, invocation: &Invocation, stdout: &mut dyn Write) -> io::Result<Output> {
+        let output = self.run(invocation)?;
+        if output.status.success() {
+            stdout.write_all(&output.stdout)?;
+        }
+        Ok(Output { stdout: Vec::new(), ..output })
+    }
+
+    /// Run the invocation, copying `stdin` into the stdin of `op` as it reads
+    /// it instead of the data of the invocation, and capturing stdout and stderr.
+    ///
+    /// Defaults to reading all of `stdin` into memory and running with `run`.
+    fn run_from(&self, invocation: &Invocation, stdin: &mut (dyn Read + Send)) -> io::Result<Output> {
+        let mut data = Vec::new();
+        stdin.read_to_end(&mut data)?;
+        self.run(&Invocation { stdin: Some(&data), args: invocation.args.clone(), session: invocation.session.clone(), ..*invocation })
+    }
+}
+
+/// Backend that runs `op` as a local child process.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessBackend;
+
+impl Backend for ProcessBackend {
+    fn run(&self, invocation: &Invocation) -> io::Result<Output> {
+        let mut stdout = Vec::new();
+        let output = self.run_to(invocation, &mut stdout)?;
+        Ok(Output { stdout, ..output })
+    }
+
+    fn run_to(&self, invocation: &Invocation, stdout: &mut dyn Write) -> io::Result<Output> {
+        let mut data = invocation.stdin();
+        let input = data.as_mut().map(|d| d as &mut (dyn Read + Send));
+        let child = invocation.spawn(piped(&mut invocation.command(), input.is_some()))?;
+        stream(child, input, stdout, invocation.timeout())
+    }
+
+    fn run_from(&self, invocation: &Invocation, stdin: &mut (dyn Read + Send)) -> io::Result<Output> {
+        let mut stdout = Vec::new();
+        let child = invocation.spawn(piped(&mut invocation.command(), true))?;
+        let output = stream(child, Some(stdin), &mut stdout, invocation.timeout())?;
+        Ok(Output { stdout, ..output })
+    }
+}
+
+/// Pipe stdout and stderr of `command`, and stdin if `stdin` is true.
+pub(crate) fn piped(command: &mut Command, stdin: bool) -> &mut Command {
+    command
+        .stdin(if stdin { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+}
+
+/// How often to check whether `op` exited while a timeout is running.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Copy `input` if any to the stdin of `child` and its stdout into `out`
+/// while it runs. Only stderr is collected in the returned `Output`.
+///
+/// If writing to `out` fails the child is killed and the error returned. If
+/// it runs longer than `timeout` it is killed and `io::ErrorKind::TimedOut`
+/// returned.
+pub(crate) fn stream(mut child: Child, input: Option<&mut (dyn Read + Send)>, out: &mut dyn Write,
+    timeout: Option<Duration>) -> io::Result<Output>
+{
+    let stdin = child.stdin.take();
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let child = Mutex::new(child);
+    let lock = || child.lock().unwrap_or_else(|p| p.into_inner());
+    thread::scope(|scope| {
+        let writer = scope.spawn(move || match (stdin, input) {
+            // op may exit without reading all of its input.
+            (Some(mut stdin), Some(input)) => match io::copy(input, &mut stdin) {
+                Err(ref err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                other => other.map(|_| ()),
+            },
+            _ => Ok(()),
+        });
+        let reader = scope.spawn(move || {
+            let mut buf = Vec::new();
+            stderr.read_to_end(&mut buf).map(|_| buf)
+        });
+        let (finished, done) = mpsc::channel::<()>();
+        let watchdog = timeout.map(|timeout| scope.spawn(move || {
+            if done.recv_timeout(timeout) != Err(RecvTimeoutError::Timeout) {
+                return false;
+            }
+            let mut child = lock();
+            match child.try_wait() {
+                Ok(None) => child.kill().is_ok(),
+                _ => false,
+            }
+        }));
+        let copied = io::copy(&mut stdout, out);
+        if copied.is_err() {
+            let _ = lock().kill();
+        }
+        drop(stdout);
+        // Waiting would hold the lock the watchdog needs to kill the child.
+        let status = match watchdog {
+            None => lock().wait()?,
+            Some(_) => loop {
+                if let Some(status) = lock().try_wait()? {
+                    break status;
+                }
+                thread::sleep(POLL_INTERVAL);
+            },
+        };
+        drop(finished);
+        let timed_out = watchdog.is_some_and(|w| w.join().expect("watchdog panicked"));
+        let stderr = reader.join().expect("stderr reader panicked")?;
+        writer.join().expect("stdin writer panicked")?;
+        if timed_out {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "op timed out"));
+        }
+        copied?;
+        Ok(Output { status, stdout: Vec::new(), stderr })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_stdin() {
+        let mut out = Vec::new();
+        let child = piped(&mut Command::new("cat"), true).spawn().unwrap();
+        let output = stream(child, Some(&mut &b"hunter2\n"[..]), &mut out, Some(Duration::from_secs(10))).unwrap();
+        assert!(output.status.success());
+        assert_eq!(out, b"hunter2\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn streams_stdout() {
+        let mut out = Vec::new();
+        let child = piped(&mut Command::new("cat"), true).spawn().unwrap();
+        let output = stream(child, Some(&mut &b"hunter2\n"[..]), &mut out, None).unwrap();
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+        assert_eq!(out, b"hunter2\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kills_after_timeout() {
+        let child = piped(&mut Command::new("sleep"), false).arg("5").spawn().unwrap();
+        let err = stream(child, None, &mut Vec::new(), Some(Duration::from_millis(100))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn calls_spawn_hooks() {
+        use std::sync::Arc;
+
+        struct Deny;
+        impl SpawnHook for Deny {
+            fn before_spawn(&self, _: &mut Command) -> io::Result<()> {
+                Ok(())
+            }
+            fn after_spawn(&self, _: &Child) -> io::Result<()> {
+                Err(io::Error::new(io::ErrorKind::PermissionDenied, "not in a job object"))
+            }
+        }
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = seen.clone();
+        let op = Op::new("echo").with_spawn_hook(move |command: &mut Command| -> io::Result<()> {
+            recorded.lock().unwrap().push(format!("{:?}", command.get_args().collect::<Vec<_>>()));
+            command.arg("hooked");
+            Ok(())
+        });
+        assert_eq!(op.version().unwrap(), "--version hooked");
+        assert_eq!(*seen.lock().unwrap(), vec![r#"["--version"]"#]);
+
+        let err = Op::new("echo").with_spawn_hook(Deny).version().unwrap_err();
+        assert!(err.to_string().contains("not in a job object"));
+    }
+
+    #[test]
+    fn sets_locale() {
+        let info = CommandInfo { subcommand: "--version".to_owned(), args: Vec::new(), targets: Vec::new(), reason: None };
+        let op = Op::new("op");
+        let command = Invocation::new(&op, Vec::new(), None, &info).command();
+        let envs : Vec<_> = command.get_envs().collect();
+        assert!(envs.contains(&(OsStr::new("LC_ALL"), Some(OsStr::new("C")))));
+        assert!(envs.contains(&(OsStr::new("LANG"), Some(OsStr::new("C")))));
+        let op = Op::new("op").with_locale(None);
+        let command = Invocation::new(&op, Vec::new(), None, &info).command();
+        assert_eq!(command.get_envs().count(), 0);
+    }
+
+    #[test]
+    fn minimal_env() {
+        let policy = EnvPolicy::Minimal(vec!["SSH_AUTH_SOCK".to_owned()]);
+        assert!(policy.keeps("HOME"));
+        assert!(policy.keeps("OP_SERVICE_ACCOUNT_TOKEN"));
+        assert!(policy.keeps("SSH_AUTH_SOCK"));
+        assert!(!policy.keeps("AWS_SECRET_ACCESS_KEY"));
+        assert!(EnvPolicy::Inherit.keeps("AWS_SECRET_ACCESS_KEY"));
+    }
+}
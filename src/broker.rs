@@ -0,0 +1,449 @@
+//! A local secrets broker: one long-running process holds a session and
+//! serves secret references to other processes of the same user, so many
+//! short-lived tools share one sign in instead of each prompting for it.
+//!
+//! The broker listens on a Unix domain socket that only its owner can open,
+//! and additionally checks the user id of every connecting process against
+//! the allowed ones. Windows named pipes are not supported yet.
+//!
+//! Requests and responses are single lines of JSON, e.g.
+//! `{"type":"resolve","reference":"op://Production/Database/password"}`
+//! answered by `{"type":"value","value":"…"}` or `{"type":"error","message":"…"}`.
+//! Clients start with `{"type":"hello","version":1}`, which the broker answers
+//! with the protocol version both sides speak, so either can be upgraded first.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use one_password::Op;
+//! use one_password::broker::Broker;
+//!
+//! let session = Op::which().unwrap().signin_subdomain("my", "password").unwrap();
+//! let broker = Broker::bind(session, Broker::default_path()).unwrap();
+//! broker.serve().unwrap();
+//! ```
+//!
+//! and in the tools using it
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use one_password::broker::BrokerClient;
+//!
+//! let client = BrokerClient::from_env().unwrap();
+//! let password = client.resolve("op://Production/Database/password").unwrap();
+//! ```
+use std::env;
+use std::fs::{self, DirBuilder};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+
+use serde_json;
+
+use super::{ErrorKind, Op, OpSession, Result, Secret};
+
+/// Newest version of the protocol spoken by this crate.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest version of the protocol this crate still speaks.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Longest request or response line accepted, far more than any secret needs.
+const MAX_LINE: u64 = 64 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    Hello { version: u32 },
+    Resolve { reference: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Response {
+    Hello { version: u32 },
+    Value { value: String },
+    Error { message: String },
+}
+
+/// Read one line of JSON, `None` at the end of the stream.
+fn read_message<R: BufRead, T: ::serde::de::DeserializeOwned>(reader: &mut R) -> io::Result<Option<serde_json::Result<T>>> {
+    let mut line = String::new();
+    if reader.take(MAX_LINE).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && line.len() as u64 >= MAX_LINE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"));
+    }
+    Ok(Some(serde_json::from_str(&line)))
+}
+
+fn write_message<W: Write, T: ::serde::Serialize>(mut writer: W, message: &T) -> io::Result<()> {
+    let mut json = serde_json::to_vec(message)?;
+    json.push(b'\n');
+    writer.write_all(&json)
+}
+
+/// User id of the process on the other end of `stream`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+
+    let mut cred : ::libc::ucred = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<::libc::ucred>() as ::libc::socklen_t;
+    let ret = unsafe {
+        ::libc::getsockopt(stream.as_raw_fd(), ::libc::SOL_SOCKET, ::libc::SO_PEERCRED,
+            &mut cred as *mut ::libc::ucred as *mut ::libc::c_void, &mut len)
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+/// User id of the process on the other end of `stream`.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut uid = 0;
+    let mut gid = 0;
+    if unsafe { ::libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// Fail unless `dir` belongs to the current user and only they can write to it.
+fn check_private(dir: &Path) -> io::Result<()> {
+    let metadata = fs::metadata(dir)?;
+    let uid = unsafe { ::libc::geteuid() };
+    if metadata.uid() != uid {
+        let message = format!("{} belongs to user {}, not {}", dir.display(), metadata.uid(), uid);
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, message));
+    }
+    if metadata.mode() & 0o022 != 0 {
+        let message = format!("{} is writable by other users", dir.display());
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, message));
+    }
+    Ok(())
+}
+
+/// Serves secret references to local processes, see the module documentation.
+///
+/// The socket is removed when the broker is dropped.
+#[derive(Debug)]
+pub struct Broker {
+    session: OpSession,
+    listener: UnixListener,
+    path: PathBuf,
+    allowed_uids: Vec<u32>,
+}
+
+impl Broker {
+    /// Socket path used unless another is given, in `XDG_RUNTIME_DIR` or else
+    /// a directory of the user in the temporary directory.
+    pub fn default_path() -> PathBuf {
+        let dir = match env::var_os("XDG_RUNTIME_DIR") {
+            Some(ref dir) if !dir.is_empty() => PathBuf::from(dir).join("one_password"),
+            _ => env::temp_dir().join(format!("one_password-{}", unsafe { ::libc::geteuid() })),
+        };
+        dir.join("broker.sock")
+    }
+
+    /// Listen on `path` for requests to be resolved with `session`.
+    ///
+    /// Missing parent directories are created, readable only by the owner.
+    /// The directory of the socket has to belong to the user running the
+    /// broker and must not be writable by anyone else, or this fails with
+    /// `PermissionDenied`, since others could replace the socket. A socket
+    /// left behind by a broker that is no longer running is replaced, while
+    /// one that is still in use makes this fail with `AddrInUse`.
+    pub fn bind<P: AsRef<Path>>(session: OpSession, path: P) -> Result<Broker> {
+        let path = path.as_ref();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        check_private(dir)?;
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                let err = io::Error::new(io::ErrorKind::AddrInUse, "a broker is already running");
+                return Err(err.into());
+            }
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        Ok(Broker {
+            session,
+            listener,
+            path: path.to_owned(),
+            allowed_uids: vec![unsafe { ::libc::geteuid() }],
+        })
+    }
+
+    /// Also serve processes running as the user `uid`. By default only the
+    /// user running the broker is served. The socket itself is only
+    /// accessible to its owner, so its permissions have to be relaxed as well.
+    pub fn allow_uid(mut self, uid: u32) -> Broker {
+        self.allowed_uids.push(uid);
+        self
+    }
+
+    /// Path of the socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Accept and serve connections until accepting fails, one thread per connection.
+    pub fn serve(&self) -> Result<()> {
+        thread::scope(|scope| {
+            loop {
+                let (stream, _) = self.listener.accept()?;
+                scope.spawn(move || {
+                    let _ = self.handle(stream);
+                });
+            }
+        })
+    }
+
+    /// Answer the requests of one client until it disconnects.
+    fn handle(&self, stream: UnixStream) -> io::Result<()> {
+        let uid = peer_uid(&stream)?;
+        if !self.allowed_uids.contains(&uid) {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("user {} may not use the broker", uid)));
+        }
+        let mut reader = BufReader::new(&stream);
+        while let Some(request) = read_message(&mut reader)? {
+            let response = match request {
+                Ok(request) => self.respond(request),
+                Err(err) => Response::Error { message: format!("invalid request: {}", err) },
+            };
+            write_message(&stream, &response)?;
+        }
+        Ok(())
+    }
+
+    fn respond(&self, request: Request) -> Response {
+        match request {
+            Request::Hello { version } if version < MIN_PROTOCOL_VERSION => Response::Error {
+                message: format!("protocol version {} is no longer supported, {} is the oldest", version, MIN_PROTOCOL_VERSION),
+            },
+            Request::Hello { version } => Response::Hello { version: version.min(PROTOCOL_VERSION) },
+            Request::Resolve { reference } => match self.session.read(&reference) {
+                Ok(value) => Response::Value { value: value.into_inner() },
+                Err(err) => Response::Error { message: err.to_string() },
+            },
+        }
+    }
+}
+
+impl Drop for Broker {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// A connection to a broker.
+#[derive(Debug)]
+struct Connection {
+    stream: UnixStream,
+    reader: BufReader<UnixStream>,
+    version: u32,
+}
+
+impl Connection {
+    fn open(path: &Path) -> Result<Connection> {
+        Connection::start(UnixStream::connect(path)?, unsafe { ::libc::geteuid() })
+    }
+
+    /// Shake hands over `stream`, after making sure the broker on the other
+    /// end runs as `uid`, so secrets aren't requested from a socket another
+    /// user put in place.
+    fn start(stream: UnixStream, uid: u32) -> Result<Connection> {
+        let peer = peer_uid(&stream)?;
+        if peer != uid {
+            bail!(ErrorKind::Broker(format!("broker runs as user {}, not {}", peer, uid)));
+        }
+        let reader = BufReader::new(stream.try_clone()?);
+        let mut connection = Connection { stream, reader, version: 0 };
+        match connection.request(&Request::Hello { version: PROTOCOL_VERSION })? {
+            Response::Hello { version } if (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version) => {
+                connection.version = version;
+                Ok(connection)
+            },
+            Response::Hello { version } => bail!(ErrorKind::Broker(format!("unsupported protocol version {}", version))),
+            Response::Error { message } => bail!(ErrorKind::Broker(message)),
+            Response::Value { .. } => bail!(ErrorKind::Broker("unexpected response to hello".to_owned())),
+        }
+    }
+
+    fn request(&mut self, request: &Request) -> Result<Response> {
+        write_message(&self.stream, request)?;
+        match read_message(&mut self.reader)? {
+            Some(response) => Ok(response?),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "broker closed the connection").into()),
+        }
+    }
+}
+
+/// Resolves secret references through a `Broker`, or with `op` directly
+/// when no broker is running.
+#[derive(Debug)]
+pub struct BrokerClient {
+    connection: Option<Mutex<Connection>>,
+    fallback: Option<OpSession>,
+}
+
+impl BrokerClient {
+    /// Connect to the broker listening on `path`.
+    ///
+    /// Fails if no broker is running there, it runs as another user or it
+    /// speaks no protocol version this crate does.
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<BrokerClient> {
+        Ok(BrokerClient { connection: Some(Mutex::new(Connection::open(path.as_ref())?)), fallback: None })
+    }
+
+    /// Connect to the broker listening on `path`, or use `fallback` if that fails.
+    ///
+    /// `fallback` is also used if the connection to the broker breaks later on.
+    pub fn connect_or<P: AsRef<Path>>(path: P, fallback: OpSession) -> BrokerClient {
+        let connection = Connection::open(path.as_ref()).ok().map(Mutex::new);
+        BrokerClient { connection, fallback: Some(fallback) }
+    }
+
+    /// Connect to the broker at `Broker::default_path`, or else use `op` with
+    /// the session from `Op::session_from_env`.
+    pub fn from_env() -> Result<BrokerClient> {
+        match Connection::open(&Broker::default_path()) {
+            Ok(connection) => Ok(BrokerClient { connection: Some(Mutex::new(connection)), fallback: None }),
+            Err(_) => Ok(BrokerClient { connection: None, fallback: Some(Op::which()?.session_from_env()?) }),
+        }
+    }
+
+    /// Whether requests go to a broker, rather than to `op` directly.
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    /// Protocol version agreed on with the broker, if connected.
+    pub fn protocol_version(&self) -> Option<u32> {
+        self.connection.as_ref().map(|c| c.lock().unwrap_or_else(|p| p.into_inner()).version)
+    }
+
+    /// Read the value a secret reference such as `op://vault/item/field` points to.
+    pub fn resolve(&self, reference: &str) -> Result<Secret<String>> {
+        if let Some(ref connection) = self.connection {
+            let mut connection = connection.lock().unwrap_or_else(|p| p.into_inner());
+            match connection.request(&Request::Resolve { reference: reference.to_owned() }) {
+                Ok(Response::Value { value }) => return Ok(Secret::locked(value)),
+                Ok(Response::Error { message }) => bail!(ErrorKind::Broker(message)),
+                Ok(Response::Hello { .. }) => bail!(ErrorKind::Broker("unexpected response to resolve".to_owned())),
+                Err(err) => if self.fallback.is_none() {
+                    return Err(err);
+                },
+            }
+        }
+        match self.fallback {
+            Some(ref session) => session.read(reference),
+            None => bail!(ErrorKind::Broker("not connected".to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{read_interaction, replay_op};
+
+    #[test]
+    fn resolves_references() {
+        let op = replay_op(vec![read_interaction("op://Ops/DB/password", "hunter2")]);
+        let path = env::temp_dir().join(format!("op-broker-{}", ::std::process::id())).join("broker.sock");
+        let broker = Broker::bind(op.session("token"), &path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(Broker::bind(op.session("token"), &path).is_err());
+
+        let (client, server) = UnixStream::pair().unwrap();
+        thread::scope(|scope| {
+            scope.spawn(|| broker.handle(server).unwrap());
+            let mut reader = BufReader::new(client.try_clone().unwrap());
+            let mut ask = |request: &str| {
+                (&client).write_all(request.as_bytes()).unwrap();
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                serde_json::from_str::<Response>(&line).unwrap()
+            };
+            assert_eq!(ask("{\"type\":\"resolve\",\"reference\":\"op://Ops/DB/password\"}\n"),
+                Response::Value { value: "hunter2".to_owned() });
+            match ask("{\"type\":\"resolve\",\"reference\":\"not a reference\"}\n") {
+                Response::Error { message } => assert!(message.contains("not a reference")),
+                other => panic!("unexpected response {:?}", other),
+            }
+            match ask("hello\n") {
+                Response::Error { message } => assert!(message.starts_with("invalid request")),
+                other => panic!("unexpected response {:?}", other),
+            }
+            client.shutdown(::std::net::Shutdown::Both).unwrap();
+        });
+        drop(broker);
+        assert!(!path.exists());
+
+        // Others could swap the socket in a directory they can write to.
+        fs::set_permissions(path.parent().unwrap(), fs::Permissions::from_mode(0o770)).unwrap();
+        let err = Broker::bind(op.session("token"), &path).unwrap_err();
+        assert!(err.to_string().ends_with("is writable by other users"), "{}", err);
+        fs::remove_dir(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn clients_handshake_and_fall_back() {
+        let broker_op = replay_op(vec![read_interaction("op://Ops/DB/password", "hunter2")]);
+        let direct_op = replay_op(vec![read_interaction("op://Ops/DB/user", "admin")]);
+        let path = env::temp_dir().join(format!("op-broker-client-{}", ::std::process::id())).join("broker.sock");
+        let broker = Broker::bind(broker_op.session("token"), &path).unwrap();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let (stream, _) = broker.listener.accept().unwrap();
+                broker.handle(stream).unwrap();
+            });
+            let client = BrokerClient::connect(&path).unwrap();
+            assert_eq!(client.protocol_version(), Some(PROTOCOL_VERSION));
+            assert_eq!(client.resolve("op://Ops/DB/password").unwrap().expose(), "hunter2");
+            match *client.resolve("op://Ops/DB/user").unwrap_err().kind() {
+                ErrorKind::Broker(ref message) => assert!(message.contains("op://Ops/DB/user")),
+                ref other => panic!("unexpected error {:?}", other),
+            }
+        });
+        assert_eq!(broker.respond(Request::Hello { version: 7 }), Response::Hello { version: PROTOCOL_VERSION });
+        assert!(matches!(broker.respond(Request::Hello { version: 0 }), Response::Error { .. }));
+        drop(broker);
+
+        assert!(BrokerClient::connect(&path).is_err());
+        let client = BrokerClient::connect_or(&path, direct_op.session("token"));
+        assert!(!client.is_connected());
+        assert_eq!(client.resolve("op://Ops/DB/user").unwrap().expose(), "admin");
+        fs::remove_dir(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn clients_refuse_brokers_of_other_users() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let uid = unsafe { ::libc::geteuid() };
+        match *Connection::start(client, uid.wrapping_add(1)).unwrap_err().kind() {
+            ErrorKind::Broker(ref message) => assert!(message.starts_with(&format!("broker runs as user {}", uid))),
+            ref other => panic!("unexpected error {:?}", other),
+        }
+        // Nothing was sent, not even the hello.
+        let mut sent = Vec::new();
+        (&server).read_to_end(&mut sent).unwrap();
+        assert!(sent.is_empty());
+    }
+}
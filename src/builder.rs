@@ -0,0 +1,248 @@
+use serde_json;
+
+use assignment::{assign, conceal, FieldAssignment, FieldType};
+use command::OpCommand;
+use secret::SecretBytes;
+use summary;
+use super::{ErrorKind, ItemSummary, OpSession, Result};
+
+/// Description of a new item, created with `OpSession::create_item`.
+///
+/// Fields are piped into `op item create` as an item template in JSON, so
+/// their values never show up in its arguments.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use one_password::{ItemBuilder, Op};
+///
+/// let session = Op::which().unwrap().env_session().unwrap();
+/// let login = ItemBuilder::login("Example")
+///     .vault("Private")
+///     .url("https://example.com")
+///     .username("user")
+///     .password("hunter2")
+///     .tag("imported");
+/// let item = session.create_item(&login).unwrap();
+/// println!("created {}", item.id);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ItemBuilder {
+    category: String,
+    title: String,
+    vault: Option<String>,
+    url: Option<String>,
+    tags: Vec<String>,
+    favorite: bool,
+    fields: Vec<FieldAssignment>,
+}
+
+impl ItemBuilder {
+    /// Start an item of `category` in `op` 2.x spelling, e.g. `LOGIN` or `SECURE_NOTE`.
+    pub fn new(category: &str, title: &str) -> ItemBuilder {
+        ItemBuilder {
+            category: category.to_owned(),
+            title: title.to_owned(),
+            vault: None,
+            url: None,
+            tags: Vec::new(),
+            favorite: false,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Start a login item.
+    pub fn login(title: &str) -> ItemBuilder {
+        ItemBuilder::new("LOGIN", title)
+    }
+
+    /// Create the item in `vault` instead of the default vault of the session.
+    pub fn vault(mut self, vault: &str) -> ItemBuilder {
+        self.vault = Some(vault.to_owned());
+        self
+    }
+
+    pub fn url(mut self, url: &str) -> ItemBuilder {
+        self.url = Some(url.to_owned());
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> ItemBuilder {
+        self.tags.push(tag.to_owned());
+        self
+    }
+
+    /// Mark the item as a favorite.
+    pub fn favorite(mut self, favorite: bool) -> ItemBuilder {
+        self.favorite = favorite;
+        self
+    }
+
+    /// Add a plain text field.
+    pub fn field(self, label: &str, value: &str) -> ItemBuilder {
+        self.set(assign(label).of_type(FieldType::Text).to(value))
+    }
+
+    /// Add a concealed field, such as a PIN or API key.
+    pub fn concealed(self, label: &str, value: &str) -> ItemBuilder {
+        self.set(conceal(label).to(value))
+    }
+
+    /// Set the built-in username field.
+    pub fn username(self, username: &str) -> ItemBuilder {
+        self.set(assign("username").to(username))
+    }
+
+    /// Set the built-in password field.
+    pub fn password(self, password: &str) -> ItemBuilder {
+        self.set(assign("password").to(password))
+    }
+
+    /// Set the notes of the item.
+    pub fn notes(self, notes: &str) -> ItemBuilder {
+        self.set(assign("notesPlain").to(notes))
+    }
+
+    /// Add a field with `assignment`, e.g. `assign("host").in_section("db").to("10.0.0.1")`.
+    pub fn set(mut self, assignment: FieldAssignment) -> ItemBuilder {
+        self.fields.push(assignment);
+        self
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Summary of the item before it is created, with an empty id.
+    fn summary(&self, vault: Option<&str>) -> ItemSummary {
+        ItemSummary {
+            id: String::new(),
+            title: self.title.clone(),
+            vault_id: vault.unwrap_or("").to_owned(),
+            vault_name: None,
+            category: self.category.clone(),
+            tags: self.tags.clone(),
+            favorite: self.favorite,
+            created_at: None,
+            updated_at: None,
+            version: None,
+            last_edited_by: None,
+            urls: Vec::new(),
+        }
+    }
+}
+
+impl OpSession {
+    /// Create an item with `op item create`.
+    ///
+    /// In dry-run mode nothing is created and the returned summary has an empty id.
+    pub fn create_item(&self, item: &ItemBuilder) -> Result<ItemSummary> {
+        let vault = item.vault.as_ref().map(|v| &v[..]);
+        let mut command : OpCommand = self.item_command(&["item", "create"], vault);
+        command.arg("--category").arg(&item.category)
+            .arg("--title").arg(&item.title)
+            .json()?;
+        if let Some(ref url) = item.url {
+            command.arg("--url").arg(url);
+        }
+        if !item.tags.is_empty() {
+            command.arg("--tags").arg(item.tags.join(","));
+        }
+        if item.favorite {
+            command.arg("--favorite");
+        }
+        let mut template = json!({});
+        for assignment in &item.fields {
+            assignment.apply(&mut command, &mut template)?;
+        }
+        if item.fields.iter().any(FieldAssignment::sets_value) {
+            command.stdin(SecretBytes::from(serde_json::to_vec(&template)?));
+        }
+        let stdout = command.run_or(ErrorKind::CommandFailed)?;
+        if stdout.is_empty() {
+            return Ok(item.summary(vault.or(self.vault())));
+        }
+        summary::parse(&command, &stdout)
+    }
+
+    /// Create a login with a username, password and optionally a website, in
+    /// `vault` or the default vault of the session.
+    ///
+    /// A shortcut for `create_item` with `ItemBuilder::login`.
+    pub fn create_login(&self, title: &str, username: &str, password: &str, url: Option<&str>, vault: Option<&str>) -> Result<ItemSummary> {
+        let mut login = ItemBuilder::login(title).username(username).password(password);
+        if let Some(url) = url {
+            login = login.url(url);
+        }
+        if let Some(vault) = vault {
+            login = login.vault(vault);
+        }
+        self.create_item(&login)
+    }
+
+    /// Create a secure note holding `body`, which may span several lines, in
+    /// `vault` or the default vault of the session.
+    ///
+    /// Useful for archiving generated configuration or recovery codes.
+    pub fn create_secure_note(&self, title: &str, body: &str, vault: Option<&str>, tags: &[&str]) -> Result<ItemSummary> {
+        let mut note = ItemBuilder::new("SECURE_NOTE", title).notes(body);
+        if let Some(vault) = vault {
+            note = note.vault(vault);
+        }
+        for tag in tags {
+            note = note.tag(tag);
+        }
+        self.create_item(&note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{self, Value};
+
+    use cassette::{interaction, Cassette, Replay, StdinLog};
+    use {CliVersion, Op};
+
+    /// `Op` replaying `op item create` with `args`, and what it got on stdin.
+    fn create(args: &[&str], category: &str) -> (Op, Arc<Mutex<Vec<Vec<u8>>>>) {
+        let cassette = Cassette {
+            interactions: vec![interaction("item create", args,
+                &format!(r#"{{"id": "a", "title": "Example", "vault": {{"id": "v"}}, "category": "{}"}}"#, category))],
+        };
+        let stdin = Arc::new(Mutex::new(Vec::new()));
+        let op = Op::new("op").with_backend(StdinLog(Replay::new(cassette), stdin.clone()))
+            .with_cli_version(CliVersion::new(2, 24, 0));
+        (op, stdin)
+    }
+
+    #[test]
+    fn creates_login() {
+        let (op, stdin) = create(&["--session=⟨redacted⟩", "--vault", "Private", "--category", "LOGIN", "--title", "Example",
+            "--format", "json", "--no-color", "--iso-timestamps", "--url", "https://example.com"], "LOGIN");
+        let item = op.session("token")
+            .create_login("Example", "user", "hunter2", Some("https://example.com"), Some("Private"))
+            .unwrap();
+        assert_eq!(item.id, "a");
+        assert_eq!(item.category, "LOGIN");
+        let sent : Value = serde_json::from_slice(&stdin.lock().unwrap()[0]).unwrap();
+        assert_eq!(sent, json!({"fields": [
+            {"id": "username", "label": "username", "type": "STRING", "purpose": "USERNAME", "value": "user"},
+            {"id": "password", "label": "password", "type": "CONCEALED", "purpose": "PASSWORD", "value": "hunter2"},
+        ]}));
+    }
+
+    #[test]
+    fn creates_secure_note() {
+        let (op, stdin) = create(&["--session=⟨redacted⟩", "--category", "SECURE_NOTE", "--title", "Recovery codes",
+            "--format", "json", "--no-color", "--iso-timestamps", "--tags", "recovery,github"], "SECURE_NOTE");
+        let item = op.session("token")
+            .create_secure_note("Recovery codes", "abcd-efgh\nijkl-mnop\n", None, &["recovery", "github"])
+            .unwrap();
+        assert_eq!(item.category, "SECURE_NOTE");
+        let sent : Value = serde_json::from_slice(&stdin.lock().unwrap()[0]).unwrap();
+        assert_eq!(sent["fields"][0]["value"], "abcd-efgh\nijkl-mnop\n");
+    }
+}
@@ -0,0 +1,312 @@
+//! Record real `op` invocations and replay them later.
+//!
+//! A `Recorder` wraps another backend and captures every invocation together
+//! with its output. The resulting `Cassette` can be saved as a JSON fixture and
+//! served back by a `Replay` backend, which makes tests of code built on this
+//! crate deterministic and independent of a real 1Password account.
+//!
+//! Arguments are stored redacted, so session tokens never end up in fixtures.
+//! Output is stored as is: review recorded fixtures for secret values before
+//! committing them.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use one_password::Op;
+//! use one_password::cassette::{Cassette, Recorder, Replay};
+//!
+//! // Record once against a real account...
+//! let recorder = Recorder::new(one_password::ProcessBackend);
+//! let op = Op::which().unwrap().with_backend(recorder.clone());
+//! op.env_session().unwrap().get_item("abc").unwrap();
+//! recorder.cassette().save("tests/fixtures/get_item.json").unwrap();
+//!
+//! // ...then replay in CI.
+//! let cassette = Cassette::load("tests/fixtures/get_item.json").unwrap();
+//! let op = Op::new("op").with_backend(Replay::new(cassette));
+//! op.session("token").get_item("abc").unwrap();
+//! ```
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::process::{ExitStatus, Output};
+use std::sync::{Arc, Mutex};
+
+use serde_json;
+
+use super::{Backend, Invocation, Result};
+#[cfg(test)]
+use {CliVersion, Op};
+
+/// Captured stdout or stderr: text when valid UTF-8, raw bytes otherwise.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Data {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Data {
+    fn new(bytes: Vec<u8>) -> Data {
+        match String::from_utf8(bytes) {
+            Ok(text) => Data::Text(text),
+            Err(err) => Data::Binary(err.into_bytes()),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Data::Text(text) => text.into_bytes(),
+            Data::Binary(bytes) => bytes,
+        }
+    }
+}
+
+/// A single recorded invocation and its output.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Interaction {
+    pub subcommand: String,
+    /// Redacted arguments after the subcommand.
+    pub args: Vec<String>,
+    pub stdout: Data,
+    pub stderr: Data,
+    /// Exit code, or `None` if `op` was killed by a signal.
+    pub exit_code: Option<i32>,
+}
+
+impl Interaction {
+    fn matches(&self, invocation: &Invocation) -> bool {
+        self.subcommand == invocation.info().subcommand && self.args == invocation.info().args
+    }
+
+    fn into_output(self) -> Output {
+        Output {
+            status: exit_status(self.exit_code.unwrap_or(1)),
+            stdout: self.stdout.into_bytes(),
+            stderr: self.stderr.into_bytes(),
+        }
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+pub(crate) fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+/// A successful interaction that printed `stdout`, for tests.
+#[cfg(test)]
+pub(crate) fn interaction(subcommand: &str, args: &[&str], stdout: &str) -> Interaction {
+    Interaction {
+        subcommand: subcommand.to_owned(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        stdout: Data::Text(stdout.to_owned()),
+        stderr: Data::Text(String::new()),
+        exit_code: Some(0),
+    }
+}
+
+/// An `Op` for `op` 2.24.0 replaying `interactions`, for tests.
+#[cfg(test)]
+pub(crate) fn replay_op(interactions: Vec<Interaction>) -> Op {
+    Op::new("op").with_backend(Replay::new(Cassette { interactions })).with_cli_version(CliVersion::new(2, 24, 0))
+}
+
+/// A successful `op read` of `reference` that printed `value`, for tests.
+#[cfg(test)]
+pub(crate) fn read_interaction(reference: &str, value: &str) -> Interaction {
+    interaction("read", &["--session=⟨redacted⟩", reference, "--no-newline"], value)
+}
+
+#[cfg(test)]
+impl Interaction {
+    /// This interaction, but exiting with `exit_code` after printing `stderr`.
+    pub(crate) fn failing(self, stderr: &str, exit_code: i32) -> Interaction {
+        Interaction { stderr: Data::Text(stderr.to_owned()), exit_code: Some(exit_code), ..self }
+    }
+}
+
+/// Replays a cassette, keeping what each invocation got on stdin, for tests.
+#[cfg(test)]
+pub(crate) struct StdinLog(pub Replay, pub Arc<Mutex<Vec<Vec<u8>>>>);
+
+#[cfg(test)]
+impl Backend for StdinLog {
+    fn run(&self, invocation: &Invocation) -> io::Result<Output> {
+        self.1.lock().unwrap().extend(invocation.stdin().map(<[u8]>::to_vec));
+        self.0.run(invocation)
+    }
+}
+
+/// A list of recorded interactions.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Load a cassette previously written with `save`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Cassette> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// Write this cassette as pretty printed JSON.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+}
+
+/// Backend that passes invocations on to another backend and records them.
+#[derive(Clone)]
+pub struct Recorder<B> {
+    inner: Arc<B>,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl<B: Backend> Recorder<B> {
+    pub fn new(inner: B) -> Recorder<B> {
+        Recorder {
+            inner: Arc::new(inner),
+            cassette: Arc::new(Mutex::new(Cassette::default())),
+        }
+    }
+
+    fn record(&self, invocation: &Invocation, output: &Output) {
+        let interaction = Interaction {
+            subcommand: invocation.info().subcommand.clone(),
+            args: invocation.info().args.clone(),
+            stdout: Data::new(output.stdout.clone()),
+            stderr: Data::new(output.stderr.clone()),
+            exit_code: output.status.code(),
+        };
+        match self.cassette.lock() {
+            Ok(mut c) => c.interactions.push(interaction),
+            Err(poisoned) => poisoned.into_inner().interactions.push(interaction),
+        }
+    }
+
+    /// Everything recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        match self.cassette.lock() {
+            Ok(c) => c.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+impl<B: Backend> Backend for Recorder<B> {
+    fn run(&self, invocation: &Invocation) -> io::Result<Output> {
+        let output = self.inner.run(invocation)?;
+        self.record(invocation, &output);
+        Ok(output)
+    }
+
+    fn run_interactive(&self, invocation: &Invocation) -> io::Result<Output> {
+        let output = self.inner.run_interactive(invocation)?;
+        self.record(invocation, &output);
+        Ok(output)
+    }
+}
+
+/// Backend that serves recorded interactions instead of running `op`.
+///
+/// Each invocation is answered by the first unused interaction with the same
+/// subcommand and redacted arguments. An invocation with no matching
+/// interaction fails with an `io::ErrorKind::NotFound` error.
+#[derive(Debug)]
+pub struct Replay {
+    remaining: Mutex<Vec<Interaction>>,
+}
+
+impl Replay {
+    pub fn new(cassette: Cassette) -> Replay {
+        Replay {
+            remaining: Mutex::new(cassette.interactions),
+        }
+    }
+
+    /// Interactions that have not been replayed yet.
+    pub fn remaining(&self) -> Vec<Interaction> {
+        match self.remaining.lock() {
+            Ok(r) => r.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+impl Backend for Replay {
+    fn run(&self, invocation: &Invocation) -> io::Result<Output> {
+        let mut remaining = match self.remaining.lock() {
+            Ok(r) => r,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match remaining.iter().position(|i| i.matches(invocation)) {
+            Some(index) => Ok(remaining.remove(index).into_output()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound,
+                format!("no recorded interaction for {}", invocation.info()))),
+        }
+    }
+
+    fn run_interactive(&self, invocation: &Invocation) -> io::Result<Output> {
+        self.run(invocation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Op;
+
+    fn get_item(uuid: &str, stdout: &str, exit_code: i32) -> Interaction {
+        Interaction { exit_code: Some(exit_code), ..interaction("get item", &["--session=⟨redacted⟩", uuid], stdout) }
+    }
+
+    #[test]
+    fn replays_recorded_item() {
+        let json = r#"{
+            "uuid": "abc", "vaultUuid": "v", "changerUuid": "c",
+            "overview": {"ainfo": "", "title": "Example"},
+            "details": {"password": "hunter2"}
+        }"#;
+        let cassette = Cassette {
+            interactions: vec![get_item("abc", json, 0), get_item("def", "", 1)],
+        };
+        let replay = Replay::new(cassette);
+        let op = Op::new("op").with_backend(replay);
+        let session = op.session("token");
+        let err = session.get_item("def").unwrap_err();
+        let diagnostics = err.diagnostics().unwrap();
+        assert_eq!(diagnostics.subcommand, "get item");
+        assert_eq!(diagnostics.status.code(), Some(1));
+        assert_eq!(err.category(), Some(::ErrorCategory::Unknown));
+        assert_eq!(diagnostics.to_string(),
+            "`op get item --session=⟨redacted⟩ def` failed with exit status: 1 after 0ms: ");
+        let item = session.get_item("abc").unwrap();
+        assert_eq!(item.password(), Some("hunter2".to_owned()));
+        assert!(session.get_item("abc").is_err());
+
+        let mut scoped = get_item("abc", json, 0);
+        scoped.args = vec!["--session=⟨redacted⟩".to_owned(), "--vault".to_owned(), "Private".to_owned(), "abc".to_owned()];
+        let op = Op::new("op").with_backend(Replay::new(Cassette { interactions: vec![scoped] }));
+        let session = op.session("token").with_vault("Shared");
+        assert_eq!(session.vault(), Some("Shared"));
+        assert!(session.get_item_in("abc", Some("Private")).is_ok());
+    }
+
+    #[test]
+    fn data_roundtrip() {
+        let data = Data::new(vec![0xff, 0x00]);
+        assert_eq!(data, Data::Binary(vec![0xff, 0x00]));
+        let json = serde_json::to_string(&data).unwrap();
+        let back : Data = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.into_bytes(), vec![0xff, 0x00]);
+    }
+}
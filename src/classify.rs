@@ -0,0 +1,101 @@
+/// Broad category of an `op` failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// Not signed in, or the session has expired.
+    NotSignedIn,
+    /// Signing in requires a one-time password from a second factor.
+    SecondFactorRequired,
+    /// Signed in but not allowed to perform the operation.
+    PermissionDenied,
+    /// The item, vault, etc. does not exist.
+    NotFound,
+    /// More than one item, vault, etc. matched.
+    Ambiguous,
+    /// `op` was invoked with invalid arguments.
+    Usage,
+    /// The 1Password service is limiting requests.
+    RateLimited,
+    /// The 1Password service could not be reached.
+    Network,
+    /// Nothing identified the failure.
+    Unknown,
+}
+
+#[cfg(feature = "process")]
+/// Fragments of `op` error messages and the category they identify.
+///
+/// These are the English messages, which `op` writes when run with the `C`
+/// locale set by `Op::with_locale`.
+const MARKERS: &[(&str, ErrorCategory)] = &[
+    ("authentication code", ErrorCategory::SecondFactorRequired),
+    ("one-time password", ErrorCategory::SecondFactorRequired),
+    ("two-factor", ErrorCategory::SecondFactorRequired),
+    ("second factor", ErrorCategory::SecondFactorRequired),
+    ("not currently signed in", ErrorCategory::NotSignedIn),
+    ("session expired", ErrorCategory::NotSignedIn),
+    ("authentication required", ErrorCategory::NotSignedIn),
+    ("invalid session", ErrorCategory::NotSignedIn),
+    ("permission denied", ErrorCategory::PermissionDenied),
+    ("forbidden", ErrorCategory::PermissionDenied),
+    ("more than one item matches", ErrorCategory::Ambiguous),
+    ("isn't an item", ErrorCategory::NotFound),
+    ("doesn't seem to be an item", ErrorCategory::NotFound),
+    ("isn't a vault", ErrorCategory::NotFound),
+    ("not found", ErrorCategory::NotFound),
+    ("unknown command", ErrorCategory::Usage),
+    ("unknown flag", ErrorCategory::Usage),
+    ("invalid argument", ErrorCategory::Usage),
+    ("too many requests", ErrorCategory::RateLimited),
+    ("rate limit", ErrorCategory::RateLimited),
+    ("no such host", ErrorCategory::Network),
+    ("connection refused", ErrorCategory::Network),
+    ("network is unreachable", ErrorCategory::Network),
+];
+
+#[cfg(feature = "process")]
+/// Categorize a failure by what `op` wrote to stderr, falling back to its
+/// exit code as looked up in `exit_codes`.
+///
+/// `op` doesn't document exit codes beyond 0 and 1, so there are no built in
+/// ones; callers who know what their `op` means by a code pass it, e.g. for
+/// messages in a language the markers don't cover.
+pub fn classify(stderr: &str, exit_code: Option<i32>, exit_codes: &[(i32, ErrorCategory)]) -> ErrorCategory {
+    let stderr = stderr.to_lowercase();
+    let by_marker = MARKERS.iter()
+        .find(|&&(marker, _)| stderr.contains(marker))
+        .map(|&(_, category)| category);
+    if let Some(category) = by_marker {
+        return category;
+    }
+    exit_code.and_then(|code| exit_codes.iter().find(|&&(c, _)| c == code))
+        .map_or(ErrorCategory::Unknown, |&(_, category)| category)
+}
+
+#[cfg(all(test, feature = "process"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stderr_markers() {
+        assert_eq!(classify("[ERROR] 2018/09/24 You are not currently signed in.", Some(1), &[]),
+            ErrorCategory::NotSignedIn);
+        assert_eq!(classify("[LOG] 2018/09/24 (ERROR) Item foo not found.", Some(1), &[]),
+            ErrorCategory::NotFound);
+    }
+
+    #[test]
+    fn exit_code_fallback() {
+        assert_eq!(classify("", Some(2), &[]), ErrorCategory::Unknown);
+        assert_eq!(classify("kaputt", Some(1), &[]), ErrorCategory::Unknown);
+        assert_eq!(classify("kaputt", Some(1), &[(1, ErrorCategory::Network)]), ErrorCategory::Network);
+        assert_eq!(classify("kaputt", None, &[]), ErrorCategory::Unknown);
+    }
+
+    #[test]
+    fn stderr_before_exit_codes() {
+        assert_eq!(classify("[ERROR] unknown flag --vualt", Some(77), &[(77, ErrorCategory::PermissionDenied)]),
+            ErrorCategory::Usage);
+        assert_eq!(classify("[ERROR] Sitzung abgelaufen", Some(6), &[(6, ErrorCategory::NotSignedIn)]),
+            ErrorCategory::NotSignedIn);
+    }
+}
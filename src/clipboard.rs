@@ -0,0 +1,76 @@
+//! Copy secrets to the system clipboard and clear them again after a while,
+//! as the 1Password apps do.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use std::time::Duration;
+//! use one_password::Op;
+//! use one_password::clipboard::copy_to_clipboard;
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let password = session.get_field_revealed("db-password", "password").unwrap();
+//! copy_to_clipboard(&password, Duration::from_secs(90)).unwrap();
+//! println!("Copied, clearing in 90 seconds");
+//! ```
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use arboard::Clipboard;
+
+use secret::Secret;
+use super::Result;
+
+fn clipboard_error(err: arboard::Error) -> io::Error {
+    io::Error::other(err)
+}
+
+/// Handle to the thread clearing the clipboard, from `copy_to_clipboard`.
+///
+/// Dropping it doesn't stop the clipboard from being cleared.
+pub struct ClipboardClear {
+    clear: Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl ClipboardClear {
+    /// Clear the clipboard now instead of when the time is up.
+    pub fn clear_now(self) {
+        let _ = self.clear.send(());
+        let _ = self.thread.join();
+    }
+
+    /// Wait until the clipboard has been cleared.
+    ///
+    /// Desktop environments on Linux drop what a process put on the
+    /// clipboard when it exits, so command line tools should wait for this
+    /// or call `clear_now` before exiting.
+    pub fn wait(self) {
+        let _ = self.thread.join();
+    }
+}
+
+/// Put `secret` on the system clipboard and clear it after `clear_after`.
+///
+/// The clipboard is only cleared if it still holds `secret`, so anything
+/// copied in the meantime is left alone.
+pub fn copy_to_clipboard(secret: &Secret<String>, clear_after: Duration) -> Result<ClipboardClear> {
+    let mut clipboard = Clipboard::new().map_err(clipboard_error)?;
+    clipboard.set_text(&secret.expose()[..]).map_err(clipboard_error)?;
+    let secret = secret.clone();
+    let deadline = Instant::now() + clear_after;
+    let (clear, cleared) = mpsc::channel();
+    let thread = thread::Builder::new().name("clipboard-clear".to_owned()).spawn(move || {
+        // The sender is dropped with the handle, which must not clear early.
+        if let Err(RecvTimeoutError::Disconnected) = cleared.recv_timeout(clear_after) {
+            thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        }
+        if clipboard.get_text().ok().as_ref() == Some(secret.expose()) {
+            let _ = clipboard.clear();
+        }
+    })?;
+    Ok(ClipboardClear { clear, thread })
+}
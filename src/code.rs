@@ -0,0 +1,162 @@
+use std::fmt;
+
+use super::{Error, ErrorCategory, ErrorKind};
+
+/// Stable, machine-readable identifier of an error, from `Error::code`.
+///
+/// Error messages may change between releases, these codes don't, so they are
+/// what metrics and alerts should be keyed on. New codes may be added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorCode {
+    /// `op` could not be found or run.
+    NotInstalled,
+    /// No session is available, e.g. no `OP_SESSION_*` variable is set.
+    NotSignedIn,
+    /// `op` rejected the session, usually because it has expired.
+    AuthExpired,
+    /// Signing in requires a one-time password.
+    SecondFactorRequired,
+    PermissionDenied,
+    /// The item, vault, field, etc. does not exist.
+    NotFound,
+    /// More than one item, vault, session, etc. matched.
+    Ambiguous,
+    /// `op` was invoked with invalid arguments.
+    Usage,
+    RateLimited,
+    Network,
+    /// `op` ran longer than the configured timeout.
+    Timeout,
+    /// Input given to this crate is invalid, e.g. a malformed secret reference.
+    InvalidInput,
+    /// `op` printed something that could not be understood.
+    UnexpectedOutput,
+    /// The installed `op` is too old for the operation.
+    UnsupportedVersion,
+    /// A concealed value was masked by `op`.
+    Concealed,
+    /// A downloaded document doesn't match what 1Password reports for it.
+    IntegrityMismatch,
+    /// An `.opvault` directory is invalid or could not be unlocked.
+    OpVault,
+    /// The secrets broker refused a request.
+    Broker,
+    /// Reading or writing a file, pipe or socket failed.
+    Io,
+    /// `op` failed for a reason that could not be identified.
+    CommandFailed,
+    /// Anything else.
+    Other,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ErrorCode::NotInstalled => "OP_NOT_INSTALLED",
+            ErrorCode::NotSignedIn => "OP_NOT_SIGNED_IN",
+            ErrorCode::AuthExpired => "OP_AUTH_EXPIRED",
+            ErrorCode::SecondFactorRequired => "OP_SECOND_FACTOR_REQUIRED",
+            ErrorCode::PermissionDenied => "OP_PERMISSION_DENIED",
+            ErrorCode::NotFound => "OP_NOT_FOUND",
+            ErrorCode::Ambiguous => "OP_AMBIGUOUS",
+            ErrorCode::Usage => "OP_USAGE",
+            ErrorCode::RateLimited => "OP_RATE_LIMITED",
+            ErrorCode::Network => "OP_NETWORK",
+            ErrorCode::Timeout => "OP_TIMEOUT",
+            ErrorCode::InvalidInput => "OP_INVALID_INPUT",
+            ErrorCode::UnexpectedOutput => "OP_UNEXPECTED_OUTPUT",
+            ErrorCode::UnsupportedVersion => "OP_UNSUPPORTED_VERSION",
+            ErrorCode::Concealed => "OP_CONCEALED",
+            ErrorCode::IntegrityMismatch => "OP_INTEGRITY_MISMATCH",
+            ErrorCode::OpVault => "OP_OPVAULT",
+            ErrorCode::Broker => "OP_BROKER",
+            ErrorCode::Io => "OP_IO",
+            ErrorCode::CommandFailed => "OP_COMMAND_FAILED",
+            ErrorCode::Other => "OP_OTHER",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<ErrorCategory> for ErrorCode {
+    fn from(category: ErrorCategory) -> ErrorCode {
+        match category {
+            ErrorCategory::NotSignedIn => ErrorCode::AuthExpired,
+            ErrorCategory::SecondFactorRequired => ErrorCode::SecondFactorRequired,
+            ErrorCategory::PermissionDenied => ErrorCode::PermissionDenied,
+            ErrorCategory::NotFound => ErrorCode::NotFound,
+            ErrorCategory::Ambiguous => ErrorCode::Ambiguous,
+            ErrorCategory::Usage => ErrorCode::Usage,
+            ErrorCategory::RateLimited => ErrorCode::RateLimited,
+            ErrorCategory::Network => ErrorCode::Network,
+            ErrorCategory::Unknown => ErrorCode::CommandFailed,
+        }
+    }
+}
+
+impl Error {
+    /// Stable code identifying this error, e.g. `OP_NOT_FOUND`.
+    ///
+    /// Failed `op` invocations get the code of their `ErrorCategory`.
+    pub fn code(&self) -> ErrorCode {
+        if let Some(category) = self.category() {
+            return category.into();
+        }
+        match *self.kind() {
+            ErrorKind::MissingOpCommand | ErrorKind::InvalidOpPath(_) => ErrorCode::NotInstalled,
+            ErrorKind::MissingSessionVariable | ErrorKind::MissingServiceAccountToken |
+            ErrorKind::UnknownSessionVariable | ErrorKind::SessionVar(_) => ErrorCode::NotSignedIn,
+            ErrorKind::MultipleSessionVariables(_) => ErrorCode::Ambiguous,
+            ErrorKind::SecretNotFound(_) | ErrorKind::CredentialsNotFound(_) |
+            ErrorKind::FieldNotFound(..) => ErrorCode::NotFound,
+            ErrorKind::ConnectTokenRejected(_) | ErrorKind::UnknownHostKey(_) |
+            ErrorKind::PolicyViolation(_) | ErrorKind::ForeignCredentialItem(_) |
+            ErrorKind::CredentialUserMismatch(_) => ErrorCode::PermissionDenied,
+            ErrorKind::InvalidSecretReference(_) | ErrorKind::InvalidSecretKey(_) |
+            ErrorKind::InvalidCredentialName(_) | ErrorKind::InvalidEnvLine(_) |
+            ErrorKind::InvalidAssignment(..) | ErrorKind::InvalidRecipe(..) | ErrorKind::InvalidFieldPath(_) |
+            ErrorKind::InvalidTag(_) | ErrorKind::InvalidItemSelector(..) |
+            ErrorKind::UnknownHelperOperation(_) => ErrorCode::InvalidInput,
+            ErrorKind::JsonParse(_) | ErrorKind::StdErrUtf8(_) | ErrorKind::InvalidUtf8Output(..) |
+            ErrorKind::InvalidVersion(_) | ErrorKind::UnexpectedHumanOutput(_) |
+            ErrorKind::SchemaMismatch(..) => ErrorCode::UnexpectedOutput,
+            ErrorKind::RequiresCliV2(_) => ErrorCode::UnsupportedVersion,
+            ErrorKind::ConcealedField(..) => ErrorCode::Concealed,
+            ErrorKind::IntegrityMismatch(..) => ErrorCode::IntegrityMismatch,
+            ErrorKind::InvalidOpVault(_) | ErrorKind::OpVaultUnlock => ErrorCode::OpVault,
+            ErrorKind::Broker(_) => ErrorCode::Broker,
+            ErrorKind::TimedOut(..) => ErrorCode::Timeout,
+            ErrorKind::Io(_) | ErrorKind::Csv(_) => ErrorCode::Io,
+            ErrorKind::CommandFailed(_) | ErrorKind::GetCommand(..) | ErrorKind::VersionCommand(_) => ErrorCode::CommandFailed,
+            _ => ErrorCode::Other,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "process"))]
+mod tests {
+    use super::*;
+    use cassette::{interaction, replay_op};
+
+    #[test]
+    fn codes() {
+        let err : Error = ErrorKind::InvalidSecretReference("x".to_owned()).into();
+        assert_eq!(err.code().as_str(), "OP_INVALID_INPUT");
+        let err : Error = ErrorKind::MissingSessionVariable.into();
+        assert_eq!(err.code(), ErrorCode::NotSignedIn);
+        let err : Error = "something".into();
+        assert_eq!(err.code().to_string(), "OP_OTHER");
+
+        let op = replay_op(vec![
+            interaction("read", &["--session=⟨redacted⟩", "op://Ops/DB/password", "--no-newline"], "")
+                .failing("[ERROR] 2023/01/01 session expired, sign in to create a new session", 1),
+        ]);
+        let err = op.session("token").read("op://Ops/DB/password").unwrap_err();
+        assert_eq!(err.code().as_str(), "OP_AUTH_EXPIRED");
+    }
+}
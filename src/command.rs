@@ -0,0 +1,678 @@
+use std::cell::Cell;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::process::{ExitStatus, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+use super::{Backend, CommandDiagnostics, CommandInfo, CommandOutcome, Error, ErrorKind, Invocation, Op, RawItem, Result};
+use classify;
+use dry_run;
+use pool;
+use refresh::SessionAuth;
+use secret::{SecretBytes, REDACTED};
+
+static LOG_COMMANDS: AtomicBool = AtomicBool::new(false);
+
+/// Print the command line of every `op` invocation to stderr right before it
+/// runs, with session tokens and field values replaced by `⟨redacted⟩`.
+///
+/// Meant for finding out what this crate actually runs; can be switched on
+/// and off at any time.
+pub fn set_log_commands(enabled: bool) {
+    LOG_COMMANDS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether command lines are printed, see `set_log_commands`.
+pub fn log_commands() -> bool {
+    LOG_COMMANDS.load(Ordering::Relaxed)
+}
+
+/// Quote `arg` for display if a shell would split or interpret it.
+fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_alphanumeric() || "-_=./:@,+%[]⟨⟩".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// How to handle `op` output that is not valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Utf8Policy {
+    /// Fail with `ErrorKind::InvalidUtf8Output`, which holds the raw bytes.
+    #[default]
+    Strict,
+    /// Replace invalid sequences with U+FFFD REPLACEMENT CHARACTER.
+    Lossy,
+}
+
+impl Utf8Policy {
+    /// Decode the `stream` ("stdout" or "stderr") output of `op` according to this policy.
+    pub fn decode(self, stream: &'static str, bytes: Vec<u8>) -> Result<String> {
+        match String::from_utf8(bytes) {
+            Ok(s) => Ok(s),
+            Err(err) => match self {
+                Utf8Policy::Strict => Err(ErrorKind::InvalidUtf8Output(stream, err.into_bytes()).into()),
+                Utf8Policy::Lossy => Ok(String::from_utf8_lossy(err.as_bytes()).into_owned()),
+            },
+        }
+    }
+}
+
+/// Result of running `op` interactively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InteractiveOutput {
+    pub status: ExitStatus,
+    /// Everything `op` wrote to stdout, such as the session token printed by
+    /// `op signin --raw`.
+    pub stdout: String,
+}
+
+#[derive(Clone)]
+enum Arg {
+    Plain(OsString),
+    Secret(OsString),
+    /// `--session=` with the current token of the `SessionAuth` of the
+    /// command, for sessions that don't know their `OP_SESSION_*` variable.
+    Session,
+}
+
+/// Writer passing everything on to `inner`, noting whether anything was written.
+struct Tracked<'w> {
+    inner: &'w mut dyn Write,
+    written: &'w Cell<bool>,
+}
+
+impl<'w> Write for Tracked<'w> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if n > 0 {
+            self.written.set(true);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A single invocation of `op`, built up before it is spawned.
+///
+/// The session token and arguments added with `secret_arg` are never
+/// included in anything that gets logged.
+#[derive(Clone)]
+pub struct OpCommand<'a> {
+    op: &'a Op,
+    subcommand: Vec<String>,
+    args: Vec<Arg>,
+    targets: Vec<String>,
+    stdin: Option<SecretBytes>,
+    interactive: bool,
+    auth: Option<&'a SessionAuth>,
+    /// `OP_SESSION_*` variable the token is passed in, if known.
+    session_var: Option<&'a str>,
+}
+
+impl<'a> OpCommand<'a> {
+    pub fn new(op: &'a Op, subcommand: &[&str]) -> OpCommand<'a> {
+        OpCommand {
+            op,
+            subcommand: subcommand.iter().map(|s| s.to_string()).collect(),
+            args: op.extra_args.iter().cloned().map(Arg::Plain).collect(),
+            targets: Vec::new(),
+            stdin: None,
+            interactive: false,
+            auth: None,
+            session_var: None,
+        }
+    }
+
+    /// Write `data` to the stdin of `op`. It is never logged or recorded.
+    pub fn stdin<D: Into<SecretBytes>>(&mut self, data: D) -> &mut OpCommand<'a> {
+        self.stdin = Some(data.into());
+        self
+    }
+
+    /// Connect stdin and stderr to the terminal so `op` can prompt the user.
+    /// Only stdout is captured.
+    pub fn interactive(&mut self) -> &mut OpCommand<'a> {
+        self.interactive = true;
+        self
+    }
+
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut OpCommand<'a> {
+        self.args.push(Arg::Plain(arg.as_ref().to_owned()));
+        self
+    }
+
+    /// Add an argument naming the item, vault, etc. the command operates on.
+    pub fn target(&mut self, target: &str) -> &mut OpCommand<'a> {
+        self.targets.push(target.to_owned());
+        self.arg(target)
+    }
+
+    /// Add an argument that must not be logged, such as a secret key.
+    pub fn secret_arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut OpCommand<'a> {
+        self.args.push(Arg::Secret(arg.as_ref().to_owned()));
+        self
+    }
+
+    /// Ask for machine-readable output: with `op` 2.x this adds `--format json`,
+    /// `--no-color` and `--iso-timestamps`; `op` 1.x always prints JSON.
+    ///
+    /// Use `run_json` or `parse_json` to read the output.
+    pub fn json(&mut self) -> Result<&mut OpCommand<'a>> {
+        if self.op.cli_version()?.is_v2() {
+            self.arg("--format").arg("json").arg("--no-color").arg("--iso-timestamps");
+        }
+        Ok(self)
+    }
+
+    /// Authenticate with the token `auth` holds when the command runs, and
+    /// run it again after a refresh if `op` rejects the token.
+    ///
+    /// The token is passed in the environment variable `session_var`, or
+    /// with `--session` if the variable isn't known.
+    pub(crate) fn auth(&mut self, auth: &'a SessionAuth, session_var: Option<&'a str>) -> &mut OpCommand<'a> {
+        if session_var.is_none() {
+            self.args.push(Arg::Session);
+        }
+        self.auth = Some(auth);
+        self.session_var = session_var;
+        self
+    }
+
+    /// Subcommand being run, e.g. `get item`.
+    pub fn subcommand(&self) -> String {
+        self.subcommand.join(" ")
+    }
+
+    /// Arguments after the subcommand with all secrets replaced by `REDACTED`.
+    pub fn redacted_args(&self) -> Vec<String> {
+        self.args.iter().map(|arg| match *arg {
+            Arg::Plain(ref a) => a.to_string_lossy().into_owned(),
+            Arg::Secret(_) => REDACTED.to_owned(),
+            Arg::Session => format!("--session={}", REDACTED),
+        }).collect()
+    }
+
+    /// The command line as it would be typed in a shell, with secrets redacted.
+    pub fn command_line(&self) -> String {
+        let mut line = quote(&self.op.command.to_string_lossy());
+        for part in self.subcommand.iter().chain(&self.redacted_args()) {
+            line.push(' ');
+            line.push_str(&quote(part));
+        }
+        line
+    }
+
+    fn full_args(&self) -> Vec<OsString> {
+        let token = self.auth.map(SessionAuth::token);
+        let mut args : Vec<OsString> = self.subcommand.iter().map(OsString::from).collect();
+        for arg in &self.args {
+            args.push(match *arg {
+                Arg::Plain(ref a) => a.clone(),
+                Arg::Secret(ref a) => a.clone(),
+                Arg::Session => {
+                    let mut full = OsString::from("--session=");
+                    full.push(token.as_ref().map_or("", |t| &t.expose()[..]));
+                    full
+                },
+            });
+        }
+        args
+    }
+
+    /// Run the command and return its stdout, failing with the error built by
+    /// `error` if it exits unsuccessfully.
+    pub fn run_or<F>(&self, error: F) -> Result<Vec<u8>>
+        where F: FnOnce(Box<CommandDiagnostics>) -> ErrorKind
+    {
+        let (output, duration) = self.execute()?;
+        if output.status.success() {
+            Ok(output.stdout)
+        } else {
+            Err(error(self.diagnostics(output, duration)?).into())
+        }
+    }
+
+    /// Run the command and parse its stdout as JSON, failing with the error
+    /// built by `error` if it exits unsuccessfully.
+    pub fn run_json<T, F>(&self, error: F) -> Result<T>
+        where T: DeserializeOwned, F: FnOnce(Box<CommandDiagnostics>) -> ErrorKind
+    {
+        let stdout = self.run_or(error)?;
+        self.parse_json(&stdout)
+    }
+
+    /// Parse `stdout` of this command as JSON.
+    ///
+    /// Fails with `ErrorKind::UnexpectedHumanOutput` rather than a JSON syntax
+    /// error when `op` printed something that isn't JSON at all, e.g. because a
+    /// wrapper script or alias dropped `--format json`, and with
+    /// `ErrorKind::SchemaMismatch` holding the JSON when it is valid but
+    /// doesn't fit `T`.
+    pub fn parse_json<T: DeserializeOwned>(&self, stdout: &[u8]) -> Result<T> {
+        let value : Value = serde_json::from_slice(stdout).map_err(|err| -> Error {
+            match stdout.iter().find(|b| !b.is_ascii_whitespace()) {
+                Some(&b'{') | Some(&b'[') | None => err.into(),
+                Some(_) => ErrorKind::UnexpectedHumanOutput(self.subcommand()).into(),
+            }
+        })?;
+        match T::deserialize(&value) {
+            Ok(parsed) => Ok(parsed),
+            Err(err) => Err(ErrorKind::SchemaMismatch(self.subcommand(), err.to_string(), Box::new(RawItem(value))).into()),
+        }
+    }
+
+    /// Run the command, copying its stdout into `stdout` as it is produced,
+    /// and fail with the error built by `error` if it exits unsuccessfully.
+    ///
+    /// Like `execute`, failures are retried and rejected session tokens
+    /// refreshed, but only while nothing has been copied into `stdout`.
+    pub fn run_to<F>(&self, stdout: &mut dyn Write, error: F) -> Result<()>
+        where F: FnOnce(Box<CommandDiagnostics>) -> ErrorKind
+    {
+        let written = Cell::new(false);
+        let mut sink = Tracked { inner: stdout, written: &written };
+        let (output, duration) = self.execute_retrying(
+            |backend, invocation| backend.run_to(invocation, &mut sink),
+            || !written.get())?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(error(self.diagnostics(output, duration)?).into())
+        }
+    }
+
+    /// Run the command with `stdin` copied into the stdin of `op` as it is
+    /// read, and return its stdout, failing with the error built by `error` if
+    /// it exits unsuccessfully. Any data set with `stdin` is not sent.
+    ///
+    /// `stdin` can only be read once, so the command is neither retried nor
+    /// run again after a session refresh.
+    pub fn run_from<F>(&self, stdin: &mut (dyn Read + Send), error: F) -> Result<Vec<u8>>
+        where F: FnOnce(Box<CommandDiagnostics>) -> ErrorKind
+    {
+        let (output, duration) = self.execute_with(|backend, invocation| backend.run_from(invocation, stdin))?;
+        if output.status.success() {
+            Ok(output.stdout)
+        } else {
+            Err(error(self.diagnostics(output, duration)?).into())
+        }
+    }
+
+    /// Describe a finished invocation of this command, remembering it as the
+    /// last failure of the `Op`.
+    pub fn diagnostics(&self, output: Output, duration: Duration) -> Result<Box<CommandDiagnostics>> {
+        let stderr = self.op.utf8_policy.decode("stderr", output.stderr)?;
+        let category = classify::classify(&stderr, output.status.code(), &self.op.exit_codes);
+        let diagnostics = CommandDiagnostics {
+            program: self.op.command.clone(),
+            subcommand: self.subcommand(),
+            args: self.redacted_args(),
+            status: output.status,
+            stderr,
+            duration,
+            category,
+        };
+        self.op.record_failure(&diagnostics);
+        Ok(Box::new(diagnostics))
+    }
+
+    /// Run the command to completion, returning its output and how long it ran.
+    ///
+    /// Failures are retried as the `RetryPolicy` of the `Op` allows. If `op`
+    /// rejects the session token and the session can be refreshed, the
+    /// command is run once more with the new token.
+    pub fn execute(&self) -> Result<(Output, Duration)> {
+        let interactive = self.interactive;
+        self.execute_retrying(|backend, invocation| if interactive {
+            backend.run_interactive(invocation)
+        } else {
+            backend.run(invocation)
+        }, || true)
+    }
+
+    /// Run the command with `run` as `execute` does, but only run it again
+    /// while `rerunnable` returns true.
+    fn execute_retrying<F, R>(&self, mut run: F, rerunnable: R) -> Result<(Output, Duration)>
+        where F: FnMut(&dyn Backend, &Invocation) -> io::Result<Output>, R: Fn() -> bool
+    {
+        let mut attempt = 1;
+        loop {
+            let (output, duration) = self.execute_refreshing(&mut run, &rerunnable)?;
+            let delay = match self.failure_category(&output) {
+                Some(category) if !self.interactive && rerunnable() => self.op.retry.delay(attempt, category),
+                _ => None,
+            };
+            match delay {
+                Some(delay) => thread::sleep(delay),
+                None => return Ok((output, duration)),
+            }
+            attempt += 1;
+        }
+    }
+
+    fn execute_refreshing<F, R>(&self, run: &mut F, rerunnable: &R) -> Result<(Output, Duration)>
+        where F: FnMut(&dyn Backend, &Invocation) -> io::Result<Output>, R: Fn() -> bool
+    {
+        let generation = self.auth.map(|auth| auth.current().0);
+        let (output, duration) = self.execute_with(&mut *run)?;
+        if let (Some(auth), Some(generation)) = (self.auth, generation) {
+            if rerunnable() && self.rejected_session(&output) && auth.refresh(self.op, generation)? {
+                return self.execute_with(run);
+            }
+        }
+        Ok((output, duration))
+    }
+
+    /// Category of the failure if `op` failed.
+    fn failure_category(&self, output: &Output) -> Option<classify::ErrorCategory> {
+        if output.status.success() {
+            return None;
+        }
+        Some(self.category(output))
+    }
+
+    /// Category of a failure from what `op` wrote to stderr and its exit code.
+    fn category(&self, output: &Output) -> classify::ErrorCategory {
+        classify::classify(&String::from_utf8_lossy(&output.stderr), output.status.code(), &self.op.exit_codes)
+    }
+
+    /// Whether `op` failed because it didn't accept the session token.
+    fn rejected_session(&self, output: &Output) -> bool {
+        !self.interactive && self.auth.is_some_and(SessionAuth::can_refresh) &&
+            self.failure_category(output) == Some(classify::ErrorCategory::NotSignedIn)
+    }
+
+    fn execute_with<F>(&self, run: F) -> Result<(Output, Duration)>
+        where F: FnOnce(&dyn Backend, &Invocation) -> io::Result<Output>
+    {
+        #[cfg(feature = "tracing")]
+        let span = info_span!("op",
+            subcommand = %self.subcommand(),
+            args = ?self.redacted_args(),
+            status = ::tracing::field::Empty,
+            duration_ms = ::tracing::field::Empty);
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        if let Some(ref policy) = self.op.policy {
+            if !policy.permits(&self.subcommand) {
+                bail!(ErrorKind::PolicyViolation(self.subcommand()));
+            }
+        }
+        let info = CommandInfo {
+            subcommand: self.subcommand(),
+            args: self.redacted_args(),
+            targets: self.targets.clone(),
+            reason: self.op.reason.clone(),
+        };
+        if let Some(ref plan) = self.op.dry_run {
+            // `op item create --dry-run` only prints what it would create.
+            if dry_run::is_mutating(&self.subcommand) && !self.redacted_args().iter().any(|a| a == "--dry-run") {
+                plan.record(info);
+                return Ok((Output {
+                    status: ExitStatus::default(),
+                    stdout: Vec::new(),
+                    stderr: Vec::new(),
+                }, Duration::from_secs(0)));
+            }
+        }
+        for observer in &self.op.observers {
+            observer.on_command_start(&info);
+        }
+
+        if log_commands() {
+            eprintln!("+ {}", self.command_line());
+        }
+        let (result, duration) = {
+            let _permit = pool::global().acquire();
+            let start = Instant::now();
+            let mut invocation = Invocation::new(self.op, self.full_args(),
+                self.stdin.as_ref().map(SecretBytes::as_bytes), &info);
+            if let (Some(auth), Some(name)) = (self.auth, self.session_var) {
+                invocation = invocation.with_session(name, auth.token());
+            }
+            let result = run(&*self.op.backend, &invocation);
+            (result, start.elapsed())
+        };
+
+        let outcome = match result {
+            Ok(ref output) if output.status.success() => CommandOutcome::Success,
+            Ok(ref output) => CommandOutcome::Failed(self.category(output), output.status.code()),
+            Err(ref err) if err.kind() == io::ErrorKind::TimedOut => CommandOutcome::TimedOut,
+            Err(_) => CommandOutcome::SpawnFailed,
+        };
+        for observer in &self.op.observers {
+            observer.on_command_finish(&info, duration, outcome);
+        }
+        if let Ok(ref output) = result {
+            if output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                for warning in stderr.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                    #[cfg(feature = "tracing")]
+                    warn!("op {}: {}", self.subcommand(), warning);
+                    for observer in &self.op.observers {
+                        observer.on_warning(&info, warning);
+                    }
+                }
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            span.record("duration_ms", duration.as_millis() as u64);
+            match result {
+                Ok(ref output) => {
+                    span.record("status", ::tracing::field::display(output.status));
+                    if output.status.success() {
+                        debug!("op {} finished", self.subcommand());
+                    } else {
+                        warn!("op {} failed with {}", self.subcommand(), output.status);
+                    }
+                },
+                Err(ref err) => error!("failed to run op {}: {}", self.subcommand(), err),
+            }
+        }
+        match result {
+            Err(ref err) if err.kind() == io::ErrorKind::TimedOut => {
+                bail!(ErrorKind::TimedOut(self.subcommand(), self.op.timeout.unwrap_or(duration)))
+            },
+            result => Ok((result?, duration)),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for OpCommand<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OpCommand")
+            .field("command", &self.op.command)
+            .field("subcommand", &self.subcommand())
+            .field("args", &self.redacted_args())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_secrets() {
+        let op = Op::new("op");
+        let mut cmd = OpCommand::new(&op, &["get", "item"]);
+        cmd.secret_arg("TOKEN").arg("abc");
+        assert_eq!(cmd.subcommand(), "get item");
+        assert_eq!(cmd.redacted_args(), vec![REDACTED.to_owned(), "abc".to_owned()]);
+        assert!(!format!("{:?}", cmd).contains("TOKEN"));
+        assert!(cmd.full_args().contains(&OsString::from("TOKEN")));
+    }
+
+    #[test]
+    fn formats_command_line() {
+        let op = Op::new("/usr/local/bin/op");
+        let mut cmd = OpCommand::new(&op, &["item", "edit"]);
+        cmd.target("My Login").secret_arg("it's secret").arg("--tags=a,b");
+        assert_eq!(cmd.command_line(), "/usr/local/bin/op item edit 'My Login' ⟨redacted⟩ --tags=a,b");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn utf8_policy() {
+        let bytes = vec![b'o', b'k', 0xff];
+        assert_eq!(Utf8Policy::Lossy.decode("stderr", bytes.clone()).unwrap(), "ok\u{fffd}");
+        match *Utf8Policy::Strict.decode("stderr", bytes.clone()).unwrap_err().kind() {
+            ErrorKind::InvalidUtf8Output(stream, ref raw) => {
+                assert_eq!(stream, "stderr");
+                assert_eq!(*raw, bytes);
+            },
+            ref other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn adds_extra_args() {
+        let op = Op::new("op").with_extra_args(&["--debug"]).with_extra_args(&["--cache=false"]);
+        let mut cmd = OpCommand::new(&op, &["item", "get"]);
+        cmd.secret_arg("TOKEN").target("abc");
+        assert_eq!(cmd.command_line(), "op item get --debug --cache=false ⟨redacted⟩ abc");
+    }
+
+    #[test]
+    fn requests_json() {
+        use CliVersion;
+
+        let op = Op::new("op").with_cli_version(CliVersion::new(2, 24, 0));
+        let mut cmd = OpCommand::new(&op, &["item", "get"]);
+        cmd.target("abc").json().unwrap();
+        assert_eq!(cmd.redacted_args(), vec!["abc", "--format", "json", "--no-color", "--iso-timestamps"]);
+        let op = Op::new("op").with_cli_version(CliVersion::new(1, 12, 0));
+        assert!(OpCommand::new(&op, &["get", "item"]).json().unwrap().redacted_args().is_empty());
+
+        let human = b"ID:          abc\nTitle:       Example\n";
+        match *cmd.parse_json::<serde_json::Value>(human).unwrap_err().kind() {
+            ErrorKind::UnexpectedHumanOutput(ref subcommand) => assert_eq!(subcommand, "item get"),
+            ref other => panic!("unexpected error {:?}", other),
+        }
+        match *cmd.parse_json::<serde_json::Value>(b" {\"id\": ").unwrap_err().kind() {
+            ErrorKind::JsonParse(_) => (),
+            ref other => panic!("unexpected error {:?}", other),
+        }
+        let err = cmd.parse_json::<::OpItem>(br#"{"uuid": "abc", "details": {"password": "hunter2"}}"#).unwrap_err();
+        assert_eq!(err.raw_json().unwrap()["uuid"], "abc");
+        assert!(!format!("{:?}", err).contains("hunter2"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn interactive_captures_stdout() {
+        let op = Op::new("echo");
+        let out = op.run_interactive(&["signin"], &["--raw"]).unwrap();
+        assert!(out.status.success());
+        assert_eq!(out.stdout, "signin --raw\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn notifies_observers() {
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+        use observer::Observer;
+
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+        impl Observer for Recorder {
+            fn on_command_start(&self, command: &CommandInfo) {
+                self.0.lock().unwrap().push(format!("start {}", command.subcommand));
+            }
+            fn on_command_finish(&self, command: &CommandInfo, _: Duration, outcome: CommandOutcome) {
+                self.0.lock().unwrap().push(format!("finish {} {}", command.subcommand, outcome.as_str()));
+                if let CommandOutcome::Failed(category, code) = outcome {
+                    self.0.lock().unwrap().push(format!("{:?} {:?}", category, code));
+                }
+            }
+        }
+
+        let recorder = Recorder::default();
+        let op = Op::new("false").with_observer(recorder.clone());
+        OpCommand::new(&op, &["get", "item"]).execute().unwrap();
+        let op = Op::new("/nonexistent/op").with_observer(recorder.clone());
+        assert!(OpCommand::new(&op, &["--version"]).execute().is_err());
+        let op = Op::builder()
+            .binary("sleep")
+            .timeout(Duration::from_millis(100))
+            .observer(recorder.clone())
+            .build()
+            .unwrap();
+        assert!(OpCommand::new(&op, &["5"]).execute().is_err());
+        assert_eq!(*recorder.0.lock().unwrap(), vec![
+            "start get item", "finish get item failed", "Unknown Some(1)",
+            "start --version", "finish --version spawn_failed",
+            "start 5", "finish 5 timed_out",
+        ]);
+    }
+
+    #[test]
+    fn reports_warnings_on_success() {
+        use std::sync::{Arc, Mutex};
+        use cassette::{interaction, Cassette, Data, Interaction, Replay};
+        use observer::Observer;
+
+        #[derive(Clone, Default)]
+        struct Warnings(Arc<Mutex<Vec<String>>>);
+        impl Observer for Warnings {
+            fn on_warning(&self, _: &CommandInfo, warning: &str) {
+                self.0.lock().unwrap().push(warning.to_owned());
+            }
+        }
+
+        let version = Interaction {
+            stderr: Data::Text("[LOG] a newer version is available\n\n".to_owned()),
+            ..interaction("--version", &[], "1.12.4\n")
+        };
+        let warnings = Warnings::default();
+        let op = Op::new("op")
+            .with_backend(Replay::new(Cassette { interactions: vec![version] }))
+            .with_observer(warnings.clone());
+        let _ = op.version();
+        assert_eq!(*warnings.0.lock().unwrap(), vec!["[LOG] a newer version is available"]);
+    }
+
+    #[test]
+    fn passes_token_in_environment() {
+        use std::sync::{Arc, Mutex};
+        use cassette::exit_status;
+        use CliVersion;
+
+        /// Keeps the arguments and session variable of each invocation.
+        struct Env(Arc<Mutex<Vec<String>>>);
+        impl Backend for Env {
+            fn run(&self, invocation: &Invocation) -> io::Result<Output> {
+                let args = invocation.args().iter().map(|a| a.to_string_lossy()).collect::<Vec<_>>().join(" ");
+                let var = invocation.session_var().map(|(name, token)| format!("{}={}", name, token.expose()));
+                self.0.lock().unwrap().push(format!("{} [{}]", args, var.unwrap_or_default()));
+                Ok(Output { status: exit_status(0), stdout: b"hunter2".to_vec(), stderr: Vec::new() })
+            }
+        }
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let op = Op::new("op").with_backend(Env(seen.clone())).with_cli_version(CliVersion::new(2, 24, 0));
+        op.session("TOKEN").with_env_var("OP_SESSION_my").read("op://Ops/DB/password").unwrap();
+        op.session("TOKEN").read("op://Ops/DB/password").unwrap();
+        let seen = seen.lock().unwrap();
+        assert_eq!(*seen, vec![
+            "read op://Ops/DB/password --no-newline [OP_SESSION_my=TOKEN]",
+            "read --session=TOKEN op://Ops/DB/password --no-newline []",
+        ]);
+    }
+}
@@ -0,0 +1,297 @@
+//! Conversions between the item JSON of `op` 1.x and 2.x.
+//!
+//! `op` 1.x prints items with `uuid`, an `overview` and `details.fields`
+//! whose `designation` marks the username and password, plus
+//! `details.sections`. `op` 2.x prints a flat `fields` list where `purpose`
+//! marks the username, password and notes and `section` refers to an entry of
+//! `sections`. These functions map one onto the other, so code written against
+//! either shape keeps working when `op` is upgraded.
+//!
+//! Properties without a counterpart in the other shape are dropped.
+//!
+//! # Example
+//!
+//! ```
+//! # extern crate one_password;
+//! # #[macro_use] extern crate serde_json;
+//! use one_password::compat;
+//!
+//! # fn main() {
+//! let v2 = json!({
+//!     "id": "abc", "title": "Example", "category": "LOGIN", "vault": {"id": "v"},
+//!     "fields": [{"id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password", "value": "hunter2"}]
+//! });
+//! let v1 = compat::v2_to_v1(&v2);
+//! assert_eq!(v1["details"]["fields"][0]["designation"], "password");
+//! # }
+//! ```
+use serde_json::{Map, Value};
+
+use summary::{category, template_uuid};
+use super::{OpItem, Result};
+
+/// Types of `details.fields` in `op` 1.x and the matching `op` 2.x field type.
+const FIELD_TYPES: &[(&str, &str)] = &[
+    ("T", "STRING"),
+    ("P", "CONCEALED"),
+    ("E", "EMAIL"),
+    ("U", "URL"),
+];
+
+/// Kinds of section fields in `op` 1.x and the matching `op` 2.x field type.
+const SECTION_FIELD_TYPES: &[(&str, &str)] = &[
+    ("string", "STRING"),
+    ("concealed", "CONCEALED"),
+    ("email", "EMAIL"),
+    ("URL", "URL"),
+    ("date", "DATE"),
+    ("monthYear", "MONTH_YEAR"),
+    ("phone", "PHONE"),
+    ("menu", "MENU"),
+    ("address", "ADDRESS"),
+    ("cctype", "CREDIT_CARD_TYPE"),
+    ("ccnum", "CREDIT_CARD_NUMBER"),
+    ("gender", "GENDER"),
+    ("reference", "REFERENCE"),
+];
+
+/// `op` 1.x names one-time password fields `TOTP_…` and types them concealed.
+const TOTP_PREFIX: &str = "TOTP_";
+
+fn v2_type(table: &'static [(&'static str, &'static str)], v1: &str) -> &'static str {
+    table.iter().find(|&&(t, _)| t == v1).map_or("STRING", |&(_, t)| t)
+}
+
+fn v1_type(table: &'static [(&'static str, &'static str)], v2: &str) -> &'static str {
+    table.iter().find(|&&(_, t)| t == v2).map_or(table[0].0, |&(t, _)| t)
+}
+
+fn str_of<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or("")
+}
+
+fn array_of<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value.get(key).and_then(Value::as_array).map_or(&[], |a| &a[..])
+}
+
+/// Copy `from[from_key]` to `to[to_key]` if it is set.
+fn copy(from: &Value, from_key: &str, to: &mut Map<String, Value>, to_key: &str) {
+    if let Some(value) = from.get(from_key).filter(|v| !v.is_null()) {
+        to.insert(to_key.to_owned(), value.clone());
+    }
+}
+
+/// Convert an item printed by `op` 1.x to the shape `op` 2.x prints.
+pub fn v1_to_v2(item: &Value) -> Value {
+    let overview = item.get("overview").cloned().unwrap_or(Value::Null);
+    let details = item.get("details").cloned().unwrap_or(Value::Null);
+    let mut v2 = Map::new();
+    copy(item, "uuid", &mut v2, "id");
+    v2.insert("title".to_owned(), json!(str_of(&overview, "title")));
+    copy(item, "itemVersion", &mut v2, "version");
+    v2.insert("vault".to_owned(), json!({"id": str_of(item, "vaultUuid")}));
+    v2.insert("category".to_owned(), json!(category(str_of(item, "templateUuid").to_owned())));
+    copy(item, "changerUuid", &mut v2, "last_edited_by");
+    copy(item, "createdAt", &mut v2, "created_at");
+    copy(item, "updatedAt", &mut v2, "updated_at");
+    copy(&overview, "tags", &mut v2, "tags");
+    if item.get("favIndex").is_some_and(|f| !f.is_null()) {
+        v2.insert("favorite".to_owned(), json!(true));
+    }
+    let primary = str_of(&overview, "url");
+    let urls : Vec<Value> = array_of(&overview, "URLs").iter()
+        .map(|u| json!({"label": str_of(u, "l"), "href": str_of(u, "u"), "primary": str_of(u, "u") == primary}))
+        .collect();
+    if !urls.is_empty() {
+        v2.insert("urls".to_owned(), json!(urls));
+    }
+
+    let mut fields = Vec::new();
+    for field in array_of(&details, "fields") {
+        let designation = str_of(field, "designation");
+        let name = if designation.is_empty() { str_of(field, "name") } else { designation };
+        let mut converted = json!({
+            "id": name,
+            "type": v2_type(FIELD_TYPES, str_of(field, "type")),
+            "label": name,
+            "value": str_of(field, "value"),
+        });
+        if designation == "username" || designation == "password" {
+            converted["purpose"] = json!(designation.to_uppercase());
+        }
+        fields.push(converted);
+    }
+    if let Some(password) = details.get("password").and_then(Value::as_str) {
+        fields.push(json!({"id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password", "value": password}));
+    }
+    if let Some(notes) = details.get("notesPlain").and_then(Value::as_str) {
+        fields.push(json!({"id": "notesPlain", "type": "STRING", "purpose": "NOTES", "label": "notesPlain", "value": notes}));
+    }
+    let mut sections = Vec::new();
+    for section in array_of(&details, "sections") {
+        let reference = json!({"id": str_of(section, "name"), "label": str_of(section, "title")});
+        for field in array_of(section, "fields") {
+            let (kind, name) = (str_of(field, "k"), str_of(field, "n"));
+            let field_type = if kind == "concealed" && name.starts_with(TOTP_PREFIX) {
+                "OTP"
+            } else {
+                v2_type(SECTION_FIELD_TYPES, kind)
+            };
+            let mut converted = json!({"id": name, "type": field_type, "label": str_of(field, "t"), "section": reference});
+            converted["value"] = field.get("v").cloned().unwrap_or_else(|| json!(""));
+            fields.push(converted);
+        }
+        sections.push(reference);
+    }
+    if !sections.is_empty() {
+        v2.insert("sections".to_owned(), json!(sections));
+    }
+    v2.insert("fields".to_owned(), json!(fields));
+    Value::Object(v2)
+}
+
+/// Convert an item printed by `op` 2.x to the shape `op` 1.x prints, which
+/// `OpItem` models.
+pub fn v2_to_v1(item: &Value) -> Value {
+    let fields = array_of(item, "fields");
+    let purpose = |p: &str| fields.iter().find(|f| str_of(f, "purpose") == p);
+    let category = str_of(item, "category");
+
+    let mut overview = Map::new();
+    overview.insert("title".to_owned(), json!(str_of(item, "title")));
+    overview.insert("ainfo".to_owned(), json!(purpose("USERNAME").map_or("", |f| str_of(f, "value"))));
+    copy(item, "tags", &mut overview, "tags");
+    let urls = array_of(item, "urls");
+    if !urls.is_empty() {
+        let primary = urls.iter().find(|u| u.get("primary") == Some(&json!(true))).unwrap_or(&urls[0]);
+        overview.insert("url".to_owned(), json!(str_of(primary, "href")));
+        let urls : Vec<Value> = urls.iter().map(|u| json!({"l": str_of(u, "label"), "u": str_of(u, "href")})).collect();
+        overview.insert("URLs".to_owned(), json!(urls));
+    }
+
+    let mut details = Map::new();
+    let mut plain = Vec::new();
+    let mut sections : Vec<(Value, Vec<Value>)> = array_of(item, "sections").iter()
+        .map(|s| (json!({"name": str_of(s, "id"), "title": str_of(s, "label")}), Vec::new()))
+        .collect();
+    for field in fields {
+        let (id, field_type, field_purpose) = (str_of(field, "id"), str_of(field, "type"), str_of(field, "purpose"));
+        if field_purpose == "NOTES" {
+            details.insert("notesPlain".to_owned(), json!(str_of(field, "value")));
+        } else if let Some(section) = field.get("section").filter(|s| !s.is_null()) {
+            let section_id = str_of(section, "id");
+            let kind = if field_type == "OTP" { "concealed" } else { v1_type(SECTION_FIELD_TYPES, field_type) };
+            let converted = json!({"k": kind, "n": id, "t": str_of(field, "label"), "v": field.get("value").cloned().unwrap_or_else(|| json!(""))});
+            match sections.iter_mut().find(|&&mut (ref s, _)| str_of(s, "name") == section_id) {
+                Some(&mut (_, ref mut section_fields)) => section_fields.push(converted),
+                None => sections.push((json!({"name": section_id, "title": str_of(section, "label")}), vec![converted])),
+            }
+        } else if category == "PASSWORD" && field_purpose == "PASSWORD" {
+            details.insert("password".to_owned(), json!(str_of(field, "value")));
+        } else {
+            let mut converted = json!({
+                "name": if field_purpose.is_empty() { str_of(field, "label") } else { id },
+                "type": v1_type(FIELD_TYPES, field_type),
+                "value": str_of(field, "value"),
+            });
+            if !field_purpose.is_empty() {
+                converted["designation"] = json!(field_purpose.to_lowercase());
+            }
+            plain.push(converted);
+        }
+    }
+    if !details.contains_key("password") {
+        details.insert("fields".to_owned(), json!(plain));
+    }
+    if !sections.is_empty() {
+        let sections : Vec<Value> = sections.into_iter().map(|(mut section, fields)| {
+            if !fields.is_empty() {
+                section["fields"] = json!(fields);
+            }
+            section
+        }).collect();
+        details.insert("sections".to_owned(), json!(sections));
+    }
+
+    let mut v1 = Map::new();
+    v1.insert("uuid".to_owned(), json!(str_of(item, "id")));
+    v1.insert("templateUuid".to_owned(), json!(template_uuid(category)));
+    v1.insert("vaultUuid".to_owned(), json!(item.get("vault").map_or("", |v| str_of(v, "id"))));
+    v1.insert("changerUuid".to_owned(), json!(str_of(item, "last_edited_by")));
+    copy(item, "created_at", &mut v1, "createdAt");
+    copy(item, "updated_at", &mut v1, "updatedAt");
+    copy(item, "version", &mut v1, "itemVersion");
+    if item.get("favorite") == Some(&json!(true)) {
+        v1.insert("favIndex".to_owned(), json!(1));
+    }
+    v1.insert("overview".to_owned(), Value::Object(overview));
+    v1.insert("details".to_owned(), Value::Object(details));
+    Value::Object(v1)
+}
+
+impl OpItem {
+    /// Parse an item printed by `op` 2.x, e.g. with `op item get --format json`.
+    pub fn from_v2(item: &Value) -> Result<OpItem> {
+        Ok(::serde_json::from_value(v2_to_v1(item))?)
+    }
+
+    /// The item in the shape `op` 2.x prints.
+    pub fn to_v2(&self) -> Value {
+        v1_to_v2(&::serde_json::to_value(self).expect("items always serialize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_v1_to_v2() {
+        let v1 = json!({
+            "uuid": "abc", "templateUuid": "001", "vaultUuid": "v", "changerUuid": "u", "itemVersion": 3,
+            "overview": {"title": "Example", "ainfo": "jane", "url": "https://example.com",
+                "URLs": [{"l": "website", "u": "https://example.com"}], "tags": ["web"]},
+            "details": {
+                "fields": [
+                    {"designation": "username", "name": "username", "type": "T", "value": "jane"},
+                    {"designation": "password", "name": "password", "type": "P", "value": "hunter2"},
+                ],
+                "notesPlain": "note",
+                "sections": [{"name": "s1", "title": "Extra", "fields": [
+                    {"k": "concealed", "n": "TOTP_1", "t": "one-time password", "v": "otpauth://totp/x"},
+                    {"k": "date", "n": "d1", "t": "expires", "v": 1700000000},
+                ]}],
+            },
+        });
+        let v2 = v1_to_v2(&v1);
+        assert_eq!(v2["id"], "abc");
+        assert_eq!(v2["category"], "LOGIN");
+        assert_eq!(v2["version"], 3);
+        assert_eq!(v2["urls"][0]["primary"], true);
+        assert_eq!(v2["fields"][0]["purpose"], "USERNAME");
+        assert_eq!(v2["fields"][1]["type"], "CONCEALED");
+        assert_eq!(v2["fields"][2]["purpose"], "NOTES");
+        assert_eq!(v2["fields"][3]["type"], "OTP");
+        assert_eq!(v2["fields"][4]["section"]["label"], "Extra");
+
+        let back = v2_to_v1(&v2);
+        assert_eq!(back["templateUuid"], "001");
+        assert_eq!(back["details"]["fields"], v1["details"]["fields"]);
+        assert_eq!(back["details"]["notesPlain"], "note");
+        assert_eq!(back["details"]["sections"], v1["details"]["sections"]);
+        assert_eq!(back["overview"]["URLs"], v1["overview"]["URLs"]);
+    }
+
+    #[test]
+    fn parses_v2_items() {
+        let v2 = json!({
+            "id": "p1", "title": "Router", "category": "PASSWORD", "vault": {"id": "v", "name": "Home"},
+            "fields": [{"id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password", "value": "hunter2"}],
+        });
+        let item = OpItem::from_v2(&v2).unwrap();
+        assert_eq!(item.uuid, "p1");
+        assert_eq!(item.password(), Some("hunter2".to_owned()));
+        assert_eq!(item.to_v2()["fields"][0]["purpose"], "PASSWORD");
+        assert_eq!(item.to_v2()["category"], "PASSWORD");
+    }
+}
@@ -0,0 +1,151 @@
+use std::env;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use serde_json;
+
+use super::Result;
+
+/// Account that `op` has been configured with, as stored in its config file.
+///
+/// The secret key stored alongside each account in the config file is never read.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OpAccount {
+    pub shorthand: String,
+    pub url: String,
+    pub email: String,
+    /// UUID of the account. Only written by `op` 2.x.
+    #[serde(rename = "accountUUID", default)]
+    pub account_uuid: Option<String>,
+    #[serde(rename = "userUUID", default)]
+    pub user_uuid: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct OpConfig {
+    #[serde(default)]
+    accounts: Vec<OpAccount>,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Config files `op` may have written, most current first.
+pub fn config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(dir) = env::var_os("OP_CONFIG_DIR") {
+        paths.push(Path::new(&dir).join("config"));
+    }
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        paths.push(Path::new(&dir).join("op").join("config"));
+    }
+    if let Some(home) = home_dir() {
+        paths.push(home.join(".config").join("op").join("config"));
+        paths.push(home.join(".op").join("config"));
+    }
+    paths.dedup();
+    paths
+}
+
+/// Read the accounts listed in a single `op` config file.
+pub fn read_accounts<P: AsRef<Path>>(path: P) -> Result<Vec<OpAccount>> {
+    let file = File::open(path)?;
+    let config : OpConfig = serde_json::from_reader(file)?;
+    Ok(config.accounts)
+}
+
+/// Read accounts from every config file that exists, skipping accounts
+/// already found in a more current file.
+///
+/// Files that can't be read or parsed, e.g. one left behind by an old `op`,
+/// are skipped rather than hiding the accounts in the others; use
+/// `read_accounts` to find out what is wrong with one.
+pub fn configured_accounts() -> Result<Vec<OpAccount>> {
+    Ok(accounts_in(config_paths()))
+}
+
+fn accounts_in<I: IntoIterator<Item = PathBuf>>(paths: I) -> Vec<OpAccount> {
+    let mut accounts : Vec<OpAccount> = Vec::new();
+    for path in paths {
+        if !path.is_file() {
+            continue;
+        }
+        for account in read_accounts(&path).unwrap_or_default() {
+            if !accounts.iter().any(|a| a.url == account.url && a.email == account.email) {
+                accounts.push(account);
+            }
+        }
+    }
+    accounts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn parse_v1_config() {
+        let json = r#"{
+            "accounts": [{
+                "shorthand": "my",
+                "url": "https://my.1password.com",
+                "email": "user@example.com",
+                "accountKey": "A3-XXXXXX",
+                "userUUID": "ABCDEF"
+            }],
+            "latest_signin": "my"
+        }"#;
+        let config : OpConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.accounts, vec![OpAccount {
+            shorthand: "my".to_owned(),
+            url: "https://my.1password.com".to_owned(),
+            email: "user@example.com".to_owned(),
+            account_uuid: None,
+            user_uuid: Some("ABCDEF".to_owned()),
+        }]);
+    }
+
+    #[test]
+    fn parse_v2_config() {
+        let json = r#"{
+            "latest_signin": "acme",
+            "device": "devid",
+            "accounts": [{
+                "shorthand": "acme",
+                "accountUUID": "ACCOUNTUUID",
+                "url": "https://acme.1password.com",
+                "email": "user@acme.com",
+                "accountKey": "A3-XXXXXX",
+                "userUUID": "USERUUID"
+            }]
+        }"#;
+        let config : OpConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.accounts[0].account_uuid, Some("ACCOUNTUUID".to_owned()));
+        assert_eq!(config.accounts[0].shorthand, "acme");
+    }
+
+    #[test]
+    fn parse_empty_config() {
+        let config : OpConfig = serde_json::from_str("{}").unwrap();
+        assert!(config.accounts.is_empty());
+    }
+
+    #[test]
+    fn skips_malformed_configs() {
+        let dir = env::temp_dir().join(format!("op-config-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (broken, good) = (dir.join("broken"), dir.join("good"));
+        fs::write(&broken, "{\"accounts\": [").unwrap();
+        fs::write(&good, r#"{"accounts": [{"shorthand": "my", "url": "https://my.1password.com", "email": "user@example.com"}]}"#)
+            .unwrap();
+        assert!(read_accounts(&broken).is_err());
+        let accounts = accounts_in(vec![broken, dir.join("missing"), good]);
+        assert_eq!(accounts.iter().map(|a| &a.shorthand[..]).collect::<Vec<_>>(), ["my"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
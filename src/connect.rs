@@ -0,0 +1,116 @@
+use super::{Error, ErrorKind, OpSession, Result, Secret};
+
+/// Outcome of `OpSession::rotate_connect_token` once the new token works.
+#[derive(Debug)]
+pub struct ConnectRotation {
+    /// The new token, already handed to `verify`.
+    pub token: Secret<String>,
+    /// Whether revoking the old token succeeded. If not, both tokens are
+    /// valid until the old one is revoked with `delete_connect_token`.
+    pub old_token_revoked: Result<()>,
+}
+
+impl OpSession {
+    /// Issue a new access token named `name` for the Connect server `server`
+    /// with access to `vaults`, using `op connect token create`.
+    ///
+    /// Vaults can include permissions as understood by `op`, e.g. `Production,r`.
+    pub fn create_connect_token(&self, server: &str, name: &str, vaults: &[&str]) -> Result<Secret<String>> {
+        let mut command = self.command(&["connect", "token", "create"]);
+        command.target(name).arg("--server").arg(server);
+        for vault in vaults {
+            command.arg("--vault").arg(vault);
+        }
+        let stdout = command.run_or(ErrorKind::CommandFailed)?;
+        let token = self.config.utf8_policy.decode("stdout", stdout)?;
+        Ok(self.config.secret(token.trim().to_owned()))
+    }
+
+    /// Revoke the Connect token with the given name or ID, using `op connect token delete`.
+    pub fn delete_connect_token(&self, server: &str, token: &str) -> Result<()> {
+        self.command(&["connect", "token", "delete"])
+            .target(token).arg("--server").arg(server)
+            .run_or(ErrorKind::CommandFailed)?;
+        Ok(())
+    }
+
+    /// Replace the Connect token `old_token` (a name or ID) with a new one named `new_name`.
+    ///
+    /// The new token is created first and handed to `verify`, which should make
+    /// a request to the Connect server with it and return whether that
+    /// succeeded. Only then is the old token revoked. If verification fails,
+    /// the new token is revoked instead, the old one is left alone and this
+    /// fails with `ErrorKind::ConnectTokenRejected`.
+    ///
+    /// Once the new token is verified it is returned even if revoking the old
+    /// one fails, so it isn't lost; check `ConnectRotation::old_token_revoked`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// # fn check_connect(_: &str) -> bool { true }
+    /// let session = Op::which().unwrap().env_session().unwrap();
+    /// let rotation = session.rotate_connect_token("Prod Connect", "deploy-2018-10", &["Production,r"], "deploy-2018-09",
+    ///     |token| check_connect(token.expose())).unwrap();
+    /// if let Err(err) = rotation.old_token_revoked {
+    ///     eprintln!("deploy-2018-09 is still valid: {}", err);
+    /// }
+    /// ```
+    pub fn rotate_connect_token<F>(&self, server: &str, new_name: &str, vaults: &[&str], old_token: &str, verify: F) -> Result<ConnectRotation>
+        where F: FnOnce(&Secret<String>) -> bool
+    {
+        let token = self.create_connect_token(server, new_name, vaults)?;
+        if !verify(&token) {
+            let rejected = ErrorKind::ConnectTokenRejected(new_name.to_owned());
+            return Err(match self.delete_connect_token(server, new_name) {
+                Ok(()) => rejected.into(),
+                Err(err) => Error::with_chain(err, rejected),
+            });
+        }
+        let old_token_revoked = self.delete_connect_token(server, old_token);
+        Ok(ConnectRotation { token, old_token_revoked })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassette::{interaction, Cassette, Interaction, Replay};
+    use {ErrorKind, Op};
+
+    fn token(subcommand: &str, token: &str, stdout: &str) -> Interaction {
+        interaction(subcommand, &["--session=⟨redacted⟩", token, "--server", "Connect"], stdout)
+    }
+
+    #[test]
+    fn rotates_only_verified_tokens() {
+        let cassette = Cassette {
+            interactions: vec![
+                token("connect token create", "new", "eyJnew\n"),
+                token("connect token delete", "old", ""),
+                token("connect token create", "bad", "eyJbad\n"),
+                token("connect token delete", "bad", ""),
+                token("connect token create", "newer", "eyJnewer\n"),
+                token("connect token delete", "new", "").failing("[ERROR] token not found", 1),
+            ],
+        };
+        let op = Op::new("op").with_backend(Replay::new(cassette));
+        let session = op.session("token");
+
+        let rotation = session.rotate_connect_token("Connect", "new", &[], "old", |t| t.expose() == "eyJnew").unwrap();
+        assert_eq!(rotation.token.expose(), "eyJnew");
+        assert!(rotation.old_token_revoked.is_ok());
+
+        let err = session.rotate_connect_token("Connect", "bad", &[], "new", |_| false).unwrap_err();
+        match *err.kind() {
+            ErrorKind::ConnectTokenRejected(ref name) => assert_eq!(name, "bad"),
+            ref other => panic!("unexpected error {:?}", other),
+        }
+
+        let rotation = session.rotate_connect_token("Connect", "newer", &[], "new", |_| true).unwrap();
+        assert_eq!(rotation.token.expose(), "eyJnewer");
+        assert!(rotation.old_token_revoked.is_err());
+    }
+}
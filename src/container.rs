@@ -0,0 +1,157 @@
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use backend::{piped, stream, Backend, Invocation};
+
+/// Backend that runs `op` inside a running container with `docker exec` or
+/// `podman exec`, for setups where `op` and its sign-in state only exist in
+/// the container.
+///
+/// The program of the `Op` is run in the container with the environment of
+/// the container. The locale, default account, session token and variables
+/// added with `env` are passed in with `-e NAME`, so their values never show up in the
+/// arguments of `docker`. Spawn hooks apply to the `docker` process. On a
+/// timeout `docker exec` is killed, which may leave `op` running in the
+/// container.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use one_password::{ContainerExec, Op};
+///
+/// let backend = ContainerExec::podman("op-vault")
+///     .env("OP_SERVICE_ACCOUNT_TOKEN", "VAULT_OP_TOKEN");
+/// let op = Op::new("/usr/local/bin/op").with_backend(backend);
+/// let session = op.service_account_session().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContainerExec {
+    runtime: PathBuf,
+    container: String,
+    user: Option<String>,
+    /// Variable in the container and the variable of this process it is set from.
+    env: Vec<(String, String)>,
+    /// Looks up the variables of this process, replaced in tests.
+    var: fn(&str) -> Option<OsString>,
+}
+
+fn var_os(name: &str) -> Option<OsString> {
+    env::var_os(name)
+}
+
+impl ContainerExec {
+    /// Run `op` in `container`, by name or id, with `docker`.
+    pub fn docker(container: &str) -> ContainerExec {
+        ContainerExec::new("docker", container)
+    }
+
+    /// Run `op` in `container`, by name or id, with `podman`.
+    pub fn podman(container: &str) -> ContainerExec {
+        ContainerExec::new("podman", container)
+    }
+
+    /// Run `op` in `container` with `runtime`, any command that takes the
+    /// arguments of `docker exec`.
+    pub fn new<P: AsRef<Path>>(runtime: P, container: &str) -> ContainerExec {
+        ContainerExec {
+            runtime: runtime.as_ref().to_owned(),
+            container: container.to_owned(),
+            user: None,
+            env: Vec::new(),
+            var: var_os,
+        }
+    }
+
+    /// Run `op` as `user` in the container, passed to `--user`.
+    pub fn user(mut self, user: &str) -> ContainerExec {
+        self.user = Some(user.to_owned());
+        self
+    }
+
+    /// Set the variable `name` in the container to the value of the variable
+    /// `from` of this process, if it is set.
+    pub fn env(mut self, name: &str, from: &str) -> ContainerExec {
+        self.env.push((name.to_owned(), from.to_owned()));
+        self
+    }
+
+    /// `docker exec` running `invocation`, `-it` if `interactive`.
+    fn command(&self, invocation: &Invocation, interactive: bool) -> Command {
+        let mut command = Command::new(&self.runtime);
+        command.arg("exec").arg(if interactive { "-it" } else { "-i" });
+        if let Some(ref user) = self.user {
+            command.arg("--user").arg(user);
+        }
+        let mut vars : Vec<(&str, OsString)> = self.env.iter()
+            .filter_map(|(name, from)| (self.var)(from).map(|value| (&name[..], value)))
+            .collect();
+        if let Some(locale) = invocation.locale() {
+            vars.push(("LC_ALL", locale.to_owned()));
+            vars.push(("LANG", locale.to_owned()));
+        }
+        if let Some(account) = invocation.account() {
+            vars.push(("OP_ACCOUNT", account.into()));
+        }
+        if let Some((name, token)) = invocation.session_var() {
+            vars.push((name, token.expose().into()));
+        }
+        for (name, value) in vars {
+            command.arg("-e").arg(name).env(name, value);
+        }
+        command.arg(&self.container).arg(invocation.program()).args(invocation.args());
+        command
+    }
+}
+
+impl Backend for ContainerExec {
+    fn run(&self, invocation: &Invocation) -> io::Result<Output> {
+        let mut stdout = Vec::new();
+        let output = self.run_to(invocation, &mut stdout)?;
+        Ok(Output { stdout, ..output })
+    }
+
+    fn run_interactive(&self, invocation: &Invocation) -> io::Result<Output> {
+        let mut command = self.command(invocation, true);
+        command.stdin(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+        invocation.spawn(&mut command)?.wait_with_output()
+    }
+
+    fn run_to(&self, invocation: &Invocation, stdout: &mut dyn Write) -> io::Result<Output> {
+        let mut data = invocation.stdin();
+        let input = data.as_mut().map(|d| d as &mut (dyn Read + Send));
+        let child = invocation.spawn(piped(&mut self.command(invocation, false), input.is_some()))?;
+        stream(child, input, stdout, invocation.timeout())
+    }
+
+    fn run_from(&self, invocation: &Invocation, stdin: &mut (dyn Read + Send)) -> io::Result<Output> {
+        let mut stdout = Vec::new();
+        let child = invocation.spawn(piped(&mut self.command(invocation, false), true))?;
+        let output = stream(child, Some(stdin), &mut stdout, invocation.timeout())?;
+        Ok(Output { stdout, ..output })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Op;
+
+    #[cfg(unix)]
+    #[test]
+    fn execs_in_container() {
+        let mut backend = ContainerExec::new("echo", "vault")
+            .user("op")
+            .env("OP_SERVICE_ACCOUNT_TOKEN", "VAULT_OP_TOKEN")
+            .env("OP_CONNECT_TOKEN", "VAULT_CONNECT_TOKEN");
+        backend.var = |name| if name == "VAULT_OP_TOKEN" { Some("hunter2".into()) } else { None };
+        let op = Op::builder().binary("/usr/bin/op").account("my").backend(backend).build().unwrap();
+        assert_eq!(op.version().unwrap(),
+            "exec -i --user op -e OP_SERVICE_ACCOUNT_TOKEN -e LC_ALL -e LANG -e OP_ACCOUNT vault /usr/bin/op --version");
+    }
+}
@@ -0,0 +1,256 @@
+#[cfg(feature = "process")]
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use serde_json;
+
+#[cfg(feature = "process")]
+use config;
+use super::{Error, ErrorCategory, ErrorKind};
+#[cfg(feature = "process")]
+use super::{Op, Result};
+
+/// Everything known about a failed `op` invocation.
+///
+/// Arguments are redacted so this can be logged or attached to bug reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandDiagnostics {
+    /// Path to the `op` binary that was run.
+    pub program: PathBuf,
+    /// Subcommand that was run, e.g. `get item`.
+    pub subcommand: String,
+    /// Arguments after the subcommand with secrets redacted.
+    pub args: Vec<String>,
+    pub status: ExitStatus,
+    pub stderr: String,
+    pub duration: Duration,
+    /// Category derived from `stderr` and the exit code.
+    pub category: ErrorCategory,
+}
+
+impl fmt::Display for CommandDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{} {}", self.program.display(), self.subcommand)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        write!(f, "` failed with {} after {}ms: {}",
+            self.status, self.duration.as_millis(), self.stderr.trim())
+    }
+}
+
+impl Error {
+    /// Diagnostics for the failed `op` invocation that caused this error, if any.
+    pub fn diagnostics(&self) -> Option<&CommandDiagnostics> {
+        match *self.kind() {
+            ErrorKind::CommandFailed(ref d) |
+            ErrorKind::GetCommand(_, ref d) |
+            ErrorKind::VersionCommand(ref d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Category of the failed `op` invocation that caused this error, if any.
+    pub fn category(&self) -> Option<ErrorCategory> {
+        self.diagnostics().map(|d| d.category)
+    }
+
+    /// JSON `op` printed that didn't match the typed model, if that is what
+    /// caused this error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// # #[cfg(feature = "process")]
+    /// # fn main() {
+    /// use one_password::Op;
+    ///
+    /// let session = Op::which().unwrap().env_session().unwrap();
+    /// match session.get_item("db") {
+    ///     Ok(item) => println!("{}", item.overview.title),
+    ///     Err(err) => match err.raw_json() {
+    ///         Some(raw) => println!("{}", raw["overview"]["title"]),
+    ///         None => panic!("{}", err),
+    ///     },
+    /// }
+    /// # }
+    /// # #[cfg(not(feature = "process"))]
+    /// # fn main() {}
+    /// ```
+    pub fn raw_json(&self) -> Option<&serde_json::Value> {
+        match *self.kind() {
+            ErrorKind::SchemaMismatch(_, _, ref raw) => Some(&raw.0),
+            _ => None,
+        }
+    }
+}
+
+/// A failed `op` invocation in a `DiagnosticsBundle`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FailedCommand {
+    pub subcommand: String,
+    /// Arguments after the subcommand with secrets redacted.
+    pub args: Vec<String>,
+    /// `None` if `op` was killed by a signal.
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+    pub duration_ms: u64,
+    /// E.g. `NotSignedIn`, see `ErrorCategory`.
+    pub category: String,
+}
+
+impl<'a> From<&'a CommandDiagnostics> for FailedCommand {
+    fn from(d: &'a CommandDiagnostics) -> FailedCommand {
+        FailedCommand {
+            subcommand: d.subcommand.clone(),
+            args: d.args.clone(),
+            exit_code: d.status.code(),
+            stderr: d.stderr.clone(),
+            duration_ms: d.duration.as_millis() as u64,
+            category: format!("{:?}", d.category),
+        }
+    }
+}
+
+/// A config file `op` may read.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub exists: bool,
+}
+
+/// Information about the environment this crate runs `op` in, returned by
+/// `Op::diagnostics` to attach to bug reports.
+///
+/// It holds no secrets: environment variables are listed by name only, config
+/// files by path, and arguments of the last failed command are redacted. The
+/// stderr of that command is included as is and may name items or vaults.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticsBundle {
+    /// Version of this crate.
+    pub crate_version: String,
+    pub op_path: PathBuf,
+    /// Output of `op --version`, if it could be run.
+    pub op_version: Option<String>,
+    /// Why `op --version` failed, if it did.
+    pub op_version_error: Option<String>,
+    /// E.g. `linux`, `macos` or `windows`.
+    pub os: String,
+    pub arch: String,
+    /// Names of the `OP_*` environment variables that are set.
+    pub env_vars: Vec<String>,
+    pub config_files: Vec<ConfigFile>,
+    /// The most recent `op` invocation that failed, if any.
+    pub last_failure: Option<FailedCommand>,
+}
+
+impl DiagnosticsBundle {
+    /// Pretty printed JSON, ready to paste into an issue.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("diagnostics always serialize")
+    }
+}
+
+#[cfg(feature = "process")]
+/// Names of the set environment variables relevant to `op`.
+fn op_env_vars<I: Iterator<Item = String>>(names: I) -> Vec<String> {
+    let mut names : Vec<String> = names.filter(|n| n.starts_with("OP_")).collect();
+    names.sort();
+    names
+}
+
+#[cfg(feature = "process")]
+impl Op {
+    pub(crate) fn record_failure(&self, diagnostics: &CommandDiagnostics) {
+        let mut last = match self.last_failure.lock() {
+            Ok(last) => last,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        *last = Some(diagnostics.clone());
+    }
+
+    /// The most recent `op` invocation that failed, by this `Op`, its clones
+    /// or sessions created from them.
+    pub fn last_failure(&self) -> Option<CommandDiagnostics> {
+        match self.last_failure.lock() {
+            Ok(last) => last.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Gather what is needed to reproduce a problem into a bundle that can be
+    /// attached to a bug report without leaking secrets.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// let op = Op::which().unwrap();
+    /// if let Err(err) = op.env_session().and_then(|s| s.get_item("abc")) {
+    ///     eprintln!("{}\n{}", err, op.diagnostics().to_json());
+    /// }
+    /// ```
+    pub fn diagnostics(&self) -> DiagnosticsBundle {
+        let (op_version, op_version_error) = match self.version() {
+            Ok(version) => (Some(version), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        let env_vars = env::vars_os().filter_map(|(name, _)| name.into_string().ok());
+        DiagnosticsBundle {
+            crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+            op_path: self.command().to_owned(),
+            op_version,
+            op_version_error,
+            os: env::consts::OS.to_owned(),
+            arch: env::consts::ARCH.to_owned(),
+            env_vars: op_env_vars(env_vars),
+            config_files: config::config_paths().into_iter()
+                .map(|path| ConfigFile { exists: path.is_file(), path })
+                .collect(),
+            last_failure: self.last_failure().as_ref().map(FailedCommand::from),
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+/// Turn a failure because something doesn't exist into `None`.
+pub(crate) fn not_found<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(ref err) if err.category() == Some(ErrorCategory::NotFound) => Ok(None),
+        Err(Error(ErrorKind::FieldNotFound(..), _)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(all(test, feature = "process"))]
+mod tests {
+    use super::*;
+    use cassette::{interaction, Cassette, Replay};
+
+    #[test]
+    fn bundles_without_secrets() {
+        let cassette = Cassette { interactions: vec![
+            interaction("--version", &[], "2.24.0\n"),
+            interaction("get item", &["--session=⟨redacted⟩", "abc"], "").failing("[ERROR] Item abc not found.", 1),
+        ] };
+        let op = Op::new("op").with_backend(Replay::new(cassette));
+        assert!(op.last_failure().is_none());
+        assert!(op.session("TOKEN").get_item("abc").is_err());
+
+        let bundle = op.diagnostics();
+        assert_eq!(bundle.op_version, Some("2.24.0".to_owned()));
+        let failure = bundle.last_failure.as_ref().unwrap();
+        assert_eq!(failure.exit_code, Some(1));
+        assert_eq!(failure.category, "NotFound");
+        assert!(!bundle.to_json().contains("TOKEN"));
+        assert_eq!(op_env_vars(vec!["PATH".to_owned(), "OP_SESSION_my".to_owned(), "OP_CLI_PATH".to_owned()].into_iter()),
+            vec!["OP_CLI_PATH", "OP_SESSION_my"]);
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use secret::REDACTED;
+use super::{FieldPath, OpItem, OpItemDetails};
+
+/// One field that differs between two items.
+///
+/// Values of concealed fields, such as passwords, are compared but replaced by
+/// a redaction marker here, so a diff can be logged or shown for review.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FieldChange {
+    pub field: String,
+    /// Value before, `None` if the field was added.
+    pub old: Option<String>,
+    /// Value after, `None` if the field was removed.
+    pub new: Option<String>,
+    pub concealed: bool,
+}
+
+/// Field by field comparison of two items, from `OpItem::diff`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ItemDiff {
+    pub changes: Vec<FieldChange>,
+}
+
+impl ItemDiff {
+    /// Whether the items are the same.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Fields only in the other item.
+    pub fn added(&self) -> impl Iterator<Item = &FieldChange> {
+        self.changes.iter().filter(|c| c.old.is_none())
+    }
+
+    /// Fields only in this item.
+    pub fn removed(&self) -> impl Iterator<Item = &FieldChange> {
+        self.changes.iter().filter(|c| c.new.is_none())
+    }
+
+    /// Fields in both items, with different values.
+    pub fn changed(&self) -> impl Iterator<Item = &FieldChange> {
+        self.changes.iter().filter(|c| c.old.is_some() && c.new.is_some())
+    }
+}
+
+impl fmt::Display for ItemDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.changes {
+            match (&change.old, &change.new) {
+                (None, Some(new)) => writeln!(f, "+ {}: {}", change.field, new)?,
+                (Some(old), None) => writeln!(f, "- {}: {}", change.field, old)?,
+                (Some(old), Some(new)) => writeln!(f, "~ {}: {} -> {}", change.field, old, new)?,
+                (None, None) => {},
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Name, value and whether it is concealed, for every field of `item`.
+///
+/// Fields in sections are named by their `FieldPath`, `section/field`.
+fn fields(item: &OpItem) -> Vec<(String, String, bool)> {
+    let mut fields = vec![
+        ("title".to_owned(), item.overview.title.clone(), false),
+        ("ainfo".to_owned(), item.overview.ainfo.clone(), false),
+    ];
+    let extra = match item.details {
+        OpItemDetails::Password { ref password, ref extra } => {
+            fields.push(("password".to_owned(), password.clone(), true));
+            extra
+        },
+        OpItemDetails::Login { fields: ref login, ref extra } => {
+            for field in login {
+                let concealed = field.field_type == "P" || field.designation.as_ref().map(|d| &d[..]) == Some("password");
+                fields.push((field.name.clone(), field.value.clone(), concealed));
+            }
+            extra
+        },
+    };
+    if let Some(notes) = extra.get("notesPlain").and_then(Value::as_str) {
+        fields.push(("notesPlain".to_owned(), notes.to_owned(), false));
+    }
+    let sections = extra.get("sections").and_then(Value::as_array).map_or(&[][..], |s| &s[..]);
+    for section in sections {
+        let section_name = label(section, "title", "name");
+        for field in section.get("fields").and_then(Value::as_array).into_iter().flatten() {
+            let path = FieldPath::in_section(section_name, label(field, "t", "n"));
+            let value = match field.get("v") {
+                Some(Value::String(value)) => value.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(value) => value.to_string(),
+            };
+            let concealed = field.get("k").and_then(Value::as_str) == Some("concealed");
+            fields.push((path.to_string(), value, concealed));
+        }
+    }
+    fields
+}
+
+/// The `label` property of `object`, or `name` if the label is empty.
+fn label<'a>(object: &'a Value, label: &str, name: &str) -> &'a str {
+    let text = |key| object.get(key).and_then(Value::as_str).unwrap_or("");
+    match text(label) {
+        "" => text(name),
+        label => label,
+    }
+}
+
+fn shown(value: &str, concealed: bool) -> String {
+    if concealed { REDACTED.to_owned() } else { value.to_owned() }
+}
+
+impl OpItem {
+    /// Compare this item with `other`, e.g. the same item after an edit.
+    ///
+    /// Fields are matched by name, fields in sections by their `FieldPath`,
+    /// e.g. `Database/password`, and the notes as `notesPlain`. Changes are listed in the order the fields
+    /// appear in this item, followed by fields only in `other`.
+    pub fn diff(&self, other: &OpItem) -> ItemDiff {
+        let before = fields(self);
+        let after = fields(other);
+        let mut changes = Vec::new();
+        for &(ref name, ref old, concealed) in &before {
+            match after.iter().find(|&(n, _, _)| n == name) {
+                Some(&(_, ref new, new_concealed)) => if old != new {
+                    let concealed = concealed || new_concealed;
+                    changes.push(FieldChange {
+                        field: name.clone(),
+                        old: Some(shown(old, concealed)),
+                        new: Some(shown(new, concealed)),
+                        concealed,
+                    });
+                },
+                None => changes.push(FieldChange {
+                    field: name.clone(),
+                    old: Some(shown(old, concealed)),
+                    new: None,
+                    concealed,
+                }),
+            }
+        }
+        for &(ref name, ref new, concealed) in &after {
+            if !before.iter().any(|(n, _, _)| n == name) {
+                changes.push(FieldChange {
+                    field: name.clone(),
+                    old: None,
+                    new: Some(shown(new, concealed)),
+                    concealed,
+                });
+            }
+        }
+        ItemDiff { changes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use OpItem;
+
+    fn item(password: &str, extra: &str) -> OpItem {
+        serde_json::from_str(&format!(r#"{{
+            "uuid": "abc", "vaultUuid": "v", "changerUuid": "c",
+            "overview": {{"ainfo": "user", "title": "Example"}},
+            "details": {{"fields": [
+                {{"designation": "username", "name": "username", "type": "T", "value": "user"}},
+                {{"designation": "password", "name": "password", "type": "P", "value": "{}"}}
+                {}
+            ]}}
+        }}"#, password, extra)).unwrap()
+    }
+
+    #[test]
+    fn redacts_concealed_changes() {
+        let before = item("hunter2", r#", {"name": "pin", "type": "T", "value": "1234"}"#);
+        let after = item("hunter3", r#", {"name": "note", "type": "T", "value": "hi"}"#);
+        assert!(before.diff(&before).is_empty());
+        let diff = before.diff(&after);
+        assert_eq!(diff.to_string(), "~ password: ⟨redacted⟩ -> ⟨redacted⟩\n- pin: 1234\n+ note: hi\n");
+        assert_eq!(diff.changed().count(), 1);
+        assert_eq!(diff.removed().count(), 1);
+        assert_eq!(diff.added().count(), 1);
+    }
+
+    #[test]
+    fn compares_section_fields_and_notes() {
+        let with = |host: &str, pin: &str, notes: &str| -> OpItem {
+            serde_json::from_str(&format!(r#"{{
+                "uuid": "abc", "vaultUuid": "v", "changerUuid": "c",
+                "overview": {{"ainfo": "user", "title": "Example"}},
+                "details": {{"fields": [], "notesPlain": "{}", "sections": [{{"name": "s1", "title": "Database", "fields": [
+                    {{"k": "string", "n": "h1", "t": "host", "v": "{}"}},
+                    {{"k": "concealed", "n": "p1", "t": "", "v": "{}"}},
+                    {{"k": "date", "n": "d1", "t": "expires", "v": 1700000000}}
+                ]}}]}}
+            }}"#, notes, host, pin)).unwrap()
+        };
+        let before = with("db1", "1234", "old");
+        assert!(before.diff(&before).is_empty());
+        let diff = before.diff(&with("db2", "4321", "new"));
+        assert_eq!(diff.to_string(),
+            "~ notesPlain: old -> new\n~ Database/host: db1 -> db2\n~ Database/p1: ⟨redacted⟩ -> ⟨redacted⟩\n");
+    }
+}
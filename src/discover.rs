@@ -0,0 +1,218 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+use which;
+
+use super::{ErrorKind, Result};
+
+/// Environment variables naming the `op` binary to use, checked first by
+/// `Op::which` and `Op::discover`. `OP_CLI_PATH` wins if both are set.
+pub const OVERRIDE_VARS: [&str; 2] = ["OP_CLI_PATH", "OP_PATH"];
+
+/// File name of the `op` executable on this platform.
+pub const OP_EXECUTABLE: &str = if cfg!(windows) { "op.exe" } else { "op" };
+
+/// Standard Windows install locations for `op.exe`, looking up environment
+/// variables with `var`.
+#[cfg(any(windows, test))]
+fn windows_locations<F: Fn(&str) -> Option<OsString>>(var: F) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for program_files in &["ProgramFiles", "ProgramFiles(x86)"] {
+        if let Some(dir) = var(program_files) {
+            paths.push(PathBuf::from(&dir).join("1Password CLI").join("op.exe"));
+        }
+    }
+    if let Some(dir) = var("LOCALAPPDATA") {
+        paths.push(PathBuf::from(&dir).join("Microsoft").join("WinGet").join("Links").join("op.exe"));
+    }
+    if let Some(dir) = var("SCOOP") {
+        paths.push(PathBuf::from(&dir).join("shims").join("op.exe"));
+    }
+    if let Some(dir) = var("USERPROFILE") {
+        paths.push(PathBuf::from(&dir).join("scoop").join("shims").join("op.exe"));
+    }
+    paths
+}
+
+/// Places `op` is commonly installed that may not be in PATH.
+#[cfg(windows)]
+pub fn default_locations() -> Vec<PathBuf> {
+    windows_locations(|name| env::var_os(name))
+}
+
+/// Places `op` is commonly installed that may not be in PATH.
+#[cfg(not(windows))]
+pub fn default_locations() -> Vec<PathBuf> {
+    ["/usr/local/bin", "/opt/homebrew/bin", "/snap/bin", "/usr/bin"].iter()
+        .map(|dir| Path::new(dir).join(OP_EXECUTABLE))
+        .collect()
+}
+
+/// Controls where `Op::discover` looks for the `op` binary.
+///
+/// Locations are tried in this order:
+///
+/// 1. The path in an override environment variable (`OP_CLI_PATH` or
+///    `OP_PATH` by default). If the variable is set, the path it names must
+///    exist, so container images and test harnesses can pin exactly which
+///    `op` runs.
+/// 2. Directories added with `dir`.
+/// 3. The directories in `PATH`.
+/// 4. Common install locations such as `/usr/local/bin`, `/opt/homebrew/bin` and
+///    `/snap/bin`, or Program Files, winget and scoop on Windows.
+#[derive(Debug, Clone)]
+pub struct DiscoverOptions {
+    env_vars: Vec<OsString>,
+    dirs: Vec<PathBuf>,
+    search_path: bool,
+    default_locations: bool,
+}
+
+impl Default for DiscoverOptions {
+    fn default() -> DiscoverOptions {
+        DiscoverOptions {
+            env_vars: OVERRIDE_VARS.iter().map(OsString::from).collect(),
+            dirs: Vec::new(),
+            search_path: true,
+            default_locations: true,
+        }
+    }
+}
+
+impl DiscoverOptions {
+    pub fn new() -> DiscoverOptions {
+        DiscoverOptions::default()
+    }
+
+    /// Name of the environment variable that overrides all other locations,
+    /// instead of `OVERRIDE_VARS`.
+    pub fn env_var<S: Into<OsString>>(mut self, name: S) -> DiscoverOptions {
+        self.env_vars = vec![name.into()];
+        self
+    }
+
+    /// Don't look at any override environment variable.
+    pub fn no_env_var(mut self) -> DiscoverOptions {
+        self.env_vars.clear();
+        self
+    }
+
+    /// Look for `op` in `dir` before searching PATH.
+    pub fn dir<P: AsRef<Path>>(mut self, dir: P) -> DiscoverOptions {
+        self.dirs.push(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Whether to search the directories in PATH. Defaults to true.
+    pub fn search_path(mut self, search: bool) -> DiscoverOptions {
+        self.search_path = search;
+        self
+    }
+
+    /// Whether to check common install locations. Defaults to true.
+    pub fn default_locations(mut self, check: bool) -> DiscoverOptions {
+        self.default_locations = check;
+        self
+    }
+
+    /// Find `op` according to these options.
+    ///
+    /// The returned path is passed to `Command` as is, so install directories
+    /// containing spaces like `Program Files` need no quoting.
+    pub fn find(&self) -> Result<PathBuf> {
+        self.find_with(|name| env::var_os(name))
+    }
+
+    /// `find`, looking up environment variables with `var`.
+    fn find_with<F: Fn(&OsStr) -> Option<OsString>>(&self, var: F) -> Result<PathBuf> {
+        if let Some(path) = self.env_vars.iter().filter_map(|name| var(name)).find(|p| !p.is_empty()) {
+            let path = PathBuf::from(path);
+            return if path.is_file() {
+                Ok(path)
+            } else {
+                Err(ErrorKind::InvalidOpPath(path).into())
+            };
+        }
+        if let Some(path) = self.dirs.iter().map(|d| d.join(OP_EXECUTABLE)).find(|p| p.is_file()) {
+            return Ok(path);
+        }
+        if self.search_path {
+            if let Ok(path) = which::which(OP_EXECUTABLE) {
+                return Ok(path);
+            }
+        }
+        if self.default_locations {
+            if let Some(path) = default_locations().into_iter().find(|p| p.is_file()) {
+                return Ok(path);
+            }
+        }
+        Err(ErrorKind::MissingOpCommand.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn windows_install_locations() {
+        let vars = |name: &str| match name {
+            "ProgramFiles" => Some(OsString::from("C:\\Program Files")),
+            "LOCALAPPDATA" => Some(OsString::from("C:\\Users\\me\\AppData\\Local")),
+            "USERPROFILE" => Some(OsString::from("C:\\Users\\me")),
+            _ => None,
+        };
+        let paths = windows_locations(vars);
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[0], Path::new("C:\\Program Files").join("1Password CLI").join("op.exe"));
+        assert!(paths[1].ends_with(Path::new("WinGet").join("Links").join("op.exe")));
+        assert!(paths[2].ends_with(Path::new("scoop").join("shims").join("op.exe")));
+    }
+
+    #[test]
+    fn searches_extra_dirs() {
+        let dir = env::temp_dir().join(format!("op-discover-{}", ::std::process::id()));
+        ::std::fs::create_dir_all(&dir).unwrap();
+        let op = dir.join(OP_EXECUTABLE);
+        ::std::fs::write(&op, b"").unwrap();
+        let options = DiscoverOptions::new()
+            .no_env_var()
+            .search_path(false)
+            .default_locations(false);
+        assert!(options.find().is_err());
+        assert_eq!(options.dir(&dir).find().unwrap(), op);
+        ::std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn env_var_overrides_search() {
+        let dir = env::temp_dir().join(format!("op-override-{}", ::std::process::id()));
+        ::std::fs::create_dir_all(&dir).unwrap();
+        let pinned = dir.join("op-2.24");
+        ::std::fs::write(&pinned, b"").unwrap();
+        let options = DiscoverOptions::new().search_path(false).default_locations(false);
+
+        let vars = |name: &OsStr| match name.to_str() {
+            Some("OP_CLI_PATH") => Some(pinned.clone().into_os_string()),
+            Some("OP_PATH") => Some(OsString::from("/nonexistent/op")),
+            _ => None,
+        };
+        assert_eq!(options.find_with(vars).unwrap(), pinned);
+        let vars = |name: &OsStr| if name == "OP_PATH" { Some(OsString::from("/nonexistent/op")) } else { None };
+        match *options.find_with(vars).unwrap_err().kind() {
+            ErrorKind::InvalidOpPath(ref path) => assert_eq!(path, Path::new("/nonexistent/op")),
+            ref other => panic!("unexpected error {:?}", other),
+        }
+        assert!(options.find_with(|_| Some(OsString::new())).is_err());
+        ::std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_executable_name() {
+        assert_eq!(OP_EXECUTABLE, "op.exe");
+        assert!(::INSTALL_HINT.contains("winget"));
+    }
+}
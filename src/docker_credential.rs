@@ -0,0 +1,225 @@
+//! The docker credential helper protocol, backed by login items in a vault.
+//!
+//! Each registry is stored as a login item titled after its server URL and
+//! tagged with `TAG`. Items without the tag are never changed or deleted, so
+//! the helper can't clobber logins saved by hand. A helper binary named `docker-credential-<name>` only has
+//! to pass its arguments and standard streams on and exit with status 1 on
+//! errors:
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use std::env;
+//! use std::io;
+//! use std::process;
+//! use one_password::Op;
+//! use one_password::docker_credential::CredentialHelper;
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let helper = CredentialHelper::new(session, "Docker");
+//! let operation = env::args().nth(1).unwrap_or_default();
+//! if let Err(err) = helper.run(&operation, io::stdin(), io::stdout()) {
+//!     println!("{}", err);
+//!     process::exit(1);
+//! }
+//! ```
+//!
+//! Configure it with `"credsStore": "<name>"` in `~/.docker/config.json`.
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use serde_json;
+
+use diagnostics::not_found;
+use super::{ErrorKind, ItemBuilder, ItemEdit, ItemSummary, OpSession, Result, Secret};
+
+/// Tag added to items created by the helper, the only ones it lists, changes or deletes.
+pub const TAG: &str = "docker-credential";
+
+/// Credentials for a registry, in the JSON shape docker uses.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Credentials {
+    #[serde(rename = "ServerURL")]
+    pub server_url: String,
+    #[serde(rename = "Username")]
+    pub username: String,
+    #[serde(rename = "Secret", serialize_with = "expose")]
+    pub secret: Secret<String>,
+}
+
+fn expose<S: ::serde::Serializer>(secret: &Secret<String>, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(secret.expose())
+}
+
+/// Serves docker credential requests from login items in a vault.
+#[derive(Debug, Clone)]
+pub struct CredentialHelper {
+    session: OpSession,
+    vault: String,
+}
+
+impl CredentialHelper {
+    pub fn new(session: OpSession, vault: &str) -> CredentialHelper {
+        CredentialHelper { session, vault: vault.to_owned() }
+    }
+
+    /// Look up the credentials for `server_url`, `None` if there is no item for it.
+    pub fn get(&self, server_url: &str) -> Result<Option<Credentials>> {
+        let vault = Some(&self.vault[..]);
+        let secret = match not_found(self.session.get_field_revealed_in(server_url, "password", vault))? {
+            Some(secret) => secret,
+            None => return Ok(None),
+        };
+        let username = self.session.get_field_in(server_url, "username", vault)?;
+        Ok(Some(Credentials {
+            server_url: server_url.to_owned(),
+            username: username.into_inner(),
+            secret,
+        }))
+    }
+
+    /// The item titled `server_url`, if there is one.
+    fn item(&self, server_url: &str) -> Result<Option<ItemSummary>> {
+        not_found(self.session.get_item_summary_in(server_url, Some(&self.vault)))
+    }
+
+    /// Save `credentials`, replacing an existing item for the same server.
+    ///
+    /// Fails with `ErrorKind::ForeignCredentialItem` if the existing item
+    /// isn't tagged with `TAG`.
+    pub fn store(&self, credentials: &Credentials) -> Result<()> {
+        let vault = Some(&self.vault[..]);
+        match self.item(&credentials.server_url)? {
+            Some(ref item) if !item.tags.iter().any(|t| t == TAG) => {
+                bail!(ErrorKind::ForeignCredentialItem(credentials.server_url.clone()))
+            },
+            Some(item) => {
+                let edit = ItemEdit::new()
+                    .username(&credentials.username)
+                    .password(credentials.secret.expose());
+                self.session.edit_item_in(&item.id, &edit, vault)?;
+            },
+            None => {
+                let login = ItemBuilder::login(&credentials.server_url)
+                    .vault(&self.vault)
+                    .url(&credentials.server_url)
+                    .username(&credentials.username)
+                    .password(credentials.secret.expose())
+                    .tag(TAG);
+                self.session.create_item(&login)?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Delete the item for `server_url`, if there is one and it is tagged
+    /// with `TAG`.
+    pub fn erase(&self, server_url: &str) -> Result<()> {
+        match self.item(server_url)? {
+            Some(ref item) if item.tags.iter().any(|t| t == TAG) => {
+                not_found(self.session.delete_item_in(&item.id, Some(&self.vault)))?;
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+
+    /// Server URLs and usernames of all stored credentials.
+    pub fn list(&self) -> Result<BTreeMap<String, String>> {
+        let vault = Some(&self.vault[..]);
+        let mut servers = BTreeMap::new();
+        for item in self.session.list_items_in(vault)? {
+            if item.tags.iter().any(|t| t == TAG) {
+                let username = self.session.get_field_in(&item.id, "username", vault)?;
+                servers.insert(item.title, username.into_inner());
+            }
+        }
+        Ok(servers)
+    }
+
+    /// Handle the helper `operation` docker invoked the helper with, reading
+    /// the request from `input` and writing any reply to `output`.
+    ///
+    /// Fails with `ErrorKind::CredentialsNotFound` when `get` finds nothing,
+    /// whose message is the one docker expects on stdout in that case.
+    pub fn run<R: Read, W: Write>(&self, operation: &str, mut input: R, mut output: W) -> Result<()> {
+        let mut request = String::new();
+        input.read_to_string(&mut request)?;
+        let request = request.trim();
+        match operation {
+            "get" => match self.get(request)? {
+                Some(credentials) => serde_json::to_writer(&mut output, &credentials)?,
+                None => return Err(ErrorKind::CredentialsNotFound(request.to_owned()).into()),
+            },
+            "store" => self.store(&serde_json::from_str(request)?)?,
+            "erase" => self.erase(request)?,
+            "list" => serde_json::to_writer(&mut output, &self.list()?)?,
+            _ => return Err(ErrorKind::UnknownHelperOperation(operation.to_owned()).into()),
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{interaction, replay_op};
+
+    #[test]
+    fn serves_get_and_list() {
+        let server = "https://index.docker.io/v1/";
+        let scoped = |args: &[&'static str]| [&["--session=⟨redacted⟩", "--vault", "Docker"][..], args].concat();
+        let op = replay_op(vec![
+            interaction("item get", &scoped(&[server, "--fields", "label=password", "--reveal"]), "s3cret\n"),
+            interaction("item get", &scoped(&[server, "--fields", "label=username"]), "bob\n"),
+            interaction("item get", &scoped(&["ghcr.io", "--fields", "label=password", "--reveal"]), "")
+                .failing("[ERROR] item not found", 1),
+            interaction("item list", &scoped(&["--format", "json", "--no-color", "--iso-timestamps"]),
+                &format!(r#"[{{"id": "a", "title": "{}", "vault": {{"id": "v"}}, "tags": ["docker-credential"]}},
+                    {{"id": "b", "title": "Other", "vault": {{"id": "v"}}}}]"#, server)),
+            interaction("item get", &scoped(&["a", "--fields", "label=username"]), "bob\n"),
+        ]);
+        let helper = CredentialHelper::new(op.session("token"), "Docker");
+
+        let mut output = Vec::new();
+        helper.run("get", server.as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),
+            r#"{"ServerURL":"https://index.docker.io/v1/","Username":"bob","Secret":"s3cret"}"#);
+
+        let err = helper.run("get", "ghcr.io\n".as_bytes(), Vec::new()).unwrap_err();
+        assert_eq!(err.to_string(), "credentials not found in native keychain");
+
+        let mut output = Vec::new();
+        helper.run("list", "".as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), r#"{"https://index.docker.io/v1/":"bob"}"#);
+    }
+
+    #[test]
+    fn only_touches_own_items() {
+        let scoped = |args: &[&'static str]| [&["--session=⟨redacted⟩", "--vault", "Docker"][..], args].concat();
+        let summary = |id: &str, tags: &str| {
+            interaction("item get", &scoped(&["ghcr.io", "--format", "json", "--no-color", "--iso-timestamps"]),
+                &format!(r#"{{"id": "{}", "title": "ghcr.io", "vault": {{"id": "v"}}, "tags": [{}]}}"#, id, tags))
+        };
+        let item = r#"{"id": "a", "title": "ghcr.io", "vault": {"id": "v"}, "tags": ["docker-credential"]}"#;
+        let op = replay_op(vec![
+            summary("m", ""),
+            summary("m", ""),
+            summary("a", r#""docker-credential""#),
+            interaction("item get", &scoped(&["a", "--format", "json", "--no-color", "--iso-timestamps", "--reveal"]), item),
+            interaction("item edit", &scoped(&["a", "--format", "json", "--no-color", "--iso-timestamps"]), item),
+            summary("a", r#""docker-credential""#),
+            interaction("item delete", &scoped(&["a"]), ""),
+        ]);
+        let helper = CredentialHelper::new(op.session("token"), "Docker");
+        let request = r#"{"ServerURL": "ghcr.io", "Username": "bob", "Secret": "s3cret"}"#;
+
+        match *helper.run("store", request.as_bytes(), Vec::new()).unwrap_err().kind() {
+            ErrorKind::ForeignCredentialItem(ref title) => assert_eq!(title, "ghcr.io"),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        helper.run("erase", "ghcr.io".as_bytes(), Vec::new()).unwrap();
+        helper.run("store", request.as_bytes(), Vec::new()).unwrap();
+        helper.run("erase", "ghcr.io".as_bytes(), Vec::new()).unwrap();
+    }
+}
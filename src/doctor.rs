@@ -0,0 +1,241 @@
+use std::fmt;
+use std::io;
+
+use super::{AccountDetails, CliVersion, Error, ErrorCategory, ErrorKind, Op, OpSession};
+
+/// Markers in `op` errors that point at the local clock being off, which makes
+/// the 1Password service reject requests.
+const CLOCK_MARKERS: &[&str] = &["clock", "skew", "out of sync"];
+
+/// What `Op::doctor` checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CheckKind {
+    /// The `op` binary can be run.
+    Binary,
+    /// The version of `op` is supported.
+    Version,
+    /// The session is signed in.
+    SignIn,
+    /// The account of the session can be reached.
+    Account,
+    /// The 1Password service accepts the local time.
+    Clock,
+}
+
+impl CheckKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CheckKind::Binary => "binary",
+            CheckKind::Version => "version",
+            CheckKind::SignIn => "sign-in",
+            CheckKind::Account => "account",
+            CheckKind::Clock => "clock",
+        }
+    }
+}
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CheckStatus {
+    Ok,
+    /// Works, but something should be looked at.
+    Warning,
+    Failed,
+    /// Not checked, because no session was given or an earlier check failed.
+    Skipped,
+}
+
+impl CheckStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Warning => "warning",
+            CheckStatus::Failed => "failed",
+            CheckStatus::Skipped => "skipped",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    pub kind: CheckKind,
+    pub status: CheckStatus,
+    /// What was found, e.g. the path of `op` or why a check failed.
+    pub detail: String,
+}
+
+/// Result of `Op::doctor`, with one entry per `CheckKind` in declaration order.
+///
+/// Displays as one line per check, ready to print from a `doctor` subcommand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthReport {
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthReport {
+    /// Whether no check failed. Warnings and skipped checks don't count.
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Failed)
+    }
+
+    pub fn check(&self, kind: CheckKind) -> Option<&HealthCheck> {
+        self.checks.iter().find(|c| c.kind == kind)
+    }
+
+    fn push<S: Into<String>>(&mut self, kind: CheckKind, status: CheckStatus, detail: S) {
+        self.checks.push(HealthCheck { kind, status, detail: detail.into() });
+    }
+}
+
+impl fmt::Display for HealthReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "[{}] {}: {}", check.status.as_str(), check.kind.as_str(), check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// The part of `err` worth showing to a user: what `op` printed, if it ran.
+fn describe(err: &Error) -> String {
+    match err.diagnostics() {
+        Some(d) if !d.stderr.trim().is_empty() => d.stderr.trim().to_owned(),
+        _ => err.to_string(),
+    }
+}
+
+impl Op {
+    /// Check that `op` and, if given, `session` are usable, without failing on
+    /// the first problem.
+    ///
+    /// This runs `op --version` and, with a session, `OpSession::get_account`.
+    /// `op` has no way to report the server time, so the clock is only judged
+    /// by whether 1Password rejected the request because of it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// let op = Op::which().unwrap();
+    /// let session = op.env_session().ok();
+    /// let report = op.doctor(session.as_ref());
+    /// print!("{}", report);
+    /// if !report.is_healthy() {
+    ///     std::process::exit(1);
+    /// }
+    /// ```
+    pub fn doctor(&self, session: Option<&OpSession>) -> HealthReport {
+        let mut report = HealthReport { checks: Vec::new() };
+        let version = match self.version() {
+            Ok(version) => {
+                report.push(CheckKind::Binary, CheckStatus::Ok, self.command().display().to_string());
+                version
+            },
+            Err(err) => {
+                let detail = match *err.kind() {
+                    ErrorKind::Io(ref io) if io.kind() == io::ErrorKind::NotFound => {
+                        format!("{} not found. {}", self.command().display(), ::INSTALL_HINT)
+                    },
+                    _ => describe(&err),
+                };
+                report.push(CheckKind::Binary, CheckStatus::Failed, detail);
+                for &kind in &[CheckKind::Version, CheckKind::SignIn, CheckKind::Account, CheckKind::Clock] {
+                    report.push(kind, CheckStatus::Skipped, "op can't be run");
+                }
+                return report;
+            },
+        };
+
+        match version.parse::<CliVersion>() {
+            Ok(v) if v.is_v2() => report.push(CheckKind::Version, CheckStatus::Ok, v.to_string()),
+            Ok(v) => report.push(CheckKind::Version, CheckStatus::Warning,
+                format!("{} is end of life, some operations need op 2.x", v)),
+            Err(err) => report.push(CheckKind::Version, CheckStatus::Failed, err.to_string()),
+        }
+
+        let session = match session {
+            Some(session) => session,
+            None => {
+                for &kind in &[CheckKind::SignIn, CheckKind::Account, CheckKind::Clock] {
+                    report.push(kind, CheckStatus::Skipped, "no session");
+                }
+                return report;
+            },
+        };
+        match session.get_account() {
+            Ok(AccountDetails { ref domain, ref account_type, .. }) => {
+                report.push(CheckKind::SignIn, CheckStatus::Ok, "signed in");
+                report.push(CheckKind::Account, CheckStatus::Ok, format!("{} ({})", domain, account_type));
+                report.push(CheckKind::Clock, CheckStatus::Ok, "accepted by 1Password");
+            },
+            Err(err) => {
+                let detail = describe(&err);
+                let clock_skew = CLOCK_MARKERS.iter().any(|m| detail.to_lowercase().contains(m));
+                match err.category() {
+                    Some(ErrorCategory::NotSignedIn) | Some(ErrorCategory::SecondFactorRequired) if !clock_skew => {
+                        report.push(CheckKind::SignIn, CheckStatus::Failed, detail);
+                        report.push(CheckKind::Account, CheckStatus::Skipped, "not signed in");
+                        report.push(CheckKind::Clock, CheckStatus::Skipped, "not signed in");
+                    },
+                    _ => {
+                        report.push(CheckKind::SignIn, CheckStatus::Skipped, "account unreachable");
+                        report.push(CheckKind::Account, CheckStatus::Failed, detail.clone());
+                        if clock_skew {
+                            report.push(CheckKind::Clock, CheckStatus::Failed, detail);
+                        } else {
+                            report.push(CheckKind::Clock, CheckStatus::Skipped, "account unreachable");
+                        }
+                    },
+                }
+            },
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{interaction, replay_op};
+
+    fn replay(account_stdout: &str, account_stderr: &str, exit_code: i32) -> Op {
+        replay_op(vec![
+            interaction("--version", &[], "2.24.0\n"),
+            interaction("account get", &["--session=⟨redacted⟩", "--format", "json", "--no-color", "--iso-timestamps"],
+                account_stdout).failing(account_stderr, exit_code),
+        ])
+    }
+
+    #[test]
+    fn reports_healthy_setup() {
+        let op = replay(r#"{"id": "A1", "name": "Acme", "domain": "acme.1password.com", "type": "BUSINESS",
+            "state": "ACTIVE"}"#, "", 0);
+        let report = op.doctor(Some(&op.session("token")));
+        assert!(report.is_healthy());
+        assert_eq!(report.check(CheckKind::Account).unwrap().detail, "acme.1password.com (BUSINESS)");
+        assert!(report.to_string().contains("[ok] version: 2.24.0\n"));
+
+        let report = replay("", "", 0).doctor(None);
+        assert!(report.is_healthy());
+        assert_eq!(report.check(CheckKind::SignIn).unwrap().status, CheckStatus::Skipped);
+    }
+
+    #[test]
+    fn reports_problems() {
+        let op = replay("", "[ERROR] You are not currently signed in.", 1);
+        let report = op.doctor(Some(&op.session("token")));
+        assert!(!report.is_healthy());
+        assert_eq!(report.check(CheckKind::SignIn).unwrap().status, CheckStatus::Failed);
+
+        let op = replay("", "[ERROR] request rejected: your system clock is out of sync", 1);
+        let report = op.doctor(Some(&op.session("token")));
+        assert_eq!(report.check(CheckKind::Clock).unwrap().status, CheckStatus::Failed);
+
+        let report = Op::new("/nonexistent/op").doctor(None);
+        assert_eq!(report.checks.len(), 5);
+        assert_eq!(report.check(CheckKind::Binary).unwrap().status, CheckStatus::Failed);
+        assert_eq!(report.check(CheckKind::Clock).unwrap().status, CheckStatus::Skipped);
+    }
+}
@@ -0,0 +1,251 @@
+use std::io::{self, Read, Write};
+
+use serde_json;
+
+use super::{ErrorKind, OpSession, Result};
+
+#[derive(Deserialize)]
+struct Created {
+    uuid: String,
+}
+
+/// File name and size of a document as reported by 1Password.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct DocumentMeta {
+    pub file_name: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl DocumentMeta {
+    /// Read from the JSON of `op item get` or, with `op` 1.x, `op get item`.
+    fn parse(item: &serde_json::Value) -> DocumentMeta {
+        if let Some(attributes) = item.pointer("/details/documentAttributes") {
+            return DocumentMeta {
+                file_name: attributes["fileName"].as_str().map(str::to_owned),
+                size: attributes["decryptedSize"].as_u64(),
+            };
+        }
+        match item["files"].as_array() {
+            Some(files) if files.len() == 1 => DocumentMeta {
+                file_name: files[0]["name"].as_str().map(str::to_owned),
+                size: files[0]["size"].as_u64(),
+            },
+            _ => DocumentMeta::default(),
+        }
+    }
+}
+
+/// Writer that counts the bytes passing through and reports the running total.
+struct Progress<W, F> {
+    inner: W,
+    written: u64,
+    callback: F,
+}
+
+impl<W: Write, F: FnMut(u64)> Write for Progress<W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        (self.callback)(self.written);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OpSession {
+    /// Download the document `item` into `out`, returning the number of bytes written.
+    ///
+    /// This calls `op document get`, or `op get document` with `op` 1.x.
+    /// See `download_document_in` for large documents.
+    pub fn download_document<W: Write>(&self, item: &str, out: W) -> Result<u64> {
+        self.download_document_in(item, None, out, |_| ())
+    }
+
+    /// Download the document `item` from `vault`, or the default vault if
+    /// `None`, into `out`.
+    ///
+    /// The contents are streamed from `op` into `out` as they arrive rather
+    /// than buffered, and `progress` is called with the number of bytes
+    /// written so far after each write. If `op` fails part way, `out` may hold
+    /// a partial document.
+    ///
+    /// Before downloading, the size of the document is looked up in its item.
+    /// If fewer or more bytes arrive, this fails with
+    /// `ErrorKind::IntegrityMismatch` and `out` must not be trusted. 1Password
+    /// doesn't report a checksum, so the size is all that can be checked.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use std::fs::File;
+    /// use one_password::Op;
+    ///
+    /// let session = Op::which().unwrap().env_session().unwrap();
+    /// let file = File::create("backup.tar.gz").unwrap();
+    /// session.download_document_in("Nightly backup", Some("Ops"), file, |bytes| {
+    ///     eprint!("\r{} MiB", bytes >> 20);
+    /// }).unwrap();
+    /// ```
+    pub fn download_document_in<W, F>(&self, item: &str, vault: Option<&str>, out: W, progress: F) -> Result<u64>
+        where W: Write, F: FnMut(u64)
+    {
+        let meta = self.document_meta(item, vault)?;
+        self.download_verified(item, vault, &meta, out, progress)
+    }
+
+    /// Download the document `item`, checking its size against `meta`.
+    pub(crate) fn download_verified<W, F>(&self, item: &str, vault: Option<&str>, meta: &DocumentMeta, out: W, progress: F) -> Result<u64>
+        where W: Write, F: FnMut(u64)
+    {
+        let subcommand : &[&str] = if self.config.cli_version()?.is_v2() {
+            &["document", "get"]
+        } else {
+            &["get", "document"]
+        };
+        let mut out = Progress { inner: out, written: 0, callback: progress };
+        self.item_command(subcommand, vault)
+            .target(item)
+            .run_to(&mut out, |d| ErrorKind::GetCommand(item.to_owned(), d))?;
+        out.flush()?;
+        match meta.size {
+            Some(expected) if expected != out.written => {
+                bail!(ErrorKind::IntegrityMismatch(item.to_owned(), expected, out.written))
+            },
+            _ => Ok(out.written),
+        }
+    }
+
+    /// File name and size of the document `item` as reported by 1Password.
+    pub(crate) fn document_meta(&self, item: &str, vault: Option<&str>) -> Result<DocumentMeta> {
+        let subcommand : &[&str] = if self.config.cli_version()?.is_v2() {
+            &["item", "get"]
+        } else {
+            &["get", "item"]
+        };
+        let json : serde_json::Value = self.item_command(subcommand, vault)
+            .target(item).json()?
+            .run_json(|d| ErrorKind::GetCommand(item.to_owned(), d))?;
+        Ok(DocumentMeta::parse(&json))
+    }
+
+    /// Create a document named `file_name` from `contents`, in `vault` or the
+    /// default vault of the session, and return the id of the new item.
+    ///
+    /// `contents` is piped into `op document create -` as `op` reads it, so
+    /// large files don't have to be staged on disk or held in memory. This
+    /// needs `op` 2.x. In dry-run mode nothing is created and the returned id
+    /// is empty.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use std::fs::File;
+    /// use one_password::Op;
+    ///
+    /// let session = Op::which().unwrap().env_session().unwrap();
+    /// let file = File::open("backup.tar.gz").unwrap();
+    /// let id = session.create_document("Nightly backup", "backup.tar.gz", file, Some("Ops")).unwrap();
+    /// ```
+    pub fn create_document<R: Read + Send>(&self, title: &str, file_name: &str, mut contents: R, vault: Option<&str>) -> Result<String> {
+        if !self.config.cli_version()?.is_v2() {
+            bail!(ErrorKind::RequiresCliV2("creating a document from a reader"));
+        }
+        let mut command = self.item_command(&["document", "create"], vault);
+        command.arg("-")
+            .arg("--title").arg(title)
+            .arg("--file-name").arg(file_name)
+            .json()?;
+        let stdout = command.run_from(&mut contents, ErrorKind::CommandFailed)?;
+        if stdout.is_empty() {
+            return Ok(String::new());
+        }
+        let created : Created = command.parse_json(&stdout)?;
+        Ok(created.uuid)
+    }
+
+    /// Replace the contents of the document `item` in `vault`, or the default
+    /// vault if `None`, with `contents`, streamed like in `create_document`.
+    ///
+    /// This calls `op document edit -` and needs `op` 2.x.
+    pub fn update_document<R: Read + Send>(&self, item: &str, file_name: &str, mut contents: R, vault: Option<&str>) -> Result<()> {
+        if !self.config.cli_version()?.is_v2() {
+            bail!(ErrorKind::RequiresCliV2("updating a document from a reader"));
+        }
+        self.item_command(&["document", "edit"], vault)
+            .target(item)
+            .arg("-")
+            .arg("--file-name").arg(file_name)
+            .run_from(&mut contents, ErrorKind::CommandFailed)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{interaction, replay_op, Data, Interaction};
+    use {CliVersion, ErrorKind, Op};
+
+    fn download(size: u64, contents: &[u8]) -> Op {
+        replay_op(vec![
+            interaction("item get", &["--session=⟨redacted⟩", "--vault", "Ops", "backup", "--format", "json", "--no-color", "--iso-timestamps"],
+                &format!(r#"{{"id": "backup", "category": "DOCUMENT", "files": [{{"id": "f", "name": "backup.tar.gz", "size": {}}}]}}"#, size)),
+            Interaction {
+                stdout: Data::Binary(contents.to_vec()),
+                ..interaction("document get", &["--session=⟨redacted⟩", "--vault", "Ops", "backup"], "")
+            },
+        ])
+    }
+
+    #[test]
+    fn downloads_document() {
+        let contents : Vec<u8> = (0..=255).collect();
+        let op = download(256, &contents);
+        let mut out = Vec::new();
+        let mut reported = 0;
+        let written = op.session("token")
+            .download_document_in("backup", Some("Ops"), &mut out, |bytes| reported = bytes)
+            .unwrap();
+        assert_eq!(written, 256);
+        assert_eq!(reported, 256);
+        assert_eq!(out, contents);
+    }
+
+    #[test]
+    fn rejects_truncated_download() {
+        let op = download(256, b"partial");
+        let err = op.session("token").download_document_in("backup", Some("Ops"), Vec::new(), |_| ()).unwrap_err();
+        match *err.kind() {
+            ErrorKind::IntegrityMismatch(ref item, 256, 7) => assert_eq!(item, "backup"),
+            ref other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reads_reported_meta() {
+        let v1 = json!({"uuid": "a", "details": {"documentAttributes": {"fileName": "a.txt", "decryptedSize": 12}}});
+        assert_eq!(DocumentMeta::parse(&v1), DocumentMeta { file_name: Some("a.txt".to_owned()), size: Some(12) });
+        assert_eq!(DocumentMeta::parse(&json!({"id": "a", "files": []})), DocumentMeta::default());
+    }
+
+    #[test]
+    fn creates_document() {
+        let op = replay_op(vec![interaction("document create", &["--session=⟨redacted⟩", "-", "--title", "Backup",
+            "--file-name", "backup.tar.gz", "--format", "json", "--no-color", "--iso-timestamps"],
+            r#"{"uuid": "doc1", "vaultUuid": "v"}"#)]);
+        let id = op.session("token").create_document("Backup", "backup.tar.gz", &b"contents"[..], None).unwrap();
+        assert_eq!(id, "doc1");
+
+        let op = Op::new("op").with_cli_version(CliVersion::new(1, 12, 0));
+        let err = op.session("token").create_document("Backup", "backup.tar.gz", &b"contents"[..], None).unwrap_err();
+        match *err.kind() {
+            ErrorKind::RequiresCliV2(_) => (),
+            ref other => panic!("unexpected error {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! `.env` files with values that may be secret references.
+//!
+//! An in-process replacement for `op run --env-file`: values of the form
+//! `op://vault/item/field` are read through a session, everything else is
+//! used as is.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use one_password::Op;
+//! use one_password::dotenv;
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let vars = dotenv::load(&session, ".env").unwrap();
+//! dotenv::apply(&vars, false);
+//! ```
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use reference::SecretReference;
+use super::{ErrorKind, OpSession, Result, Secret};
+
+fn unquote(value: &str, line: usize) -> Result<String> {
+    let invalid = || ErrorKind::InvalidEnvLine(line).into();
+    if let Some(rest) = value.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or_else(invalid)?;
+        return Ok(rest[..end].to_owned());
+    }
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut out = String::new();
+        let mut chars = rest.chars();
+        loop {
+            match chars.next() {
+                None => return Err(invalid()),
+                Some('"') => return Ok(out),
+                Some('\\') => match chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some(c) => out.push(c),
+                    None => return Err(invalid()),
+                },
+                Some(c) => out.push(c),
+            }
+        }
+    }
+    let value = match value.find(" #") {
+        Some(index) => &value[..index],
+        None => value,
+    };
+    Ok(value.trim().to_owned())
+}
+
+/// Parse the contents of a `.env` file into names and raw values, in order.
+///
+/// Supports `#` comments, an optional `export ` prefix, single quoted values
+/// taken literally and double quoted values with `\n`, `\r`, `\t` escapes.
+pub fn parse(contents: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let eq = line.find('=').ok_or(ErrorKind::InvalidEnvLine(index + 1))?;
+        let name = line[..eq].trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(ErrorKind::InvalidEnvLine(index + 1).into());
+        }
+        vars.push((name.to_owned(), unquote(line[eq + 1..].trim(), index + 1)?));
+    }
+    Ok(vars)
+}
+
+/// Read the values of `vars` that are secret references with `OpSession::read`.
+pub fn resolve(session: &OpSession, vars: Vec<(String, String)>) -> Result<Vec<(String, Secret<String>)>> {
+    vars.into_iter().map(|(name, value)| {
+        let value = if SecretReference::is_reference(&value) {
+            session.read(&value)?
+        } else {
+            Secret::new(value)
+        };
+        Ok((name, value))
+    }).collect()
+}
+
+/// Read, parse and resolve the `.env` file at `path`.
+pub fn load<P: AsRef<Path>>(session: &OpSession, path: P) -> Result<Vec<(String, Secret<String>)>> {
+    resolve(session, parse(&fs::read_to_string(path)?)?)
+}
+
+/// Set `vars` in the environment of this process. Variables that are already
+/// set are only replaced if `overwrite` is true.
+///
+/// Like `std::env::set_var`, this must not race with other threads reading
+/// or writing the environment.
+pub fn apply(vars: &[(String, Secret<String>)], overwrite: bool) {
+    for (name, value) in vars {
+        if overwrite || env::var_os(name).is_none() {
+            env::set_var(name, value.expose());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{read_interaction, replay_op};
+
+    #[test]
+    fn parses_env_files() {
+        let vars = parse("# comment\nexport A=1\nB = two words # note\nC='lit\\n'\nD=\"esc\\n\"\n\nE=\n").unwrap();
+        assert_eq!(vars, vec![
+            ("A".to_owned(), "1".to_owned()),
+            ("B".to_owned(), "two words".to_owned()),
+            ("C".to_owned(), "lit\\n".to_owned()),
+            ("D".to_owned(), "esc\n".to_owned()),
+            ("E".to_owned(), "".to_owned()),
+        ]);
+        match *parse("A=1\nnot a var\n").unwrap_err().kind() {
+            ErrorKind::InvalidEnvLine(2) => {},
+            ref other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resolves_references() {
+        let read = read_interaction("op://Prod/DB/password", "hunter2");
+        let op = replay_op(vec![read]);
+        let vars = parse("DB_PASSWORD=op://Prod/DB/password\nDB_HOST=localhost\n").unwrap();
+        let vars = resolve(&op.session("token"), vars).unwrap();
+        assert_eq!(vars[0].1.expose(), "hunter2");
+        assert_eq!(vars[1].1.expose(), "localhost");
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::{Arc, Mutex};
+
+use super::CommandInfo;
+
+/// Subcommand words that make an `op` invocation change something in 1Password.
+const MUTATING: &[&str] = &["create", "edit", "delete", "move", "share"];
+
+/// Returns true if running `subcommand` would change data in 1Password.
+pub fn is_mutating<S: AsRef<str>>(subcommand: &[S]) -> bool {
+    subcommand.iter().any(|word| MUTATING.contains(&word.as_ref()))
+}
+
+/// Plan of mutating commands collected while running in dry-run mode.
+///
+/// Register with `Op::with_dry_run`. Commands that would create, edit, delete,
+/// move or share anything are not run but recorded here and treated as if they
+/// succeeded with no output. Read-only commands still run as normal.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use one_password::{DryRun, Op};
+///
+/// let plan = DryRun::new();
+/// let op = Op::which().unwrap().with_dry_run(plan.clone());
+/// // ... run automation against `op` ...
+/// for command in plan.commands() {
+///     println!("{}", command);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DryRun {
+    commands: Arc<Mutex<Vec<CommandInfo>>>,
+}
+
+impl DryRun {
+    pub fn new() -> DryRun {
+        DryRun::default()
+    }
+
+    /// Commands recorded so far, in the order they would have run.
+    pub fn commands(&self) -> Vec<CommandInfo> {
+        match self.commands.lock() {
+            Ok(c) => c.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Remove and return all commands recorded so far.
+    pub fn take(&self) -> Vec<CommandInfo> {
+        match self.commands.lock() {
+            Ok(mut c) => c.split_off(0),
+            Err(poisoned) => poisoned.into_inner().split_off(0),
+        }
+    }
+
+    pub(crate) fn record(&self, command: CommandInfo) {
+        match self.commands.lock() {
+            Ok(mut c) => c.push(command),
+            Err(poisoned) => poisoned.into_inner().push(command),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_mutating_commands() {
+        assert!(is_mutating(&["item", "create"]));
+        assert!(is_mutating(&["delete", "item"]));
+        assert!(is_mutating(&["vault", "share"]));
+        assert!(!is_mutating(&["get", "item"]));
+        assert!(!is_mutating(&["--version"]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn records_instead_of_running() {
+        use command::OpCommand;
+        use Op;
+
+        let plan = DryRun::new();
+        let op = Op::new("false").with_dry_run(plan.clone());
+        let (output, _) = OpCommand::new(&op, &["item", "delete"])
+            .secret_arg("TOKEN")
+            .target("abc")
+            .execute()
+            .unwrap();
+        assert!(output.status.success());
+        let (output, _) = OpCommand::new(&op, &["get", "item"]).execute().unwrap();
+        assert!(!output.status.success());
+
+        let commands = plan.take();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].to_string(), "op item delete ⟨redacted⟩ abc");
+        assert!(plan.commands().is_empty());
+    }
+}
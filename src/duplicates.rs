@@ -0,0 +1,95 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+use super::{ErrorCategory, ItemSummary, OpSession, Result};
+
+/// Items sharing the same password.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DuplicateGroup {
+    pub items: Vec<ItemSummary>,
+}
+
+/// Result of `OpSession::duplicate_passwords`.
+///
+/// Only the grouping is kept; neither the passwords nor their hashes are part of the report.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DuplicateReport {
+    /// Groups of two or more items with the same password, largest first.
+    pub groups: Vec<DuplicateGroup>,
+    /// Number of items whose password was compared.
+    pub scanned: usize,
+}
+
+/// Categories of items whose `password` field is compared.
+pub(crate) const CATEGORIES: &[&str] = &["LOGIN", "PASSWORD"];
+
+impl OpSession {
+    /// Find items in `vaults` that share a password, or in the default vault if `vaults` is empty.
+    ///
+    /// Every login and password item is fetched one at a time. Each password is only
+    /// held long enough to hash it with a key that is random for every report, so
+    /// the hashes are of no use outside of it.
+    pub fn duplicate_passwords(&self, vaults: &[&str]) -> Result<DuplicateReport> {
+        let mut items = Vec::new();
+        if vaults.is_empty() {
+            items.extend(self.list_items()?);
+        }
+        for vault in vaults {
+            items.extend(self.list_items_in(Some(vault))?);
+        }
+
+        let hasher = RandomState::new();
+        let mut by_hash : HashMap<u64, Vec<ItemSummary>> = HashMap::new();
+        let mut report = DuplicateReport::default();
+        for item in items.into_iter().filter(|i| CATEGORIES.contains(&&i.category[..])) {
+            let hash = match self.get_field_revealed_in(&item.id, "password", Some(&item.vault_id)) {
+                Ok(ref password) if password.expose().is_empty() => continue,
+                Ok(password) => hasher.hash_one(password.expose()),
+                Err(ref err) if err.category() == Some(ErrorCategory::NotFound) => continue,
+                Err(err) => return Err(err),
+            };
+            report.scanned += 1;
+            by_hash.entry(hash).or_default().push(item);
+        }
+        report.groups = groups(by_hash);
+        Ok(report)
+    }
+}
+
+/// Groups of two or more items with the same password hash, largest first.
+pub(crate) fn groups(by_hash: HashMap<u64, Vec<ItemSummary>>) -> Vec<DuplicateGroup> {
+    let mut groups : Vec<DuplicateGroup> = by_hash.into_values()
+        .map(|items| DuplicateGroup { items })
+        .filter(|group| group.items.len() > 1)
+        .collect();
+    groups.sort_by_key(|group| Reverse(group.items.len()));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use cassette::{interaction, replay_op};
+
+    #[test]
+    fn groups_shared_passwords() {
+        let list = r#"[
+            {"id": "a", "title": "A", "vault": {"id": "v"}, "category": "LOGIN"},
+            {"id": "b", "title": "B", "vault": {"id": "v"}, "category": "PASSWORD"},
+            {"id": "c", "title": "C", "vault": {"id": "v"}, "category": "LOGIN"},
+            {"id": "d", "title": "D", "vault": {"id": "v"}, "category": "SECURE_NOTE"}
+        ]"#;
+        let field = |id: &str, password: &str| {
+            interaction("item get", &["--session=⟨redacted⟩", "--vault", "v", id, "--fields", "label=password", "--reveal"], password)
+        };
+        let list = interaction("item list",
+            &["--session=⟨redacted⟩", "--vault", "v", "--format", "json", "--no-color", "--iso-timestamps"], list);
+        let op = replay_op(vec![list, field("a", "hunter2\n"), field("b", "hunter2\n"), field("c", "secret\n")]);
+        let report = op.session("token").duplicate_passwords(&["v"]).unwrap();
+        assert_eq!(report.scanned, 3);
+        assert_eq!(report.groups.len(), 1);
+        let ids : Vec<&str> = report.groups[0].items.iter().map(|i| &i.id[..]).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,425 @@
+use assignment::{assign, conceal, FieldAssignment, FieldType};
+use serde_json::{self, Value};
+
+use pool;
+use secret::SecretBytes;
+use summary;
+use super::{max_concurrency, ErrorKind, ItemSummary, ItemUrl, OpSession, Result, CONCEALED_PLACEHOLDER, REVEAL_SINCE};
+
+/// Changes to an existing item, applied with `OpSession::edit_item`.
+///
+/// Field values are piped into `op item edit` as part of the item JSON, so
+/// they never show up in its arguments: the item is fetched with its
+/// concealed values revealed, changed and written back. Other changes, such
+/// as the title or a generated password, are passed as arguments. Editing
+/// values needs `op` 2.x, and fails with `ErrorKind::ConcealedField` rather
+/// than overwrite a value `op` didn't reveal.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use one_password::{ItemEdit, Op};
+///
+/// let session = Op::which().unwrap().env_session().unwrap();
+/// let edit = ItemEdit::new().password("correct horse battery staple").field("rotated", "2018-09-24");
+/// session.edit_item("db-password", &edit).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ItemEdit {
+    title: Option<String>,
+    url: Option<String>,
+    tags: Option<Vec<String>>,
+    favorite: Option<bool>,
+    fields: Vec<FieldAssignment>,
+}
+
+impl ItemEdit {
+    pub fn new() -> ItemEdit {
+        ItemEdit::default()
+    }
+
+    pub fn title(mut self, title: &str) -> ItemEdit {
+        self.title = Some(title.to_owned());
+        self
+    }
+
+    pub fn url(mut self, url: &str) -> ItemEdit {
+        self.url = Some(url.to_owned());
+        self
+    }
+
+    /// Replace all tags of the item. An empty list removes all tags.
+    pub fn tags<S: AsRef<str>>(mut self, tags: &[S]) -> ItemEdit {
+        self.tags = Some(tags.iter().map(|t| t.as_ref().to_owned()).collect());
+        self
+    }
+
+    /// Mark or unmark the item as a favorite.
+    pub fn favorite(mut self, favorite: bool) -> ItemEdit {
+        self.favorite = Some(favorite);
+        self
+    }
+
+    /// Set a plain text field, adding it if it doesn't exist.
+    pub fn field(self, label: &str, value: &str) -> ItemEdit {
+        self.set(assign(label).of_type(FieldType::Text).to(value))
+    }
+
+    /// Set a concealed field, adding it if it doesn't exist.
+    pub fn concealed(self, label: &str, value: &str) -> ItemEdit {
+        self.set(conceal(label).to(value))
+    }
+
+    /// Set the built-in username field.
+    pub fn username(self, username: &str) -> ItemEdit {
+        self.set(assign("username").to(username))
+    }
+
+    /// Set the built-in password field.
+    pub fn password(self, password: &str) -> ItemEdit {
+        self.set(assign("password").to(password))
+    }
+
+    /// Remove a field.
+    pub fn delete_field(self, label: &str) -> ItemEdit {
+        self.set(assign(label).delete())
+    }
+
+    /// Apply `assignment`, e.g. `assign("host").in_section("db").to("10.0.0.1")`
+    /// or, for a `FieldPath`, `path.assign().to("10.0.0.1")`.
+    pub fn set(mut self, assignment: FieldAssignment) -> ItemEdit {
+        self.fields.push(assignment);
+        self
+    }
+}
+
+/// Label, or id, of the first field of `item`, `op` 2.x item JSON, whose
+/// value `op` masked instead of revealing it.
+fn masked_field(item: &Value) -> Option<String> {
+    let field = item.get("fields").and_then(Value::as_array).into_iter().flatten()
+        .find(|f| f.get("value").and_then(Value::as_str).is_some_and(|v| v.starts_with(CONCEALED_PLACEHOLDER)))?;
+    let label = field.get("label").or_else(|| field.get("id")).and_then(Value::as_str).unwrap_or("");
+    Some(label.to_owned())
+}
+
+impl OpSession {
+    /// Get the overview of a single item, without any secret values.
+    ///
+    /// This calls `op item get --format json`, or `op get item` with `op` 1.x.
+    pub fn get_item_summary(&self, item: &str) -> Result<ItemSummary> {
+        self.get_item_summary_in(item, None)
+    }
+
+    /// Get the overview of a single item in `vault`, or the default vault if `None`.
+    pub fn get_item_summary_in(&self, item: &str, vault: Option<&str>) -> Result<ItemSummary> {
+        let subcommand : &[&str] = if self.config.cli_version()?.is_v2() {
+            &["item", "get"]
+        } else {
+            &["get", "item"]
+        };
+        let mut command = self.item_command(subcommand, vault);
+        let stdout = command.target(item).json()?
+            .run_or(|d| ErrorKind::GetCommand(item.to_owned(), d))?;
+        summary::parse(&command, &stdout)
+    }
+
+    /// Apply `edit` to `item` with `op item edit`.
+    ///
+    /// In dry-run mode nothing is changed and the returned summary has an empty id.
+    pub fn edit_item(&self, item: &str, edit: &ItemEdit) -> Result<ItemSummary> {
+        self.edit_item_in(item, edit, None)
+    }
+
+    /// Apply `edit` to `item` in `vault`, or the default vault if `None`.
+    pub fn edit_item_in(&self, item: &str, edit: &ItemEdit, vault: Option<&str>) -> Result<ItemSummary> {
+        let mut json = if edit.fields.iter().any(FieldAssignment::sets_value) {
+            self.get_revealed(item, vault, "setting field values")?
+        } else {
+            Value::Null
+        };
+        let mut command = self.item_command(&["item", "edit"], vault);
+        command.target(item).json()?;
+        if let Some(ref title) = edit.title {
+            command.arg("--title").arg(title);
+        }
+        if let Some(ref url) = edit.url {
+            command.arg("--url").arg(url);
+        }
+        if let Some(ref tags) = edit.tags {
+            command.arg(format!("--tags={}", tags.join(",")));
+        }
+        if let Some(favorite) = edit.favorite {
+            command.arg(format!("--favorite={}", favorite));
+        }
+        for assignment in &edit.fields {
+            assignment.apply(&mut command, &mut json)?;
+        }
+        if !json.is_null() {
+            // The item includes its secret values, so zero the copy once done.
+            command.stdin(SecretBytes::from(serde_json::to_vec(&json)?));
+        }
+        let stdout = command.run_or(ErrorKind::CommandFailed)?;
+        if stdout.is_empty() {
+            return Ok(ItemSummary {
+                id: String::new(),
+                title: edit.title.clone().unwrap_or_default(),
+                vault_id: vault.or(self.vault()).unwrap_or("").to_owned(),
+                vault_name: None,
+                category: String::new(),
+                tags: edit.tags.clone().unwrap_or_default(),
+                favorite: edit.favorite.unwrap_or_default(),
+                created_at: None,
+                updated_at: None,
+                version: None,
+                last_edited_by: None,
+                urls: Vec::new(),
+            });
+        }
+        summary::parse(&command, &stdout)
+    }
+
+    /// Delete `item` using `op item delete`.
+    pub fn delete_item(&self, item: &str) -> Result<()> {
+        self.delete_item_in(item, None)
+    }
+
+    /// Delete `item` from `vault`, or the default vault if `None`, using `op item delete`.
+    pub fn delete_item_in(&self, item: &str, vault: Option<&str>) -> Result<()> {
+        self.item_command(&["item", "delete"], vault)
+            .target(item)
+            .run_or(ErrorKind::CommandFailed)?;
+        Ok(())
+    }
+
+    /// Mark or unmark `item` as a favorite.
+    pub fn set_favorite(&self, item: &str, favorite: bool) -> Result<ItemSummary> {
+        self.edit_item(item, &ItemEdit::new().favorite(favorite))
+    }
+
+    /// Add `href` as a website of `item`, labeled `label` unless it is empty.
+    ///
+    /// The first URL of an item becomes its primary URL. URLs the item
+    /// already has are left alone.
+    ///
+    /// `op item edit --url` can only replace the primary URL, so this and the
+    /// other URL helpers fetch the whole item, with its concealed values
+    /// revealed, and pipe it back into `op item edit` with only its URLs
+    /// changed. They need `op` 2.x, and fail with `ErrorKind::ConcealedField`
+    /// rather than overwrite a value `op` didn't reveal.
+    pub fn add_url(&self, item: &str, href: &str, label: &str) -> Result<ItemSummary> {
+        self.add_url_in(item, href, label, None)
+    }
+
+    /// Add `href` as a website of `item` in `vault`, or the default vault if `None`.
+    pub fn add_url_in(&self, item: &str, href: &str, label: &str, vault: Option<&str>) -> Result<ItemSummary> {
+        self.edit_urls(item, vault, |urls| {
+            if !urls.iter().any(|u| u.href == href) {
+                let primary = urls.is_empty();
+                urls.push(ItemUrl { href: href.to_owned(), label: label.to_owned(), primary });
+            }
+        })
+    }
+
+    /// Remove the website `href` from `item`. If it was the primary URL, the
+    /// first remaining URL becomes primary.
+    pub fn remove_url(&self, item: &str, href: &str) -> Result<ItemSummary> {
+        self.remove_url_in(item, href, None)
+    }
+
+    /// Remove the website `href` from `item` in `vault`, or the default vault if `None`.
+    pub fn remove_url_in(&self, item: &str, href: &str, vault: Option<&str>) -> Result<ItemSummary> {
+        self.edit_urls(item, vault, |urls| {
+            urls.retain(|u| u.href != href);
+            if !urls.iter().any(|u| u.primary) {
+                if let Some(first) = urls.first_mut() {
+                    first.primary = true;
+                }
+            }
+        })
+    }
+
+    /// Make `href` the primary website of `item`, adding it if needed.
+    pub fn set_primary_url(&self, item: &str, href: &str) -> Result<ItemSummary> {
+        self.set_primary_url_in(item, href, None)
+    }
+
+    /// Make `href` the primary website of `item` in `vault`, or the default vault if `None`.
+    pub fn set_primary_url_in(&self, item: &str, href: &str, vault: Option<&str>) -> Result<ItemSummary> {
+        self.edit_urls(item, vault, |urls| {
+            if !urls.iter().any(|u| u.href == href) {
+                urls.push(ItemUrl::new(href));
+            }
+            for url in urls.iter_mut() {
+                url.primary = url.href == href;
+            }
+        })
+    }
+
+    /// Get `item` as `op` 2.x JSON with its concealed values revealed, to
+    /// pipe it back into `op item edit` for `what`.
+    fn get_revealed(&self, item: &str, vault: Option<&str>, what: &'static str) -> Result<Value> {
+        let version = self.config.cli_version()?;
+        if !version.is_v2() {
+            bail!(ErrorKind::RequiresCliV2(what));
+        }
+        let mut get = self.item_command(&["item", "get"], vault);
+        get.target(item).json()?;
+        if version >= REVEAL_SINCE {
+            get.arg("--reveal");
+        }
+        let json : Value = get.run_json(|d| ErrorKind::GetCommand(item.to_owned(), d))?;
+        // Piping back a placeholder would replace the value it stands for.
+        if let Some(field) = masked_field(&json) {
+            bail!(ErrorKind::ConcealedField(item.to_owned(), field));
+        }
+        Ok(json)
+    }
+
+    fn edit_urls<F: FnOnce(&mut Vec<ItemUrl>)>(&self, item: &str, vault: Option<&str>, update: F) -> Result<ItemSummary> {
+        let mut json = self.get_revealed(item, vault, "editing the URLs of an item")?;
+        let mut urls : Vec<ItemUrl> = match json.get("urls") {
+            Some(urls) => serde_json::from_value(urls.clone())?,
+            None => Vec::new(),
+        };
+        update(&mut urls);
+        json["urls"] = serde_json::to_value(&urls)?;
+        // The item includes its secret values, so zero the copy once done.
+        let edited = SecretBytes::from(serde_json::to_vec(&json)?);
+        let mut command = self.item_command(&["item", "edit"], vault);
+        let stdout = command.target(item).json()?
+            .stdin(edited.clone())
+            .run_or(ErrorKind::CommandFailed)?;
+        summary::parse(&command, if stdout.is_empty() { edited.as_bytes() } else { &stdout })
+    }
+
+    /// Add `tags` to each of `items`, keeping their other tags.
+    ///
+    /// Items are fetched and edited concurrently. Returns the result for each
+    /// item in the order of `items`; a failure doesn't stop the other edits.
+    pub fn add_tags<S: AsRef<str> + Sync>(&self, items: &[S], tags: &[&str]) -> Vec<Result<ItemSummary>> {
+        self.retag(items, |current| {
+            let mut current = current.to_vec();
+            for tag in tags {
+                if !current.iter().any(|t| t == tag) {
+                    current.push(tag.to_string());
+                }
+            }
+            current
+        })
+    }
+
+    /// Remove `tags` from each of `items`, keeping their other tags.
+    ///
+    /// Items that have none of `tags` are left alone.
+    pub fn remove_tags<S: AsRef<str> + Sync>(&self, items: &[S], tags: &[&str]) -> Vec<Result<ItemSummary>> {
+        self.retag(items, |current| {
+            current.iter().filter(|t| !tags.contains(&&t[..])).cloned().collect()
+        })
+    }
+
+    fn retag<S, F>(&self, items: &[S], update: F) -> Vec<Result<ItemSummary>>
+        where S: AsRef<str> + Sync, F: Fn(&[String]) -> Vec<String> + Sync
+    {
+        pool::map_concurrent(items, max_concurrency(), |_, item| {
+            let current = self.get_item_summary(item.as_ref())?;
+            let tags = update(&current.tags);
+            if tags == current.tags {
+                return Ok(current);
+            }
+            self.edit_item(&current.id, &ItemEdit::new().tags(&tags))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{self, Value};
+
+    use cassette::{interaction, replay_op, Cassette, Replay, StdinLog};
+    use {CliVersion, ErrorKind, Op};
+
+    fn item(id: &str, tags: &str) -> String {
+        format!(r#"{{"id": "{}", "title": "T", "vault": {{"id": "v"}}, "category": "LOGIN", "tags": [{}]}}"#, id, tags)
+    }
+
+    #[test]
+    fn adds_and_removes_tags() {
+        let get = |id: &str, stdout: &str| {
+            interaction("item get", &["--session=⟨redacted⟩", id, "--format", "json", "--no-color", "--iso-timestamps"], stdout)
+        };
+        let edit = |id: &str, arg: &str, stdout: &str| {
+            interaction("item edit", &["--session=⟨redacted⟩", id, "--format", "json", "--no-color", "--iso-timestamps", arg], stdout)
+        };
+        let op = replay_op(vec![
+            get("a", &item("a", r#""web""#)),
+            get("b", &item("b", r#""web", "proj""#)),
+            edit("a", "--tags=web,proj", &item("a", r#""web", "proj""#)),
+            get("b", &item("b", r#""web", "proj""#)),
+            edit("b", "--tags=web", &item("b", r#""web""#)),
+            edit("b", "--favorite=true", r#"{"id": "b", "title": "T", "vault": {"id": "v"}, "category": "LOGIN", "favorite": true}"#),
+        ]);
+        let session = op.session("token");
+
+        let added = session.add_tags(&["a", "b"], &["proj"]);
+        assert_eq!(added.len(), 2);
+        assert_eq!(added[0].as_ref().unwrap().tags, vec!["web", "proj"]);
+        assert_eq!(added[1].as_ref().unwrap().tags, vec!["web", "proj"]);
+
+        let removed = session.remove_tags(&["b"], &["proj"]);
+        assert_eq!(removed[0].as_ref().unwrap().tags, vec!["web"]);
+
+        assert!(session.set_favorite("b", true).unwrap().favorite);
+    }
+
+    #[test]
+    fn edits_urls() {
+        let before = r#"{"id": "a", "title": "T", "vault": {"id": "v"}, "category": "LOGIN",
+            "urls": [{"label": "website", "primary": true, "href": "https://old.example.com"}]}"#;
+        let after = r#"{"id": "a", "title": "T", "vault": {"id": "v"}, "category": "LOGIN",
+            "urls": [{"label": "website", "primary": false, "href": "https://old.example.com"},
+                {"primary": true, "href": "https://example.com"}]}"#;
+        let op = replay_op(vec![
+            interaction("item get",
+                &["--session=⟨redacted⟩", "a", "--format", "json", "--no-color", "--iso-timestamps", "--reveal"], before),
+            interaction("item edit", &["--session=⟨redacted⟩", "a", "--format", "json", "--no-color", "--iso-timestamps"],
+                after),
+        ]);
+        let item = op.session("token").set_primary_url("a", "https://example.com").unwrap();
+        assert_eq!(item.urls.len(), 2);
+        assert_eq!(item.urls.iter().find(|u| u.primary).unwrap().href, "https://example.com");
+    }
+
+    #[test]
+    fn sends_revealed_values_back() {
+        let get = |stdout: &str| interaction("item get",
+            &["--session=⟨redacted⟩", "a", "--format", "json", "--no-color", "--iso-timestamps", "--reveal"], stdout);
+        let login = |password: &str| format!(r#"{{"id": "a", "title": "T", "vault": {{"id": "v"}}, "category": "LOGIN",
+            "fields": [{{"id": "password", "type": "CONCEALED", "label": "password", "value": "{}"}}]}}"#, password);
+        let cassette = Cassette {
+            interactions: vec![
+                get(&login("hunter2")),
+                interaction("item edit", &["--session=⟨redacted⟩", "a", "--format", "json", "--no-color", "--iso-timestamps"],
+                    &login("hunter2")),
+                get(&login("[use 'op item get a --reveal' to reveal]")),
+            ],
+        };
+        let stdin = Arc::new(Mutex::new(Vec::new()));
+        let op = Op::new("op").with_backend(StdinLog(Replay::new(cassette), stdin.clone()))
+            .with_cli_version(CliVersion::new(2, 24, 0));
+        let session = op.session("token");
+
+        session.add_url("a", "https://example.com", "").unwrap();
+        let sent : Value = serde_json::from_slice(&stdin.lock().unwrap()[0]).unwrap();
+        assert_eq!(sent["fields"][0]["value"], "hunter2");
+        assert_eq!(sent["urls"][0]["href"], "https://example.com");
+
+        match *session.add_url("a", "https://example.com", "").unwrap_err().kind() {
+            ErrorKind::ConcealedField(ref item, ref field) => assert_eq!((&item[..], &field[..]), ("a", "password")),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(stdin.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,307 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use diagnostics::not_found;
+use pool;
+use summary::{civil_from_days, days_from_civil};
+use super::{ErrorKind, ItemSummary, OpSession, Result};
+
+/// Categories with a built-in expiry date.
+const CATEGORIES: &[&str] = &[
+    "API_CREDENTIAL", "CREDIT_CARD", "DRIVER_LICENSE", "MEMBERSHIP", "OUTDOOR_LICENSE", "PASSPORT",
+    "SOFTWARE_LICENSE",
+];
+
+/// Parts of field labels that mark a date as the end of validity.
+const LABELS: &[&str] = &["expir", "valid until", "valid thru", "valid through"];
+
+/// Options for `OpSession::expiring_items`.
+#[derive(Debug, Clone)]
+pub struct ExpiryOptions {
+    vaults: Vec<String>,
+    categories: Vec<String>,
+    all_categories: bool,
+    within: Duration,
+    expired: bool,
+    concurrency: usize,
+}
+
+impl Default for ExpiryOptions {
+    fn default() -> ExpiryOptions {
+        ExpiryOptions {
+            vaults: Vec::new(),
+            categories: CATEGORIES.iter().map(|c| c.to_string()).collect(),
+            all_categories: false,
+            within: Duration::from_secs(30 * 86_400),
+            expired: true,
+            concurrency: 1,
+        }
+    }
+}
+
+impl ExpiryOptions {
+    pub fn new() -> ExpiryOptions {
+        ExpiryOptions::default()
+    }
+
+    /// Scan `vault`. Without any vault, the default vault of the session is scanned.
+    pub fn vault(mut self, vault: &str) -> ExpiryOptions {
+        self.vaults.push(vault.to_owned());
+        self
+    }
+
+    /// Also scan items in `category`, e.g. `DOCUMENT` for documents with an
+    /// expiry field added by hand. By default only categories with a built-in
+    /// expiry date, such as `CREDIT_CARD` and `PASSPORT`, are scanned.
+    pub fn category(mut self, category: &str) -> ExpiryOptions {
+        self.categories.push(category.to_owned());
+        self
+    }
+
+    /// Scan items of every category. This fetches every item.
+    pub fn all_categories(mut self) -> ExpiryOptions {
+        self.all_categories = true;
+        self
+    }
+
+    /// Report items expiring within `within` from now. Defaults to 30 days.
+    pub fn within(mut self, within: Duration) -> ExpiryOptions {
+        self.within = within;
+        self
+    }
+
+    /// Also report items that have already expired. Defaults to true.
+    pub fn expired(mut self, expired: bool) -> ExpiryOptions {
+        self.expired = expired;
+        self
+    }
+
+    /// Number of items to fetch at the same time. Defaults to 1.
+    ///
+    /// The total number of `op` processes is still limited by `set_max_concurrency`.
+    pub fn concurrency(mut self, concurrency: usize) -> ExpiryOptions {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+}
+
+/// An expiry field of an item, as returned by `OpSession::expiring_items`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExpiringCredential {
+    pub item: ItemSummary,
+    /// Label of the field, e.g. `expiry date`.
+    pub field: String,
+    /// Last day the credential is valid, as `YYYY-MM-DD`. For month and year
+    /// fields such as card expiries this is the last day of the month.
+    pub expires_on: String,
+    /// Days from today until `expires_on`, negative once it has passed.
+    pub days_left: i64,
+}
+
+/// Days since 1970-01-01 of the last day of `month` in `year`.
+fn end_of_month(year: i64, month: u32) -> Option<i64> {
+    if month == 0 || month > 12 {
+        return None;
+    }
+    let (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    Some(days_from_civil(year, month, 1) - 1)
+}
+
+fn digits(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Parse a month and year, `202512` as `op` 1.x stores it or `12/2025`.
+fn parse_month_year(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if text.len() == 6 && digits(text) {
+        return end_of_month(text[..4].parse().ok()?, text[4..].parse().ok()?);
+    }
+    let (month, year) = text.split_once('/')?;
+    if !digits(month) || !digits(year) {
+        return None;
+    }
+    let year : i64 = match year.len() {
+        2 => 2000 + year.parse::<i64>().ok()?,
+        4 => year.parse().ok()?,
+        _ => return None,
+    };
+    end_of_month(year, month.parse().ok()?)
+}
+
+/// Parse a date, seconds since the Unix epoch as `op` 1.x stores it or `2025-12-31`.
+fn parse_date(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if digits(text) && text.len() >= 9 {
+        return text.parse::<i64>().ok().map(|seconds| seconds.div_euclid(86_400));
+    }
+    let date = text.get(..10)?;
+    let bytes = date.as_bytes();
+    if bytes[4] != b'-' || bytes[7] != b'-' || !digits(&date[..4]) || !digits(&date[5..7]) || !digits(&date[8..]) {
+        return None;
+    }
+    let (month, day) = (date[5..7].parse().ok()?, date[8..].parse().ok()?);
+    if month == 0 || month > 12 || day == 0 || day > 31 {
+        return None;
+    }
+    Some(days_from_civil(date[..4].parse().ok()?, month, day))
+}
+
+/// Expiry dates in the JSON of an item printed by `op item get` or `op get item`,
+/// as the label of the field and days since 1970-01-01.
+fn expiry_fields(item: &Value) -> Vec<(String, i64)> {
+    let is_expiry = |name: &str| {
+        let name = name.to_lowercase();
+        LABELS.iter().any(|l| name.contains(l))
+    };
+    let text = |value: &Value| match *value {
+        Value::String(ref s) => Some(s.clone()),
+        Value::Number(ref n) => Some(n.to_string()),
+        _ => None,
+    };
+    // op 2.x lists all fields at the top level, 1.x nests them in sections.
+    let v2 = item["fields"].as_array().into_iter().flatten()
+        .map(|f| (&f["label"], &f["id"], &f["type"], &f["value"]));
+    let v1 = item["details"]["sections"].as_array().into_iter().flatten()
+        .flat_map(|s| s["fields"].as_array().into_iter().flatten())
+        .map(|f| (&f["t"], &f["n"], &f["k"], &f["v"]));
+    let mut found = Vec::new();
+    for (label, id, kind, value) in v2.chain(v1) {
+        let label = label.as_str().filter(|l| !l.is_empty()).or_else(|| id.as_str()).unwrap_or("");
+        if !is_expiry(label) && !id.as_str().is_some_and(is_expiry) {
+            continue;
+        }
+        let value = match text(value) {
+            Some(value) => value,
+            None => continue,
+        };
+        let days = match kind.as_str() {
+            Some("MONTH_YEAR") | Some("monthYear") => parse_month_year(&value),
+            Some("DATE") | Some("date") => parse_date(&value),
+            _ => parse_date(&value).or_else(|| parse_month_year(&value)),
+        };
+        if let Some(days) = days {
+            found.push((label.to_owned(), days));
+        }
+    }
+    found
+}
+
+fn format_date(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Expiry fields of `item` due by `until`, both in days since 1970-01-01.
+fn expiring(item: &ItemSummary, json: &Value, today: i64, until: i64, expired: bool) -> Vec<ExpiringCredential> {
+    expiry_fields(json).into_iter()
+        .filter(|&(_, days)| days <= until && (expired || days >= today))
+        .map(|(field, days)| ExpiringCredential {
+            item: item.clone(),
+            field,
+            expires_on: format_date(days),
+            days_left: days - today,
+        })
+        .collect()
+}
+
+impl OpSession {
+    /// Find credentials expiring within the window of `options`, soonest first.
+    ///
+    /// Items are listed first and only those in the categories of `options` are
+    /// fetched. A field counts as an expiry date when its label contains e.g.
+    /// `expiry`, `expires` or `valid until`.
+    pub fn expiring_items(&self, options: &ExpiryOptions) -> Result<Vec<ExpiringCredential>> {
+        let mut items = Vec::new();
+        let mut keep = |item: ItemSummary| if options.all_categories || options.categories.contains(&item.category) {
+            items.push(item);
+        };
+        if options.vaults.is_empty() {
+            self.for_each_item_in(None, &mut keep)?;
+        }
+        for vault in &options.vaults {
+            self.for_each_item_in(Some(vault), &mut keep)?;
+        }
+
+        let subcommand : &[&str] = if self.config.cli_version()?.is_v2() {
+            &["item", "get"]
+        } else {
+            &["get", "item"]
+        };
+        let fetched = pool::map_concurrent(&items, options.concurrency, |_, item| {
+            let mut command = self.item_command(subcommand, Some(&item.vault_id));
+            command.target(&item.id).json()?;
+            not_found(command.run_json::<Value, _>(|d| ErrorKind::GetCommand(item.id.clone(), d)))
+        }).into_iter().collect::<Result<Vec<_>>>()?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let today = (now / 86_400) as i64;
+        let until = today + (options.within.as_secs() / 86_400) as i64;
+        let mut found = Vec::new();
+        for (item, json) in items.into_iter().zip(fetched) {
+            if let Some(json) = json {
+                found.extend(expiring(&item, &json, today, until, options.expired));
+            }
+        }
+        found.sort_by_key(|e| e.days_left);
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn item() -> ItemSummary {
+        ItemSummary {
+            id: "card".to_owned(),
+            title: "Visa".to_owned(),
+            vault_id: "v1".to_owned(),
+            vault_name: None,
+            category: "CREDIT_CARD".to_owned(),
+            tags: Vec::new(),
+            favorite: false,
+            created_at: None,
+            updated_at: None,
+            version: None,
+            last_edited_by: None,
+            urls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(parse_month_year("202402").map(format_date), Some("2024-02-29".to_owned()));
+        assert_eq!(parse_month_year("12/25").map(format_date), Some("2025-12-31".to_owned()));
+        assert_eq!(parse_month_year("13/2025"), None);
+        assert_eq!(parse_date("1735603200").map(format_date), Some("2024-12-31".to_owned()));
+        assert_eq!(parse_date("2024-12-31").map(format_date), Some("2024-12-31".to_owned()));
+        assert_eq!(parse_date("31.12.2024"), None);
+    }
+
+    #[test]
+    fn finds_expiry_fields() {
+        let v2 : Value = serde_json::from_str(r#"{"id": "card", "fields": [
+            {"id": "expiry", "type": "MONTH_YEAR", "label": "expiry date", "value": "202407"},
+            {"id": "validFrom", "type": "MONTH_YEAR", "label": "valid from", "value": "202107"},
+            {"id": "x", "type": "STRING", "label": "Valid until", "value": "2024-06-20"},
+            {"id": "y", "type": "DATE", "label": "warranty expires", "value": "2030-01-01"}
+        ]}"#).unwrap();
+        let today = days_from_civil(2024, 7, 1);
+        let found = expiring(&item(), &v2, today, today + 60, true);
+        let fields : Vec<(&str, &str, i64)> = found.iter()
+            .map(|e| (&e.field[..], &e.expires_on[..], e.days_left))
+            .collect();
+        assert_eq!(fields, vec![("expiry date", "2024-07-31", 30), ("Valid until", "2024-06-20", -11)]);
+        assert_eq!(expiring(&item(), &v2, today, today + 60, false).len(), 1);
+
+        let v1 : Value = serde_json::from_str(r#"{"uuid": "card", "details": {"sections": [
+            {"name": "", "fields": [{"k": "monthYear", "n": "expiry", "t": "expiry date", "v": 202408},
+                {"k": "date", "n": "issued", "t": "issued on", "v": 1577836800}]}]}}"#).unwrap();
+        let found = expiring(&item(), &v1, today, today + 90, true);
+        assert_eq!(found.len(), 1);
+        assert_eq!((&found[0].expires_on[..], found[0].days_left), ("2024-08-31", 61));
+    }
+}
@@ -0,0 +1,145 @@
+//! Inventory reports of items, e.g. for compliance reviews.
+//!
+//! Reports are rendered from `ItemSummary` values as returned by
+//! `OpSession::list_items`, which never contain secret values.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! # #[cfg(feature = "process")]
+//! # fn main() {
+//! use one_password::Op;
+//! use one_password::export::{self, Format};
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let items = session.list_items_in(Some("Production")).unwrap();
+//! export::to_file(&items, "inventory.csv", Format::Csv).unwrap();
+//! # }
+//! # #[cfg(not(feature = "process"))]
+//! # fn main() {}
+//! ```
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use csv;
+use serde_json;
+
+use super::{ItemSummary, Result};
+
+#[cfg(feature = "onepux")]
+pub mod onepux;
+
+/// File format of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One row per item with a header row. Tags are separated by `;`.
+    Csv,
+    /// Pretty printed JSON array with one object per item.
+    Json,
+}
+
+impl Format {
+    /// Guess the format from the extension of `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Format> {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("csv") => Some(Format::Csv),
+            Some(e) if e.eq_ignore_ascii_case("json") => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A row of a report.
+#[derive(Serialize)]
+struct Row<'a> {
+    id: &'a str,
+    title: &'a str,
+    vault: &'a str,
+    category: &'a str,
+    tags: String,
+    updated_at: &'a str,
+}
+
+impl<'a> Row<'a> {
+    fn new(item: &'a ItemSummary) -> Row<'a> {
+        Row {
+            id: &item.id,
+            title: &item.title,
+            vault: item.vault_name.as_ref().unwrap_or(&item.vault_id),
+            category: &item.category,
+            tags: item.tags.join(";"),
+            updated_at: item.updated_at.as_ref().map(|u| &u[..]).unwrap_or(""),
+        }
+    }
+}
+
+/// Write a CSV report of `items` to `writer`.
+pub fn write_csv<W: Write>(items: &[ItemSummary], writer: W) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for item in items {
+        writer.serialize(Row::new(item))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write a JSON report of `items` to `writer`.
+pub fn write_json<W: Write>(items: &[ItemSummary], writer: W) -> Result<()> {
+    let rows : Vec<Row> = items.iter().map(Row::new).collect();
+    serde_json::to_writer_pretty(writer, &rows)?;
+    Ok(())
+}
+
+/// Write a report of `items` to `writer` in `format`.
+pub fn write<W: Write>(items: &[ItemSummary], writer: W, format: Format) -> Result<()> {
+    match format {
+        Format::Csv => write_csv(items, writer),
+        Format::Json => write_json(items, writer),
+    }
+}
+
+/// Create or truncate the file at `path` and write a report of `items` to it.
+pub fn to_file<P: AsRef<Path>>(items: &[ItemSummary], path: P, format: Format) -> Result<()> {
+    write(items, File::create(path)?, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item() -> ItemSummary {
+        ItemSummary {
+            id: "abc".to_owned(),
+            title: "Example, Inc.".to_owned(),
+            vault_id: "v".to_owned(),
+            vault_name: Some("Private".to_owned()),
+            category: "LOGIN".to_owned(),
+            tags: vec!["web".to_owned(), "prod".to_owned()],
+            favorite: false,
+            created_at: None,
+            updated_at: Some("2018-09-25T10:00:00Z".to_owned()),
+            version: None,
+            last_edited_by: None,
+            urls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn csv_report() {
+        let mut out = Vec::new();
+        write_csv(&[item()], &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+            "id,title,vault,category,tags,updated_at\nabc,\"Example, Inc.\",Private,LOGIN,web;prod,2018-09-25T10:00:00Z\n");
+    }
+
+    #[test]
+    fn json_report() {
+        let mut out = Vec::new();
+        write(&[item()], &mut out, Format::from_path("inventory.JSON").unwrap()).unwrap();
+        let rows : serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(rows[0]["vault"], "Private");
+        assert_eq!(rows[0]["tags"], "web;prod");
+    }
+}
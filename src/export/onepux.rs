@@ -0,0 +1,302 @@
+//! Offline access to `.1pux` export archives.
+//!
+//! A `.1pux` file, as written by *File > Export* in 1Password 8, is a zip
+//! archive with the accounts, vaults and items in `export.data` and any
+//! attached documents under `files/`. Reading one doesn't need `op` or a
+//! network connection, which makes it useful for migrations and audits.
+//!
+//! The archive isn't encrypted: treat it, and the values read from it, with
+//! the same care as the vault itself.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use one_password::export::onepux;
+//!
+//! let export = onepux::open("1PasswordExport.1pux").unwrap();
+//! for item in export.items() {
+//!     println!("{} {}", item.category, item.title);
+//! }
+//! ```
+use std::fs::File;
+use std::io::{self, Read, Seek};
+use std::path::Path;
+
+use serde_json;
+use zip::ZipArchive;
+
+use summary;
+use super::super::{ItemSummary, ItemUrl, Result, Secret};
+
+/// Name of the entry holding the accounts, vaults and items.
+pub const DATA_ENTRY: &str = "export.data";
+
+/// Contents of `export.data`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    pub accounts: Vec<Account>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountAttrs {
+    #[serde(default)]
+    pub account_name: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub email: String,
+    pub uuid: String,
+    #[serde(default)]
+    pub domain: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub attrs: AccountAttrs,
+    #[serde(default)]
+    pub vaults: Vec<Vault>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VaultAttrs {
+    pub uuid: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub desc: String,
+    /// `P` for the personal vault, `E` for everyone, `U` for user created vaults.
+    #[serde(default, rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Vault {
+    pub attrs: VaultAttrs,
+    #[serde(default)]
+    pub items: Vec<Item>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    #[serde(default)]
+    pub label: String,
+    pub url: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Overview {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub subtitle: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub urls: Vec<Url>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A field of a login form, such as the username and password.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginField {
+    pub value: Secret<String>,
+    #[serde(default)]
+    pub name: String,
+    /// `T` for text, `P` for password, `E` for email and so on.
+    #[serde(default)]
+    pub field_type: String,
+    /// `username` or `password` for the fields filled in by 1Password.
+    pub designation: Option<String>,
+}
+
+/// A field in a section, whose value is an object keyed by its type, e.g.
+/// `{"concealed": "hunter2"}` or `{"date": 1614297600}`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SectionField {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub id: String,
+    pub value: Secret<serde_json::Value>,
+}
+
+impl SectionField {
+    /// The value if it is a string, e.g. a `string`, `concealed` or `totp` field.
+    pub fn text(&self) -> Option<Secret<String>> {
+        self.value.expose().as_object()
+            .and_then(|o| o.values().next())
+            .and_then(|v| v.as_str())
+            .map(|v| Secret::new(v.to_owned()))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub fields: Vec<SectionField>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Details {
+    #[serde(default)]
+    pub login_fields: Vec<LoginField>,
+    pub notes_plain: Option<Secret<String>>,
+    /// Set for `PASSWORD` items.
+    pub password: Option<Secret<String>>,
+    #[serde(default)]
+    pub sections: Vec<Section>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Item {
+    pub uuid: String,
+    #[serde(default)]
+    pub fav_index: i64,
+    /// Seconds since the Unix epoch.
+    #[serde(default)]
+    pub created_at: i64,
+    /// Seconds since the Unix epoch.
+    #[serde(default)]
+    pub updated_at: i64,
+    /// `active` or `archived`.
+    #[serde(default)]
+    pub state: String,
+    pub category_uuid: String,
+    pub details: Details,
+    pub overview: Overview,
+}
+
+impl Item {
+    fn designated(&self, designation: &str) -> Option<&Secret<String>> {
+        self.details.login_fields.iter()
+            .find(|f| f.designation.as_ref().map(|d| &d[..]) == Some(designation))
+            .map(|f| &f.value)
+    }
+
+    /// The username of a login.
+    pub fn username(&self) -> Option<&Secret<String>> {
+        self.designated("username")
+    }
+
+    /// The password of a login, or of a `PASSWORD` item.
+    pub fn password(&self) -> Option<&Secret<String>> {
+        self.designated("password").or(self.details.password.as_ref())
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.state == "archived"
+    }
+
+    /// Describe this item like `OpSession::list_items` would.
+    pub fn summary(&self, vault: &Vault) -> ItemSummary {
+        ItemSummary {
+            id: self.uuid.clone(),
+            title: self.overview.title.clone(),
+            vault_id: vault.attrs.uuid.clone(),
+            vault_name: Some(vault.attrs.name.clone()),
+            category: summary::category(self.category_uuid.clone()),
+            tags: self.overview.tags.clone(),
+            favorite: self.fav_index > 0,
+            created_at: Some(summary::rfc3339(self.created_at)),
+            updated_at: Some(summary::rfc3339(self.updated_at)),
+            version: None,
+            last_edited_by: None,
+            urls: self.overview.urls.iter()
+                .map(|u| ItemUrl { href: u.url.clone(), label: u.label.clone(), primary: u.url == self.overview.url })
+                .collect(),
+        }
+    }
+}
+
+impl Export {
+    /// Summaries of all items in all vaults of all accounts.
+    pub fn items(&self) -> Vec<ItemSummary> {
+        self.accounts.iter()
+            .flat_map(|a| a.vaults.iter())
+            .flat_map(|v| v.items.iter().map(move |i| i.summary(v)))
+            .collect()
+    }
+}
+
+/// Read the `export.data` entry of a `.1pux` archive.
+pub fn read<R: Read + Seek>(reader: R) -> Result<Export> {
+    let mut archive = ZipArchive::new(reader).map_err(io::Error::from)?;
+    let entry = archive.by_name(DATA_ENTRY).map_err(io::Error::from)?;
+    Ok(serde_json::from_reader(entry)?)
+}
+
+/// Open the `.1pux` archive at `path` and read its `export.data`.
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Export> {
+    read(File::open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    const DATA: &str = r#"{"accounts": [{
+        "attrs": {"accountName": "Acme", "name": "Jane", "email": "jane@acme.test", "uuid": "A1", "domain": "acme.1password.com"},
+        "vaults": [{
+            "attrs": {"uuid": "V1", "name": "Private", "desc": "", "type": "P"},
+            "items": [{
+                "uuid": "I1", "favIndex": 1, "createdAt": 1614298956, "updatedAt": 1614298956,
+                "state": "active", "categoryUuid": "001",
+                "details": {
+                    "loginFields": [
+                        {"value": "jane", "name": "username", "fieldType": "T", "designation": "username"},
+                        {"value": "hunter2", "name": "password", "fieldType": "P", "designation": "password"}
+                    ],
+                    "notesPlain": "",
+                    "sections": [{"title": "Extra", "name": "extra", "fields": [
+                        {"title": "pin", "id": "pin", "value": {"concealed": "1234"}}
+                    ]}]
+                },
+                "overview": {"title": "Acme", "url": "https://acme.test", "tags": ["work"]}
+            }]
+        }]
+    }]}"#;
+
+    #[test]
+    fn reads_archive() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(DATA_ENTRY, SimpleFileOptions::default()).unwrap();
+        zip.write_all(DATA.as_bytes()).unwrap();
+        let archive = zip.finish().unwrap();
+
+        let export = read(Cursor::new(archive.into_inner())).unwrap();
+        let vault = &export.accounts[0].vaults[0];
+        let item = &vault.items[0];
+        assert_eq!(item.username().unwrap().expose(), "jane");
+        assert_eq!(item.password().unwrap().expose(), "hunter2");
+        assert_eq!(item.details.sections[0].fields[0].text().unwrap().expose(), "1234");
+        assert_eq!(export.items(), vec![ItemSummary {
+            id: "I1".to_owned(),
+            title: "Acme".to_owned(),
+            vault_id: "V1".to_owned(),
+            vault_name: Some("Private".to_owned()),
+            category: "LOGIN".to_owned(),
+            tags: vec!["work".to_owned()],
+            favorite: true,
+            created_at: Some("2021-02-26T00:22:36Z".to_owned()),
+            updated_at: Some("2021-02-26T00:22:36Z".to_owned()),
+            version: None,
+            last_edited_by: None,
+            urls: Vec::new(),
+        }]);
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Error, ErrorKind, Result};
+#[cfg(feature = "process")]
+use serde_json::Value;
+#[cfg(feature = "process")]
+use compat;
+#[cfg(feature = "process")]
+use super::{OpSession, Secret, CONCEALED_PLACEHOLDER, REVEAL_SINCE};
+
+/// The field of an item, by label, optionally within a section: `password`
+/// or `Database/password`.
+///
+/// Items with several sections often reuse labels such as `username`, which
+/// only the section tells apart. Accepted by `OpSession::get_field` and,
+/// with `FieldPath::assign`, by `ItemEdit`. A `/` or `\` in a label is
+/// escaped with `\`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FieldPath {
+    pub section: Option<String>,
+    pub field: String,
+}
+
+impl FieldPath {
+    /// The field labelled `field`, in any section.
+    pub fn new(field: &str) -> FieldPath {
+        FieldPath { section: None, field: field.to_owned() }
+    }
+
+    /// The field labelled `field` in `section`, by label or id.
+    pub fn in_section(section: &str, field: &str) -> FieldPath {
+        FieldPath { section: Some(section.to_owned()), field: field.to_owned() }
+    }
+}
+
+/// Escape `/` and `\` in a section or field label.
+fn escape(label: &str) -> String {
+    label.replace('\\', r"\\").replace('/', r"\/")
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref section) = self.section {
+            write!(f, "{}/", escape(section))?;
+        }
+        f.write_str(&escape(&self.field))
+    }
+}
+
+impl FromStr for FieldPath {
+    type Err = Error;
+
+    /// Parse `field` or `section/field`. A backslash escapes the next `/` or
+    /// `\`, and is kept before anything else.
+    fn from_str(s: &str) -> Result<FieldPath> {
+        let mut parts = vec![String::new()];
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some(next @ '/') | Some(next @ '\\') => parts.last_mut().unwrap().push(next),
+                    Some(next) => parts.last_mut().unwrap().extend(&['\\', next]),
+                    None => parts.last_mut().unwrap().push('\\'),
+                },
+                '/' => parts.push(String::new()),
+                c => parts.last_mut().unwrap().push(c),
+            }
+        }
+        if parts.iter().any(String::is_empty) {
+            bail!(ErrorKind::InvalidFieldPath(s.to_owned()));
+        }
+        let field = parts.pop().unwrap_or_default();
+        match parts.pop() {
+            None => Ok(FieldPath { section: None, field }),
+            Some(section) if parts.is_empty() => Ok(FieldPath { section: Some(section), field }),
+            Some(_) => bail!(ErrorKind::InvalidFieldPath(s.to_owned())),
+        }
+    }
+}
+
+/// Whether `value`, a section or field of `op` 2.x item JSON, has `label` or id `label`.
+#[cfg(feature = "process")]
+fn named(value: &Value, label: &str) -> bool {
+    ["label", "id"].iter().any(|key| value.get(key).and_then(Value::as_str) == Some(label))
+}
+
+#[cfg(feature = "process")]
+impl OpSession {
+    /// Value of the field `field` in `section` of `item`, looked up in the
+    /// item JSON since `op item get --fields` doesn't take a section.
+    pub(crate) fn section_field(&self, item: &str, section: &str, field: &str, vault: Option<&str>, reveal: bool)
+            -> Result<Secret<String>> {
+        let version = self.config.cli_version()?;
+        let subcommand : &[&str] = if version.is_v2() { &["item", "get"] } else { &["get", "item"] };
+        let mut command = self.item_command(subcommand, vault);
+        command.target(item).json()?;
+        if reveal && version >= REVEAL_SINCE {
+            command.arg("--reveal");
+        }
+        let json : Value = command.run_json(|d| ErrorKind::GetCommand(item.to_owned(), d))?;
+        let json = if version.is_v2() { json } else { compat::v1_to_v2(&json) };
+        let path = FieldPath::in_section(section, field).to_string();
+        let value = json.get("fields").and_then(Value::as_array).into_iter().flatten()
+            .find(|f| named(f, field) && f.get("section").is_some_and(|s| named(s, section)))
+            .ok_or_else(|| ErrorKind::FieldNotFound(item.to_owned(), path.clone()))?
+            .get("value").and_then(Value::as_str).unwrap_or("");
+        if value.starts_with(CONCEALED_PLACEHOLDER) {
+            bail!(ErrorKind::ConcealedField(item.to_owned(), path));
+        }
+        Ok(self.config.secret(value.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let path : FieldPath = "password".parse().unwrap();
+        assert_eq!(path, FieldPath::new("password"));
+        let path : FieldPath = "Database/password".parse().unwrap();
+        assert_eq!(path, FieldPath::in_section("Database", "password"));
+        let path : FieldPath = r"Client ID\/Secret".parse().unwrap();
+        assert_eq!(path, FieldPath::new("Client ID/Secret"));
+        assert_eq!(path.to_string(), r"Client ID\/Secret");
+        let path : FieldPath = r"C:\Users/a\\b".parse().unwrap();
+        assert_eq!(path, FieldPath::in_section(r"C:\Users", r"a\b"));
+        assert!("a/b/c".parse::<FieldPath>().is_err());
+        assert!("/password".parse::<FieldPath>().is_err());
+        assert!("".parse::<FieldPath>().is_err());
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn section_field() {
+        use cassette::{interaction, Cassette, Replay};
+        use {CliVersion, Op};
+
+        let item = r#"{"id": "abc", "title": "Servers", "fields": [
+            {"id": "u1", "label": "username", "value": "alice", "section": {"id": "s1", "label": "Staging"}},
+            {"id": "u2", "label": "username", "value": "bob", "section": {"id": "s2", "label": "Production"}}
+        ]}"#;
+        let get = || {
+            interaction("item get", &["--session=⟨redacted⟩", "abc", "--format", "json", "--no-color", "--iso-timestamps"], item)
+        };
+        let cassette = Cassette { interactions: vec![get(), get()] };
+        let op = Op::new("op").with_backend(Replay::new(cassette)).with_cli_version(CliVersion::new(2, 18, 0));
+        let session = op.session("token");
+        assert_eq!(session.get_field("abc", "Production/username").unwrap().expose(), "bob");
+        match *session.get_field("abc", "Development/username").unwrap_err().kind() {
+            ErrorKind::FieldNotFound(ref item, ref field) => assert_eq!((&item[..], &field[..]), ("abc", "Development/username")),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+}
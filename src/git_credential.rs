@@ -0,0 +1,284 @@
+//! The git credential helper protocol, backed by login items.
+//!
+//! Each credential is stored as a login item titled after the URL it is for,
+//! e.g. `https://github.com`, or `https://github.com/org/repo.git` when git is
+//! configured with `credential.useHttpPath`, and tagged with `TAG`. Items
+//! without the tag, or with another username, are never changed or deleted,
+//! so the helper can't clobber logins saved by hand or by another user. A helper binary only has to pass its arguments and
+//! standard streams on:
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use std::env;
+//! use std::io;
+//! use one_password::Op;
+//! use one_password::git_credential::CredentialHelper;
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let helper = CredentialHelper::new(session).vault("Git");
+//! let operation = env::args().nth(1).unwrap_or_default();
+//! let stdin = io::stdin();
+//! helper.run(&operation, stdin.lock(), io::stdout()).unwrap();
+//! ```
+//!
+//! Configure it with `git config credential.helper /path/to/helper`.
+use std::io::{BufRead, Write};
+
+use diagnostics::not_found;
+use super::{ErrorKind, ItemBuilder, ItemEdit, ItemSummary, OpSession, Result, Secret};
+
+/// Tag added to items created by the helper, the only ones it changes or deletes.
+pub const TAG: &str = "git-credential";
+
+/// Attributes git passes to and expects from a credential helper.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Credential {
+    pub protocol: Option<String>,
+    pub host: Option<String>,
+    pub path: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+}
+
+impl Credential {
+    /// Read `key=value` lines up to an empty line or the end of input.
+    /// Unknown keys are ignored.
+    pub fn read<R: BufRead>(input: R) -> Result<Credential> {
+        let mut credential = Credential::default();
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = match line.find('=') {
+                Some(index) => (&line[..index], line[index + 1..].to_owned()),
+                None => continue,
+            };
+            match key {
+                "protocol" => credential.protocol = Some(value),
+                "host" => credential.host = Some(value),
+                "path" => credential.path = Some(value),
+                "username" => credential.username = Some(value),
+                "password" => credential.password = Some(Secret::new(value)),
+                _ => {},
+            }
+        }
+        Ok(credential)
+    }
+
+    /// Write the username and password as expected in reply to `get`.
+    pub fn write<W: Write>(&self, mut output: W) -> Result<()> {
+        if let Some(ref username) = self.username {
+            writeln!(output, "username={}", username)?;
+        }
+        if let Some(ref password) = self.password {
+            writeln!(output, "password={}", password.expose())?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+
+    /// URL identifying the credential, used as the item title.
+    pub fn url(&self) -> String {
+        let mut url = format!("{}://{}",
+            self.protocol.as_ref().map(|p| &p[..]).unwrap_or("https"),
+            self.host.as_ref().map(|h| &h[..]).unwrap_or(""));
+        if let Some(ref path) = self.path {
+            url.push('/');
+            url.push_str(path.trim_start_matches('/'));
+        }
+        url
+    }
+}
+
+/// Serves git credential requests from login items.
+#[derive(Debug, Clone)]
+pub struct CredentialHelper {
+    session: OpSession,
+    vault: Option<String>,
+}
+
+impl CredentialHelper {
+    pub fn new(session: OpSession) -> CredentialHelper {
+        CredentialHelper { session, vault: None }
+    }
+
+    /// Keep credentials in `vault` instead of the default vault of the session.
+    pub fn vault(mut self, vault: &str) -> CredentialHelper {
+        self.vault = Some(vault.to_owned());
+        self
+    }
+
+    fn vault_ref(&self) -> Option<&str> {
+        self.vault.as_ref().map(|v| &v[..])
+    }
+
+    /// Look up the username and password for `request`, `None` if there is no item for it.
+    pub fn get(&self, request: &Credential) -> Result<Option<Credential>> {
+        let title = request.url();
+        let password = match not_found(self.session.get_field_revealed_in(&title, "password", self.vault_ref()))? {
+            Some(password) => password,
+            None => return Ok(None),
+        };
+        let username = not_found(self.session.get_field_in(&title, "username", self.vault_ref()))?;
+        Ok(Some(Credential {
+            username: username.map(Secret::into_inner).filter(|u| !u.is_empty()),
+            password: Some(password),
+            ..request.clone()
+        }))
+    }
+
+    /// The item titled `title`, if there is one.
+    fn item(&self, title: &str) -> Result<Option<ItemSummary>> {
+        not_found(self.session.get_item_summary_in(title, self.vault_ref()))
+    }
+
+    /// Save the username and password of `credential`, replacing an existing
+    /// item for it.
+    ///
+    /// Fails with `ErrorKind::ForeignCredentialItem` if the existing item
+    /// isn't tagged with `TAG`, and with `ErrorKind::CredentialUserMismatch`
+    /// if it holds the credential of another user.
+    pub fn store(&self, credential: &Credential) -> Result<()> {
+        let title = credential.url();
+        let username = credential.username.as_ref().map(|u| &u[..]).unwrap_or("");
+        let password = credential.password.as_ref().map(|p| &p.expose()[..]).unwrap_or("");
+        match self.item(&title)? {
+            Some(ref item) if !item.tags.iter().any(|t| t == TAG) => bail!(ErrorKind::ForeignCredentialItem(title)),
+            Some(item) => {
+                if self.session.get_field_in(&item.id, "username", self.vault_ref())?.expose() != username {
+                    bail!(ErrorKind::CredentialUserMismatch(title));
+                }
+                let edit = ItemEdit::new().username(username).password(password);
+                self.session.edit_item_in(&item.id, &edit, self.vault_ref())?;
+            },
+            None => {
+                let mut login = ItemBuilder::login(&title)
+                    .url(&title)
+                    .username(username)
+                    .password(password)
+                    .tag(TAG);
+                if let Some(ref vault) = self.vault {
+                    login = login.vault(vault);
+                }
+                self.session.create_item(&login)?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Delete the item for `credential`, if there is one, it is tagged with
+    /// `TAG` and its username is the one in `credential`. Git asks to erase
+    /// credentials it got rejected, which may not be the stored ones.
+    pub fn erase(&self, credential: &Credential) -> Result<()> {
+        let item = match self.item(&credential.url())? {
+            Some(ref item) if !item.tags.iter().any(|t| t == TAG) => return Ok(()),
+            Some(item) => item,
+            None => return Ok(()),
+        };
+        let username = self.session.get_field_in(&item.id, "username", self.vault_ref())?;
+        if username.expose() == credential.username.as_ref().map(|u| &u[..]).unwrap_or("") {
+            not_found(self.session.delete_item_in(&item.id, self.vault_ref()))?;
+        }
+        Ok(())
+    }
+
+    /// Handle the helper `operation` git invoked the helper with, reading the
+    /// request from `input` and writing any reply to `output`.
+    ///
+    /// Unknown operations are ignored, as the protocol requires.
+    pub fn run<R: BufRead, W: Write>(&self, operation: &str, input: R, output: W) -> Result<()> {
+        let request = Credential::read(input)?;
+        match operation {
+            "get" => {
+                if let Some(credential) = self.get(&request)? {
+                    credential.write(output)?;
+                }
+            },
+            "store" => self.store(&request)?,
+            "erase" => self.erase(&request)?,
+            _ => {},
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{interaction, replay_op};
+
+    #[test]
+    fn reads_requests() {
+        let input = "protocol=https\nhost=example.com\npath=org/repo.git\nusername=bob\n\nignored=1\n";
+        let credential = Credential::read(input.as_bytes()).unwrap();
+        assert_eq!(credential.url(), "https://example.com/org/repo.git");
+        assert_eq!(credential.username, Some("bob".to_owned()));
+        assert_eq!(credential.password, None);
+    }
+
+    #[test]
+    fn answers_get() {
+        let get = |args: &[&str], stdout: &str| {
+            interaction("item get", &[&["--session=⟨redacted⟩", "https://example.com", "--fields"][..], args].concat(), stdout)
+        };
+        let op = replay_op(vec![
+            get(&["label=password", "--reveal"], "hunter2\n"),
+            get(&["label=username"], "bob\n"),
+            get(&["label=password", "--reveal"], "").failing("[ERROR] item not found", 1),
+        ]);
+        let helper = CredentialHelper::new(op.session("token"));
+
+        let mut output = Vec::new();
+        helper.run("get", "protocol=https\nhost=example.com\n\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "username=bob\npassword=hunter2\n");
+
+        let mut output = Vec::new();
+        helper.run("get", "protocol=https\nhost=example.com\n\n".as_bytes(), &mut output).unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn only_touches_own_items() {
+        let summary = |id: &str, tags: &str| {
+            interaction("item get", &["--session=⟨redacted⟩", "https://example.com", "--format", "json", "--no-color", "--iso-timestamps"],
+                &format!(r#"{{"id": "{}", "title": "https://example.com", "vault": {{"id": "v"}}, "tags": [{}]}}"#, id, tags))
+        };
+        let username = interaction("item get", &["--session=⟨redacted⟩", "a", "--fields", "label=username"], "bob\n");
+        let item = r#"{"id": "a", "title": "https://example.com", "vault": {"id": "v"}, "tags": ["git-credential"]}"#;
+        let op = replay_op(vec![
+            summary("m", ""),
+            summary("m", ""),
+            summary("a", r#""git-credential""#),
+            username.clone(),
+            interaction("item get", &["--session=⟨redacted⟩", "a", "--format", "json", "--no-color", "--iso-timestamps",
+                "--reveal"], item),
+            interaction("item edit", &["--session=⟨redacted⟩", "a", "--format", "json", "--no-color", "--iso-timestamps"],
+                item),
+            summary("a", r#""git-credential""#),
+            username.clone(),
+            summary("a", r#""git-credential""#),
+            username.clone(),
+            summary("a", r#""git-credential""#),
+            username,
+            interaction("item delete", &["--session=⟨redacted⟩", "a"], ""),
+        ]);
+        let helper = CredentialHelper::new(op.session("token"));
+        let request = "protocol=https\nhost=example.com\nusername=bob\npassword=hunter2\n\n";
+
+        match *helper.run("store", request.as_bytes(), Vec::new()).unwrap_err().kind() {
+            ErrorKind::ForeignCredentialItem(ref title) => assert_eq!(title, "https://example.com"),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        helper.run("erase", request.as_bytes(), Vec::new()).unwrap();
+        helper.run("store", request.as_bytes(), Vec::new()).unwrap();
+        let alice = "protocol=https\nhost=example.com\nusername=alice\npassword=secret\n\n";
+        match *helper.run("store", alice.as_bytes(), Vec::new()).unwrap_err().kind() {
+            ErrorKind::CredentialUserMismatch(ref title) => assert_eq!(title, "https://example.com"),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        helper.run("erase", "protocol=https\nhost=example.com\nusername=alice\n\n".as_bytes(), Vec::new()).unwrap();
+        helper.run("erase", request.as_bytes(), Vec::new()).unwrap();
+    }
+}
@@ -0,0 +1,238 @@
+//! Bulk creation of logins, e.g. to migrate credentials into 1Password.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use std::fs::File;
+//! use one_password::Op;
+//! use one_password::import::{self, ImportOptions};
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let records = import::read_csv(File::open("logins.csv").unwrap()).unwrap();
+//! let options = ImportOptions::new().concurrency(4).progress(|done, total| {
+//!     eprintln!("{}/{}", done, total);
+//! });
+//! let report = session.import_logins(records, &options);
+//! for failure in &report.failed {
+//!     eprintln!("row {} ({}): {}", failure.row, failure.title, failure.error);
+//! }
+//! ```
+use std::fmt;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use csv;
+use pool;
+use serde::{Deserialize, Deserializer};
+
+use super::{Error, ItemBuilder, ItemSummary, OpSession, Result, Secret};
+
+pub mod onepassword;
+
+/// A login to create, as read from CSV or any other serde format.
+///
+/// CSV files need a header row naming the columns `title`, `username`,
+/// `password`, `url`, `vault` and `tags`. Only `title` is required. In CSV,
+/// tags are separated by `;`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LoginRecord {
+    pub title: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<Secret<String>>,
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Vault to create the login in, instead of the default vault of the session.
+    #[serde(default)]
+    pub vault: Option<String>,
+    #[serde(default, deserialize_with = "tags")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Tags {
+    Joined(String),
+    List(Vec<String>),
+}
+
+fn tags<'de, D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Vec<String>, D::Error> {
+    Ok(match Tags::deserialize(deserializer)? {
+        Tags::Joined(s) => s.split(';').map(str::trim).filter(|t| !t.is_empty()).map(str::to_owned).collect(),
+        Tags::List(list) => list,
+    })
+}
+
+impl LoginRecord {
+    fn builder(&self) -> ItemBuilder {
+        let mut builder = ItemBuilder::login(&self.title);
+        if let Some(ref vault) = self.vault {
+            builder = builder.vault(vault);
+        }
+        if let Some(ref url) = self.url {
+            builder = builder.url(url);
+        }
+        if let Some(ref username) = self.username {
+            builder = builder.username(username);
+        }
+        if let Some(ref password) = self.password {
+            builder = builder.password(password.expose());
+        }
+        for tag in &self.tags {
+            builder = builder.tag(tag);
+        }
+        builder
+    }
+}
+
+/// Read login records from CSV with a header row.
+pub fn read_csv<R: Read>(reader: R) -> Result<Vec<LoginRecord>> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let mut records = Vec::new();
+    for record in reader.deserialize() {
+        records.push(record?);
+    }
+    Ok(records)
+}
+
+type ProgressCallback<'a> = Box<dyn Fn(usize, usize) + Send + Sync + 'a>;
+
+/// Options for `OpSession::import_logins` and `OpSession::import_items`.
+pub struct ImportOptions<'a> {
+    concurrency: usize,
+    progress: Option<ProgressCallback<'a>>,
+}
+
+impl<'a> Default for ImportOptions<'a> {
+    fn default() -> ImportOptions<'a> {
+        ImportOptions { concurrency: 1, progress: None }
+    }
+}
+
+impl<'a> fmt::Debug for ImportOptions<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ImportOptions")
+            .field("concurrency", &self.concurrency)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl<'a> ImportOptions<'a> {
+    pub fn new() -> ImportOptions<'a> {
+        ImportOptions::default()
+    }
+
+    /// Number of logins to create at the same time. Defaults to 1.
+    ///
+    /// The total number of `op` processes is still limited by `set_max_concurrency`.
+    pub fn concurrency(mut self, concurrency: usize) -> ImportOptions<'a> {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Call `progress` with the number of finished and total records after each record.
+    pub fn progress<F: Fn(usize, usize) + Send + Sync + 'a>(mut self, progress: F) -> ImportOptions<'a> {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+}
+
+/// A record that could not be created.
+#[derive(Debug)]
+pub struct ImportFailure {
+    /// Index of the record, starting at 0.
+    pub row: usize,
+    pub title: String,
+    pub error: Error,
+}
+
+/// Result of `OpSession::import_logins` and `OpSession::import_items`.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Index of each created record and the created item, in record order.
+    pub created: Vec<(usize, ItemSummary)>,
+    /// Records that failed, in record order.
+    pub failed: Vec<ImportFailure>,
+}
+
+impl OpSession {
+    /// Create a login for each record.
+    ///
+    /// A failing record doesn't stop the import; it is listed in the report instead.
+    pub fn import_logins<I: IntoIterator<Item = LoginRecord>>(&self, records: I, options: &ImportOptions) -> ImportReport {
+        self.import_items(records.into_iter().map(|r| r.builder()), options)
+    }
+
+    /// Create an item for each builder, e.g. from `onepassword::Record::builder`.
+    ///
+    /// A failing item doesn't stop the import; it is listed in the report instead.
+    pub fn import_items<I: IntoIterator<Item = ItemBuilder>>(&self, items: I, options: &ImportOptions) -> ImportReport {
+        let records : Vec<ItemBuilder> = items.into_iter().collect();
+        let done = AtomicUsize::new(0);
+        let results = pool::map_concurrent(&records, options.concurrency, |_, record| {
+            let result = self.create_item(record);
+            let done = done.fetch_add(1, Ordering::SeqCst) + 1;
+            if let Some(ref progress) = options.progress {
+                progress(done, records.len());
+            }
+            result
+        });
+        let mut report = ImportReport::default();
+        for (row, (result, record)) in results.into_iter().zip(records).enumerate() {
+            match result {
+                Ok(item) => report.created.push((row, item)),
+                Err(error) => report.failed.push(ImportFailure { row, title: record.title().to_owned(), error }),
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use cassette::{interaction, replay_op, Interaction};
+
+    #[test]
+    fn reads_csv() {
+        let csv = "title,username,password,url,tags\nExample,user,hunter2,https://example.com,web; prod\nBare,,,,\n";
+        let records = read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].password.as_ref().unwrap().expose(), "hunter2");
+        assert_eq!(records[0].tags, vec!["web", "prod"]);
+        assert_eq!(records[1].username, None);
+        assert!(records[1].tags.is_empty());
+    }
+
+    #[test]
+    fn reports_failed_rows() {
+        let create = |title: &str, stdout: &str| -> Interaction {
+            interaction("item create", &["--session=⟨redacted⟩", "--category", "LOGIN", "--title", title, "--format", "json",
+                "--no-color", "--iso-timestamps"], stdout)
+        };
+        let op = replay_op(vec![
+            create("A", r#"{"id": "a", "title": "A", "vault": {"id": "v"}, "category": "LOGIN"}"#),
+            create("B", "").failing("", 1),
+        ]);
+        let records = read_csv("title,password\nA,x\nB,y\n".as_bytes()).unwrap();
+        let progress = Mutex::new(Vec::new());
+        let options = ImportOptions::new().concurrency(2).progress(|done, total| {
+            progress.lock().unwrap().push((done, total));
+        });
+        let report = op.session("token").import_logins(records, &options);
+        assert_eq!(report.created.len(), 1);
+        assert_eq!(report.created[0].1.id, "a");
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].row, 1);
+        assert_eq!(report.failed[0].title, "B");
+        drop(options);
+        let mut progress = progress.into_inner().unwrap();
+        progress.sort();
+        assert_eq!(progress, vec![(1, 2), (2, 2)]);
+    }
+}
@@ -0,0 +1,237 @@
+//! 1Password's own CSV import format.
+//!
+//! Each category has a fixed column layout and files have no header row,
+//! so the layout of a file has to be known up front. Columns after the last
+//! one of the layout are read as pairs of custom field labels and values.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use std::fs::File;
+//! use one_password::Op;
+//! use one_password::import::ImportOptions;
+//! use one_password::import::onepassword::{self, Layout};
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let cards = onepassword::read_csv(Layout::CreditCard, File::open("cards.csv").unwrap(), false).unwrap();
+//! let report = session.import_items(cards.iter().map(|c| c.builder()), &ImportOptions::new());
+//! ```
+use std::io::{Read, Write};
+
+use csv;
+
+use super::super::{ItemBuilder, Result, Secret};
+
+/// Column layout of a category, in file order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layout {
+    /// title, website, username, password, notes
+    Login,
+    /// title, website, password, notes
+    Password,
+    /// title, notes
+    SecureNote,
+    /// title, card number, expiry date, cardholder name, PIN, bank name, CVV, notes
+    CreditCard,
+    /// title, bank name, name on account, type, routing number, account number,
+    /// SWIFT, IBAN, PIN, phone, address, notes
+    BankAccount,
+    /// title, version, license key, licensed to, registered email, company,
+    /// download page, publisher, website, retail price, support email,
+    /// purchase date, order number, notes
+    SoftwareLicense,
+}
+
+/// Columns and whether their values are concealed.
+const LOGIN: &[(&str, bool)] = &[
+    ("title", false), ("website", false), ("username", false), ("password", true), ("notes", true),
+];
+const PASSWORD: &[(&str, bool)] = &[("title", false), ("website", false), ("password", true), ("notes", true)];
+const SECURE_NOTE: &[(&str, bool)] = &[("title", false), ("notes", true)];
+const CREDIT_CARD: &[(&str, bool)] = &[
+    ("title", false), ("card number", true), ("expiry date", false), ("cardholder name", false),
+    ("PIN", true), ("bank name", false), ("CVV", true), ("notes", true),
+];
+const BANK_ACCOUNT: &[(&str, bool)] = &[
+    ("title", false), ("bank name", false), ("name on account", false), ("type", false),
+    ("routing number", false), ("account number", true), ("SWIFT", false), ("IBAN", true),
+    ("PIN", true), ("phone", false), ("address", false), ("notes", true),
+];
+const SOFTWARE_LICENSE: &[(&str, bool)] = &[
+    ("title", false), ("version", false), ("license key", true), ("licensed to", false),
+    ("registered email", false), ("company", false), ("download page", false), ("publisher", false),
+    ("website", false), ("retail price", false), ("support email", false), ("purchase date", false),
+    ("order number", false), ("notes", true),
+];
+
+impl Layout {
+    fn columns(self) -> &'static [(&'static str, bool)] {
+        match self {
+            Layout::Login => LOGIN,
+            Layout::Password => PASSWORD,
+            Layout::SecureNote => SECURE_NOTE,
+            Layout::CreditCard => CREDIT_CARD,
+            Layout::BankAccount => BANK_ACCOUNT,
+            Layout::SoftwareLicense => SOFTWARE_LICENSE,
+        }
+    }
+
+    /// Names of the columns, in file order.
+    pub fn column_names(self) -> Vec<&'static str> {
+        self.columns().iter().map(|&(name, _)| name).collect()
+    }
+
+    /// Category in `op` 2.x spelling.
+    pub fn category(self) -> &'static str {
+        match self {
+            Layout::Login => "LOGIN",
+            Layout::Password => "PASSWORD",
+            Layout::SecureNote => "SECURE_NOTE",
+            Layout::CreditCard => "CREDIT_CARD",
+            Layout::BankAccount => "BANK_ACCOUNT",
+            Layout::SoftwareLicense => "SOFTWARE_LICENSE",
+        }
+    }
+}
+
+/// A row of a CSV file in 1Password's format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub layout: Layout,
+    /// Values of the columns of the layout, in order. Missing trailing columns are empty.
+    pub values: Vec<Secret<String>>,
+    /// Custom fields from the columns after the layout, as labels and values.
+    pub custom: Vec<(String, Secret<String>)>,
+}
+
+impl Record {
+    /// An empty record of `layout` with the given title.
+    pub fn new(layout: Layout, title: &str) -> Record {
+        let mut values = vec![Secret::default(); layout.columns().len()];
+        values[0] = Secret::new(title.to_owned());
+        Record { layout, values, custom: Vec::new() }
+    }
+
+    fn index(&self, column: &str) -> Option<usize> {
+        self.layout.columns().iter().position(|&(name, _)| name.eq_ignore_ascii_case(column))
+    }
+
+    /// The value of `column`, if the layout has it and it isn't empty.
+    pub fn get(&self, column: &str) -> Option<&Secret<String>> {
+        self.index(column).map(|i| &self.values[i]).filter(|v| !v.expose().is_empty())
+    }
+
+    /// Set the value of `column`. Returns false if the layout has no such column.
+    pub fn set(&mut self, column: &str, value: &str) -> bool {
+        match self.index(column) {
+            Some(i) => {
+                self.values[i] = Secret::new(value.to_owned());
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        self.values[0].expose()
+    }
+
+    /// An item to create with `OpSession::create_item` or `OpSession::import_items`.
+    ///
+    /// Website, username, password and notes map to the built-in fields of
+    /// the item; other columns become text or concealed fields with the
+    /// column name as label.
+    pub fn builder(&self) -> ItemBuilder {
+        let mut builder = ItemBuilder::new(self.layout.category(), self.title());
+        for (&(name, concealed), value) in self.layout.columns().iter().zip(&self.values).skip(1) {
+            let value = value.expose();
+            if value.is_empty() {
+                continue;
+            }
+            builder = match name {
+                "website" => builder.url(value),
+                "username" => builder.username(value),
+                "password" => builder.password(value),
+                "notes" => builder.notes(value),
+                _ if concealed => builder.concealed(name, value),
+                _ => builder.field(name, value),
+            };
+        }
+        for (label, value) in &self.custom {
+            builder = builder.field(label, value.expose());
+        }
+        builder
+    }
+}
+
+/// Read records of `layout`, skipping the first row if `has_headers`.
+pub fn read_csv<R: Read>(layout: Layout, reader: R, has_headers: bool) -> Result<Vec<Record>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(has_headers)
+        .flexible(true)
+        .from_reader(reader);
+    let width = layout.columns().len();
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let row = row?;
+        let mut values : Vec<Secret<String>> = row.iter().take(width).map(|v| Secret::new(v.to_owned())).collect();
+        values.resize(width, Secret::default());
+        let extra : Vec<&str> = row.iter().skip(width).collect();
+        let custom = extra.chunks(2)
+            .filter(|pair| !pair[0].is_empty())
+            .map(|pair| (pair[0].to_owned(), Secret::new(pair.get(1).cloned().unwrap_or("").to_owned())))
+            .collect();
+        records.push(Record { layout, values, custom });
+    }
+    Ok(records)
+}
+
+/// Write `records` without a header row, each in the columns of its layout.
+///
+/// The output contains the secret values of the records in plain text.
+pub fn write_csv<W: Write>(records: &[Record], writer: W) -> Result<()> {
+    let mut writer = csv::WriterBuilder::new().flexible(true).from_writer(writer);
+    for record in records {
+        let mut row : Vec<&str> = record.values.iter().map(|v| &v.expose()[..]).collect();
+        for (label, value) in &record.custom {
+            row.push(label);
+            row.push(value.expose());
+        }
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_records() {
+        let csv = "Example,https://example.com,user,hunter2,\"multi\nline\",Recovery code,abc\nBare\n";
+        let records = read_csv(Layout::Login, csv.as_bytes(), false).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].get("password").unwrap().expose(), "hunter2");
+        assert_eq!(records[0].custom, vec![("Recovery code".to_owned(), Secret::new("abc".to_owned()))]);
+        assert_eq!(records[1].title(), "Bare");
+        assert_eq!(records[1].get("username"), None);
+
+        let mut out = Vec::new();
+        write_csv(&records, &mut out).unwrap();
+        assert_eq!(read_csv(Layout::Login, &out[..], false).unwrap(), records);
+    }
+
+    #[test]
+    fn builds_items() {
+        let mut card = Record::new(Layout::CreditCard, "Visa");
+        assert!(card.set("card number", "4111111111111111"));
+        assert!(card.set("expiry date", "12/2030"));
+        assert!(!card.set("website", "https://example.com"));
+        let builder = card.builder();
+        assert_eq!(builder.title(), "Visa");
+        assert_eq!(format!("{:?}", builder).matches("card number[password]").count(), 1);
+        assert_eq!(format!("{:?}", builder).matches("expiry date[text]").count(), 1);
+    }
+}
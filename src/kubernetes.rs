@@ -0,0 +1,119 @@
+//! Kubernetes `Secret` manifests with values read from 1Password.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use std::collections::BTreeMap;
+//! use one_password::Op;
+//! use one_password::kubernetes::SecretManifest;
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let mut references = BTreeMap::new();
+//! references.insert("DB_PASSWORD".to_owned(), "op://Production/Database/password".to_owned());
+//! let manifest = SecretManifest::resolve(&session, "database", Some("prod"), &references).unwrap();
+//! println!("{}", manifest.to_yaml().expose());
+//! ```
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde_json;
+
+use super::{ErrorKind, OpSession, Result, Secret};
+
+/// An `Opaque` Kubernetes `Secret` with resolved values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretManifest {
+    pub name: String,
+    pub namespace: Option<String>,
+    pub data: BTreeMap<String, Secret<String>>,
+}
+
+fn valid_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+impl SecretManifest {
+    /// Read every reference in `references` with `OpSession::read` into a
+    /// secret named `name`, under the key it is mapped from.
+    pub fn resolve(session: &OpSession, name: &str, namespace: Option<&str>, references: &BTreeMap<String, String>) -> Result<SecretManifest> {
+        let mut data = BTreeMap::new();
+        for (key, reference) in references {
+            if !valid_key(key) {
+                return Err(ErrorKind::InvalidSecretKey(key.clone()).into());
+            }
+            data.insert(key.clone(), session.read(reference)?);
+        }
+        Ok(SecretManifest {
+            name: name.to_owned(),
+            namespace: namespace.map(str::to_owned),
+            data,
+        })
+    }
+
+    fn encoded(&self) -> BTreeMap<&str, String> {
+        self.data.iter().map(|(key, value)| (&key[..], STANDARD.encode(value.expose()))).collect()
+    }
+
+    /// Render the manifest as JSON.
+    pub fn to_json(&self) -> Secret<String> {
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("name".to_owned(), self.name.clone().into());
+        if let Some(ref namespace) = self.namespace {
+            metadata.insert("namespace".to_owned(), namespace.clone().into());
+        }
+        let manifest = json!({
+            "apiVersion": "v1",
+            "kind": "Secret",
+            "metadata": metadata,
+            "type": "Opaque",
+            "data": self.encoded(),
+        });
+        Secret::new(serde_json::to_string_pretty(&manifest).expect("JSON values always serialize"))
+    }
+
+    /// Render the manifest as YAML.
+    pub fn to_yaml(&self) -> Secret<String> {
+        // JSON strings are valid YAML double-quoted scalars.
+        let quote = |s: &str| serde_json::to_string(s).expect("strings always serialize");
+        let mut yaml = String::from("apiVersion: v1\nkind: Secret\nmetadata:\n");
+        let _ = writeln!(yaml, "  name: {}", quote(&self.name));
+        if let Some(ref namespace) = self.namespace {
+            let _ = writeln!(yaml, "  namespace: {}", quote(namespace));
+        }
+        yaml.push_str("type: Opaque\ndata:");
+        if self.data.is_empty() {
+            yaml.push_str(" {}");
+        }
+        yaml.push('\n');
+        for (key, value) in self.encoded() {
+            let _ = writeln!(yaml, "  {}: {}", key, value);
+        }
+        Secret::new(yaml)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{read_interaction, replay_op};
+
+    #[test]
+    fn renders_manifest() {
+        let read = read_interaction("op://Prod/DB/password", "hunter2");
+        let op = replay_op(vec![read]);
+        let mut references = BTreeMap::new();
+        references.insert("password".to_owned(), "op://Prod/DB/password".to_owned());
+        let manifest = SecretManifest::resolve(&op.session("token"), "db", Some("prod"), &references).unwrap();
+        assert_eq!(manifest.to_yaml().expose(),
+            "apiVersion: v1\nkind: Secret\nmetadata:\n  name: \"db\"\n  namespace: \"prod\"\ntype: Opaque\ndata:\n  password: aHVudGVyMg==\n");
+        let json : serde_json::Value = serde_json::from_str(manifest.to_json().expose()).unwrap();
+        assert_eq!(json["data"]["password"], "aHVudGVyMg==");
+        assert_eq!(json["metadata"]["namespace"], "prod");
+
+        references.insert("not valid".to_owned(), "op://Prod/DB/password".to_owned());
+        assert!(SecretManifest::resolve(&op.session("token"), "db", None, &references).is_err());
+    }
+}
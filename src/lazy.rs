@@ -0,0 +1,131 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reference::SecretReference;
+use super::{OpSession, Result, Secret};
+
+/// A configuration value that lives in 1Password, read on first use.
+///
+/// The value is read with `OpSession::read` the first time `get` is called
+/// and then cached, for ever or until it is older than the TTL given to
+/// `with_ttl`. `refresh` reads it again right away.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use std::time::Duration;
+/// use one_password::{LazySecret, Op};
+///
+/// let session = Op::which().unwrap().env_session().unwrap();
+/// let db_password = LazySecret::new(session, "op://Production/Database/password").unwrap()
+///     .with_ttl(Duration::from_secs(3600));
+/// // Nothing has been read yet.
+/// let password = db_password.get().unwrap();
+/// ```
+pub struct LazySecret {
+    session: OpSession,
+    reference: String,
+    ttl: Option<Duration>,
+    cached: Mutex<Option<(Secret<String>, Instant)>>,
+}
+
+impl fmt::Debug for LazySecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LazySecret")
+            .field("reference", &self.reference)
+            .field("ttl", &self.ttl)
+            .field("resolved", &self.is_resolved())
+            .finish()
+    }
+}
+
+impl LazySecret {
+    /// Create a handle for `reference`, which is checked to be a valid secret
+    /// reference but not read.
+    pub fn new(session: OpSession, reference: &str) -> Result<LazySecret> {
+        reference.parse::<SecretReference>()?;
+        Ok(LazySecret {
+            session,
+            reference: reference.to_owned(),
+            ttl: None,
+            cached: Mutex::new(None),
+        })
+    }
+
+    /// Read the value again when it is older than `ttl`.
+    pub fn with_ttl(mut self, ttl: Duration) -> LazySecret {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    fn fresh(&self, read_at: Instant) -> bool {
+        self.ttl.map(|ttl| read_at.elapsed() < ttl).unwrap_or(true)
+    }
+
+    /// Whether a value is cached that is not past its TTL.
+    pub fn is_resolved(&self) -> bool {
+        match *self.cached.lock().unwrap_or_else(|p| p.into_inner()) {
+            Some((_, read_at)) => self.fresh(read_at),
+            None => false,
+        }
+    }
+
+    /// The value, read now if it hasn't been read yet or is past its TTL.
+    ///
+    /// Concurrent callers wait for a single read.
+    pub fn get(&self) -> Result<Secret<String>> {
+        let mut cached = self.cached.lock().unwrap_or_else(|p| p.into_inner());
+        if let Some((ref value, read_at)) = *cached {
+            if self.fresh(read_at) {
+                return Ok(value.clone());
+            }
+        }
+        let value = self.session.read(&self.reference)?;
+        *cached = Some((value.clone(), Instant::now()));
+        Ok(value)
+    }
+
+    /// Read the value again, regardless of the TTL.
+    ///
+    /// If reading fails the previous value is kept.
+    pub fn refresh(&self) -> Result<Secret<String>> {
+        let mut cached = self.cached.lock().unwrap_or_else(|p| p.into_inner());
+        let value = self.session.read(&self.reference)?;
+        *cached = Some((value.clone(), Instant::now()));
+        Ok(value)
+    }
+
+    /// Forget the cached value, so the next `get` reads it again.
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap_or_else(|p| p.into_inner()) = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{read_interaction, replay_op};
+
+    #[test]
+    fn reads_once_until_refreshed() {
+        let read = |value: &str| read_interaction("op://Prod/DB/password", value);
+        let op = replay_op(vec![read("one"), read("two")]);
+        assert!(LazySecret::new(op.session("token"), "not a reference").is_err());
+
+        let secret = LazySecret::new(op.session("token"), "op://Prod/DB/password").unwrap();
+        assert!(!secret.is_resolved());
+        assert_eq!(secret.get().unwrap().expose(), "one");
+        assert_eq!(secret.get().unwrap().expose(), "one");
+        assert_eq!(secret.refresh().unwrap().expose(), "two");
+        assert_eq!(secret.get().unwrap().expose(), "two");
+        // The cassette is used up, so a failed read keeps the old value.
+        assert!(secret.refresh().is_err());
+        assert_eq!(secret.get().unwrap().expose(), "two");
+    }
+}
@@ -1,18 +1,254 @@
+#![recursion_limit = "1024"]
+
+extern crate base64;
 #[macro_use]
 extern crate error_chain;
+extern crate csv;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
+#[cfg(feature = "process")]
 extern crate which;
+#[cfg(feature = "prompt")]
+extern crate rpassword;
+#[cfg(feature = "clipboard")]
+extern crate arboard;
+#[cfg(feature = "aws")]
+extern crate aws_credential_types;
+#[cfg(feature = "secrecy")]
+extern crate secrecy;
+#[cfg(any(feature = "mlock", feature = "broker"))]
+extern crate libc;
+#[cfg(feature = "onepux")]
+extern crate zip;
+#[cfg(feature = "opvault")]
+extern crate aes;
+#[cfg(feature = "opvault")]
+extern crate cbc;
+#[cfg(feature = "opvault")]
+extern crate hmac;
+#[cfg(feature = "opvault")]
+extern crate pbkdf2;
+#[cfg(feature = "opvault")]
+extern crate sha2;
+#[cfg(feature = "search")]
+extern crate regex;
+#[cfg(feature = "ssh")]
+extern crate ssh2;
+#[cfg(feature = "generate")]
+extern crate getrandom;
+#[cfg(feature = "strength")]
+extern crate zxcvbn;
+#[cfg(feature = "tracing")]
+#[macro_use]
+extern crate tracing;
 
+#[cfg(feature = "process")]
 use std::env;
-use std::path::{Path, PathBuf};
-use std::process::{Command, ExitStatus};
+#[cfg(feature = "process")]
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+#[cfg(feature = "process")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "process")]
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+mod account;
+#[cfg(feature = "process")]
+mod assignment;
+#[cfg(feature = "process")]
+mod audit;
+#[cfg(feature = "aws")]
+pub mod aws;
+#[cfg(feature = "process")]
+mod backend;
+#[cfg(all(feature = "broker", unix))]
+pub mod broker;
+#[cfg(feature = "process")]
+mod builder;
+#[cfg(feature = "process")]
+pub mod cassette;
+mod classify;
+mod code;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+#[cfg(feature = "process")]
+mod command;
+#[cfg(feature = "process")]
+mod container;
+pub mod compat;
+pub mod config;
+#[cfg(feature = "process")]
+mod connect;
+#[cfg(feature = "process")]
+mod discover;
+#[cfg(feature = "process")]
+mod doctor;
+#[cfg(feature = "process")]
+mod document;
+mod diagnostics;
+mod diff;
+#[cfg(feature = "process")]
+pub mod docker_credential;
+#[cfg(feature = "process")]
+pub mod dotenv;
+#[cfg(feature = "process")]
+mod dry_run;
+#[cfg(feature = "process")]
+mod duplicates;
+#[cfg(feature = "process")]
+mod edit;
+#[cfg(feature = "process")]
+mod expiry;
+pub mod export;
+mod field_path;
+#[cfg(feature = "process")]
+pub mod git_credential;
+#[cfg(feature = "process")]
+pub mod import;
+#[cfg(feature = "process")]
+pub mod kubernetes;
+#[cfg(feature = "process")]
+mod lazy;
+mod memlock;
+pub mod models;
+#[cfg(feature = "process")]
+mod observer;
+#[cfg(feature = "process")]
+mod op_builder;
+#[cfg(feature = "opvault")]
+pub mod opvault;
+#[cfg(feature = "process")]
+mod plugin;
+#[cfg(feature = "process")]
+mod policy;
+#[cfg(feature = "process")]
+mod pool;
+#[cfg(feature = "process")]
+mod provider;
+mod purpose;
+mod query;
+#[cfg(feature = "process")]
+mod read_only;
+mod recipe;
+mod reference;
+#[cfg(feature = "process")]
+mod refresh;
+#[cfg(feature = "process")]
+mod resolver;
+#[cfg(feature = "process")]
+mod retry;
+#[cfg(feature = "process")]
+mod report;
+#[cfg(feature = "search")]
+pub mod search;
+mod secret;
+mod selector;
+#[cfg(feature = "process")]
+mod shell;
+#[cfg(feature = "ssh")]
+pub mod ssh;
+#[cfg(feature = "process")]
+pub mod systemd;
+#[cfg(feature = "process")]
+mod stream;
+#[cfg(feature = "strength")]
+pub mod strength;
+mod summary;
+#[cfg(feature = "process")]
+pub mod sync;
+mod tag;
+mod user;
+mod vault;
+mod version;
+#[cfg(feature = "prompt")]
+pub mod prompt;
+
+#[cfg(feature = "process")]
+use command::OpCommand;
+#[cfg(feature = "process")]
+use refresh::SessionAuth;
+use secret::Redacted;
+#[cfg(feature = "process")]
+pub use command::{log_commands, set_log_commands, InteractiveOutput, Utf8Policy};
+
+pub use account::{AccountDetails, AccountSelector, AccountType};
+#[cfg(feature = "process")]
+pub use assignment::{assign, conceal, FieldAssignment, FieldType};
+#[cfg(feature = "process")]
+pub use audit::{AuditLog, AuditRecord};
+#[cfg(feature = "process")]
+pub use backend::{Backend, EnvPolicy, Invocation, ProcessBackend, SpawnHook, ESSENTIAL_VARS};
+#[cfg(feature = "process")]
+pub use builder::ItemBuilder;
+pub use classify::ErrorCategory;
+pub use code::ErrorCode;
+pub use config::OpAccount;
+#[cfg(feature = "process")]
+pub use connect::ConnectRotation;
+#[cfg(feature = "process")]
+pub use container::ContainerExec;
+pub use diagnostics::{CommandDiagnostics, ConfigFile, DiagnosticsBundle, FailedCommand};
+pub use diff::{FieldChange, ItemDiff};
+#[cfg(feature = "process")]
+pub use discover::{DiscoverOptions, OVERRIDE_VARS};
+#[cfg(feature = "process")]
+pub use doctor::{CheckKind, CheckStatus, HealthCheck, HealthReport};
+#[cfg(feature = "process")]
+pub use dry_run::DryRun;
+#[cfg(feature = "process")]
+pub use duplicates::{DuplicateGroup, DuplicateReport};
+#[cfg(feature = "process")]
+pub use edit::ItemEdit;
+#[cfg(feature = "process")]
+pub use expiry::{ExpiringCredential, ExpiryOptions};
+#[cfg(feature = "process")]
+pub use lazy::LazySecret;
+#[cfg(feature = "process")]
+pub use observer::{CommandInfo, CommandOutcome, Observer};
+#[cfg(feature = "process")]
+pub use op_builder::OpBuilder;
+#[cfg(feature = "process")]
+pub use plugin::{PluginCredential, PluginInspection, PluginItem, PluginVault, ShellPlugin};
+#[cfg(feature = "process")]
+pub use policy::CommandPolicy;
+#[cfg(feature = "process")]
+pub use pool::{max_concurrency, set_max_concurrency, DEFAULT_MAX_CONCURRENCY};
+#[cfg(feature = "process")]
+pub use provider::{MemoryProvider, SecretsProvider};
+pub use field_path::FieldPath;
+pub use purpose::FieldPurpose;
+pub use query::Query;
+#[cfg(feature = "process")]
+pub use read_only::ReadOnlySession;
+#[cfg(feature = "generate")]
+pub use recipe::generate_password;
+pub use recipe::{PasswordRecipe, MAX_LENGTH};
+pub use reference::SecretReference;
+#[cfg(feature = "process")]
+pub use resolver::CachingResolver;
+#[cfg(feature = "process")]
+pub use retry::RetryPolicy;
+#[cfg(feature = "process")]
+pub use report::VaultReport;
+pub use secret::{debug_reveal, set_debug_reveal, Secret, SecretBytes};
+pub use selector::ItemSelector;
+#[cfg(feature = "process")]
+pub use shell::Shell;
+pub use summary::{ItemSummary, ItemUrl};
+pub use tag::Tag;
+pub use user::OpUser;
+pub use vault::{VaultDetails, VaultType};
+pub use version::CliVersion;
 
 error_chain! {
     foreign_links {
         JsonParse(::serde_json::error::Error) #[doc = "Failed to parse JSON"];
+        Csv(::csv::Error) #[doc = "Failed to read or write CSV"];
         Io(::std::io::Error)
             #[doc = "IO error while calling `op` command"];
         SessionVar(::std::env::VarError)
@@ -24,42 +260,248 @@ error_chain! {
         #[doc = "op command not found in path."]
         MissingOpCommand {
             description("op command not found in path")
+            display("op command not found in path. {}", INSTALL_HINT)
+        }
+        #[doc = "The op command named by the override environment variable does not exist."]
+        InvalidOpPath(path: PathBuf) {
+            description("op command from environment not found")
+            display("op command from environment not found: {}", path.display())
         }
         #[doc = "Could not find any session environment variable."]
         MissingSessionVariable {
             description("could not find any session environment variable")
         }
+        #[doc = "The service account token environment variable is not set."]
+        MissingServiceAccountToken {
+            description("OP_SERVICE_ACCOUNT_TOKEN is not set")
+        }
+        #[doc = "The name of the session environment variable of a session isn't known, see `OpSession::with_env_var`."]
+        UnknownSessionVariable {
+            description("session environment variable is not known")
+        }
+        #[doc = "A `SecretsProvider` has no value for a secret reference. Holds the reference."]
+        SecretNotFound(reference: String) {
+            description("secret not found")
+            display("secret not found: {}", reference)
+        }
+        #[doc = "The secrets broker refused a request. Holds its message."]
+        Broker(message: String) {
+            description("secrets broker error")
+            display("secrets broker error: {}", message)
+        }
+        #[doc = "JSON printed by `op` doesn't have the shape this crate expects, e.g. because a newer `op` changed it. Holds the subcommand, the parse error and the JSON, so callers can still get at what they need."]
+        SchemaMismatch(subcommand: String, message: String, raw: Box<RawItem>) {
+            description("unexpected JSON schema")
+            display("unexpected JSON from op {}: {}", subcommand, message)
+        }
+        #[doc = "`op` ran longer than the timeout set with `OpBuilder::timeout` and was killed. Holds the subcommand."]
+        TimedOut(subcommand: String, timeout: Duration) {
+            description("op timed out")
+            display("op {} timed out after {:?}", subcommand, timeout)
+        }
+        #[doc = "The SSH host `op` should run on is not in `~/.ssh/known_hosts` or its key doesn't match, see `ssh::RemoteOp::connect`."]
+        UnknownHostKey(host: String) {
+            description("unknown SSH host key")
+            display("host key of {} is unknown or doesn't match ~/.ssh/known_hosts", host)
+        }
         #[doc = "More than one session environment variable found."]
         MultipleSessionVariables(domains: Vec<String>) {
             description("more than one session environment variable found")
             display("more than one session environment variable found: {:?}", domains)
         }
+        #[doc = "Output from op was not valid UTF-8. Holds the name of the stream and the raw bytes."]
+        InvalidUtf8Output(stream: &'static str, bytes: Vec<u8>) {
+            description("op output was not valid UTF-8")
+            display("op {} output was not valid UTF-8", stream)
+        }
+        #[doc = "Output of `op --version` could not be parsed."]
+        InvalidVersion(version: String) {
+            description("invalid op version")
+            display("invalid op version: {}", version)
+        }
+        #[doc = "op masked a concealed field. Use `OpSession::get_field_revealed` to read it."]
+        ConcealedField(item: String, field: String) {
+            description("field is concealed")
+            display("field {} of {} is concealed, use get_field_revealed to read it", field, item)
+        }
+        #[doc = "A new Connect token failed verification and was revoked again."]
+        ConnectTokenRejected(name: String) {
+            description("new Connect token failed verification")
+            display("new Connect token {} failed verification", name)
+        }
+        #[doc = "A credential helper found no credentials for the server."]
+        CredentialsNotFound(server: String) {
+            description("credentials not found in native keychain")
+            display("credentials not found in native keychain")
+        }
+        #[doc = "A credential helper was invoked with an operation it doesn't know."]
+        UnknownHelperOperation(operation: String) {
+            description("unknown credential helper operation")
+            display("unknown credential helper operation: {}", operation)
+        }
+        #[doc = "A credential helper would replace an item it didn't create, see `git_credential::TAG` and"]
+        #[doc = "`docker_credential::TAG`."]
+        ForeignCredentialItem(title: String) {
+            description("item not created by the credential helper")
+            display("item {} was not created by the credential helper", title)
+        }
+        #[doc = "A credential helper would replace the credential of another user in an item."]
+        CredentialUserMismatch(title: String) {
+            description("item holds the credential of another user")
+            display("item {} holds the credential of another user", title)
+        }
+        #[doc = "A secret reference was not of the form `op://vault/item/[section/]field`."]
+        InvalidSecretReference(reference: String) {
+            description("invalid secret reference")
+            display("invalid secret reference: {}", reference)
+        }
+        #[doc = "A key is not allowed in a Kubernetes secret."]
+        InvalidSecretKey(key: String) {
+            description("invalid Kubernetes secret key")
+            display("invalid Kubernetes secret key: {}", key)
+        }
+        #[doc = "A credential name can't be used as a file name."]
+        InvalidCredentialName(name: String) {
+            description("invalid credential name")
+            display("invalid credential name: {}", name)
+        }
+        #[doc = "A line of a `.env` file could not be parsed. Holds the line number."]
+        InvalidEnvLine(line: usize) {
+            description("invalid line in .env file")
+            display("invalid line {} in .env file", line)
+        }
+        #[doc = "An OPVault directory is missing data or has data in an unexpected shape."]
+        InvalidOpVault(reason: String) {
+            description("invalid OPVault")
+            display("invalid OPVault: {}", reason)
+        }
+        #[doc = "An OPVault could not be decrypted, usually because the master password is wrong."]
+        OpVaultUnlock {
+            description("incorrect master password or corrupted OPVault")
+        }
+        #[doc = "The operation needs a feature of `op` 2.x, but `op` 1.x is installed."]
+        RequiresCliV2(operation: &'static str) {
+            description("operation requires op 2.x")
+            display("{} requires op 2.x", operation)
+        }
+        #[doc = "A downloaded document differs in size from what 1Password reports for it."]
+        IntegrityMismatch(item: String, expected: u64, actual: u64) {
+            description("downloaded document doesn't match its metadata")
+            display("document {} should have {} bytes but {} were downloaded", item, expected, actual)
+        }
+        #[doc = "A field assignment can't be passed to op. Holds the assignment and why."]
+        InvalidAssignment(assignment: String, reason: &'static str) {
+            description("invalid field assignment")
+            display("invalid field assignment {}: {}", assignment, reason)
+        }
+        #[doc = "A field path is not `field` or `section/field`. Holds the path."]
+        InvalidFieldPath(path: String) {
+            description("invalid field path")
+            display("invalid field path: {}", path)
+        }
+        #[doc = "An item has no field at a `section/field` path. Holds the item and the path."]
+        FieldNotFound(item: String, path: String) {
+            description("field not found")
+            display("item {} has no field {}", item, path)
+        }
+        #[doc = "An `ItemSelector` can't select an item. Holds the selector and why."]
+        InvalidItemSelector(selector: String, reason: &'static str) {
+            description("invalid item selector")
+            display("invalid item selector {}: {}", selector, reason)
+        }
+        #[doc = "The `CommandPolicy` of the `Op` doesn't allow a subcommand. Holds the subcommand."]
+        PolicyViolation(subcommand: String) {
+            description("subcommand not allowed by the command policy")
+            display("op {} is not allowed by the command policy", subcommand)
+        }
+        #[doc = "A tag is empty or contains a comma. Holds the tag."]
+        InvalidTag(tag: String) {
+            description("invalid tag")
+            display("invalid tag: {:?}", tag)
+        }
+        #[doc = "A password recipe can't be used to generate a password. Holds the recipe and why."]
+        InvalidRecipe(recipe: String, reason: &'static str) {
+            description("invalid password recipe")
+            display("invalid password recipe {}: {}", recipe, reason)
+        }
+        #[doc = "op printed human-readable text where JSON was requested. Holds the subcommand."]
+        UnexpectedHumanOutput(subcommand: String) {
+            description("op printed human-readable output instead of JSON")
+            display("op {} printed human-readable output instead of JSON, check for a wrapper or alias overriding --format", subcommand)
+        }
+        #[doc = "op exited unsuccessfully."]
+        CommandFailed(diagnostics: Box<CommandDiagnostics>) {
+            description("op command failed")
+            display("op {} error code: {}, {}", diagnostics.subcommand, diagnostics.status, diagnostics.stderr)
+        }
         #[doc = "`op get` error"]
-        GetCommand(uuid: String, stderr: String, status: ExitStatus) {
+        GetCommand(uuid: String, diagnostics: Box<CommandDiagnostics>) {
             description("op get error")
-            display("op get error for {} code: {}, {}", uuid, status, stderr)
+            display("op get error for {} code: {}, {}", uuid, diagnostics.status, diagnostics.stderr)
         }
         #[doc = "`op --version` error"]
-        VersionCommand(stderr: String, status: ExitStatus) {
+        VersionCommand(diagnostics: Box<CommandDiagnostics>) {
             description("op --version error")
-            display("op --version error code: {}, {}", status, stderr)
+            display("op --version error code: {}, {}", diagnostics.status, diagnostics.stderr)
         }
     }
 }
 
-///
-///
-#[derive(Debug, Clone)]
+#[cfg(feature = "process")]
+/// Handle to the 1Password `op` command line utility.
+#[derive(Clone)]
 pub struct Op {
     command: PathBuf,
+    backend: Arc<dyn Backend>,
+    observers: Vec<Arc<dyn Observer>>,
+    spawn_hooks: Vec<Arc<dyn SpawnHook>>,
+    reason: Option<String>,
+    dry_run: Option<DryRun>,
+    policy: Option<CommandPolicy>,
+    utf8_policy: Utf8Policy,
+    exit_codes: Vec<(i32, ErrorCategory)>,
+    extra_args: Vec<OsString>,
+    locale: Option<OsString>,
+    account: Option<String>,
+    env_policy: EnvPolicy,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    cli_version: Arc<OnceLock<CliVersion>>,
+    memory_lock: bool,
+    last_failure: Arc<Mutex<Option<CommandDiagnostics>>>,
+}
+
+#[cfg(feature = "process")]
+impl fmt::Debug for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Op")
+            .field("command", &self.command)
+            .field("observers", &self.observers.len())
+            .field("spawn_hooks", &self.spawn_hooks.len())
+            .field("reason", &self.reason)
+            .field("dry_run", &self.dry_run.is_some())
+            .field("policy", &self.policy)
+            .field("utf8_policy", &self.utf8_policy)
+            .field("exit_codes", &self.exit_codes)
+            .field("extra_args", &self.extra_args)
+            .field("locale", &self.locale)
+            .field("account", &self.account)
+            .field("env_policy", &self.env_policy)
+            .field("timeout", &self.timeout)
+            .field("retry", &self.retry)
+            .field("cli_version", &self.cli_version.get())
+            .field("memory_lock", &self.memory_lock)
+            .finish()
+    }
 }
 
+#[cfg(feature = "process")]
 impl Op {
     /// # Example
     ///
-    /// ```
-    /// # extern crate _1password;
-    /// use _1password::Op;
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
     ///
     /// let op = Op::new("op");
     /// println!("Op Version: {}", op.version().unwrap());
@@ -67,28 +509,196 @@ impl Op {
     pub fn new<P: AsRef<Path>>(command: P) -> Op {
         Op {
             command: command.as_ref().to_owned(),
+            backend: Arc::new(ProcessBackend),
+            observers: Vec::new(),
+            spawn_hooks: Vec::new(),
+            reason: None,
+            dry_run: None,
+            policy: None,
+            utf8_policy: Utf8Policy::Strict,
+            exit_codes: Vec::new(),
+            extra_args: Vec::new(),
+            locale: Some(OsString::from("C")),
+            account: None,
+            env_policy: EnvPolicy::Inherit,
+            timeout: None,
+            retry: RetryPolicy::never(),
+            cli_version: Arc::new(OnceLock::new()),
+            memory_lock: false,
+            last_failure: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Configure an `Op` in one place, see `OpBuilder`.
+    pub fn builder() -> OpBuilder {
+        OpBuilder::new()
+    }
+
     /// Find `op` command line utility by search the current PATH environment variable.
     ///
+    /// This uses the default `DiscoverOptions`, so the `OP_CLI_PATH` or `OP_PATH` environment
+    /// variable takes precedence and common install locations are checked when `op` is not in PATH. On
+    /// Windows this looks for `op.exe` and falls back to the standard install locations used
+    /// by the installer, winget and scoop.
+    ///
     /// # Example
     ///
-    /// ```
-    /// # extern crate _1password;
-    /// use _1password::Op;
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
     ///
     /// let op = Op::which().unwrap();
     /// println!("Op Version: {}", op.version().unwrap());
     /// ```
     pub fn which() -> Result<Op> {
-        if let Ok(p) = which::which("op") {
-            Ok(Op {
-                command: p,
-            })
-        } else {
-            Err(ErrorKind::MissingOpCommand.into())
+        Op::discover(DiscoverOptions::default())
+    }
+
+    /// Find `op` command line utility as configured by `options`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::{DiscoverOptions, Op};
+    ///
+    /// let op = Op::discover(DiscoverOptions::new().dir("/opt/tools/bin")).unwrap();
+    /// ```
+    pub fn discover(options: DiscoverOptions) -> Result<Op> {
+        Ok(Op::new(options.find()?))
+    }
+
+    /// Accounts `op` has been signed in to on this machine.
+    ///
+    /// This reads `~/.config/op/config` and the legacy `~/.op/config` and does not require
+    /// a session, so it can be used to present a list of accounts before signing in.
+    /// Config files that can't be read are skipped.
+    pub fn configured_accounts() -> Result<Vec<OpAccount>> {
+        config::configured_accounts()
+    }
+
+    /// Run `op` invocations with `backend` instead of spawning a local process.
+    pub fn with_backend<B: Backend + 'static>(mut self, backend: B) -> Op {
+        self.backend = Arc::new(backend);
+        self
+    }
+
+    /// Add an observer that is notified before and after every `op` invocation.
+    pub fn with_observer<O: Observer + 'static>(mut self, observer: O) -> Op {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Add a hook that customizes the `op` process before and after it is spawned.
+    ///
+    /// See `SpawnHook` for details.
+    pub fn with_spawn_hook<H: SpawnHook + 'static>(mut self, hook: H) -> Op {
+        self.spawn_hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Record mutating commands in `plan` instead of running them.
+    ///
+    /// See `DryRun` for details.
+    pub fn with_dry_run(mut self, plan: DryRun) -> Op {
+        self.dry_run = Some(plan);
+        self
+    }
+
+    /// Only run the subcommands `policy` allows. If the `Op` already has a
+    /// policy, only what both allow may run.
+    ///
+    /// See `CommandPolicy` for details.
+    pub fn with_policy(mut self, policy: CommandPolicy) -> Op {
+        self.policy = Some(match self.policy {
+            Some(current) => current.intersect(&policy),
+            None => policy,
+        });
+        self
+    }
+
+    /// Set how output from `op` that is not valid UTF-8 is handled.
+    ///
+    /// Defaults to `Utf8Policy::Strict`.
+    pub fn with_utf8_policy(mut self, policy: Utf8Policy) -> Op {
+        self.utf8_policy = policy;
+        self
+    }
+
+    /// Pass `args` to every invocation of `op`, right after the subcommand.
+    ///
+    /// Meant for global flags this crate doesn't model, such as `--debug` or
+    /// `--cache=false`. The arguments are logged and recorded like any other
+    /// plain argument, so don't pass secrets here.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// let op = Op::which().unwrap().with_extra_args(&["--debug"]);
+    /// ```
+    pub fn with_extra_args<S: AsRef<OsStr>>(mut self, args: &[S]) -> Op {
+        self.extra_args.extend(args.iter().map(|a| a.as_ref().to_owned()));
+        self
+    }
+
+    /// Set `LC_ALL` and `LANG` to `locale` for every invocation of `op`, or
+    /// leave the environment alone with `None`.
+    ///
+    /// Defaults to `C`, because failures are partly classified by the English
+    /// messages `op` writes to stderr, which don't match on systems with
+    /// another language.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// let op = Op::which().unwrap().with_locale(Some("C.UTF-8"));
+    /// ```
+    pub fn with_locale(mut self, locale: Option<&str>) -> Op {
+        self.locale = locale.map(OsString::from);
+        self
+    }
+
+    /// Treat failures with exit code `code` as `category`, unless what `op`
+    /// wrote to stderr identifies the failure.
+    pub fn with_exit_code(mut self, code: i32, category: ErrorCategory) -> Op {
+        self.exit_codes.push((code, category));
+        self
+    }
+
+    /// Run `op` with stdin and stderr connected to the terminal, so it can prompt the user.
+    ///
+    /// This is meant for subcommands like `signin` or `account add` that ask for input.
+    /// Stdout is captured and returned together with the exit status, which is not
+    /// checked.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// let op = Op::which().unwrap();
+    /// let out = op.run_interactive(&["signin"], &["my", "--raw"]).unwrap();
+    /// if out.status.success() {
+    ///     let session = op.session(out.stdout.trim());
+    /// }
+    /// ```
+    pub fn run_interactive(&self, subcommand: &[&str], args: &[&str]) -> Result<InteractiveOutput> {
+        let mut command = OpCommand::new(self, subcommand);
+        for arg in args {
+            command.arg(arg);
         }
+        let (output, _) = command.interactive().execute()?;
+        Ok(InteractiveOutput {
+            status: output.status,
+            stdout: self.utf8_policy.decode("stdout", output.stdout)?,
+        })
     }
 
     /// Path to `op` command
@@ -98,48 +708,247 @@ impl Op {
 
     /// Returns version of `op` that this struct uses.
     pub fn version(&self) -> Result<String> {
-        let output = Command::new(&self.command)
-                .arg("--version")
-                .output()?;
-        let stdout = String::from_utf8(output.stdout)?;
-        let stderr = String::from_utf8(output.stderr)?;
-        if let Some(1) = output.status.code() {
+        let command = OpCommand::new(self, &["--version"]);
+        let (output, duration) = command.execute()?;
+        // Early releases of op exit with 1 after printing their version.
+        if output.status.success() || output.status.code() == Some(1) {
+            let stdout = self.utf8_policy.decode("stdout", output.stdout)?;
             Ok(stdout.trim().to_owned())
         } else {
-            Err(ErrorKind::VersionCommand(stderr, output.status).into())
+            Err(ErrorKind::VersionCommand(command.diagnostics(output, duration)?).into())
         }
     }
 
-    /*
-    pub fn signin_subdomain(&self, subdomain: &str, password: &str) -> OpSession {
+    /// Parsed version of `op`.
+    ///
+    /// The version is only looked up once and then cached, also for clones of this `Op`.
+    pub fn cli_version(&self) -> Result<CliVersion> {
+        if let Some(version) = self.cli_version.get() {
+            return Ok(*version);
+        }
+        let version : CliVersion = self.version()?.parse()?;
+        let _ = self.cli_version.set(version);
+        Ok(version)
+    }
 
+    /// Assume `op` is the given version instead of running `op --version` to find out.
+    pub fn with_cli_version(mut self, version: CliVersion) -> Op {
+        let cell = OnceLock::new();
+        let _ = cell.set(version);
+        self.cli_version = Arc::new(cell);
+        self
     }
 
-    pub fn signin(&self, signinaddress: &str, emailaddress: &str, secretkey: &str, password: &str) -> OpSession {
+    /// Lock the memory holding session tokens and secrets read through this handle so
+    /// it can't be swapped to disk.
+    ///
+    /// Locking is best effort: when it is refused, e.g. because `RLIMIT_MEMLOCK` is
+    /// exhausted, secrets are used unlocked. Check `Secret::is_locked` to find out.
+    #[cfg(feature = "mlock")]
+    pub fn with_memory_lock(mut self, lock: bool) -> Op {
+        self.memory_lock = lock;
+        self
+    }
+
+    /// Wrap a secret read by this handle, locking it if asked to.
+    fn secret(&self, value: String) -> Secret<String> {
+        if self.memory_lock {
+            Secret::locked(value)
+        } else {
+            Secret::new(value)
+        }
+    }
+
+    /// Sign in to an account that `op` has signed in to before, e.g. using its subdomain.
+    ///
+    /// The password is written to the stdin of `op signin`, never passed as an argument.
+    pub fn signin_subdomain<A: Into<AccountSelector>>(&self, account: A, password: &str) -> Result<OpSession> {
+        self.signin_account(&account.into(), password)
+    }
+
+    fn signin_account(&self, account: &AccountSelector, password: &str) -> Result<OpSession> {
+        let mut command = OpCommand::new(self, &["signin"]);
+        if self.cli_version()?.is_v2() {
+            command.arg("--account");
+        }
+        let stdout = command
+            .target(&account.account_arg())
+            .arg("--raw")
+            .stdin(password)
+            .run_or(ErrorKind::CommandFailed)?;
+        self.signin_session(stdout, account)
+    }
 
+    /// Sign in to an account for the first time on this machine.
+    ///
+    /// The password is written to the stdin of `op signin`. The secret key has to be passed
+    /// as an argument, but is redacted from anything this crate logs.
+    pub fn signin(&self, signinaddress: &str, emailaddress: &str, secretkey: &str, password: &str) -> Result<OpSession> {
+        let stdout = OpCommand::new(self, &["signin"])
+            .target(signinaddress)
+            .arg(emailaddress)
+            .secret_arg(secretkey)
+            .arg("--raw")
+            .stdin(password)
+            .run_or(ErrorKind::CommandFailed)?;
+        self.signin_session(stdout, &AccountSelector::from(signinaddress))
+    }
+
+    /// Sign in to an account that requires a second factor, using its subdomain.
+    ///
+    /// With `SecondFactor::Code` the one-time password is sent right after the master
+    /// password. With `SecondFactor::Callback` sign in is first attempted with just the
+    /// password, and if `op` asks for a second factor the callback is called with its
+    /// prompt and sign in is retried with the returned code. Returning `None` from the
+    /// callback gives up with the original error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::{Op, SecondFactor};
+    ///
+    /// let op = Op::which().unwrap();
+    /// let session = op.signin_subdomain_mfa("my", "password", SecondFactor::Callback(Box::new(|prompt| {
+    ///     println!("{}", prompt);
+    ///     let mut code = String::new();
+    ///     std::io::stdin().read_line(&mut code).ok()?;
+    ///     Some(code.trim().to_owned())
+    /// }))).unwrap();
+    /// ```
+    pub fn signin_subdomain_mfa<A: Into<AccountSelector>>(&self, account: A, password: &str, second_factor: SecondFactor) -> Result<OpSession> {
+        let account = account.into();
+        match second_factor {
+            SecondFactor::Code(code) => {
+                self.signin_account(&account, &format!("{}\n{}\n", password, code))
+            },
+            SecondFactor::Callback(mut callback) => {
+                let err = match self.signin_account(&account, password) {
+                    Ok(session) => return Ok(session),
+                    Err(err) => err,
+                };
+                let prompt = match err.diagnostics() {
+                    Some(d) if d.category == ErrorCategory::SecondFactorRequired => d.stderr.trim().to_owned(),
+                    _ => return Err(err),
+                };
+                match callback(&prompt) {
+                    Some(code) => self.signin_account(&account, &format!("{}\n{}\n", password, code)),
+                    None => Err(err),
+                }
+            },
+        }
+    }
+
+    fn signin_session(&self, stdout: Vec<u8>, account: &AccountSelector) -> Result<OpSession> {
+        let token = self.utf8_policy.decode("stdout", stdout)?;
+        let mut session = self.session(token.trim());
+        // `op` 2.x names the variable after the account UUID, if it is known.
+        let accounts = Op::configured_accounts().unwrap_or_default();
+        let uuid = accounts.iter().filter(|a| account.matches(a)).find_map(|a| a.account_uuid.clone());
+        session.env_var = match uuid {
+            Some(ref uuid) if self.cli_version()?.is_v2() => Some(format!("OP_SESSION_{}", uuid)),
+            _ => account.session_var_names(&accounts).into_iter().next(),
+        };
+        Ok(session)
+    }
+
+    /// Sign in like `signin_subdomain` with a password held in a `secrecy` wrapper.
+    #[cfg(feature = "secrecy")]
+    pub fn signin_subdomain_secret<A, P>(&self, account: A, password: &P) -> Result<OpSession>
+        where A: Into<AccountSelector>, P: secrecy::ExposeSecret<str>
+    {
+        self.signin_account(&account.into(), password.expose_secret())
+    }
+
+    /// Prompt for the master password on the terminal and sign in to the given account.
+    #[cfg(feature = "prompt")]
+    pub fn signin_prompt<A: Into<AccountSelector>>(&self, account: A) -> Result<OpSession> {
+        let account = account.into();
+        let password = prompt::prompt_password(&account.to_string())?;
+        self.signin_account(&account, &password)
     }
-    */
 
     /// Make new session with the specified session token.
+    ///
+    /// `op` reads the token from the `OP_SESSION_*` variable of its account,
+    /// which this session doesn't know, so until one is set with
+    /// `with_env_var` the token is passed as `--session=<token>`. Sessions
+    /// from `signin_subdomain` and `env_session` know their variable.
     pub fn session(&self, session: &str) -> OpSession {
         OpSession {
-            config: self.clone(),
-            session: session.to_owned(),
+            config: Arc::new(self.clone()),
+            auth: Some(Arc::new(SessionAuth::new(self.secret(session.to_owned()), None))),
+            vault: None,
+            env_var: None,
+        }
+    }
+
+    /// Create a session for the service account whose token is in the
+    /// `OP_SERVICE_ACCOUNT_TOKEN` environment variable.
+    ///
+    /// `op` 2.x reads the token from the environment itself, so no `--session`
+    /// is passed to commands run by this session.
+    pub fn service_account_session(&self) -> Result<OpSession> {
+        self.service_account_session_with(|name| env::var_os(name))
+    }
+
+    /// `service_account_session`, looking up environment variables with `var`.
+    fn service_account_session_with<F: Fn(&str) -> Option<OsString>>(&self, var: F) -> Result<OpSession> {
+        match var(SERVICE_ACCOUNT_TOKEN_VAR) {
+            Some(ref token) if !token.is_empty() => Ok(self.ambient_session()),
+            _ => Err(ErrorKind::MissingServiceAccountToken.into()),
+        }
+    }
+
+    /// Create a session that passes no credentials, leaving `op` to use
+    /// whatever sign-in it finds itself.
+    ///
+    /// Useful when `op` runs elsewhere, e.g. through `ssh::RemoteOp` on a host
+    /// where it is signed in, or authenticates through the 1Password app.
+    /// Unlike `service_account_session` nothing is checked up front, so
+    /// commands fail if `op` isn't signed in.
+    pub fn ambient_session(&self) -> OpSession {
+        OpSession {
+            config: Arc::new(self.clone()),
+            auth: None,
+            vault: None,
+            env_var: None,
+        }
+    }
+
+    /// Create a session from whatever credentials the environment provides.
+    ///
+    /// A service account token in `OP_SERVICE_ACCOUNT_TOKEN` is preferred,
+    /// then a session token as looked up by `env_session`.
+    pub fn session_from_env(&self) -> Result<OpSession> {
+        self.session_from_env_with(|name| env::var_os(name))
+    }
+
+    /// `session_from_env`, looking up the service account token with `var`.
+    pub(crate) fn session_from_env_with<F: Fn(&str) -> Option<OsString>>(&self, var: F) -> Result<OpSession> {
+        match self.service_account_session_with(var) {
+            Ok(session) => Ok(session),
+            Err(Error(ErrorKind::MissingServiceAccountToken, _)) => self.env_session(),
+            Err(err) => Err(err),
         }
     }
 
-    /// Lookup session token for the supplied subdomain in environment.
-    /// This will look for an environment variable named `OP_SESSION_<subdomain>` and if found
-    /// will return a new session that uses the session token stored in that environment variable.
-    pub fn env_account_session(&self, subdomain: &str) -> Result<OpSession> {
-        match env::var(format!("OP_SESSION_{}", subdomain)) {
-            Err(env::VarError::NotPresent) => Err(ErrorKind::MissingSessionVariable.into()),
-            Err(err) => Err(err.into()),
-            Ok(session) => Ok(OpSession {
-                config: self.clone(),
-                session: session,
-            })
+    /// Lookup session token for the supplied account in environment.
+    /// This will look for an environment variable named `OP_SESSION_<subdomain>` or, as
+    /// `op` 2.x names it, `OP_SESSION_<account UUID>` and if found will return a new session
+    /// that uses the session token stored in that environment variable.
+    ///
+    /// The config file of `op` is used to map between shorthands, URLs and UUIDs.
+    pub fn env_account_session<A: Into<AccountSelector>>(&self, account: A) -> Result<OpSession> {
+        let accounts = Op::configured_accounts().unwrap_or_default();
+        for name in account.into().session_var_names(&accounts) {
+            match env::var(&name) {
+                Err(env::VarError::NotPresent) => continue,
+                Err(err) => return Err(err.into()),
+                Ok(session) => return Ok(self.session(&session).with_env_var(&name)),
+            }
         }
+        Err(ErrorKind::MissingSessionVariable.into())
     }
 
     /// Lookup session token in environment.
@@ -153,10 +962,8 @@ impl Op {
         match vars.len() {
             0 => Err(ErrorKind::MissingSessionVariable.into()),
             1 => {
-                Ok(OpSession {
-                    config: self.clone(),
-                    session: vars.into_iter().next().unwrap().1,
-                })
+                let (name, session) = vars.into_iter().next().unwrap();
+                Ok(self.session(&session).with_env_var(&name))
             },
             _ => {
                 let names : Vec<String> = vars.into_iter().map(|(key, _)| key).collect();
@@ -166,84 +973,567 @@ impl Op {
     }
 }
 
+/// First version of `op` that masks concealed fields unless `--reveal` is passed.
+pub const REVEAL_SINCE: CliVersion = CliVersion::new(2, 19, 0);
+
+#[cfg(feature = "process")]
+/// Start of what `op` prints instead of the value of a concealed field.
+const CONCEALED_PLACEHOLDER: &str = "[use 'op item get";
+
+/// How to get `op`, appended to the error when it can't be found.
+#[cfg(windows)]
+pub(crate) const INSTALL_HINT: &str = "Install 1Password CLI with `winget install AgileBits.1Password.CLI` \
+    or from https://developer.1password.com/docs/cli/get-started/";
+#[cfg(not(windows))]
+pub(crate) const INSTALL_HINT: &str = "Install 1Password CLI from \
+    https://developer.1password.com/docs/cli/get-started/";
+
+/// Environment variable `op` reads a service account token from.
+pub const SERVICE_ACCOUNT_TOKEN_VAR: &str = "OP_SERVICE_ACCOUNT_TOKEN";
+
+#[cfg(feature = "process")]
+/// Callback given the prompt from `op` that returns a one-time password.
+pub type SecondFactorCallback<'a> = Box<dyn FnMut(&str) -> Option<String> + 'a>;
+
+#[cfg(feature = "process")]
+/// How to provide a one-time password when signing in to an account with a
+/// second factor enforced.
+pub enum SecondFactor<'a> {
+    /// Use this one-time password, e.g. one generated from a TOTP secret.
+    Code(&'a str),
+    /// Called with the prompt from `op` when it asks for a one-time password.
+    Callback(SecondFactorCallback<'a>),
+}
+
+#[cfg(feature = "process")]
 /// A configured session what can be used to actually lookup information in 1Password.
+///
+/// Sessions are `Send + Sync` and cheap to clone: copies share the
+/// configuration and the session token, so a session can be handed to as many
+/// threads as needed. When one is refreshed, see `with_refresh`, all copies use
+/// the new token.
 #[derive(Debug, Clone)]
 pub struct OpSession {
-    config: Op,
-    session: String,
+    config: Arc<Op>,
+    /// `None` for service accounts, which authenticate through the environment.
+    auth: Option<Arc<SessionAuth>>,
+    vault: Option<String>,
+    /// `OP_SESSION_*` variable `op` reads the token from, if known.
+    env_var: Option<String>,
 }
 
+#[cfg(feature = "process")]
 impl OpSession {
+    /// Return a copy of this session that reports `reason` to observers for every
+    /// command it runs, e.g. for use in an `AuditLog`.
+    pub fn with_reason(&self, reason: &str) -> OpSession {
+        let mut session = self.clone();
+        Arc::make_mut(&mut session.config).reason = Some(reason.to_owned());
+        session
+    }
+
+    /// Return a copy of this session whose item operations default to the given vault.
+    ///
+    /// Methods ending in `_in` take a vault that overrides this default for a single call.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// let session = Op::which().unwrap().env_session().unwrap().with_vault("Production");
+    /// let item = session.get_item("db-password").unwrap();
+    /// let other = session.get_item_in("db-password", Some("Staging")).unwrap();
+    /// ```
+    pub fn with_vault(&self, vault: &str) -> OpSession {
+        let mut session = self.clone();
+        session.vault = Some(vault.to_owned());
+        session
+    }
+
+    /// Return a copy of this session that passes its token to `op`, and that
+    /// `export_env` hands out, as the environment variable `name`, e.g.
+    /// `OP_SESSION_my`.
+    pub fn with_env_var(&self, name: &str) -> OpSession {
+        let mut session = self.clone();
+        session.env_var = Some(name.to_owned());
+        session
+    }
+
+    /// Return a copy of this session that signs in again with `refresh` when `op`
+    /// rejects the session token, e.g. because it expired after 30 minutes of
+    /// inactivity, and retries the command.
+    ///
+    /// The copy and its clones share one token: when several threads find it
+    /// expired at the same time, `refresh` is called once and the others wait
+    /// for it and retry with its token. Commands streaming their stdout, such
+    /// as `list_items` and document downloads, are retried as long as `op`
+    /// hasn't printed anything yet, which it doesn't when it rejects the
+    /// token. Commands streaming their stdin, such as document uploads, are
+    /// not retried.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// let password = std::env::var("OP_PASSWORD").unwrap();
+    /// let op = Op::which().unwrap();
+    /// let session = op.signin_subdomain("my", &password).unwrap()
+    ///     .with_refresh(move |op: &Op| op.signin_subdomain("my", &password));
+    /// ```
+    pub fn with_refresh<F>(&self, refresh: F) -> OpSession
+        where F: Fn(&Op) -> Result<OpSession> + Send + Sync + 'static
+    {
+        let mut session = self.clone();
+        if let Some(ref auth) = self.auth {
+            session.auth = Some(Arc::new(SessionAuth::new(auth.token(), Some(Box::new(refresh)))));
+        }
+        session
+    }
+
+    /// Session token, `None` for service accounts.
+    pub(crate) fn token(&self) -> Option<Secret<String>> {
+        self.auth.as_ref().map(|auth| auth.token())
+    }
+
+    /// Vault item operations default to, if any.
+    pub fn vault(&self) -> Option<&str> {
+        self.vault.as_ref().map(|v| &v[..])
+    }
+
+    /// Command authenticated with this session.
+    fn command(&self, subcommand: &[&str]) -> OpCommand<'_> {
+        let mut command = OpCommand::new(&self.config, subcommand);
+        if let Some(ref auth) = self.auth {
+            command.auth(auth, self.env_var.as_deref());
+        }
+        command
+    }
+
+    /// Command for an item operation, scoped to `vault` or else the default vault.
+    fn item_command(&self, subcommand: &[&str], vault: Option<&str>) -> OpCommand<'_> {
+        let mut command = self.command(subcommand);
+        if let Some(vault) = vault.or(self.vault()) {
+            command.arg("--vault").arg(vault);
+        }
+        command
+    }
+
     /// Get item with specified UUID.
     ///
     /// This calls `op get item` and parses the returned JSON.
     pub fn get_item(&self, uuid: &str) -> Result<OpItem> {
-        let output = Command::new(&self.config.command)
-                .args(&["get", "item"])
-                .arg(format!("--session={}", self.session))
-                .arg(&uuid)
-                .output()?;
-        if output.status.success() {
-            Ok(serde_json::from_slice(&output.stdout)?)
+        self.get_item_in(uuid, None)
+    }
+
+    /// Get item with specified UUID from `vault`, or the default vault if `None`.
+    pub fn get_item_in(&self, uuid: &str, vault: Option<&str>) -> Result<OpItem> {
+        self.item_command(&["get", "item"], vault)
+            .target(uuid)
+            .run_json(|d| ErrorKind::GetCommand(uuid.to_owned(), d))
+    }
+
+    /// List the items in the default vault, or all vaults if there is none.
+    ///
+    /// This calls `op item list`, or `op list items` with `op` 1.x.
+    pub fn list_items(&self) -> Result<Vec<ItemSummary>> {
+        self.list_items_in(None)
+    }
+
+    /// List the items in `vault`, or the default vault if `None`.
+    pub fn list_items_in(&self, vault: Option<&str>) -> Result<Vec<ItemSummary>> {
+        let mut items = Vec::new();
+        self.for_each_item_in(vault, |item| items.push(item))?;
+        Ok(items)
+    }
+
+    /// Call `f` with each item in `vault`, or the default vault if `None`, as
+    /// `op` lists them.
+    ///
+    /// Unlike `list_items_in` this only holds one item at a time, which keeps
+    /// memory flat for accounts with tens of thousands of items.
+    ///
+    /// `f` is called while `op item list` runs, so the timeout set with
+    /// `OpBuilder::timeout` includes the time spent in it. It may run other
+    /// commands of the session, which don't wait for the limit set with
+    /// `set_max_concurrency`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// let session = Op::which().unwrap().env_session().unwrap();
+    /// let mut logins = 0;
+    /// session.for_each_item_in(None, |item| if item.category == "LOGIN" { logins += 1 }).unwrap();
+    /// ```
+    pub fn for_each_item_in<F: FnMut(ItemSummary)>(&self, vault: Option<&str>, f: F) -> Result<()> {
+        let subcommand : &[&str] = if self.config.cli_version()?.is_v2() {
+            &["item", "list"]
         } else {
-            let stderr = String::from_utf8(output.stderr)?;
-            Err(ErrorKind::GetCommand(uuid.to_owned(), stderr, output.status).into())
+            &["list", "items"]
+        };
+        let mut command = self.item_command(subcommand, vault);
+        command.json()?;
+        summary::stream_list(&command, f)
+    }
+
+    /// List the favorite items in the default vault, or all vaults if there is none.
+    pub fn list_favorites(&self) -> Result<Vec<ItemSummary>> {
+        self.list_favorites_in(None)
+    }
+
+    /// List the favorite items in `vault`, or the default vault if `None`.
+    ///
+    /// `op` 2.x filters with `op item list --favorite`; with `op` 1.x all items
+    /// are listed and filtered here.
+    pub fn list_favorites_in(&self, vault: Option<&str>) -> Result<Vec<ItemSummary>> {
+        if self.config.cli_version()?.is_v2() {
+            let mut command = self.item_command(&["item", "list"], vault);
+            command.arg("--favorite").json()?;
+            let mut items = Vec::new();
+            summary::stream_list(&command, |item| items.push(item))?;
+            Ok(items)
+        } else {
+            let mut items = self.list_items_in(vault)?;
+            items.retain(|i| i.favorite);
+            Ok(items)
+        }
+    }
+
+    /// Get the value of a single field of an item, without fetching and parsing the whole item.
+    ///
+    /// This calls `op item get --fields label=<field_label>`, or `op get item --fields` with
+    /// `op` 1.x. Recent versions of `op` mask concealed fields such as passwords, in which
+    /// case this fails with `ErrorKind::ConcealedField`; use `get_field_revealed` for those.
+    ///
+    /// `field_label` is parsed as a `FieldPath`, so `Production/username` picks the
+    /// `username` of the section `Production`. That fetches the whole item, since
+    /// `--fields` doesn't take a section, and fails with `ErrorKind::FieldNotFound` if
+    /// there is no such field.
+    pub fn get_field(&self, item: &str, field_label: &str) -> Result<Secret<String>> {
+        self.field(item, field_label, None, false)
+    }
+
+    /// Get the value of a single field of an item in `vault`, or the default vault if `None`.
+    pub fn get_field_in(&self, item: &str, field_label: &str, vault: Option<&str>) -> Result<Secret<String>> {
+        self.field(item, field_label, vault, false)
+    }
+
+    /// Get the value of a single field of an item, revealing it if it is concealed.
+    ///
+    /// This passes `--reveal` to versions of `op` that mask concealed fields by default.
+    pub fn get_field_revealed(&self, item: &str, field_label: &str) -> Result<Secret<String>> {
+        self.field(item, field_label, None, true)
+    }
+
+    /// Get the value of a single field of an item in `vault`, revealing it if it is concealed.
+    pub fn get_field_revealed_in(&self, item: &str, field_label: &str, vault: Option<&str>) -> Result<Secret<String>> {
+        self.field(item, field_label, vault, true)
+    }
+
+    fn field(&self, item: &str, field_label: &str, vault: Option<&str>, reveal: bool) -> Result<Secret<String>> {
+        let path : FieldPath = field_label.parse()?;
+        if let Some(ref section) = path.section {
+            return self.section_field(item, section, &path.field, vault, reveal);
+        }
+        let field_label = &path.field[..];
+        let version = self.config.cli_version()?;
+        let command = if version.is_v2() {
+            let mut command = self.item_command(&["item", "get"], vault);
+            command.target(item).arg("--fields").arg(format!("label={}", field_label));
+            if reveal && version >= REVEAL_SINCE {
+                command.arg("--reveal");
+            }
+            command
+        } else {
+            let mut command = self.item_command(&["get", "item"], vault);
+            command.target(item).arg("--fields").arg(field_label);
+            command
+        };
+        let stdout = command.run_or(|d| ErrorKind::GetCommand(item.to_owned(), d))?;
+        let mut value = self.config.utf8_policy.decode("stdout", stdout)?;
+        if value.ends_with('\n') {
+            value.pop();
+            if value.ends_with('\r') {
+                value.pop();
+            }
         }
+        if value.starts_with(CONCEALED_PLACEHOLDER) {
+            return Err(ErrorKind::ConcealedField(item.to_owned(), field_label.to_owned()).into());
+        }
+        Ok(self.config.secret(value))
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct OpItemOverview {
     pub ainfo: String,
-    pub title: String
+    pub title: String,
+    /// Properties this crate doesn't model, kept so the item serializes back unchanged.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct OpItemField {
     pub designation: Option<String>,
     pub name: String,
     #[serde(rename="type")]
     pub field_type: String,
-    pub value: String
+    pub value: String,
+    /// Properties this crate doesn't model, kept so the item serializes back unchanged.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[serde(untagged)]
 pub enum OpItemDetails {
-    Password { password: String },
-    Login { fields: Vec<OpItemField> },
+    Password {
+        password: String,
+        #[serde(flatten)]
+        extra: serde_json::Map<String, serde_json::Value>,
+    },
+    Login {
+        fields: Vec<OpItemField>,
+        /// E.g. `notesPlain`, `sections` and `passwordHistory`.
+        #[serde(flatten)]
+        extra: serde_json::Map<String, serde_json::Value>,
+    },
+}
+
+/// Shows the value only if `set_debug_reveal` was called.
+impl fmt::Debug for OpItemField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OpItemField")
+            .field("designation", &self.designation)
+            .field("name", &self.name)
+            .field("field_type", &self.field_type)
+            .field("value", &Redacted(&self.value))
+            .field("extra", &self.extra)
+            .finish()
+    }
+}
+
+/// Shows the password and unmodeled details only if `set_debug_reveal` was called.
+impl fmt::Debug for OpItemDetails {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OpItemDetails::Password { ref password, ref extra } => f.debug_struct("Password")
+                .field("password", &Redacted(password))
+                .field("extra", &Redacted(extra))
+                .finish(),
+            OpItemDetails::Login { ref fields, ref extra } => f.debug_struct("Login")
+                .field("fields", fields)
+                .field("extra", &Redacted(extra))
+                .finish(),
+        }
+    }
 }
 
 /// Item returned from `OpSession::get_item`
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct OpItem {
     pub uuid: String,
     pub vault_uuid: String,
+    /// The user who last edited the item, see `OpSession::resolve_user`.
     pub changer_uuid: String,
+    /// RFC 3339 timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    /// RFC 3339 timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// Incremented by every edit.
+    #[serde(default, rename = "itemVersion", skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
     pub overview: OpItemOverview,
     pub details: OpItemDetails,
+    /// Properties this crate doesn't model, such as `templateUuid` and `trashed`.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// JSON printed by `op` for an item or other object, as carried by
+/// `ErrorKind::SchemaMismatch` when it couldn't be parsed into a typed model.
+///
+/// Shows the JSON in `Debug` output only if `set_debug_reveal` was called,
+/// since it usually holds secrets.
+#[derive(Clone, PartialEq)]
+pub struct RawItem(pub serde_json::Value);
+
+impl fmt::Debug for RawItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("RawItem").field(&Redacted(&self.0)).finish()
+    }
 }
 
 impl OpItem {
+    /// Serialize the item back to JSON in the shape `op` returned it, including
+    /// properties this crate doesn't model. Modeled properties come first, so
+    /// the keys of an object may be in a different order than `op` printed them.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("items always serialize")
+    }
+
     /// Return password of this item if any.
     pub fn password(&self) -> Option<String> {
-        match &self.details {
-            &OpItemDetails::Password{ ref password } => Some(password.clone()),
-            &OpItemDetails::Login{ ref fields } => {
+        match self.details {
+            OpItemDetails::Password{ ref password, .. } => Some(password.clone()),
+            OpItemDetails::Login{ ref fields, .. } => {
                 let p : Option<String> = Some("password".to_string());
                 fields.iter()
-                    .find(|ref x| x.designation == p)
+                    .find(|x| x.designation == p)
                     .map(|x| x.value.clone())
             }
         }
     }
+
+    /// Return password of this item if any, as a `secrecy::SecretString`.
+    #[cfg(feature = "secrecy")]
+    pub fn secret_password(&self) -> Option<secrecy::SecretString> {
+        self.password().map(secrecy::SecretString::from)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn ambient_sessions_pass_no_credentials() {
+        use cassette::{interaction, replay_op};
+
+        let op = replay_op(vec![interaction("read", &["op://Ops/DB/password", "--no-newline"], "hunter2")]);
+        assert_eq!(op.ambient_session().read("op://Ops/DB/password").unwrap().expose(), "hunter2");
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn reads_while_listing() {
+        use cassette::{interaction, read_interaction, replay_op};
+
+        let op = replay_op(vec![
+            interaction("item list", &["--session=⟨redacted⟩", "--format", "json", "--no-color", "--iso-timestamps"],
+                r#"[{"id": "a", "title": "A", "vault": {"id": "v"}, "category": "LOGIN"}]"#),
+            read_interaction("op://v/a/password", "hunter2"),
+        ]);
+        let session = op.session("token");
+        set_max_concurrency(1);
+        let mut passwords = Vec::new();
+        let listed = session.for_each_item_in(None, |item| {
+            let reference = format!("op://{}/{}/password", item.vault_id, item.id);
+            passwords.push(session.read(&reference).map(|p| p.expose().clone()));
+        });
+        set_max_concurrency(DEFAULT_MAX_CONCURRENCY);
+        listed.unwrap();
+        assert_eq!(passwords.into_iter().collect::<Result<Vec<_>>>().unwrap(), vec!["hunter2"]);
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn signin_with_second_factor() {
+        use cassette::{interaction, Cassette, Replay};
+
+        let signin = |stdout: &str| interaction("signin", &["my", "--raw"], stdout);
+        let cassette = Cassette {
+            interactions: vec![
+                signin("").failing("Enter your six-digit authentication code:", 1),
+                signin("TOKEN\n"),
+            ],
+        };
+        let op = Op::new("op")
+            .with_backend(Replay::new(cassette))
+            .with_cli_version(CliVersion::new(1, 12, 4));
+        let mut prompts = Vec::new();
+        {
+            let callback = SecondFactor::Callback(Box::new(|prompt: &str| {
+                prompts.push(prompt.to_owned());
+                Some("123456".to_owned())
+            }));
+            op.signin_subdomain_mfa("my", "hunter2", callback).unwrap();
+        }
+        assert_eq!(prompts, vec!["Enter your six-digit authentication code:"]);
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn get_field() {
+        use cassette::{interaction, replay_op, Data, Interaction};
+
+        let field = interaction("item get", &["--session=⟨redacted⟩", "abc", "--fields", "label=password"], "hunter2\n");
+        let op = replay_op(vec![field.clone()]);
+        let value = op.session("token").get_field("abc", "password").unwrap();
+        assert_eq!(value.expose(), "hunter2");
+
+        let masked = Interaction {
+            stdout: Data::Text("[use 'op item get abc --reveal' to reveal]\n".to_owned()),
+            ..field.clone()
+        };
+        let mut revealed = field.clone();
+        revealed.args.push("--reveal".to_owned());
+        let session = replay_op(vec![masked, revealed]).session("token");
+        match *session.get_field("abc", "password").unwrap_err().kind() {
+            ErrorKind::ConcealedField(..) => {},
+            ref other => panic!("unexpected error {:?}", other),
+        }
+        assert_eq!(session.get_field_revealed("abc", "password").unwrap().expose(), "hunter2");
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn service_account_session() {
+        use cassette::{interaction, replay_op};
+
+        let op = replay_op(vec![interaction("item get", &["abc", "--fields", "label=username"], "user\n")]);
+        assert!(op.service_account_session_with(|_| None).is_err());
+        let session = op.session_from_env_with(|name| {
+            Some(OsString::from(if name == SERVICE_ACCOUNT_TOKEN_VAR { "ops_token" } else { "" }))
+        }).unwrap();
+        assert_eq!(session.get_field("abc", "username").unwrap().expose(), "user");
+    }
+
+    #[test]
+    fn items_can_be_compared_and_hashed() {
+        let json = r#"{"uuid": "abc", "vaultUuid": "v", "changerUuid": "c",
+            "overview": {"ainfo": "user", "title": "Example"},
+            "details": {"fields": [{"designation": "password", "name": "password", "type": "P", "value": "hunter2"}]}}"#;
+        let item : OpItem = serde_json::from_str(json).unwrap();
+        let mut changed = item.clone();
+        changed.overview.title = "Other".to_owned();
+        assert_eq!(item, serde_json::from_str::<OpItem>(json).unwrap());
+        assert_ne!(item, changed);
+        let set : HashSet<OpItem> = vec![item.clone(), item, changed].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn items_round_trip_unknown_properties() {
+        let json = concat!(r#"{"uuid":"abc","templateUuid":"001","trashed":"N","createdAt":"2018-09-24T10:00:00Z","#,
+            r#""vaultUuid":"v","changerUuid":"c","#,
+            r#""overview":{"URLs":[{"l":"website","u":"https://example.com"}],"ainfo":"user","ps":72,"title":"Example"},"#,
+            r#""details":{"fields":[{"designation":"password","name":"password","type":"P","value":"hunter2","id":"1"}],"#,
+            r#""notesPlain":"note","sections":[{"name":"linked items","title":"Related Items"}]}}"#);
+        let item : OpItem = serde_json::from_str(json).unwrap();
+        assert_eq!(item.extra["templateUuid"], "001");
+        assert_eq!(item.created_at, Some("2018-09-24T10:00:00Z".to_owned()));
+        assert_eq!(item.version, None);
+        let original : serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&item.to_json()).unwrap(), original);
+        assert!(!format!("{:?}", item).contains("note"));
+    }
 }
@@ -0,0 +1,42 @@
+//! Locking the memory of secrets so it isn't swapped to disk.
+//!
+//! `mlock` works on whole pages, so unlocking one secret also unlocks any
+//! other secret sharing a page with it. Locking is best effort: it fails when
+//! `RLIMIT_MEMLOCK` is exhausted or the platform doesn't support it, and the
+//! secret then simply stays unlocked.
+
+/// A locked memory region, unlocked again when dropped.
+#[derive(Debug)]
+#[cfg_attr(not(all(feature = "mlock", unix)), allow(dead_code))]
+pub struct Lock {
+    addr: usize,
+    len: usize,
+}
+
+impl Lock {
+    /// Lock the memory holding `bytes`. Returns `None` if locking failed or
+    /// isn't supported.
+    #[cfg(all(feature = "mlock", unix))]
+    pub fn new(bytes: &[u8]) -> Option<Lock> {
+        if bytes.is_empty() {
+            return None;
+        }
+        let addr = bytes.as_ptr() as usize;
+        let ok = unsafe { ::libc::mlock(addr as *const ::libc::c_void, bytes.len()) } == 0;
+        if ok { Some(Lock { addr, len: bytes.len() }) } else { None }
+    }
+
+    #[cfg(not(all(feature = "mlock", unix)))]
+    pub fn new(_bytes: &[u8]) -> Option<Lock> {
+        None
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        #[cfg(all(feature = "mlock", unix))]
+        unsafe {
+            ::libc::munlock(self.addr as *const ::libc::c_void, self.len);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! Serde models of items, one module per `op` version, generated from the
+//! JSON samples in `samples/op`.
+//!
+//! Don't edit this file, add a sample and run `cargo xtask codegen` instead.
+//! Keys missing from some samples are optional and keys not in any sample
+//! end up in `extra`.
+
+/// Items as printed by `op` 1.12.4.
+pub mod v1_12_4 {
+    use std::fmt;
+
+    use serde_json::{Map, Value};
+
+    use secret::Redacted;
+
+    /// Template UUIDs of the sampled items.
+    pub const TEMPLATE_UUIDS: &[&str] = &["001", "005"];
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Item {
+        pub uuid: String,
+        #[serde(rename = "templateUuid")]
+        pub template_uuid: String,
+        pub trashed: String,
+        #[serde(rename = "createdAt")]
+        pub created_at: String,
+        #[serde(rename = "updatedAt")]
+        pub updated_at: String,
+        #[serde(rename = "changerUuid")]
+        pub changer_uuid: String,
+        #[serde(rename = "itemVersion")]
+        pub item_version: i64,
+        #[serde(rename = "vaultUuid")]
+        pub vault_uuid: String,
+        pub overview: Overview,
+        pub details: Details,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Overview {
+        #[serde(rename = "URLs", default, skip_serializing_if = "Option::is_none")]
+        pub urls: Option<Vec<OverviewURL>>,
+        pub ainfo: String,
+        pub ps: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub tags: Option<Vec<String>>,
+        pub title: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub url: Option<String>,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct OverviewURL {
+        pub l: String,
+        pub u: String,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    /// Shows secret values only if `set_debug_reveal` was called.
+    #[derive(Serialize, Deserialize, Clone, PartialEq)]
+    pub struct Details {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub fields: Option<Vec<DetailsField>>,
+        #[serde(rename = "notesPlain")]
+        pub notes_plain: String,
+        #[serde(rename = "passwordHistory", default, skip_serializing_if = "Option::is_none")]
+        pub password_history: Option<Vec<DetailsPasswordHistory>>,
+        pub sections: Vec<DetailsSection>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub password: Option<String>,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    impl fmt::Debug for Details {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_struct("Details")
+                .field("fields", &self.fields)
+                .field("notes_plain", &Redacted(&self.notes_plain))
+                .field("password_history", &self.password_history)
+                .field("sections", &self.sections)
+                .field("password", &Redacted(&self.password))
+                .field("extra", &Redacted(&self.extra))
+                .finish()
+        }
+    }
+
+    /// Shows secret values only if `set_debug_reveal` was called.
+    #[derive(Serialize, Deserialize, Clone, PartialEq)]
+    pub struct DetailsField {
+        pub designation: String,
+        pub name: String,
+        pub r#type: String,
+        pub value: String,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    impl fmt::Debug for DetailsField {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_struct("DetailsField")
+                .field("designation", &self.designation)
+                .field("name", &self.name)
+                .field("type", &self.r#type)
+                .field("value", &Redacted(&self.value))
+                .field("extra", &Redacted(&self.extra))
+                .finish()
+        }
+    }
+
+    /// Shows secret values only if `set_debug_reveal` was called.
+    #[derive(Serialize, Deserialize, Clone, PartialEq)]
+    pub struct DetailsPasswordHistory {
+        pub time: i64,
+        pub value: String,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    impl fmt::Debug for DetailsPasswordHistory {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_struct("DetailsPasswordHistory")
+                .field("time", &self.time)
+                .field("value", &Redacted(&self.value))
+                .field("extra", &Redacted(&self.extra))
+                .finish()
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct DetailsSection {
+        pub fields: Vec<DetailsSectionField>,
+        pub name: String,
+        pub title: String,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    /// Shows secret values only if `set_debug_reveal` was called.
+    #[derive(Serialize, Deserialize, Clone, PartialEq)]
+    pub struct DetailsSectionField {
+        pub k: String,
+        pub n: String,
+        pub t: String,
+        pub v: String,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    impl fmt::Debug for DetailsSectionField {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_struct("DetailsSectionField")
+                .field("k", &self.k)
+                .field("n", &self.n)
+                .field("t", &self.t)
+                .field("v", &Redacted(&self.v))
+                .field("extra", &Redacted(&self.extra))
+                .finish()
+        }
+    }
+}
+
+/// Items as printed by `op` 2.24.0.
+pub mod v2_24_0 {
+    use std::fmt;
+
+    use serde_json::{Map, Value};
+
+    use secret::Redacted;
+
+    /// Categories of the sampled items.
+    pub const CATEGORIES: &[&str] = &["API_CREDENTIAL", "LOGIN", "PASSWORD"];
+
+    /// Types of the fields of the sampled items.
+    pub const FIELD_TYPES: &[&str] = &["CONCEALED", "DATE", "OTP", "STRING"];
+
+    /// Purposes of the fields of the sampled items.
+    pub const FIELD_PURPOSES: &[&str] = &["NOTES", "PASSWORD", "USERNAME"];
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Item {
+        pub id: String,
+        pub title: String,
+        pub version: i64,
+        pub vault: Vault,
+        pub category: String,
+        pub last_edited_by: String,
+        pub created_at: String,
+        pub updated_at: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub additional_information: Option<String>,
+        pub fields: Vec<Field>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub tags: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub favorite: Option<bool>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub urls: Option<Vec<Url>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub sections: Option<Vec<Section>>,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Vault {
+        pub id: String,
+        pub name: String,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    /// Shows secret values only if `set_debug_reveal` was called.
+    #[derive(Serialize, Deserialize, Clone, PartialEq)]
+    pub struct Field {
+        pub id: String,
+        pub r#type: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub purpose: Option<::FieldPurpose>,
+        pub label: String,
+        pub reference: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub value: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub entropy: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub password_details: Option<FieldPasswordDetails>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub section: Option<FieldSection>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub totp: Option<String>,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    impl fmt::Debug for Field {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_struct("Field")
+                .field("id", &self.id)
+                .field("type", &self.r#type)
+                .field("purpose", &self.purpose)
+                .field("label", &self.label)
+                .field("reference", &self.reference)
+                .field("value", &Redacted(&self.value))
+                .field("entropy", &self.entropy)
+                .field("password_details", &self.password_details)
+                .field("section", &self.section)
+                .field("totp", &Redacted(&self.totp))
+                .field("extra", &Redacted(&self.extra))
+                .finish()
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct FieldPasswordDetails {
+        pub entropy: i64,
+        pub generated: bool,
+        pub strength: String,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct FieldSection {
+        pub id: String,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Url {
+        pub label: String,
+        pub primary: bool,
+        pub href: String,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Section {
+        pub id: String,
+        /// Properties missing from the samples.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    #[test]
+    fn parses_samples() {
+        serde_json::from_str::<super::v1_12_4::Item>(include_str!("../samples/op/1.12.4/login.json")).unwrap();
+        serde_json::from_str::<super::v1_12_4::Item>(include_str!("../samples/op/1.12.4/password.json")).unwrap();
+        serde_json::from_str::<super::v2_24_0::Item>(include_str!("../samples/op/2.24.0/api_credential.json")).unwrap();
+        serde_json::from_str::<super::v2_24_0::Item>(include_str!("../samples/op/2.24.0/login.json")).unwrap();
+        serde_json::from_str::<super::v2_24_0::Item>(include_str!("../samples/op/2.24.0/password.json")).unwrap();
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+use std::time::Duration;
+
+use classify::ErrorCategory;
+
+/// Description of an `op` invocation passed to an `Observer`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CommandInfo {
+    /// Subcommand being run, e.g. `get item`.
+    pub subcommand: String,
+    /// Arguments after the subcommand with session tokens and other secrets redacted.
+    pub args: Vec<String>,
+    /// UUIDs or names of the items, vaults, etc. the command is run against.
+    pub targets: Vec<String>,
+    /// Reason given with `OpSession::with_reason`.
+    pub reason: Option<String>,
+}
+
+impl fmt::Display for CommandInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "op {}", self.subcommand)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+/// How an `op` invocation ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandOutcome {
+    /// `op` exited successfully.
+    Success,
+    /// `op` exited with a failure. Contains what kind of failure it was and the
+    /// exit code if there is one.
+    Failed(ErrorCategory, Option<i32>),
+    /// `op` was killed because it ran longer than its timeout.
+    TimedOut,
+    /// `op` could not be started at all.
+    SpawnFailed,
+}
+
+impl CommandOutcome {
+    /// Short stable name for this outcome, suitable as a metrics label.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            CommandOutcome::Success => "success",
+            CommandOutcome::Failed(..) => "failed",
+            CommandOutcome::TimedOut => "timed_out",
+            CommandOutcome::SpawnFailed => "spawn_failed",
+        }
+    }
+}
+
+/// Hooks called around every `op` invocation.
+///
+/// Register with `Op::with_observer` to feed metrics such as call counts,
+/// latency histograms and error rates. Both methods default to doing nothing.
+pub trait Observer: Send + Sync {
+    /// Called right before `op` is started.
+    fn on_command_start(&self, _command: &CommandInfo) {}
+
+    /// Called after `op` has finished or failed to start.
+    fn on_command_finish(&self, _command: &CommandInfo, _duration: Duration, _outcome: CommandOutcome) {}
+
+    /// Called for each line `op` wrote to stderr while still exiting successfully,
+    /// such as deprecation or version notices.
+    fn on_warning(&self, _command: &CommandInfo, _warning: &str) {}
+}
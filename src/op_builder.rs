@@ -0,0 +1,196 @@
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use backend::{Backend, EnvPolicy, SpawnHook};
+use discover::{DiscoverOptions, OP_EXECUTABLE};
+use observer::Observer;
+use policy::CommandPolicy;
+use retry::RetryPolicy;
+use super::{Op, Result};
+
+/// Builds an `Op` with all of its configuration in one place, from `Op::builder`.
+///
+/// Timeouts, retries, the default account and the environment policy can
+/// only be set here. The `Op` it builds is not changed by anything later;
+/// methods such as `Op::with_observer` return a new handle.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use std::time::Duration;
+/// use one_password::{EnvPolicy, Op, RetryPolicy};
+///
+/// let op = Op::builder()
+///     .binary("/usr/local/bin/op")
+///     .timeout(Duration::from_secs(30))
+///     .retry(RetryPolicy::new(3))
+///     .account("my")
+///     .env_policy(EnvPolicy::Minimal(Vec::new()))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct OpBuilder {
+    binary: Option<PathBuf>,
+    discover: DiscoverOptions,
+    op: Op,
+}
+
+impl Default for OpBuilder {
+    fn default() -> OpBuilder {
+        OpBuilder::new()
+    }
+}
+
+impl OpBuilder {
+    pub fn new() -> OpBuilder {
+        OpBuilder {
+            binary: None,
+            discover: DiscoverOptions::default(),
+            op: Op::new(OP_EXECUTABLE),
+        }
+    }
+
+    /// Run the `op` binary at `path` instead of discovering it.
+    pub fn binary<P: AsRef<Path>>(mut self, path: P) -> OpBuilder {
+        self.binary = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Where to look for `op` if no `binary` is given. Defaults to
+    /// `DiscoverOptions::default()`, like `Op::which`.
+    pub fn discover(mut self, options: DiscoverOptions) -> OpBuilder {
+        self.discover = options;
+        self
+    }
+
+    /// Kill `op` and fail with `ErrorKind::TimedOut` if it runs longer than
+    /// `timeout`. Commands that prompt on the terminal have no timeout.
+    pub fn timeout(mut self, timeout: Duration) -> OpBuilder {
+        self.op.timeout = Some(timeout);
+        self
+    }
+
+    /// Run failed commands again as described by `retry`. Defaults to
+    /// `RetryPolicy::never()`.
+    pub fn retry(mut self, retry: RetryPolicy) -> OpBuilder {
+        self.op.retry = retry;
+        self
+    }
+
+    /// Account `op` 2.x uses when a command doesn't name one, passed in
+    /// `OP_ACCOUNT`. Takes a shorthand, sign-in address, user or account ID.
+    pub fn account(mut self, account: &str) -> OpBuilder {
+        self.op.account = Some(account.to_owned());
+        self
+    }
+
+    /// Pass `args` to every invocation of `op`, see `Op::with_extra_args`.
+    pub fn extra_args<S: AsRef<OsStr>>(mut self, args: &[S]) -> OpBuilder {
+        self.op.extra_args.extend(args.iter().map(|a| a.as_ref().to_owned()));
+        self
+    }
+
+    /// Which environment variables of this process `op` gets. Defaults to
+    /// `EnvPolicy::Inherit`.
+    pub fn env_policy(mut self, policy: EnvPolicy) -> OpBuilder {
+        self.op.env_policy = policy;
+        self
+    }
+
+    /// Locale `op` runs with, see `Op::with_locale`.
+    pub fn locale(mut self, locale: Option<&str>) -> OpBuilder {
+        self.op.locale = locale.map(OsString::from);
+        self
+    }
+
+    /// Only run the subcommands `policy` allows, see `Op::with_policy`.
+    pub fn policy(mut self, policy: CommandPolicy) -> OpBuilder {
+        self.op = self.op.with_policy(policy);
+        self
+    }
+
+    /// Add an observer, see `Op::with_observer`.
+    pub fn observer<O: Observer + 'static>(mut self, observer: O) -> OpBuilder {
+        self.op = self.op.with_observer(observer);
+        self
+    }
+
+    /// Add a spawn hook, see `Op::with_spawn_hook`.
+    pub fn spawn_hook<H: SpawnHook + 'static>(mut self, hook: H) -> OpBuilder {
+        self.op = self.op.with_spawn_hook(hook);
+        self
+    }
+
+    /// Run `op` with `backend`, see `Op::with_backend`.
+    pub fn backend<B: Backend + 'static>(mut self, backend: B) -> OpBuilder {
+        self.op = self.op.with_backend(backend);
+        self
+    }
+
+    /// Find `op` unless a binary was given, and return the configured `Op`.
+    pub fn build(self) -> Result<Op> {
+        let command = match self.binary {
+            Some(binary) => binary,
+            None => self.discover.find()?,
+        };
+        Ok(Op { command, ..self.op })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::process::Output;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use {CliVersion, ErrorCategory, Invocation};
+
+    /// Fails with a network error until it has been called `failures` times.
+    struct Flaky {
+        calls: Arc<AtomicUsize>,
+        failures: usize,
+    }
+
+    impl Backend for Flaky {
+        fn run(&self, invocation: &Invocation) -> io::Result<Output> {
+            assert_eq!(invocation.account(), Some("my"));
+            let failed = self.calls.fetch_add(1, Ordering::SeqCst) < self.failures;
+            let script = if failed { "echo 'dial tcp: no such host' >&2; exit 1" } else { "printf hunter2" };
+            ::std::process::Command::new("sh").arg("-c").arg(script).output()
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn builds_configured_op() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let op = Op::builder()
+            .binary("op")
+            .account("my")
+            .retry(RetryPolicy::new(3).backoff(Duration::from_millis(1)))
+            .backend(Flaky { calls: calls.clone(), failures: 2 })
+            .build()
+            .unwrap()
+            .with_cli_version(CliVersion::new(2, 24, 0));
+        assert_eq!(op.session("token").read("op://Ops/DB/password").unwrap().expose(), "hunter2");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        calls.store(0, Ordering::SeqCst);
+        let op = Op::builder()
+            .binary("op")
+            .account("my")
+            .retry(RetryPolicy::new(2).backoff(Duration::from_millis(1)))
+            .backend(Flaky { calls: calls.clone(), failures: 2 })
+            .build()
+            .unwrap()
+            .with_cli_version(CliVersion::new(2, 24, 0));
+        let err = op.session("token").read("op://Ops/DB/password").unwrap_err();
+        assert_eq!(err.category(), Some(ErrorCategory::Network));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
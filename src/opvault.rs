@@ -0,0 +1,476 @@
+//! Reading legacy OPVault vaults.
+//!
+//! OPVault is the on-disk format 1Password 4 to 7 used for local and
+//! Dropbox synced vaults: a `.opvault` directory with a `default/profile.js`
+//! holding the encrypted keys and `band_*.js` files holding the items. This
+//! module unlocks such a vault with its master password and decrypts its items
+//! in pure Rust, without `op`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use one_password::opvault::OpVault;
+//!
+//! let vault = OpVault::open("Backup.opvault").unwrap().unlock("master password").unwrap();
+//! for item in vault.items().unwrap() {
+//!     let details = vault.details(&item).unwrap();
+//!     println!("{} has a password: {}", item.overview.title, details.password().is_some());
+//! }
+//! ```
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use aes::Aes256;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+use cbc::cipher::block_padding::NoPadding;
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac;
+use serde::de::DeserializeOwned;
+use serde_json;
+use sha2::{Digest, Sha256, Sha512};
+
+use summary;
+use super::{ErrorKind, ItemSummary, ItemUrl, Result, Secret, SecretBytes};
+
+type Aes256CbcDec = cbc::Decryptor<Aes256>;
+type HmacSha256 = Hmac<Sha256>;
+
+const OPDATA01: &[u8] = b"opdata01";
+const BANDS: &str = "0123456789ABCDEF";
+
+fn invalid<S: Into<String>>(reason: S) -> ::Error {
+    ErrorKind::InvalidOpVault(reason.into()).into()
+}
+
+/// An encryption key and the key used to authenticate what it encrypts.
+#[derive(Debug)]
+struct Keys(SecretBytes);
+
+impl Keys {
+    fn new(bytes: Vec<u8>) -> Result<Keys> {
+        if bytes.len() != 64 {
+            return Err(invalid("key is not 64 bytes"));
+        }
+        Ok(Keys(SecretBytes::new(bytes)))
+    }
+
+    fn encryption(&self) -> &[u8] {
+        &self.0.as_bytes()[..32]
+    }
+
+    fn mac(&self) -> &[u8] {
+        &self.0.as_bytes()[32..]
+    }
+
+    /// Check the HMAC-SHA256 at the end of `data` and return what precedes it.
+    fn authenticate<'a>(&self, data: &'a [u8]) -> Result<&'a [u8]> {
+        if data.len() < 32 {
+            return Err(invalid("data too short"));
+        }
+        let (body, tag) = data.split_at(data.len() - 32);
+        let mut mac = HmacSha256::new_from_slice(self.mac()).expect("HMAC takes keys of any size");
+        mac.update(body);
+        mac.verify_slice(tag).map_err(|_| ErrorKind::OpVaultUnlock)?;
+        Ok(body)
+    }
+
+    fn decrypt(&self, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let mut buffer = ciphertext.to_vec();
+        let len = Aes256CbcDec::new_from_slices(self.encryption(), iv)
+            .map_err(|_| invalid("bad IV"))?
+            .decrypt_padded_mut::<NoPadding>(&mut buffer)
+            .map_err(|_| invalid("ciphertext is not a whole number of blocks"))?
+            .len();
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+
+    /// Decrypt an `opdata01` blob: magic, plaintext length, IV, ciphertext and
+    /// a MAC over all of it. The plaintext is padded at the front.
+    fn opdata01(&self, data: &[u8]) -> Result<SecretBytes> {
+        if !data.starts_with(OPDATA01) || data.len() < 64 {
+            return Err(invalid("not opdata01"));
+        }
+        let body = self.authenticate(data)?;
+        let mut size = [0; 8];
+        size.copy_from_slice(&body[8..16]);
+        let size = u64::from_le_bytes(size) as usize;
+        let plaintext = SecretBytes::new(self.decrypt(&body[16..32], &body[32..])?);
+        if size > plaintext.len() {
+            return Err(invalid("opdata01 length exceeds its data"));
+        }
+        Ok(SecretBytes::new(plaintext.as_bytes()[plaintext.len() - size..].to_vec()))
+    }
+
+    /// Keys stored as `opdata01`, e.g. the master key: the SHA-512 of the plaintext.
+    fn derive(&self, data: &[u8]) -> Result<Keys> {
+        Keys::new(Sha512::digest(self.opdata01(data)?.as_bytes()).to_vec())
+    }
+
+    /// An item key: IV, ciphertext of both keys and a MAC.
+    fn item_keys(&self, data: &[u8]) -> Result<Keys> {
+        let body = self.authenticate(data)?;
+        if body.len() < 16 {
+            return Err(invalid("item key too short"));
+        }
+        Keys::new(self.decrypt(&body[..16], &body[16..])?)
+    }
+}
+
+/// Parse the JSON object in a JavaScript file such as `var profile={...};`.
+fn read_js<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents = fs::read_to_string(path)?;
+    let json = match (contents.find('{'), contents.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &contents[start..=end],
+        _ => return Err(invalid(format!("no object in {}", path.display()))),
+    };
+    Ok(serde_json::from_str(json)?)
+}
+
+fn decode(value: &str) -> Result<Vec<u8>> {
+    STANDARD.decode(value).map_err(|_| invalid("invalid base64"))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Profile {
+    uuid: String,
+    #[serde(default)]
+    profile_name: String,
+    salt: String,
+    iterations: u32,
+    master_key: String,
+    overview_key: String,
+}
+
+/// A locked OPVault profile.
+pub struct OpVault {
+    dir: PathBuf,
+    profile: Profile,
+}
+
+impl ::std::fmt::Debug for OpVault {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("OpVault")
+            .field("dir", &self.dir)
+            .field("uuid", &self.profile.uuid)
+            .field("name", &self.profile.profile_name)
+            .finish()
+    }
+}
+
+impl OpVault {
+    /// Open the `default` profile of the `.opvault` directory at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<OpVault> {
+        let dir = path.as_ref().join("default");
+        let profile = read_js(&dir.join("profile.js"))?;
+        Ok(OpVault { dir, profile })
+    }
+
+    /// Derive the keys from `password` and decrypt the master and overview keys.
+    ///
+    /// Fails with `ErrorKind::OpVaultUnlock` if the password is wrong.
+    pub fn unlock(&self, password: &str) -> Result<UnlockedVault> {
+        let mut derived = vec![0; 64];
+        let salt = decode(&self.profile.salt)?;
+        pbkdf2_hmac::<Sha512>(password.as_bytes(), &salt, self.profile.iterations, &mut derived);
+        let derived = Keys::new(derived)?;
+        Ok(UnlockedVault {
+            dir: self.dir.clone(),
+            uuid: self.profile.uuid.clone(),
+            name: self.profile.profile_name.clone(),
+            master: derived.derive(&decode(&self.profile.master_key)?)?,
+            overview: derived.derive(&decode(&self.profile.overview_key)?)?,
+        })
+    }
+}
+
+/// Overview of an item, decrypted when the items are listed.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Overview {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Additional information shown in lists, e.g. the username.
+    #[serde(default)]
+    pub ainfo: String,
+}
+
+/// An item of an unlocked vault. Use `UnlockedVault::details` to decrypt its fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub uuid: String,
+    /// Category in `op` 2.x spelling, e.g. `LOGIN`.
+    pub category: String,
+    /// Seconds since the Unix epoch.
+    pub created_at: i64,
+    /// Seconds since the Unix epoch.
+    pub updated_at: i64,
+    pub trashed: bool,
+    pub favorite: bool,
+    pub folder: Option<String>,
+    pub overview: Overview,
+    key: String,
+    details: String,
+}
+
+#[derive(Deserialize)]
+struct RawItem {
+    uuid: String,
+    #[serde(default)]
+    category: String,
+    k: String,
+    d: String,
+    o: String,
+    #[serde(default)]
+    created: i64,
+    #[serde(default)]
+    updated: i64,
+    #[serde(default)]
+    trashed: bool,
+    fave: Option<i64>,
+    folder: Option<String>,
+}
+
+/// A field of a login form, such as the username and password.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    #[serde(default)]
+    pub name: String,
+    /// `T` for text, `P` for password, `E` for email and so on.
+    #[serde(default, rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub value: Secret<String>,
+    /// `username` or `password` for the fields filled in by 1Password.
+    pub designation: Option<String>,
+}
+
+/// A field in a section.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SectionField {
+    /// Title shown to the user.
+    #[serde(default, rename = "t")]
+    pub title: String,
+    #[serde(default, rename = "n")]
+    pub name: String,
+    /// Type of the value, e.g. `string`, `concealed` or `date`.
+    #[serde(default, rename = "k")]
+    pub kind: String,
+    #[serde(default, rename = "v")]
+    pub value: Secret<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub fields: Vec<SectionField>,
+}
+
+/// The decrypted details of an item.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Details {
+    #[serde(default)]
+    pub fields: Vec<Field>,
+    pub notes_plain: Option<Secret<String>>,
+    /// Set for `PASSWORD` items.
+    pub password: Option<Secret<String>>,
+    #[serde(default)]
+    pub sections: Vec<Section>,
+}
+
+impl Details {
+    fn designated(&self, designation: &str) -> Option<&Secret<String>> {
+        self.fields.iter()
+            .find(|f| f.designation.as_ref().map(|d| &d[..]) == Some(designation))
+            .map(|f| &f.value)
+    }
+
+    /// The username of a login.
+    pub fn username(&self) -> Option<&Secret<String>> {
+        self.designated("username")
+    }
+
+    /// The password of a login, or of a `PASSWORD` item.
+    pub fn password(&self) -> Option<&Secret<String>> {
+        self.designated("password").or(self.password.as_ref())
+    }
+}
+
+/// An OPVault profile unlocked with its master password.
+#[derive(Debug)]
+pub struct UnlockedVault {
+    dir: PathBuf,
+    uuid: String,
+    name: String,
+    master: Keys,
+    overview: Keys,
+}
+
+impl UnlockedVault {
+    /// All items, including those in the trash, with their overviews decrypted.
+    pub fn items(&self) -> Result<Vec<Item>> {
+        let mut items = Vec::new();
+        for band in BANDS.chars() {
+            let path = self.dir.join(format!("band_{}.js", band));
+            let raw: ::std::collections::BTreeMap<String, RawItem> = match read_js(&path) {
+                Ok(raw) => raw,
+                Err(::Error(ErrorKind::Io(ref err), _)) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            for item in raw.into_values() {
+                let overview = self.overview.opdata01(&decode(&item.o)?)?;
+                items.push(Item {
+                    category: summary::category(item.category),
+                    overview: serde_json::from_slice(overview.as_bytes())?,
+                    uuid: item.uuid,
+                    created_at: item.created,
+                    updated_at: item.updated,
+                    trashed: item.trashed,
+                    favorite: item.fave.is_some(),
+                    folder: item.folder,
+                    key: item.k,
+                    details: item.d,
+                });
+            }
+        }
+        Ok(items)
+    }
+
+    /// Decrypt the fields, notes and sections of `item`.
+    pub fn details(&self, item: &Item) -> Result<Details> {
+        let keys = self.master.item_keys(&decode(&item.key)?)?;
+        let details = keys.opdata01(&decode(&item.details)?)?;
+        Ok(serde_json::from_slice(details.as_bytes())?)
+    }
+
+    /// Describe `item` like `OpSession::list_items` would.
+    pub fn summary(&self, item: &Item) -> ItemSummary {
+        ItemSummary {
+            id: item.uuid.clone(),
+            title: item.overview.title.clone(),
+            vault_id: self.uuid.clone(),
+            vault_name: Some(self.name.clone()),
+            category: item.category.clone(),
+            tags: item.overview.tags.clone(),
+            favorite: item.favorite,
+            created_at: Some(summary::rfc3339(item.created_at)),
+            updated_at: Some(summary::rfc3339(item.updated_at)),
+            version: None,
+            last_edited_by: None,
+            urls: if item.overview.url.is_empty() {
+                Vec::new()
+            } else {
+                vec![ItemUrl { primary: true, ..ItemUrl::new(&item.overview.url) }]
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use cbc::cipher::BlockEncryptMut;
+
+    use super::*;
+
+    type Aes256CbcEnc = cbc::Encryptor<Aes256>;
+
+    fn mac(keys: &[u8], data: &mut Vec<u8>) {
+        let mut mac = HmacSha256::new_from_slice(&keys[32..]).unwrap();
+        mac.update(data);
+        data.extend_from_slice(&mac.finalize().into_bytes());
+    }
+
+    fn encrypt(keys: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut buffer = plaintext.to_vec();
+        let len = buffer.len();
+        Aes256CbcEnc::new_from_slices(&keys[..32], iv).unwrap()
+            .encrypt_padded_mut::<NoPadding>(&mut buffer, len).unwrap();
+        buffer
+    }
+
+    fn opdata01(keys: &[u8], plaintext: &[u8]) -> String {
+        let padding = 16 - plaintext.len() % 16;
+        let mut padded = vec![7; padding];
+        padded.extend_from_slice(plaintext);
+        let mut data = OPDATA01.to_vec();
+        data.extend_from_slice(&(plaintext.len() as u64).to_le_bytes());
+        data.extend_from_slice(&[1; 16]);
+        data.extend(encrypt(keys, &[1; 16], &padded));
+        mac(keys, &mut data);
+        STANDARD.encode(data)
+    }
+
+    /// Build a vault with one login, the way 1Password would.
+    fn write_vault(dir: &Path, password: &str) {
+        let salt = [3; 16];
+        let mut derived = vec![0; 64];
+        pbkdf2_hmac::<Sha512>(password.as_bytes(), &salt, 1000, &mut derived);
+        let master_plain = [4; 256];
+        let overview_plain = [5; 256];
+        let master = Sha512::digest(master_plain).to_vec();
+        let overview = Sha512::digest(overview_plain).to_vec();
+        let profile = json!({
+            "uuid": "P1", "profileName": "default", "salt": STANDARD.encode(salt), "iterations": 1000,
+            "masterKey": opdata01(&derived, &master_plain), "overviewKey": opdata01(&derived, &overview_plain),
+        });
+
+        let item_keys = [6; 64];
+        let mut k = vec![2; 16];
+        k.extend(encrypt(&master, &[2; 16], &item_keys));
+        mac(&master, &mut k);
+        let details = json!({"fields": [
+            {"name": "username", "type": "T", "value": "jane", "designation": "username"},
+            {"name": "password", "type": "P", "value": "hunter2", "designation": "password"},
+        ]});
+        let band = json!({"I1": {
+            "uuid": "I1", "category": "001", "created": 0, "updated": 86400, "fave": 1,
+            "k": STANDARD.encode(k),
+            "o": opdata01(&overview, br#"{"title": "Acme", "tags": ["work"], "ainfo": "jane"}"#),
+            "d": opdata01(&item_keys, details.to_string().as_bytes()),
+        }});
+
+        let default = dir.join("default");
+        fs::create_dir_all(&default).unwrap();
+        fs::write(default.join("profile.js"), format!("var profile={};", profile)).unwrap();
+        fs::write(default.join("band_A.js"), format!("ld({});", band)).unwrap();
+    }
+
+    #[test]
+    fn unlocks_and_decrypts_items() {
+        let dir = env::temp_dir().join(format!("op-opvault-{}.opvault", ::std::process::id()));
+        write_vault(&dir, "correct horse");
+        let vault = OpVault::open(&dir).unwrap();
+        match *vault.unlock("wrong").unwrap_err().kind() {
+            ErrorKind::OpVaultUnlock => {},
+            ref other => panic!("unexpected error {:?}", other),
+        }
+
+        let unlocked = vault.unlock("correct horse").unwrap();
+        let items = unlocked.items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].overview.title, "Acme");
+        assert_eq!(items[0].category, "LOGIN");
+        let details = unlocked.details(&items[0]).unwrap();
+        assert_eq!(details.username().unwrap().expose(), "jane");
+        assert_eq!(details.password().unwrap().expose(), "hunter2");
+        let summary = unlocked.summary(&items[0]);
+        assert!(summary.favorite);
+        assert_eq!(summary.updated_at, Some("1970-01-02T00:00:00Z".to_owned()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+use super::{ErrorKind, OpSession, Result};
+
+/// A shell plugin known to `op`, from `OpSession::plugins`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShellPlugin {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Commands the plugin provides credentials to, e.g. `aws`.
+    #[serde(default)]
+    pub executables: Vec<String>,
+}
+
+/// Vault an item sourced by a plugin is in.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PluginVault {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Item a plugin sources a credential from.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PluginItem {
+    pub id: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub vault: Option<PluginVault>,
+}
+
+/// A configured credential of a plugin.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PluginCredential {
+    /// Kind of credential, e.g. `Access Key`.
+    #[serde(default)]
+    pub credential_type: Option<String>,
+    pub item: PluginItem,
+    /// Where the configuration applies: `global`, `directory` or `session`.
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Directory the configuration applies to, for directory scoped credentials.
+    #[serde(default)]
+    pub directory: Option<String>,
+}
+
+/// Configuration of a single plugin, from `OpSession::inspect_plugin`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PluginInspection {
+    pub name: String,
+    /// Empty if the plugin hasn't been set up.
+    #[serde(default)]
+    pub credentials: Vec<PluginCredential>,
+}
+
+impl OpSession {
+    /// List the shell plugins available in `op`, using `op plugin list`.
+    pub fn plugins(&self) -> Result<Vec<ShellPlugin>> {
+        self.command(&["plugin", "list"])
+            .json()?
+            .run_json(ErrorKind::CommandFailed)
+    }
+
+    /// Show which items the plugin named `plugin` sources its credentials from,
+    /// using `op plugin inspect`.
+    pub fn inspect_plugin(&self, plugin: &str) -> Result<PluginInspection> {
+        self.command(&["plugin", "inspect"])
+            .target(plugin)
+            .json()?
+            .run_json(ErrorKind::CommandFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassette::{interaction, replay_op};
+
+    #[test]
+    fn inspects_plugins() {
+        let session = replay_op(vec![
+            interaction("plugin list", &["--session=⟨redacted⟩", "--format", "json", "--no-color", "--iso-timestamps"],
+                r#"[{"name": "aws", "description": "AWS CLI", "executables": ["aws", "cdk"]}]"#),
+            interaction("plugin inspect", &["--session=⟨redacted⟩", "aws", "--format", "json", "--no-color", "--iso-timestamps"],
+                r#"{"name": "aws", "credentials": [{"credential_type": "Access Key", "scope": "global",
+                    "item": {"id": "abc", "title": "AWS", "vault": {"id": "v", "name": "Private"}}}]}"#),
+        ]).session("token");
+        let plugins = session.plugins().unwrap();
+        assert_eq!(plugins[0].executables, vec!["aws", "cdk"]);
+        let aws = session.inspect_plugin("aws").unwrap();
+        assert_eq!(aws.credentials[0].item.id, "abc");
+        assert_eq!(aws.credentials[0].scope, Some("global".to_owned()));
+    }
+}
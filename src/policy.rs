@@ -0,0 +1,141 @@
+/// Subcommands that only read from 1Password, in the syntax of `op` 2.x and 1.x.
+const READ_ONLY: &[&[&str]] = &[
+    &["read"],
+    &["whoami"],
+    &["item", "get"],
+    &["item", "list"],
+    &["item", "template"],
+    &["document", "get"],
+    &["document", "list"],
+    &["vault", "get"],
+    &["vault", "list"],
+    &["user", "get"],
+    &["user", "list"],
+    &["group", "get"],
+    &["group", "list"],
+    &["account", "get"],
+    &["account", "list"],
+    &["plugin", "list"],
+    &["plugin", "inspect"],
+    &["get"],
+    &["list"],
+];
+
+/// Allow-list of the subcommands an `Op` may run, for handing it to plugins
+/// or other code that should only get as much access as it needs.
+///
+/// Register with `Op::with_policy`. Running anything else fails with
+/// `ErrorKind::PolicyViolation` before `op` is started. Subcommands are
+/// matched by their leading words, so allowing `item` allows `item get` and
+/// `item delete`. `op --version` is always allowed. Registering a second
+/// policy only allows what both of them allow.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use one_password::{CommandPolicy, Op};
+///
+/// let op = Op::which().unwrap().with_policy(CommandPolicy::read_only());
+/// let session = op.service_account_session().unwrap();
+/// let password = session.read("op://Ops/DB/password").unwrap();
+/// assert!(session.delete_item("DB").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandPolicy {
+    allowed: Vec<Vec<String>>,
+}
+
+impl CommandPolicy {
+    /// Allow nothing but what is added with `allow`.
+    pub fn deny_all() -> CommandPolicy {
+        CommandPolicy::default()
+    }
+
+    /// Allow reading items, documents, vaults, users, groups and accounts,
+    /// with `op` 1.x or 2.x, but nothing that changes them. Signing in is
+    /// not allowed either, so use a service account or an existing session,
+    /// or `allow(&["signin"])`. Neither are `inject` and `run`, which hand
+    /// secrets to templates and commands this policy can't vet; allow them
+    /// the same way if needed.
+    pub fn read_only() -> CommandPolicy {
+        READ_ONLY.iter().fold(CommandPolicy::deny_all(), |policy, subcommand| policy.allow(subcommand))
+    }
+
+    /// Also allow `subcommand` and everything under it, e.g. `&["item", "edit"]`.
+    pub fn allow(mut self, subcommand: &[&str]) -> CommandPolicy {
+        self.allowed.push(subcommand.iter().map(|word| word.to_string()).collect());
+        self
+    }
+
+    /// Only allow what both this policy and `other` allow.
+    pub fn intersect(&self, other: &CommandPolicy) -> CommandPolicy {
+        let mut allowed = Vec::new();
+        for a in &self.allowed {
+            for b in &other.allowed {
+                // Of two prefixes, the longer one allows what both allow.
+                if a.starts_with(b) {
+                    allowed.push(a.clone());
+                } else if b.starts_with(a) {
+                    allowed.push(b.clone());
+                }
+            }
+        }
+        CommandPolicy { allowed }
+    }
+
+    /// Whether `subcommand`, e.g. `["item", "get"]`, may run.
+    pub fn permits<S: AsRef<str>>(&self, subcommand: &[S]) -> bool {
+        let words : Vec<&str> = subcommand.iter().map(AsRef::as_ref).collect();
+        words == ["--version"] || self.allowed.iter().any(|allowed| {
+            !allowed.is_empty() && words.len() >= allowed.len() && allowed.iter().zip(&words).all(|(a, w)| a == w)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::replay_op;
+    use ErrorKind;
+
+    #[test]
+    fn allows_listed_subcommands() {
+        let policy = CommandPolicy::read_only();
+        assert!(policy.permits(&["item", "get"]));
+        assert!(policy.permits(&["get", "item"]));
+        assert!(policy.permits(&["--version"]));
+        assert!(!policy.permits(&["item", "delete"]));
+        assert!(!policy.permits(&["item"]));
+        assert!(!policy.permits(&["signin"]));
+        assert!(!policy.permits(&["run"]));
+        assert!(!policy.permits(&["inject"]));
+        assert!(policy.allow(&["item"]).permits(&["item", "delete"]));
+        assert!(!CommandPolicy::deny_all().permits(&["read"]));
+
+        let items = CommandPolicy::deny_all().allow(&["item"]).allow(&["read"]);
+        let both = items.intersect(&CommandPolicy::read_only());
+        assert!(both.permits(&["item", "get"]));
+        assert!(both.permits(&["read"]));
+        assert!(!both.permits(&["item", "delete"]));
+        assert!(!both.permits(&["vault", "list"]));
+
+        let op = replay_op(Vec::new())
+            .with_policy(CommandPolicy::read_only());
+        match *op.session("token").delete_item("abc").unwrap_err().kind() {
+            ErrorKind::PolicyViolation(ref subcommand) => assert_eq!(subcommand, "item delete"),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn later_policies_cant_allow_more() {
+        let op = replay_op(Vec::new())
+            .with_policy(CommandPolicy::read_only())
+            .with_policy(CommandPolicy::deny_all().allow(&["item"]));
+        match *op.session("token").delete_item("abc").unwrap_err().kind() {
+            ErrorKind::PolicyViolation(ref subcommand) => assert_eq!(subcommand, "item delete"),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+/// Number of `op` processes allowed to run at once unless changed with
+/// `set_max_concurrency`.
+pub const DEFAULT_MAX_CONCURRENCY: usize = 8;
+
+static POOL: SpawnPool = SpawnPool::new(DEFAULT_MAX_CONCURRENCY);
+
+struct State {
+    running: usize,
+    max: usize,
+    /// Threads holding a permit.
+    holders: Vec<ThreadId>,
+}
+
+/// Counting semaphore limiting how many `op` processes run at once.
+///
+/// A thread that already holds a permit gets another one right away, so `op`
+/// can be run from a callback called while a command streams its output,
+/// such as the one of `OpSession::for_each_item_in`, even with a limit of 1.
+/// Such nested processes don't count against the limit.
+pub struct SpawnPool {
+    state: Mutex<State>,
+    available: Condvar,
+}
+
+/// Permission to run one process. Returned to the pool when dropped.
+pub struct Permit<'a> {
+    pool: &'a SpawnPool,
+    nested: bool,
+}
+
+impl SpawnPool {
+    pub const fn new(max: usize) -> SpawnPool {
+        SpawnPool {
+            state: Mutex::new(State { running: 0, max, holders: Vec::new() }),
+            available: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        match self.state.lock() {
+            Ok(s) => s,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Wait until fewer than the maximum number of processes are running.
+    pub fn acquire(&self) -> Permit<'_> {
+        let thread = thread::current().id();
+        let mut state = self.lock();
+        if state.holders.contains(&thread) {
+            return Permit { pool: self, nested: true };
+        }
+        while state.running >= state.max {
+            state = match self.available.wait(state) {
+                Ok(s) => s,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+        }
+        state.running += 1;
+        state.holders.push(thread);
+        Permit { pool: self, nested: false }
+    }
+
+    pub fn max(&self) -> usize {
+        self.lock().max
+    }
+
+    pub fn set_max(&self, max: usize) {
+        self.lock().max = max.max(1);
+        self.available.notify_all();
+    }
+}
+
+impl<'a> Drop for Permit<'a> {
+    fn drop(&mut self) {
+        if self.nested {
+            return;
+        }
+        let thread = thread::current().id();
+        let mut state = self.pool.lock();
+        state.running -= 1;
+        if let Some(index) = state.holders.iter().position(|&t| t == thread) {
+            state.holders.swap_remove(index);
+        }
+        drop(state);
+        self.pool.available.notify_one();
+    }
+}
+
+/// The pool shared by every `Op` and `OpSession` in the process.
+pub fn global() -> &'static SpawnPool {
+    &POOL
+}
+
+/// Limit how many `op` processes this crate runs concurrently across all
+/// sessions. Further commands wait until a running one finishes.
+///
+/// Defaults to `DEFAULT_MAX_CONCURRENCY`. Values below 1 are treated as 1.
+/// Commands run from a callback while another command runs on the same
+/// thread, such as in `OpSession::for_each_item_in`, don't wait.
+pub fn set_max_concurrency(max: usize) {
+    POOL.set_max(max)
+}
+
+/// Current limit on concurrently running `op` processes.
+pub fn max_concurrency() -> usize {
+    POOL.max()
+}
+
+/// Call `f` with the index of each of `items` and the item, on up to `workers`
+/// threads, and return the results in the order of `items`.
+pub(crate) fn map_concurrent<T, R, F>(items: &[T], workers: usize, f: F) -> Vec<R>
+    where T: Sync, R: Send, F: Fn(usize, &T) -> R + Sync
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1).min(items.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let item = match items.get(index) {
+                    Some(item) => item,
+                    None => break,
+                };
+                let result = f(index, item);
+                match results.lock() {
+                    Ok(mut r) => r.push((index, result)),
+                    Err(poisoned) => poisoned.into_inner().push((index, result)),
+                }
+            });
+        }
+    });
+    let mut results = match results.into_inner() {
+        Ok(r) => r,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    results.sort_by_key(|&(index, _)| index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn limits_concurrency() {
+        let pool = Arc::new(SpawnPool::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let threads : Vec<_> = (0..8).map(|_| {
+            let (pool, running, peak) = (pool.clone(), running.clone(), peak.clone());
+            thread::spawn(move || {
+                let _permit = pool.acquire();
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(10));
+                running.fetch_sub(1, Ordering::SeqCst);
+            })
+        }).collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn minimum_of_one() {
+        let pool = SpawnPool::new(4);
+        pool.set_max(0);
+        assert_eq!(pool.max(), 1);
+        let _permit = pool.acquire();
+    }
+
+    #[test]
+    fn nested_permits_dont_wait() {
+        let pool = SpawnPool::new(1);
+        let outer = pool.acquire();
+        let nested = pool.acquire();
+        assert_eq!(pool.lock().running, 1);
+        drop(nested);
+        assert_eq!(pool.lock().holders.len(), 1);
+        drop(outer);
+        assert_eq!(pool.lock().running, 0);
+        assert!(pool.lock().holders.is_empty());
+    }
+}
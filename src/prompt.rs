@@ -0,0 +1,11 @@
+//! Prompting for the master password on the terminal.
+use std::io;
+
+use rpassword;
+
+/// Ask for the master password of the account with the given subdomain without
+/// echoing it. Reads from the controlling terminal, so it works even when stdin
+/// is redirected.
+pub fn prompt_password(subdomain: &str) -> io::Result<String> {
+    rpassword::prompt_password(format!("Enter the password for {}: ", subdomain))
+}
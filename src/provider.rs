@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use assignment::{assign, conceal, FieldAssignment};
+use diagnostics::not_found;
+use super::{ErrorKind, ItemBuilder, ItemEdit, OpSession, Result, Secret, SecretReference};
+
+/// A minimal key-value interface to a secret store, so applications can
+/// abstract over where their secrets come from.
+///
+/// Keys are secret references such as `op://vault/item/field`, which map onto
+/// the `mount/path` plus key of stores like HashiCorp Vault's KV engine. Both
+/// `OpSession` and the in-memory `MemoryProvider` for tests implement it.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use one_password::{Op, Result, SecretsProvider};
+///
+/// fn database_url(secrets: &dyn SecretsProvider) -> Result<String> {
+///     let password = secrets.get("op://Production/Database/password")?;
+///     Ok(format!("postgres://app:{}@db/app", password.expose()))
+/// }
+///
+/// let session = Op::which().unwrap().env_session().unwrap();
+/// println!("{}", database_url(&session).unwrap());
+/// ```
+pub trait SecretsProvider {
+    /// Read the value at `reference`.
+    fn get(&self, reference: &str) -> Result<Secret<String>>;
+
+    /// Titles of the items in `vault`, sorted.
+    fn list(&self, vault: &str) -> Result<Vec<String>>;
+
+    /// Set the value at `reference`, creating the item if it doesn't exist.
+    fn put(&self, reference: &str, value: &str) -> Result<()>;
+}
+
+fn field_assignment(reference: &SecretReference, field: fn(&str) -> FieldAssignment) -> FieldAssignment {
+    match reference.section {
+        Some(ref section) => field(&reference.field).in_section(section),
+        None => field(&reference.field),
+    }
+}
+
+impl SecretsProvider for OpSession {
+    fn get(&self, reference: &str) -> Result<Secret<String>> {
+        self.read(reference)
+    }
+
+    fn list(&self, vault: &str) -> Result<Vec<String>> {
+        let mut titles = Vec::new();
+        self.for_each_item_in(Some(vault), |item| titles.push(item.title))?;
+        titles.sort();
+        Ok(titles)
+    }
+
+    /// Set the field with `op item edit`, or create a secure note holding
+    /// it as a concealed field if the item doesn't exist.
+    fn put(&self, reference: &str, value: &str) -> Result<()> {
+        let parsed : SecretReference = reference.parse()?;
+        let edit = ItemEdit::new().set(field_assignment(&parsed, assign).to(value));
+        if not_found(self.edit_item_in(&parsed.item, &edit, Some(&parsed.vault)))?.is_none() {
+            let item = ItemBuilder::new("SECURE_NOTE", &parsed.item)
+                .vault(&parsed.vault)
+                .set(field_assignment(&parsed, conceal).to(value));
+            self.create_item(&item)?;
+        }
+        Ok(())
+    }
+}
+
+/// A `SecretsProvider` keeping secrets in memory, for tests of code using one.
+///
+/// References are compared as written, so the same field named with and
+/// without its section are different keys.
+#[derive(Debug, Default)]
+pub struct MemoryProvider {
+    values: Mutex<BTreeMap<String, String>>,
+}
+
+impl MemoryProvider {
+    pub fn new() -> MemoryProvider {
+        MemoryProvider::default()
+    }
+
+    /// Add a secret, e.g. while setting up a test.
+    pub fn with(self, reference: &str, value: &str) -> MemoryProvider {
+        self.values.lock().unwrap_or_else(|p| p.into_inner()).insert(reference.to_owned(), value.to_owned());
+        self
+    }
+}
+
+impl SecretsProvider for MemoryProvider {
+    fn get(&self, reference: &str) -> Result<Secret<String>> {
+        reference.parse::<SecretReference>()?;
+        match self.values.lock().unwrap_or_else(|p| p.into_inner()).get(reference) {
+            Some(value) => Ok(Secret::new(value.clone())),
+            None => bail!(ErrorKind::SecretNotFound(reference.to_owned())),
+        }
+    }
+
+    fn list(&self, vault: &str) -> Result<Vec<String>> {
+        let values = self.values.lock().unwrap_or_else(|p| p.into_inner());
+        let mut titles : Vec<String> = values.keys()
+            .filter_map(|key| key.parse::<SecretReference>().ok())
+            .filter(|reference| reference.vault == vault)
+            .map(|reference| reference.item)
+            .collect();
+        titles.sort();
+        titles.dedup();
+        Ok(titles)
+    }
+
+    fn put(&self, reference: &str, value: &str) -> Result<()> {
+        reference.parse::<SecretReference>()?;
+        self.values.lock().unwrap_or_else(|p| p.into_inner()).insert(reference.to_owned(), value.to_owned());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{self, Value};
+
+    use super::*;
+    use cassette::{interaction, Cassette, Replay, StdinLog};
+    use {CliVersion, Op};
+
+    #[test]
+    fn memory_provider() {
+        let secrets = MemoryProvider::new().with("op://Ops/DB/password", "hunter2");
+        let provider : &dyn SecretsProvider = &secrets;
+        provider.put("op://Ops/API/token", "abc").unwrap();
+        provider.put("op://Dev/DB/password", "dev").unwrap();
+        assert_eq!(provider.get("op://Ops/DB/password").unwrap().expose(), "hunter2");
+        assert_eq!(provider.list("Ops").unwrap(), vec!["API", "DB"]);
+        assert!(provider.get("op://Ops/DB/user").is_err());
+        assert!(provider.put("Ops/DB/user", "admin").is_err());
+    }
+
+    #[test]
+    fn session_creates_missing_items() {
+        let cassette = Cassette { interactions: vec![
+            interaction("item get", &["--session=⟨redacted⟩", "--vault", "Ops", "API", "--format", "json", "--no-color",
+                "--iso-timestamps", "--reveal"], "")
+                .failing(r#"[ERROR] "API" isn't an item in the "Ops" vault"#, 1),
+            interaction("item create", &["--session=⟨redacted⟩", "--vault", "Ops", "--category", "SECURE_NOTE", "--title", "API",
+                "--format", "json", "--no-color", "--iso-timestamps"],
+                r#"{"id": "a1", "title": "API", "vault": {"id": "v"}, "category": "SECURE_NOTE"}"#),
+        ] };
+        let stdin = Arc::new(Mutex::new(Vec::new()));
+        let op = Op::new("op").with_backend(StdinLog(Replay::new(cassette), stdin.clone()))
+            .with_cli_version(CliVersion::new(2, 24, 0));
+        op.session("token").put("op://Ops/API/creds/token", "abc").unwrap();
+        let sent : Value = serde_json::from_slice(&stdin.lock().unwrap()[0]).unwrap();
+        assert_eq!(sent["fields"][0], json!({"label": "token", "type": "CONCEALED", "value": "abc", "section": {"id": "creds"}}));
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use models::v2_24_0::{Field, Item};
+
+/// What a field of an `op` 2.x item is for, independent of its label.
+///
+/// Labels are localized and can be renamed, so `Item::username` and
+/// `Item::password` go by the purpose first.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldPurpose {
+    Username,
+    Password,
+    Notes,
+    /// A purpose this crate doesn't know, as printed by `op`.
+    Other(String),
+}
+
+impl FieldPurpose {
+    /// The name `op` uses for the purpose, e.g. `PASSWORD`.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            FieldPurpose::Username => "USERNAME",
+            FieldPurpose::Password => "PASSWORD",
+            FieldPurpose::Notes => "NOTES",
+            FieldPurpose::Other(ref purpose) => purpose,
+        }
+    }
+
+    pub fn parse(s: &str) -> FieldPurpose {
+        match s {
+            "USERNAME" => FieldPurpose::Username,
+            "PASSWORD" => FieldPurpose::Password,
+            "NOTES" => FieldPurpose::Notes,
+            other => FieldPurpose::Other(other.to_owned()),
+        }
+    }
+
+    /// Label of the built-in field with this purpose, matched when no field
+    /// has the purpose, e.g. in items converted from `op` 1.x.
+    fn label(&self) -> Option<&'static str> {
+        match *self {
+            FieldPurpose::Username => Some("username"),
+            FieldPurpose::Password => Some("password"),
+            FieldPurpose::Notes => Some("notesPlain"),
+            FieldPurpose::Other(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for FieldPurpose {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for FieldPurpose {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldPurpose {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<FieldPurpose, D::Error> {
+        String::deserialize(deserializer).map(|s| FieldPurpose::parse(&s))
+    }
+}
+
+impl Item {
+    /// The field with `purpose`, or if there is none the built-in field
+    /// labelled for it, e.g. `password`.
+    pub fn field_with_purpose(&self, purpose: &FieldPurpose) -> Option<&Field> {
+        self.fields.iter().find(|f| f.purpose.as_ref() == Some(purpose))
+            .or_else(|| {
+                let label = purpose.label()?;
+                self.fields.iter().find(|f| f.purpose.is_none() && (f.id == label || f.label == label))
+            })
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        self.field_with_purpose(&FieldPurpose::Username).and_then(|f| f.value.as_deref())
+    }
+
+    pub fn password(&self) -> Option<&str> {
+        self.field_with_purpose(&FieldPurpose::Password).and_then(|f| f.value.as_deref())
+    }
+
+    pub fn notes(&self) -> Option<&str> {
+        self.field_with_purpose(&FieldPurpose::Notes).and_then(|f| f.value.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    #[test]
+    fn prefers_purpose() {
+        let item : Item = serde_json::from_str(r#"{"id": "a", "title": "T", "version": 1,
+            "vault": {"id": "v", "name": "Private"}, "category": "LOGIN", "last_edited_by": "U",
+            "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z", "fields": [
+            {"id": "password", "type": "CONCEALED", "label": "password", "reference": "", "value": "old"},
+            {"id": "u", "type": "STRING", "purpose": "USERNAME", "label": "Benutzername", "reference": "", "value": "admin"},
+            {"id": "p", "type": "CONCEALED", "purpose": "PASSWORD", "label": "Passwort", "reference": "", "value": "hunter2"},
+            {"id": "x", "type": "STRING", "purpose": "FUTURE", "label": "x", "reference": ""}
+        ]}"#).unwrap();
+        assert_eq!(item.username(), Some("admin"));
+        assert_eq!(item.password(), Some("hunter2"));
+        assert_eq!(item.notes(), None);
+        assert_eq!(item.fields[3].purpose, Some(FieldPurpose::Other("FUTURE".to_owned())));
+        assert_eq!(serde_json::to_value(&item.fields[1]).unwrap()["purpose"], "USERNAME");
+    }
+}
@@ -0,0 +1,169 @@
+use super::ItemSummary;
+use tag;
+#[cfg(feature = "process")]
+use super::{OpSession, Result};
+
+/// Normalize a category to `op` 2.x spelling, so `Secure Note` matches `SECURE_NOTE`.
+fn normalize_category(category: &str) -> String {
+    category.trim().to_uppercase().replace([' ', '-'], "_")
+}
+
+/// Filter for item summaries, applied client-side.
+///
+/// All conditions must hold for an item to match. Text comparisons ignore
+/// case. An empty query matches every item.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// # #[cfg(feature = "process")]
+/// # fn main() {
+/// use one_password::{Op, Query};
+///
+/// let session = Op::which().unwrap().env_session().unwrap();
+/// let query = Query::new().title_contains("prod").tag("db").category("LOGIN");
+/// for item in session.query_items(None, &query).unwrap() {
+///     println!("{} in {}", item.title, item.vault_id);
+/// }
+/// # }
+/// # #[cfg(not(feature = "process"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    title: Option<String>,
+    url: Option<String>,
+    tags: Vec<String>,
+    categories: Vec<String>,
+    vaults: Vec<String>,
+    favorite: Option<bool>,
+}
+
+impl Query {
+    pub fn new() -> Query {
+        Query::default()
+    }
+
+    /// Match items whose title contains `text`.
+    pub fn title_contains(mut self, text: &str) -> Query {
+        self.title = Some(text.to_lowercase());
+        self
+    }
+
+    /// Match items with a URL containing `text`.
+    pub fn url_contains(mut self, text: &str) -> Query {
+        self.url = Some(text.to_lowercase());
+        self
+    }
+
+    /// Match items tagged with `tag`, compared like `Tag`s. Given several
+    /// times, items need all of them.
+    pub fn tag(mut self, tag: &str) -> Query {
+        self.tags.push(tag::key(tag));
+        self
+    }
+
+    /// Match items in `category`, e.g. `LOGIN` or `Secure Note`. Given several
+    /// times, items need to be in any of them.
+    pub fn category(mut self, category: &str) -> Query {
+        self.categories.push(normalize_category(category));
+        self
+    }
+
+    /// Match items in the vault with id or name `vault`. Given several times,
+    /// items need to be in any of them. Names are only listed by `op` 2.x.
+    pub fn vault(mut self, vault: &str) -> Query {
+        self.vaults.push(vault.to_owned());
+        self
+    }
+
+    /// Match only favorites, or only items that aren't.
+    pub fn favorite(mut self, favorite: bool) -> Query {
+        self.favorite = Some(favorite);
+        self
+    }
+
+    pub fn matches(&self, item: &ItemSummary) -> bool {
+        if let Some(ref title) = self.title {
+            if !item.title.to_lowercase().contains(title) {
+                return false;
+            }
+        }
+        if let Some(ref url) = self.url {
+            if !item.urls.iter().any(|u| u.href.to_lowercase().contains(url)) {
+                return false;
+            }
+        }
+        if !self.tags.iter().all(|tag| item.tags.iter().any(|t| tag::key(t) == *tag)) {
+            return false;
+        }
+        if !self.categories.is_empty() && !self.categories.contains(&normalize_category(&item.category)) {
+            return false;
+        }
+        let in_vault = |v: &String| *v == item.vault_id || item.vault_name.as_ref() == Some(v);
+        if !self.vaults.is_empty() && !self.vaults.iter().any(in_vault) {
+            return false;
+        }
+        self.favorite.is_none_or(|favorite| favorite == item.favorite)
+    }
+
+    /// Keep the items that match.
+    pub fn filter<I: IntoIterator<Item = ItemSummary>>(&self, items: I) -> Vec<ItemSummary> {
+        items.into_iter().filter(|item| self.matches(item)).collect()
+    }
+}
+
+#[cfg(feature = "process")]
+impl OpSession {
+    /// List the items in `vault`, or the default vault if `None`, that match `query`.
+    ///
+    /// Items are filtered as `op` lists them, so only matches are kept in memory.
+    pub fn query_items(&self, vault: Option<&str>, query: &Query) -> Result<Vec<ItemSummary>> {
+        let mut items = Vec::new();
+        self.for_each_item_in(vault, |item| if query.matches(&item) {
+            items.push(item);
+        })?;
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ItemUrl;
+
+    fn item(title: &str, category: &str, tags: &[&str]) -> ItemSummary {
+        ItemSummary {
+            id: title.to_lowercase(),
+            title: title.to_owned(),
+            vault_id: "v1".to_owned(),
+            vault_name: Some("Ops".to_owned()),
+            category: category.to_owned(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            favorite: false,
+            created_at: None,
+            updated_at: None,
+            version: None,
+            last_edited_by: None,
+            urls: vec![ItemUrl::new("https://db.example.com")],
+        }
+    }
+
+    #[test]
+    fn filters_summaries() {
+        let items = vec![
+            item("Prod DB", "LOGIN", &["db", "prod"]),
+            item("Staging DB", "LOGIN", &["db"]),
+            item("prod notes", "SECURE_NOTE", &["DB"]),
+        ];
+        let query = Query::new().title_contains("PROD").tag("db");
+        assert_eq!(query.filter(items.clone()).len(), 2);
+        assert_eq!(query.clone().category("Login").filter(items.clone())[0].title, "Prod DB");
+        assert_eq!(query.clone().category("login").category("secure note").filter(items.clone()).len(), 2);
+        assert_eq!(Query::new().vault("Ops").url_contains("DB.example").filter(items.clone()).len(), 3);
+        assert!(Query::new().vault("v2").filter(items.clone()).is_empty());
+        assert!(Query::new().favorite(true).filter(items.clone()).is_empty());
+        assert_eq!(Query::new().filter(items).len(), 3);
+    }
+}
@@ -0,0 +1,202 @@
+use std::io::Write;
+use std::sync::Arc;
+
+use account::AccountDetails;
+use policy::CommandPolicy;
+use query::Query;
+use selector::ItemSelector;
+use tag::Tag;
+use user::OpUser;
+use vault::VaultDetails;
+use super::{ItemSummary, OpItem, OpSession, Result, Secret};
+
+/// A session that can only read, for handing to code that has no business
+/// changing anything in 1Password. Get one with `OpSession::read_only`.
+///
+/// Only the getters of `OpSession` are available, so writes don't compile.
+/// On top of that the `Op` runs with `CommandPolicy::read_only`, intersected
+/// with any policy it already has, which stops writes through code outside
+/// this crate's API too. There is no way back to the `OpSession`.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use one_password::{Op, ReadOnlySession};
+///
+/// fn render_config(secrets: &ReadOnlySession) -> String {
+///     format!("password = {}", secrets.read("op://Ops/DB/password").unwrap().expose())
+/// }
+///
+/// let session = Op::which().unwrap().env_session().unwrap();
+/// println!("{}", render_config(&session.read_only()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadOnlySession {
+    session: OpSession,
+}
+
+impl OpSession {
+    /// A read-only handle on this session, see `ReadOnlySession`.
+    pub fn read_only(&self) -> ReadOnlySession {
+        let mut session = self.clone();
+        session.config = Arc::new((*session.config).clone().with_policy(CommandPolicy::read_only()));
+        ReadOnlySession { session }
+    }
+}
+
+impl ReadOnlySession {
+    /// See `OpSession::with_reason`.
+    pub fn with_reason(&self, reason: &str) -> ReadOnlySession {
+        ReadOnlySession { session: self.session.with_reason(reason) }
+    }
+
+    /// See `OpSession::with_vault`.
+    pub fn with_vault(&self, vault: &str) -> ReadOnlySession {
+        ReadOnlySession { session: self.session.with_vault(vault) }
+    }
+
+    pub fn vault(&self) -> Option<&str> {
+        self.session.vault()
+    }
+
+    /// See `OpSession::read`.
+    pub fn read(&self, reference: &str) -> Result<Secret<String>> {
+        self.session.read(reference)
+    }
+
+    /// See `OpSession::get_item`.
+    pub fn get_item(&self, uuid: &str) -> Result<OpItem> {
+        self.session.get_item(uuid)
+    }
+
+    /// See `OpSession::get_item_in`.
+    pub fn get_item_in(&self, uuid: &str, vault: Option<&str>) -> Result<OpItem> {
+        self.session.get_item_in(uuid, vault)
+    }
+
+    /// See `OpSession::get_item_by`.
+    pub fn get_item_by<S: Into<ItemSelector>>(&self, item: S) -> Result<OpItem> {
+        self.session.get_item_by(item)
+    }
+
+    /// See `OpSession::get_item_by_in`.
+    pub fn get_item_by_in<S: Into<ItemSelector>>(&self, item: S, vault: Option<&str>) -> Result<OpItem> {
+        self.session.get_item_by_in(item, vault)
+    }
+
+    /// See `OpSession::get_item_summary`.
+    pub fn get_item_summary(&self, item: &str) -> Result<ItemSummary> {
+        self.session.get_item_summary(item)
+    }
+
+    /// See `OpSession::get_item_summary_in`.
+    pub fn get_item_summary_in(&self, item: &str, vault: Option<&str>) -> Result<ItemSummary> {
+        self.session.get_item_summary_in(item, vault)
+    }
+
+    /// See `OpSession::list_items`.
+    pub fn list_items(&self) -> Result<Vec<ItemSummary>> {
+        self.session.list_items()
+    }
+
+    /// See `OpSession::list_items_in`.
+    pub fn list_items_in(&self, vault: Option<&str>) -> Result<Vec<ItemSummary>> {
+        self.session.list_items_in(vault)
+    }
+
+    /// See `OpSession::for_each_item_in`.
+    pub fn for_each_item_in<F: FnMut(ItemSummary)>(&self, vault: Option<&str>, f: F) -> Result<()> {
+        self.session.for_each_item_in(vault, f)
+    }
+
+    /// See `OpSession::list_favorites`.
+    pub fn list_favorites(&self) -> Result<Vec<ItemSummary>> {
+        self.session.list_favorites()
+    }
+
+    /// See `OpSession::list_favorites_in`.
+    pub fn list_favorites_in(&self, vault: Option<&str>) -> Result<Vec<ItemSummary>> {
+        self.session.list_favorites_in(vault)
+    }
+
+    /// See `OpSession::list_items_tagged`.
+    pub fn list_items_tagged(&self, tag: &Tag, vault: Option<&str>) -> Result<Vec<ItemSummary>> {
+        self.session.list_items_tagged(tag, vault)
+    }
+
+    /// See `OpSession::query_items`.
+    pub fn query_items(&self, vault: Option<&str>, query: &Query) -> Result<Vec<ItemSummary>> {
+        self.session.query_items(vault, query)
+    }
+
+    /// See `OpSession::get_field`.
+    pub fn get_field(&self, item: &str, field_label: &str) -> Result<Secret<String>> {
+        self.session.get_field(item, field_label)
+    }
+
+    /// See `OpSession::get_field_in`.
+    pub fn get_field_in(&self, item: &str, field_label: &str, vault: Option<&str>) -> Result<Secret<String>> {
+        self.session.get_field_in(item, field_label, vault)
+    }
+
+    /// See `OpSession::get_field_revealed`.
+    pub fn get_field_revealed(&self, item: &str, field_label: &str) -> Result<Secret<String>> {
+        self.session.get_field_revealed(item, field_label)
+    }
+
+    /// See `OpSession::get_field_revealed_in`.
+    pub fn get_field_revealed_in(&self, item: &str, field_label: &str, vault: Option<&str>) -> Result<Secret<String>> {
+        self.session.get_field_revealed_in(item, field_label, vault)
+    }
+
+    /// See `OpSession::download_document`.
+    pub fn download_document<W: Write>(&self, item: &str, out: W) -> Result<u64> {
+        self.session.download_document(item, out)
+    }
+
+    /// See `OpSession::get_vault`.
+    pub fn get_vault(&self, vault: &str) -> Result<VaultDetails> {
+        self.session.get_vault(vault)
+    }
+
+    /// See `OpSession::get_account`.
+    pub fn get_account(&self) -> Result<AccountDetails> {
+        self.session.get_account()
+    }
+
+    /// See `OpSession::resolve_user`.
+    pub fn resolve_user(&self, uuid: &str) -> Result<OpUser> {
+        self.session.resolve_user(uuid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassette::{read_interaction, replay_op};
+    use {CommandPolicy, ErrorKind};
+
+    #[test]
+    fn reads_but_cant_write() {
+        let op = replay_op(vec![read_interaction("op://Ops/DB/password", "hunter2")]);
+        let session = op.session("token");
+        let read_only = session.read_only();
+        assert_eq!(read_only.read("op://Ops/DB/password").unwrap().expose(), "hunter2");
+        // Crate internals can still reach the session, code outside can't.
+        match *read_only.session.delete_item("DB").unwrap_err().kind() {
+            ErrorKind::PolicyViolation(_) => {},
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn keeps_policy_read_only() {
+        let op = replay_op(Vec::new())
+            .with_policy(CommandPolicy::deny_all().allow(&["item"]));
+        let read_only = op.session("token").read_only();
+        match *read_only.session.delete_item("DB").unwrap_err().kind() {
+            ErrorKind::PolicyViolation(ref subcommand) => assert_eq!(subcommand, "item delete"),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+#[cfg(feature = "generate")]
+use std::io;
+
+#[cfg(feature = "generate")]
+use getrandom;
+#[cfg(feature = "process")]
+use serde_json::Value;
+
+use super::{ErrorKind, Result};
+#[cfg(any(feature = "generate", feature = "process"))]
+use super::Secret;
+#[cfg(feature = "process")]
+use super::{OpSession, RawItem};
+
+/// Longest password `op` generates.
+pub const MAX_LENGTH: u32 = 64;
+
+const LETTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const DIGITS: &[u8] = b"0123456789";
+const SYMBOLS: &[u8] = b"!#$%&()*+,-./:;<=>?@[]^_{|}~";
+
+/// Character sets and length of a password generated by 1Password.
+///
+/// Formats as the recipe syntax of `op`, e.g. `letters,digits,32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PasswordRecipe {
+    pub length: u32,
+    pub letters: bool,
+    pub digits: bool,
+    pub symbols: bool,
+}
+
+impl Default for PasswordRecipe {
+    fn default() -> PasswordRecipe {
+        PasswordRecipe { length: 32, letters: true, digits: true, symbols: true }
+    }
+}
+
+impl PasswordRecipe {
+    pub fn new(length: u32) -> PasswordRecipe {
+        PasswordRecipe { length, ..PasswordRecipe::default() }
+    }
+
+    pub fn letters(mut self, letters: bool) -> PasswordRecipe {
+        self.letters = letters;
+        self
+    }
+
+    pub fn digits(mut self, digits: bool) -> PasswordRecipe {
+        self.digits = digits;
+        self
+    }
+
+    pub fn symbols(mut self, symbols: bool) -> PasswordRecipe {
+        self.symbols = symbols;
+        self
+    }
+
+    /// Characters of each enabled set.
+    fn sets(&self) -> Vec<&'static [u8]> {
+        [(self.letters, LETTERS), (self.digits, DIGITS), (self.symbols, SYMBOLS)].iter()
+            .filter(|s| s.0)
+            .map(|s| s.1)
+            .collect()
+    }
+
+    /// Fail with `ErrorKind::InvalidRecipe` unless a password can be
+    /// generated: some set has to be enabled and the length has to be at
+    /// most `MAX_LENGTH` and leave room for a character of each set.
+    pub fn check(&self) -> Result<()> {
+        let reason = if self.sets().is_empty() {
+            "no character set enabled"
+        } else if self.length > MAX_LENGTH {
+            "longer than 64 characters"
+        } else if (self.length as usize) < self.sets().len() {
+            "too short for a character of each set"
+        } else {
+            return Ok(());
+        };
+        Err(ErrorKind::InvalidRecipe(self.to_string(), reason).into())
+    }
+}
+
+impl fmt::Display for PasswordRecipe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sets = [(self.letters, "letters"), (self.digits, "digits"), (self.symbols, "symbols")];
+        for &(_, name) in sets.iter().filter(|s| s.0) {
+            write!(f, "{},", name)?;
+        }
+        write!(f, "{}", self.length)
+    }
+}
+
+/// Generate a password for `recipe` locally, without `op`.
+///
+/// Like 1Password, the password has at least one character of each enabled
+/// set and is otherwise picked uniformly from all of them, using the
+/// randomness of the operating system. Use `OpSession::generate_password`
+/// to have `op` generate it instead.
+///
+/// # Example
+///
+/// ```
+/// # extern crate one_password;
+/// use one_password::{generate_password, PasswordRecipe};
+///
+/// let password = generate_password(PasswordRecipe::new(20).symbols(false)).unwrap();
+/// assert_eq!(password.expose().len(), 20);
+/// ```
+#[cfg(feature = "generate")]
+pub fn generate_password(recipe: PasswordRecipe) -> Result<Secret<String>> {
+    recipe.check()?;
+    let sets = recipe.sets();
+    let all = sets.concat();
+    let mut password = Vec::with_capacity(recipe.length as usize);
+    for set in &sets {
+        password.push(set[below(set.len())?]);
+    }
+    while password.len() < recipe.length as usize {
+        password.push(all[below(all.len())?]);
+    }
+    for i in (1..password.len()).rev() {
+        password.swap(i, below(i + 1)?);
+    }
+    Ok(Secret::new(String::from_utf8(password).expect("character sets are ASCII")))
+}
+
+/// Random number below `n`, without modulo bias.
+#[cfg(feature = "generate")]
+fn below(n: usize) -> Result<usize> {
+    let n = n as u32;
+    let zone = u32::MAX - u32::MAX % n;
+    loop {
+        let mut bytes = [0; 4];
+        getrandom::fill(&mut bytes).map_err(io::Error::from)?;
+        let value = u32::from_ne_bytes(bytes);
+        if value < zone {
+            return Ok((value % n) as usize);
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+impl OpSession {
+    /// Have `op` generate a password for `recipe` without saving an item.
+    ///
+    /// This calls `op item create --dry-run`, which needs `op` 2.x, and also
+    /// runs in dry-run mode, see `DryRun`.
+    pub fn generate_password(&self, recipe: PasswordRecipe) -> Result<Secret<String>> {
+        recipe.check()?;
+        if !self.config.cli_version()?.is_v2() {
+            bail!(ErrorKind::RequiresCliV2("generating a password"));
+        }
+        let mut command = self.command(&["item", "create"]);
+        command.arg("--category").arg("password")
+            .arg("--title").arg("generated")
+            .arg(format!("--generate-password={}", recipe))
+            .arg("--dry-run")
+            .json()?;
+        let item : Value = command.run_json(ErrorKind::CommandFailed)?;
+        let password = item["fields"].as_array()
+            .and_then(|fields| fields.iter().find(|f| f["purpose"] == "PASSWORD"))
+            .and_then(|field| field["value"].as_str())
+            .map(str::to_owned);
+        match password {
+            Some(password) => Ok(self.config.secret(password)),
+            None => Err(ErrorKind::SchemaMismatch(command.subcommand(), "no password field".to_owned(),
+                Box::new(RawItem(item))).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_recipes() {
+        assert!(PasswordRecipe::new(2).symbols(false).check().is_ok());
+        assert!(PasswordRecipe::new(2).check().is_err());
+        assert!(PasswordRecipe::new(65).check().is_err());
+        let err = PasswordRecipe::new(20).letters(false).digits(false).symbols(false).check().unwrap_err();
+        assert_eq!(err.to_string(), "invalid password recipe 20: no character set enabled");
+    }
+
+    #[cfg(feature = "generate")]
+    #[test]
+    fn generates_from_each_set() {
+        for _ in 0..100 {
+            let password = generate_password(PasswordRecipe::new(3)).unwrap();
+            let password = password.expose().as_bytes();
+            assert_eq!(password.len(), 3);
+            for set in &[LETTERS, DIGITS, SYMBOLS] {
+                assert!(password.iter().any(|c| set.contains(c)));
+            }
+        }
+        let digits = generate_password(PasswordRecipe::new(64).letters(false).symbols(false)).unwrap();
+        assert!(digits.expose().bytes().all(|c| c.is_ascii_digit()));
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn generates_with_op() {
+        use cassette::{interaction, replay_op};
+
+        let op = replay_op(vec![interaction("item create", &["--session=⟨redacted⟩", "--category", "password",
+            "--title", "generated", "--generate-password=letters,digits,20", "--dry-run", "--format", "json", "--no-color", "--iso-timestamps"],
+            r#"{"id": "", "title": "generated", "category": "PASSWORD", "fields": [
+                {"id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "value": "hunter2hunter2hunter"}]}"#)]);
+        let password = op.session("TOKEN").generate_password(PasswordRecipe::new(20).symbols(false)).unwrap();
+        assert_eq!(password.expose(), "hunter2hunter2hunter");
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Error, ErrorKind, Result};
+#[cfg(feature = "process")]
+use pool;
+#[cfg(feature = "process")]
+use super::{max_concurrency, OpSession, Secret};
+
+/// Prefix of secret references.
+pub const SCHEME: &str = "op://";
+
+/// A secret reference such as `op://Production/Database/password`, naming a
+/// field of an item, optionally within a section.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SecretReference {
+    pub vault: String,
+    pub item: String,
+    pub section: Option<String>,
+    pub field: String,
+}
+
+impl SecretReference {
+    pub fn new(vault: &str, item: &str, field: &str) -> SecretReference {
+        SecretReference {
+            vault: vault.to_owned(),
+            item: item.to_owned(),
+            section: None,
+            field: field.to_owned(),
+        }
+    }
+
+    /// Whether `s` looks like a secret reference, i.e. starts with `op://`.
+    pub fn is_reference(s: &str) -> bool {
+        s.starts_with(SCHEME)
+    }
+}
+
+impl fmt::Display for SecretReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}/{}/", SCHEME, self.vault, self.item)?;
+        if let Some(ref section) = self.section {
+            write!(f, "{}/", section)?;
+        }
+        f.write_str(&self.field)
+    }
+}
+
+impl FromStr for SecretReference {
+    type Err = Error;
+
+    /// Parse `op://vault/item/field` or `op://vault/item/section/field`.
+    /// Query parameters such as `?attribute=otp` are not supported.
+    fn from_str(s: &str) -> Result<SecretReference> {
+        let invalid = || -> Error { ErrorKind::InvalidSecretReference(s.to_owned()).into() };
+        if !SecretReference::is_reference(s) || s.contains('?') {
+            return Err(invalid());
+        }
+        let parts : Vec<&str> = s[SCHEME.len()..].split('/').collect();
+        if parts.iter().any(|p| p.is_empty()) {
+            return Err(invalid());
+        }
+        match parts[..] {
+            [vault, item, field] => Ok(SecretReference::new(vault, item, field)),
+            [vault, item, section, field] => Ok(SecretReference {
+                section: Some(section.to_owned()),
+                ..SecretReference::new(vault, item, field)
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+impl OpSession {
+    /// Read the value a secret reference points to.
+    ///
+    /// This calls `op read`. `op` 1.x has no `op read`, so there the field is
+    /// looked up with `get_field_revealed_in`, ignoring any section.
+    pub fn read(&self, reference: &str) -> Result<Secret<String>> {
+        let parsed : SecretReference = reference.parse()?;
+        if !self.config.cli_version()?.is_v2() {
+            return self.get_field_revealed_in(&parsed.item, &parsed.field, Some(&parsed.vault));
+        }
+        let stdout = self.command(&["read"])
+            .target(reference)
+            .arg("--no-newline")
+            .run_or(|d| ErrorKind::GetCommand(reference.to_owned(), d))?;
+        Ok(self.config.secret(self.config.utf8_policy.decode("stdout", stdout)?))
+    }
+
+    /// Read all of `references` up front, concurrently, and return their
+    /// values in the same order.
+    ///
+    /// Meant for the secrets a service needs, read at startup so it fails
+    /// right away if one is missing rather than on the first request that
+    /// needs it. All references are checked before anything is read. If any
+    /// can't be read this fails with the error of the first of them, once
+    /// all reads are done. See `CachingResolver::prefetch` to keep the values.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// let session = Op::which().unwrap().env_session().unwrap();
+    /// let secrets = session.prefetch(&["op://Production/Database/password", "op://Production/API/token"])
+    ///     .expect("secrets missing");
+    /// ```
+    pub fn prefetch<S: AsRef<str> + Sync>(&self, references: &[S]) -> Result<Vec<Secret<String>>> {
+        for reference in references {
+            reference.as_ref().parse::<SecretReference>()?;
+        }
+        pool::map_concurrent(references, max_concurrency(), |_, reference| self.read(reference.as_ref()))
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let reference : SecretReference = "op://Production/Database/password".parse().unwrap();
+        assert_eq!(reference, SecretReference::new("Production", "Database", "password"));
+        let reference : SecretReference = "op://Production/Database/admin/password".parse().unwrap();
+        assert_eq!(reference.section, Some("admin".to_owned()));
+        assert_eq!(reference.to_string(), "op://Production/Database/admin/password");
+        assert!("op://Production/Database".parse::<SecretReference>().is_err());
+        assert!("op://Production//password".parse::<SecretReference>().is_err());
+        assert!("https://example.com/a/b".parse::<SecretReference>().is_err());
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn prefetches() {
+        use cassette::{read_interaction, replay_op};
+
+        let op = replay_op(vec![
+            read_interaction("op://Ops/DB/password", "hunter2"),
+            read_interaction("op://Ops/API/token", "t1"),
+            read_interaction("op://Ops/DB/password", "hunter2"),
+            read_interaction("op://Ops/Gone/token", "").failing("[ERROR] item not found", 1),
+        ]);
+        let session = op.session("token");
+        let values = session.prefetch(&["op://Ops/DB/password", "op://Ops/API/token"]).unwrap();
+        let values : Vec<&str> = values.iter().map(|v| &v.expose()[..]).collect();
+        assert_eq!(values, vec!["hunter2", "t1"]);
+        assert!(session.prefetch(&["op://Ops/DB/password", "invalid"]).is_err());
+        let err = session.prefetch(&["op://Ops/DB/password", "op://Ops/Gone/token"]).unwrap_err();
+        assert!(err.to_string().contains("op://Ops/Gone/token"));
+    }
+}
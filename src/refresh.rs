@@ -0,0 +1,156 @@
+use std::fmt;
+use std::sync::{Mutex, RwLock};
+
+use secret::Secret;
+use super::{Op, OpSession, Result};
+
+/// Signs in again after a session has expired, see `OpSession::with_refresh`.
+pub(crate) type RefreshFn = Box<dyn Fn(&Op) -> Result<OpSession> + Send + Sync>;
+
+/// Session token shared by all copies of an `OpSession`.
+///
+/// `generation` counts refreshes, so a caller whose command failed with an
+/// older token knows someone else already signed in again.
+pub(crate) struct SessionAuth {
+    current: RwLock<(u64, Secret<String>)>,
+    refresh: Option<RefreshFn>,
+    refreshing: Mutex<()>,
+}
+
+impl SessionAuth {
+    pub(crate) fn new(token: Secret<String>, refresh: Option<RefreshFn>) -> SessionAuth {
+        SessionAuth {
+            current: RwLock::new((0, token)),
+            refresh,
+            refreshing: Mutex::new(()),
+        }
+    }
+
+    /// Current token and its generation.
+    pub(crate) fn current(&self) -> (u64, Secret<String>) {
+        self.current.read().unwrap_or_else(|p| p.into_inner()).clone()
+    }
+
+    pub(crate) fn token(&self) -> Secret<String> {
+        self.current().1
+    }
+
+    pub(crate) fn can_refresh(&self) -> bool {
+        self.refresh.is_some()
+    }
+
+    /// Sign in again because the token of `generation` was rejected.
+    ///
+    /// Callers queue up behind the first one, which runs the refresh; the
+    /// others find a newer token when they get the lock and return right away.
+    /// Returns whether there is a new token to retry with.
+    pub(crate) fn refresh(&self, op: &Op, generation: u64) -> Result<bool> {
+        let refresh = match self.refresh {
+            Some(ref refresh) => refresh,
+            None => return Ok(false),
+        };
+        let _refreshing = self.refreshing.lock().unwrap_or_else(|p| p.into_inner());
+        if self.current().0 != generation {
+            return Ok(true);
+        }
+        let token = match refresh(op)?.auth {
+            Some(ref auth) => auth.token(),
+            None => return Ok(false),
+        };
+        let mut current = self.current.write().unwrap_or_else(|p| p.into_inner());
+        *current = (generation + 1, token);
+        Ok(true)
+    }
+}
+
+impl fmt::Debug for SessionAuth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SessionAuth")
+            .field("generation", &self.current().0)
+            .field("refresh", &self.refresh.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::process::Output;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    use cassette::{exit_status, interaction, replay_op};
+    use {Backend, CliVersion, Invocation, Op, OpSession};
+
+    /// Accepts only the token `fresh`.
+    struct Expiring;
+
+    impl Backend for Expiring {
+        fn run(&self, invocation: &Invocation) -> io::Result<Output> {
+            let signed_in = invocation.args().iter().any(|a| a == "--session=fresh");
+            let (code, stdout, stderr) = if signed_in {
+                (0, "secret", "")
+            } else {
+                (1, "", "[ERROR] 2023/01/01 session expired, sign in to create a new session")
+            };
+            Ok(Output {
+                status: exit_status(code),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: stderr.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    #[test]
+    fn sessions_are_send_and_sync() {
+        fn shareable<T: Send + Sync>() {}
+        shareable::<Op>();
+        shareable::<OpSession>();
+    }
+
+    #[test]
+    fn refreshes_once() {
+        let op = Op::new("op").with_backend(Expiring).with_cli_version(CliVersion::new(2, 24, 0));
+        let signins = Arc::new(AtomicUsize::new(0));
+        let counted = signins.clone();
+        let session = op.session("stale").with_refresh(move |op: &Op| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(50));
+            Ok(op.session("fresh"))
+        });
+        let vault = session.with_vault("Ops");
+        let readers : Vec<_> = (0..8).map(|_| {
+            let session = session.clone();
+            thread::spawn(move || session.read("op://Ops/DB/password").unwrap().expose().clone())
+        }).collect();
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), "secret");
+        }
+        assert_eq!(signins.load(Ordering::SeqCst), 1);
+        assert_eq!(vault.read("op://Ops/DB/password").unwrap().expose(), "secret");
+
+        assert!(op.session("stale").read("op://Ops/DB/password").is_err());
+    }
+
+    #[test]
+    fn refreshes_streamed_lists() {
+        let list = |stdout: &str| interaction("item list",
+            &["--session=⟨redacted⟩", "--format", "json", "--no-color", "--iso-timestamps"], stdout);
+        let op = replay_op(vec![
+            list("").failing("[ERROR] 2023/01/01 session expired, sign in to create a new session", 1),
+            list(r#"[{"id": "a", "title": "A", "vault": {"id": "v"}, "category": "LOGIN"}]"#),
+        ]);
+        let signins = Arc::new(AtomicUsize::new(0));
+        let counted = signins.clone();
+        let session = op.session("stale").with_refresh(move |op: &Op| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            Ok(op.session("fresh"))
+        });
+        let items = session.list_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, "a");
+        assert_eq!(signins.load(Ordering::SeqCst), 1);
+    }
+}
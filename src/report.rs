@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use summary::parse_rfc3339;
+use super::{ItemSummary, OpSession, Result};
+
+/// Categories of items that are expected to have a URL.
+const URL_CATEGORIES: &[&str] = &["LOGIN"];
+
+/// Hygiene overview of a vault, as returned by `OpSession::vault_report`.
+///
+/// Built from the item list alone, so it holds no secret values and can be
+/// serialized and stored or mailed for periodic reviews.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use std::time::Duration;
+/// use one_password::Op;
+///
+/// let session = Op::which().unwrap().env_session().unwrap();
+/// let report = session.vault_report(Some("Shared"), Duration::from_secs(365 * 86_400)).unwrap();
+/// println!("{} items, {} not updated in a year", report.total, report.stale.len());
+/// for (category, count) in &report.by_category {
+///     println!("{}: {}", category, count);
+/// }
+/// ```
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultReport {
+    /// The vault as given to `vault_report`, `None` for the default vault.
+    pub vault: Option<String>,
+    pub total: usize,
+    /// Number of items per category, in `op` 2.x spelling.
+    pub by_category: BTreeMap<String, usize>,
+    pub favorites: usize,
+    /// Number of items without any tag.
+    pub untagged: usize,
+    /// Items not updated within the period given to the report, oldest first.
+    pub stale: Vec<ItemSummary>,
+    /// Number of items whose last update is unknown, e.g. because `op` 1.x
+    /// didn't list it. These are never counted as stale.
+    pub undated: usize,
+    /// Logins without any URL, which 1Password can't fill in or match.
+    pub without_urls: Vec<ItemSummary>,
+}
+
+impl VaultReport {
+    /// Summarize `items`, counting those not updated in the `stale_after`
+    /// before `now` as stale.
+    pub fn from_items<I>(vault: Option<&str>, items: I, stale_after: Duration, now: SystemTime) -> VaultReport
+        where I: IntoIterator<Item = ItemSummary>
+    {
+        let mut report = VaultReport { vault: vault.map(str::to_owned), ..VaultReport::default() };
+        let cutoff = cutoff(stale_after, now);
+        for item in items {
+            report.add(item, cutoff);
+        }
+        report.finish();
+        report
+    }
+
+    fn add(&mut self, item: ItemSummary, cutoff: i64) {
+        self.total += 1;
+        *self.by_category.entry(item.category.clone()).or_insert(0) += 1;
+        if item.favorite {
+            self.favorites += 1;
+        }
+        if item.tags.is_empty() {
+            self.untagged += 1;
+        }
+        if URL_CATEGORIES.contains(&&item.category[..]) && item.urls.is_empty() {
+            self.without_urls.push(item.clone());
+        }
+        match item.updated_at.as_ref().and_then(|t| parse_rfc3339(t)) {
+            Some(updated) if updated < cutoff => self.stale.push(item),
+            Some(_) => {},
+            None => self.undated += 1,
+        }
+    }
+
+    fn finish(&mut self) {
+        self.stale.sort_by_cached_key(|item| item.updated_at.as_ref().and_then(|t| parse_rfc3339(t)));
+    }
+}
+
+/// Seconds since the Unix epoch of `stale_after` before `now`.
+fn cutoff(stale_after: Duration, now: SystemTime) -> i64 {
+    let now = match now.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    };
+    now.saturating_sub(stale_after.as_secs() as i64)
+}
+
+impl OpSession {
+    /// Summarize the items in `vault`, or the default vault if `None`, counting
+    /// those not updated in `stale_after` as stale.
+    ///
+    /// Only the item list is read, with a single `op` call.
+    pub fn vault_report(&self, vault: Option<&str>, stale_after: Duration) -> Result<VaultReport> {
+        let mut report = VaultReport { vault: vault.map(str::to_owned), ..VaultReport::default() };
+        let cutoff = cutoff(stale_after, SystemTime::now());
+        self.for_each_item_in(vault, |item| report.add(item, cutoff))?;
+        report.finish();
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ItemUrl;
+
+    fn item(id: &str, category: &str, updated_at: Option<&str>, url: bool) -> ItemSummary {
+        ItemSummary {
+            id: id.to_owned(),
+            title: id.to_uppercase(),
+            vault_id: "v1".to_owned(),
+            vault_name: None,
+            category: category.to_owned(),
+            tags: if url { vec!["web".to_owned()] } else { Vec::new() },
+            favorite: id == "a",
+            created_at: None,
+            updated_at: updated_at.map(str::to_owned),
+            version: None,
+            last_edited_by: None,
+            urls: if url { vec![ItemUrl::new("https://example.com")] } else { Vec::new() },
+        }
+    }
+
+    #[test]
+    fn summarizes_items() {
+        let items = vec![
+            item("a", "LOGIN", Some("2024-06-01T00:00:00Z"), true),
+            item("b", "LOGIN", Some("2020-01-01T00:00:00Z"), false),
+            item("c", "SECURE_NOTE", Some("2022-03-01T10:00:00.123+02:00"), false),
+            item("d", "PASSWORD", None, false),
+        ];
+        let now = UNIX_EPOCH + Duration::from_secs(1_719_792_000); // 2024-07-01
+        let report = VaultReport::from_items(Some("Ops"), items, Duration::from_secs(365 * 86_400), now);
+        assert_eq!(report.vault.as_ref().map(|v| &v[..]), Some("Ops"));
+        assert_eq!(report.total, 4);
+        assert_eq!(report.by_category.get("LOGIN"), Some(&2));
+        assert_eq!(report.by_category.get("PASSWORD"), Some(&1));
+        assert_eq!((report.favorites, report.untagged, report.undated), (1, 3, 1));
+        let stale : Vec<&str> = report.stale.iter().map(|i| &i.id[..]).collect();
+        assert_eq!(stale, vec!["b", "c"]);
+        let without_urls : Vec<&str> = report.without_urls.iter().map(|i| &i.id[..]).collect();
+        assert_eq!(without_urls, vec!["b"]);
+        let json = ::serde_json::to_value(&report).unwrap();
+        assert_eq!(json["by_category"]["SECURE_NOTE"], 1);
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use pool;
+use reference::SecretReference;
+use super::{max_concurrency, OpSession, Result, Secret};
+
+enum Entry {
+    /// A lookup is running. Holds its generation, so a lookup that finishes
+    /// after `invalidate` doesn't store its value.
+    Fetching(u64),
+    Ready(Secret<String>, Instant),
+}
+
+struct State {
+    entries: HashMap<String, Entry>,
+    generation: u64,
+}
+
+/// Resolves secret references with `OpSession::read` and caches the values,
+/// for services that look up the same references over and over.
+///
+/// Values are kept for ever, or until they are older than the TTL of their
+/// reference, see `with_ttl` and `with_ttl_for`, or are dropped with
+/// `invalidate`. Concurrent lookups of the same reference wait for a single
+/// `op read`. Failures are not cached: the caller gets the error, and
+/// lookups that were waiting try again.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate one_password;
+/// use std::time::Duration;
+/// use one_password::{CachingResolver, Op};
+///
+/// let session = Op::which().unwrap().env_session().unwrap();
+/// let resolver = CachingResolver::new(session)
+///     .with_ttl(Duration::from_secs(3600))
+///     .with_ttl_for("op://Production/Rotating/token", Duration::from_secs(60));
+/// let password = resolver.resolve("op://Production/Database/password").unwrap();
+/// // After rotating the password:
+/// resolver.invalidate("op://Production/Database/password");
+/// ```
+pub struct CachingResolver {
+    session: OpSession,
+    ttl: Option<Duration>,
+    ttls: HashMap<String, Duration>,
+    state: Mutex<State>,
+    fetched: Condvar,
+}
+
+impl fmt::Debug for CachingResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CachingResolver")
+            .field("ttl", &self.ttl)
+            .field("ttls", &self.ttls)
+            .field("cached", &self.lock().entries.len())
+            .finish()
+    }
+}
+
+impl CachingResolver {
+    pub fn new(session: OpSession) -> CachingResolver {
+        CachingResolver {
+            session,
+            ttl: None,
+            ttls: HashMap::new(),
+            state: Mutex::new(State { entries: HashMap::new(), generation: 0 }),
+            fetched: Condvar::new(),
+        }
+    }
+
+    /// Read values again when they are older than `ttl`, unless their
+    /// reference has a TTL of its own.
+    pub fn with_ttl(mut self, ttl: Duration) -> CachingResolver {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Read the value of `reference` again when it is older than `ttl`.
+    pub fn with_ttl_for(mut self, reference: &str, ttl: Duration) -> CachingResolver {
+        self.ttls.insert(reference.to_owned(), ttl);
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    fn is_fresh(&self, reference: &str, read_at: Instant) -> bool {
+        self.ttls.get(reference).or(self.ttl.as_ref()).is_none_or(|ttl| read_at.elapsed() < *ttl)
+    }
+
+    /// The value of `reference`, from the cache or read now.
+    pub fn resolve(&self, reference: &str) -> Result<Secret<String>> {
+        reference.parse::<SecretReference>()?;
+        let mut state = self.lock();
+        let generation = loop {
+            match state.entries.get(reference) {
+                Some(&Entry::Ready(ref value, read_at)) if self.is_fresh(reference, read_at) => return Ok(value.clone()),
+                Some(&Entry::Fetching(_)) => {
+                    state = self.fetched.wait(state).unwrap_or_else(|p| p.into_inner());
+                },
+                _ => {
+                    state.generation += 1;
+                    let generation = state.generation;
+                    state.entries.insert(reference.to_owned(), Entry::Fetching(generation));
+                    break generation;
+                },
+            }
+        };
+        drop(state);
+
+        let result = self.session.read(reference);
+        let mut state = self.lock();
+        if let Some(&Entry::Fetching(g)) = state.entries.get(reference) {
+            if g == generation {
+                match result {
+                    Ok(ref value) => state.entries.insert(reference.to_owned(), Entry::Ready(value.clone(), Instant::now())),
+                    Err(_) => state.entries.remove(reference),
+                };
+            }
+        }
+        self.fetched.notify_all();
+        result
+    }
+
+    /// Resolve all of `references` concurrently, so they are cached before
+    /// they are needed, e.g. at startup. Fails with the error of the first
+    /// reference that can't be read, once all lookups are done.
+    pub fn prefetch<S: AsRef<str> + Sync>(&self, references: &[S]) -> Result<()> {
+        for reference in references {
+            reference.as_ref().parse::<SecretReference>()?;
+        }
+        pool::map_concurrent(references, max_concurrency(), |_, reference| self.resolve(reference.as_ref()).map(|_| ()))
+            .into_iter()
+            .collect()
+    }
+
+    /// Drop the cached value of `reference`, so the next `resolve` reads it
+    /// again. A lookup that is running doesn't cache what it reads.
+    pub fn invalidate(&self, reference: &str) {
+        if self.lock().entries.remove(reference).is_some() {
+            self.fetched.notify_all();
+        }
+    }
+
+    /// Drop all cached values.
+    pub fn invalidate_all(&self) {
+        self.lock().entries.clear();
+        self.fetched.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use cassette::{read_interaction, replay_op};
+
+    #[test]
+    fn caches_and_coalesces() {
+        let db = "op://Ops/DB/password";
+        let token = "op://Ops/API/token";
+        let op = replay_op(vec![
+            read_interaction(db, "hunter2"), read_interaction(db, "rotated"),
+            read_interaction(token, "t1"), read_interaction(token, "t2"),
+        ]);
+        let resolver = CachingResolver::new(op.session("token")).with_ttl_for(token, Duration::from_secs(0));
+
+        // One `op read` for all threads, the cassette only has one for now.
+        thread::scope(|scope| {
+            let lookups : Vec<_> = (0..4).map(|_| scope.spawn(|| resolver.resolve(db).unwrap())).collect();
+            for lookup in lookups {
+                assert_eq!(lookup.join().unwrap().expose(), "hunter2");
+            }
+        });
+        resolver.prefetch(&[db]).unwrap();
+        resolver.invalidate(db);
+        assert_eq!(resolver.resolve(db).unwrap().expose(), "rotated");
+        assert_eq!(resolver.resolve(db).unwrap().expose(), "rotated");
+
+        assert_eq!(resolver.resolve(token).unwrap().expose(), "t1");
+        assert_eq!(resolver.resolve(token).unwrap().expose(), "t2");
+        assert!(resolver.resolve("not a reference").is_err());
+    }
+}
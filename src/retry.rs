@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use classify::ErrorCategory;
+
+/// When to run a failed `op` command again, set with `OpBuilder::retry`.
+///
+/// Only failures of the given categories are retried, by default
+/// `RateLimited` and `Network`. The delay before retry `n` is `backoff * 2^n`,
+/// capped at `max_backoff`. Interactive commands and commands streaming
+/// their stdin or stdout are never retried.
+///
+/// # Example
+///
+/// ```
+/// # extern crate one_password;
+/// use std::time::Duration;
+/// use one_password::RetryPolicy;
+///
+/// let retry = RetryPolicy::new(4).backoff(Duration::from_millis(250));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    attempts: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+    categories: Vec<ErrorCategory>,
+}
+
+impl Default for RetryPolicy {
+    /// Run every command once.
+    fn default() -> RetryPolicy {
+        RetryPolicy::new(1)
+    }
+}
+
+impl RetryPolicy {
+    /// Run a command up to `attempts` times in total.
+    pub fn new(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts: attempts.max(1),
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            categories: vec![ErrorCategory::RateLimited, ErrorCategory::Network],
+        }
+    }
+
+    /// Never run a command more than once.
+    pub fn never() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Delay before the first retry. Defaults to 500ms.
+    pub fn backoff(mut self, backoff: Duration) -> RetryPolicy {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Longest delay between retries. Defaults to 30s.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> RetryPolicy {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Retry failures of exactly these categories.
+    pub fn categories(mut self, categories: &[ErrorCategory]) -> RetryPolicy {
+        self.categories = categories.to_vec();
+        self
+    }
+
+    /// Total number of times a command may run.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// How long to wait before running a command again after attempt
+    /// `attempt`, counting from 1, failed with `category`, or `None` to give up.
+    pub fn delay(&self, attempt: u32, category: ErrorCategory) -> Option<Duration> {
+        if attempt >= self.attempts || !self.categories.contains(&category) {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(attempt - 1);
+        Some(self.backoff.saturating_mul(factor).min(self.max_backoff))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off() {
+        let retry = RetryPolicy::new(4).backoff(Duration::from_secs(1)).max_backoff(Duration::from_secs(3));
+        assert_eq!(retry.delay(1, ErrorCategory::Network), Some(Duration::from_secs(1)));
+        assert_eq!(retry.delay(2, ErrorCategory::RateLimited), Some(Duration::from_secs(2)));
+        assert_eq!(retry.delay(3, ErrorCategory::Network), Some(Duration::from_secs(3)));
+        assert_eq!(retry.delay(4, ErrorCategory::Network), None);
+        assert_eq!(retry.delay(1, ErrorCategory::NotFound), None);
+        assert_eq!(RetryPolicy::never().delay(1, ErrorCategory::Network), None);
+    }
+}
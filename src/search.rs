@@ -0,0 +1,167 @@
+//! Search items with regular expressions, e.g. for references to a host
+//! that is being decommissioned.
+//!
+//! Titles and URLs come with the item list, so searching them costs one `op`
+//! call per vault. Notes have to be fetched item by item and are only searched
+//! when asked for with `SearchOptions::notes`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! # extern crate regex;
+//! use regex::Regex;
+//! use one_password::Op;
+//! use one_password::search::SearchOptions;
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let pattern = Regex::new(r"(?i)db-0[1-3]\.internal").unwrap();
+//! let options = SearchOptions::new().vault("Ops").vault("Shared").notes(true);
+//! for found in session.search_items(&pattern, &options).unwrap() {
+//!     println!("{} ({}): {:?}", found.item.title, found.item.id, found.field);
+//! }
+//! ```
+use regex::Regex;
+
+use diagnostics::not_found;
+use pool;
+use super::{ItemSummary, OpSession, Result};
+
+/// Label of the notes field of every item.
+const NOTES: &str = "notesPlain";
+
+/// Options for `OpSession::search_items`.
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    vaults: Vec<String>,
+    notes: bool,
+    concurrency: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> SearchOptions {
+        SearchOptions { vaults: Vec::new(), notes: false, concurrency: 1 }
+    }
+}
+
+impl SearchOptions {
+    pub fn new() -> SearchOptions {
+        SearchOptions::default()
+    }
+
+    /// Search `vault`. Without any vault, the default vault of the session is
+    /// searched, or all vaults if it has none.
+    pub fn vault(mut self, vault: &str) -> SearchOptions {
+        self.vaults.push(vault.to_owned());
+        self
+    }
+
+    /// Also search the notes of every item. Defaults to false.
+    ///
+    /// This reads the notes of each item with a separate `op` call, and notes
+    /// may hold secrets.
+    pub fn notes(mut self, notes: bool) -> SearchOptions {
+        self.notes = notes;
+        self
+    }
+
+    /// Number of items to fetch notes for at the same time. Defaults to 1.
+    ///
+    /// The total number of `op` processes is still limited by `set_max_concurrency`.
+    pub fn concurrency(mut self, concurrency: usize) -> SearchOptions {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+}
+
+/// Where in an item a pattern matched.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MatchedField {
+    Title,
+    /// One of the URLs of the item. Holds the whole URL.
+    Url(String),
+    Notes,
+}
+
+/// A field of an item that matched, as returned by `OpSession::search_items`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SearchMatch {
+    pub item: ItemSummary,
+    pub field: MatchedField,
+    /// The first part of the field that matched the pattern.
+    pub matched: String,
+}
+
+impl OpSession {
+    /// Find the items whose title, URLs and, if enabled, notes match `pattern`,
+    /// with one `SearchMatch` per matching field.
+    pub fn search_items(&self, pattern: &Regex, options: &SearchOptions) -> Result<Vec<SearchMatch>> {
+        let mut items = Vec::new();
+        if options.vaults.is_empty() {
+            self.for_each_item_in(None, |item| items.push(item))?;
+        }
+        for vault in &options.vaults {
+            self.for_each_item_in(Some(vault), |item| items.push(item))?;
+        }
+
+        let notes = if options.notes {
+            pool::map_concurrent(&items, options.concurrency, |_, item| {
+                not_found(self.get_field_in(&item.id, NOTES, Some(&item.vault_id)))
+            }).into_iter().collect::<Result<Vec<_>>>()?
+        } else {
+            Vec::new()
+        };
+
+        let mut matches = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            let mut found = Vec::new();
+            if let Some(m) = pattern.find(&item.title) {
+                found.push((MatchedField::Title, m.as_str().to_owned()));
+            }
+            for url in &item.urls {
+                if let Some(m) = pattern.find(&url.href) {
+                    found.push((MatchedField::Url(url.href.clone()), m.as_str().to_owned()));
+                }
+            }
+            if let Some(m) = notes.get(index).and_then(Option::as_ref).and_then(|n| pattern.find(n.expose())) {
+                found.push((MatchedField::Notes, m.as_str().to_owned()));
+            }
+            matches.extend(found.into_iter().map(|(field, matched)| SearchMatch { item: item.clone(), field, matched }));
+        }
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{interaction, replay_op};
+
+    #[test]
+    fn searches_titles_urls_and_notes() {
+        let notes = |item: &str, stdout: &str| interaction("item get",
+            &["--session=⟨redacted⟩", "--vault", "v", item, "--fields", "label=notesPlain"], stdout);
+        let list = r#"[
+            {"id": "a", "title": "db-02 root", "vault": {"id": "v"}, "category": "LOGIN"},
+            {"id": "b", "title": "Grafana", "vault": {"id": "v"}, "category": "LOGIN",
+                "urls": [{"href": "https://grafana.example.com"}, {"href": "https://db-02.internal:3000"}]},
+            {"id": "c", "title": "Runbook", "vault": {"id": "v"}, "category": "SECURE_NOTE"}]"#;
+        let op = replay_op(vec![
+            interaction("item list", &["--session=⟨redacted⟩", "--vault", "Ops", "--format", "json", "--no-color", "--iso-timestamps"],
+                list),
+            notes("a", "").failing("[ERROR] field notesPlain not found", 1),
+            notes("b", "\n"),
+            notes("c", "Failover: promote DB-02.internal\n"),
+        ]);
+        let pattern = Regex::new(r"(?i)db-02").unwrap();
+        let found = op.session("token").search_items(&pattern, &SearchOptions::new().vault("Ops").notes(true)).unwrap();
+        let fields : Vec<(&str, &MatchedField, &str)> = found.iter()
+            .map(|m| (&m.item.id[..], &m.field, &m.matched[..]))
+            .collect();
+        assert_eq!(fields, vec![
+            ("a", &MatchedField::Title, "db-02"),
+            ("b", &MatchedField::Url("https://db-02.internal:3000".to_owned()), "db-02"),
+            ("c", &MatchedField::Notes, "DB-02"),
+        ]);
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Deserializer};
+
+use memlock::Lock;
+
+/// Placeholder shown instead of secrets.
+pub const REDACTED: &str = "⟨redacted⟩";
+
+static DEBUG_REVEAL: AtomicBool = AtomicBool::new(false);
+
+/// Make `Debug` of `Secret` and of item fields show the actual values instead
+/// of `⟨redacted⟩`.
+///
+/// Off by default, so dumping state in logs is safe. Only turn this on while
+/// debugging locally.
+pub fn set_debug_reveal(reveal: bool) {
+    DEBUG_REVEAL.store(reveal, Ordering::Relaxed);
+}
+
+/// Whether `Debug` shows secret values, see `set_debug_reveal`.
+pub fn debug_reveal() -> bool {
+    DEBUG_REVEAL.load(Ordering::Relaxed)
+}
+
+/// Formats a value with `Debug` only if `debug_reveal` is on.
+pub(crate) struct Redacted<'a, T: 'a>(pub &'a T);
+
+impl<'a, T: fmt::Debug> fmt::Debug for Redacted<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if debug_reveal() {
+            self.0.fmt(f)
+        } else {
+            f.write_str(REDACTED)
+        }
+    }
+}
+
+/// Locks the memory of a value, used again to lock clones.
+type Locker<T> = fn(&T) -> Option<Lock>;
+
+/// A secret value, such as a password, read from 1Password.
+///
+/// `Debug` doesn't show the value unless `set_debug_reveal` was called, so
+/// secrets can't end up in logs by accident. Use `expose` to get at the value
+/// when it is actually needed.
+pub struct Secret<T>(T, Option<(Lock, Locker<T>)>);
+
+fn lock_string(value: &String) -> Option<Lock> {
+    Lock::new(value.as_bytes())
+}
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Secret<T> {
+        Secret(value, None)
+    }
+
+    /// Access the secret value.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// Take the secret value out of this wrapper. The memory is unlocked.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    /// Whether the memory holding the value is locked, see `Op::with_memory_lock`.
+    pub fn is_locked(&self) -> bool {
+        self.1.is_some()
+    }
+}
+
+impl Secret<String> {
+    /// Wrap `value` and try to lock the memory holding it so it can't be
+    /// swapped to disk. This needs the `mlock` feature, without it or when
+    /// locking fails the secret is just not locked.
+    pub fn locked(value: String) -> Secret<String> {
+        let lock = lock_string(&value).map(|lock| (lock, lock_string as Locker<String>));
+        Secret(value, lock)
+    }
+}
+
+impl<T: Clone> Clone for Secret<T> {
+    fn clone(&self) -> Secret<T> {
+        let value = self.0.clone();
+        let lock = self.1.as_ref().and_then(|&(_, locker)| locker(&value).map(|lock| (lock, locker)));
+        Secret(value, lock)
+    }
+}
+
+impl<T: PartialEq> PartialEq for Secret<T> {
+    fn eq(&self, other: &Secret<T>) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Eq> Eq for Secret<T> {}
+
+impl<T: Hash> Hash for Secret<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<T: Default> Default for Secret<T> {
+    fn default() -> Secret<T> {
+        Secret::new(T::default())
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Secret<T> {
+        Secret::new(value)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Secret<T>, D::Error> {
+        T::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+/// Hand a secret read by this crate to code using `secrecy`, e.g.
+/// `let password: SecretString = session.read(reference)?.into();`.
+#[cfg(feature = "secrecy")]
+impl From<Secret<String>> for ::secrecy::SecretString {
+    fn from(secret: Secret<String>) -> ::secrecy::SecretString {
+        ::secrecy::SecretString::from(secret.into_inner())
+    }
+}
+
+/// Lets a `Secret<String>` be passed wherever `secrecy` expects a secret string.
+#[cfg(feature = "secrecy")]
+impl ::secrecy::ExposeSecret<str> for Secret<String> {
+    fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Secret bytes sent to `op`, such as a master password.
+///
+/// This crate never writes secrets to temporary files: they reach `op` through
+/// the stdin pipe or the environment. To keep it that way, `SecretBytes` has
+/// no way to get at its contents other than handing them to the stdin of `op`
+/// or to a writer provided by the caller with `write_to`. It implements
+/// neither `Display` nor `Serialize`, `Debug` shows only its length and the
+/// memory is zeroed when it is dropped.
+
+#[derive(Clone)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn new(bytes: Vec<u8>) -> SecretBytes {
+        SecretBytes(bytes)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Write the bytes to `writer`, e.g. the stdin of a process spawned by the caller.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.0)
+    }
+
+    /// The bytes, for writing them to the stdin of `op`.
+    #[cfg(any(feature = "process", feature = "opvault"))]
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> SecretBytes {
+        SecretBytes(bytes)
+    }
+}
+
+impl From<String> for SecretBytes {
+    fn from(value: String) -> SecretBytes {
+        SecretBytes(value.into_bytes())
+    }
+}
+
+impl<'a> From<&'a str> for SecretBytes {
+    fn from(value: &'a str) -> SecretBytes {
+        SecretBytes(value.as_bytes().to_vec())
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        for byte in self.0.iter_mut() {
+            // Volatile so the compiler can't skip zeroing memory about to be freed.
+            unsafe { ptr::write_volatile(byte, 0) };
+        }
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretBytes({} bytes)", self.0.len())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Secret").field(&Redacted(&self.0)).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_is_redacted() {
+        let secret = Secret::new("hunter2".to_owned());
+        assert_eq!(format!("{:?}", secret), "Secret(⟨redacted⟩)");
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn debug_reveal_is_opt_in() {
+        let secret = Secret::new("hunter2".to_owned());
+        assert!(!debug_reveal());
+        assert_eq!(format!("{:?}", Redacted(&"hunter2")), REDACTED);
+        // Not toggled globally here, other tests rely on the default.
+        assert!(!format!("{:?}", secret).contains("hunter2"));
+    }
+
+    #[test]
+    fn secret_bytes_only_go_to_writers() {
+        let bytes = SecretBytes::from("hunter2");
+        assert_eq!(format!("{:?}", bytes), "SecretBytes(7 bytes)");
+        let mut out = Vec::new();
+        bytes.write_to(&mut out).unwrap();
+        assert_eq!(out, b"hunter2");
+    }
+
+    #[test]
+    fn locked_secrets_stay_locked_when_cloned() {
+        let secret = Secret::locked("hunter2".to_owned());
+        assert_eq!(secret.is_locked(), cfg!(all(feature = "mlock", unix)));
+        let copy = secret.clone();
+        assert_eq!(copy.is_locked(), secret.is_locked());
+        assert_eq!(copy, secret);
+        assert!(!Secret::new("hunter2".to_owned()).is_locked());
+    }
+
+    #[cfg(feature = "secrecy")]
+    #[test]
+    fn converts_to_secrecy() {
+        use secrecy::{ExposeSecret, SecretString};
+
+        let secret = Secret::new("hunter2".to_owned());
+        assert_eq!(ExposeSecret::<str>::expose_secret(&secret), "hunter2");
+        let converted: SecretString = secret.into();
+        assert_eq!(converted.expose_secret(), "hunter2");
+    }
+}
@@ -0,0 +1,193 @@
+#[cfg(feature = "process")]
+use serde_json::Value;
+
+use super::{ErrorKind, Result};
+#[cfg(feature = "process")]
+use super::{OpItem, OpSession, REVEAL_SINCE};
+
+/// Host of the links 1Password shares items with people outside the
+/// account, which `op` can't read.
+const SHARE_HOST: &str = "share.1password.com";
+
+/// Identifies an item.
+///
+/// Anything that takes an item selector accepts `Into<ItemSelector>`, so a
+/// plain `&str` can be used and is interpreted by `ItemSelector::parse`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ItemSelector {
+    /// Item id, 26 lower case letters and digits.
+    Id(String),
+    /// Title of the item. `op` fails if several items in the vaults searched
+    /// have it, or if it looks like an id.
+    Title(String),
+    /// Private link copied with "Copy Private Link", e.g.
+    /// `https://start.1password.com/open/i?a=…&v=…&i=…&h=my.1password.com`.
+    Link(String),
+}
+
+impl ItemSelector {
+    /// Interpret `s` as a link if it starts with `https://` or `http://`, as
+    /// an id if it is 26 lower case letters and digits, and otherwise as a title.
+    pub fn parse(s: &str) -> ItemSelector {
+        if s.starts_with("https://") || s.starts_with("http://") {
+            ItemSelector::Link(s.to_owned())
+        } else if is_id(s) {
+            ItemSelector::Id(s.to_owned())
+        } else {
+            ItemSelector::Title(s.to_owned())
+        }
+    }
+
+    /// Item and vault to pass to `op`: a link is taken apart into the item and
+    /// vault ids it holds, since `op` 1.x doesn't take links.
+    ///
+    /// Fails with `ErrorKind::InvalidItemSelector` for an id that isn't one,
+    /// an empty title, a share link or a link without an item id.
+    pub fn target(&self) -> Result<(String, Option<String>)> {
+        let invalid = |selector: &str, reason| ErrorKind::InvalidItemSelector(selector.to_owned(), reason);
+        match *self {
+            ItemSelector::Id(ref id) if is_id(id) => Ok((id.clone(), None)),
+            ItemSelector::Id(ref id) => bail!(invalid(id, "an item id is 26 lower case letters and digits")),
+            ItemSelector::Title(ref title) if title.trim().is_empty() => bail!(invalid(title, "the title is empty")),
+            ItemSelector::Title(ref title) => Ok((title.clone(), None)),
+            ItemSelector::Link(ref link) => {
+                let rest = link.strip_prefix("https://").or_else(|| link.strip_prefix("http://"))
+                    .ok_or_else(|| invalid(link, "not a URL"))?;
+                let (host, rest) = rest.split_once('/').unwrap_or((rest, ""));
+                if host.eq_ignore_ascii_case(SHARE_HOST) {
+                    bail!(invalid(link, "share links are for people outside the account and can't be read with op"));
+                }
+                let query = rest.split('#').next().and_then(|r| r.split_once('?')).map_or("", |(_, q)| q);
+                let param = |name: &str| query.split('&')
+                    .filter_map(|p| p.split_once('='))
+                    .find(|&(key, value)| key == name && !value.is_empty())
+                    .map(|(_, value)| value.to_owned());
+                let item = param("i").ok_or_else(|| invalid(link, "not a private link, it has no item id"))?;
+                Ok((item, param("v")))
+            },
+        }
+    }
+}
+
+/// Whether `s` has the form of an item id.
+fn is_id(s: &str) -> bool {
+    s.len() == 26 && s.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+}
+
+impl<'a> From<&'a str> for ItemSelector {
+    fn from(s: &'a str) -> ItemSelector {
+        ItemSelector::parse(s)
+    }
+}
+
+impl From<String> for ItemSelector {
+    fn from(s: String) -> ItemSelector {
+        ItemSelector::parse(&s)
+    }
+}
+
+#[cfg(feature = "process")]
+impl OpSession {
+    /// Get the item `item` selects by id, title or private link.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::{ItemSelector, Op};
+    ///
+    /// let session = Op::which().unwrap().env_session().unwrap();
+    /// let item = session.get_item_by("https://start.1password.com/open/i?a=A&v=V&i=I&h=my.1password.com").unwrap();
+    /// let item = session.get_item_by(ItemSelector::Title("Database".to_owned())).unwrap();
+    /// ```
+    pub fn get_item_by<S: Into<ItemSelector>>(&self, item: S) -> Result<OpItem> {
+        self.get_item_by_in(item, None)
+    }
+
+    /// Get the item `item` selects from `vault`, or the default vault if
+    /// `None`. The vault of a private link takes precedence.
+    ///
+    /// With `op` 2.x this runs `op item get`, with concealed values revealed,
+    /// and converts the item with `OpItem::from_v2`. Links are passed to `op`
+    /// as they are.
+    pub fn get_item_by_in<S: Into<ItemSelector>>(&self, item: S, vault: Option<&str>) -> Result<OpItem> {
+        let selector = item.into();
+        let (item, link_vault) = selector.target()?;
+        let version = self.config.cli_version()?;
+        if !version.is_v2() {
+            return self.get_item_in(&item, link_vault.as_deref().or(vault));
+        }
+        let mut command = match selector {
+            ItemSelector::Link(ref link) => {
+                let mut command = self.command(&["item", "get"]);
+                command.target(link);
+                command
+            },
+            _ => {
+                let mut command = self.item_command(&["item", "get"], vault);
+                command.target(&item);
+                command
+            },
+        };
+        command.json()?;
+        if version >= REVEAL_SINCE {
+            command.arg("--reveal");
+        }
+        let json : Value = command.run_json(|d| ErrorKind::GetCommand(item.clone(), d))?;
+        OpItem::from_v2(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let id = "ktjdcjsb2efmbmc3v6xsxmcwga";
+        assert_eq!(ItemSelector::parse(id), ItemSelector::Id(id.to_owned()));
+        assert_eq!(ItemSelector::from("Database").target().unwrap(), ("Database".to_owned(), None));
+        let link = format!("https://start.1password.com/open/i?a=ACC&v=vlt&i={}&h=my.1password.com", id);
+        assert_eq!(ItemSelector::from(link).target().unwrap(), (id.to_owned(), Some("vlt".to_owned())));
+        assert!(ItemSelector::Id("Database".to_owned()).target().is_err());
+        assert!(ItemSelector::Title(" ".to_owned()).target().is_err());
+        assert!(ItemSelector::parse("https://share.1password.com/s#abc").target().is_err());
+        assert!(ItemSelector::parse("https://start.1password.com/open/i?a=ACC").target().is_err());
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn gets_item_by_link() {
+        use cassette::{interaction, Cassette, Replay};
+        use {CliVersion, Op};
+
+        let cassette = Cassette { interactions: vec![interaction("get item", &["--session=⟨redacted⟩", "--vault", "vlt", "abc"],
+            r#"{"uuid": "abc", "vaultUuid": "vlt", "changerUuid": "U",
+                "overview": {"ainfo": "user", "title": "Database"}, "details": {"password": "hunter2"}}"#)] };
+        let op = Op::new("op").with_backend(Replay::new(cassette)).with_cli_version(CliVersion::new(1, 12, 4));
+        let item = op.session("token").get_item_by("https://start.1password.com/open/i?a=A&v=vlt&i=abc").unwrap();
+        assert_eq!(item.overview.title, "Database");
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn gets_item_by_link_with_v2() {
+        use cassette::{interaction, replay_op};
+
+        let link = "https://start.1password.com/open/i?a=A&v=vlt&i=abc";
+        let item = r#"{"id": "abc", "title": "Database", "category": "LOGIN", "vault": {"id": "vlt"},
+            "fields": [{"id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password", "value": "hunter2"}]}"#;
+        let op = replay_op(vec![
+            interaction("item get", &["--session=⟨redacted⟩", link, "--format", "json", "--no-color", "--iso-timestamps", "--reveal"],
+                item),
+            interaction("item get",
+                &["--session=⟨redacted⟩", "--vault", "Ops", "Database", "--format", "json", "--no-color", "--iso-timestamps", "--reveal"],
+                item),
+        ]);
+        let session = op.session("token");
+        let item = session.read_only().get_item_by(link).unwrap();
+        assert_eq!((&item.uuid[..], &item.overview.title[..]), ("abc", "Database"));
+        assert_eq!(item.password(), Some("hunter2".to_owned()));
+        assert_eq!(session.get_item_by_in("Database", Some("Ops")).unwrap().uuid, "abc");
+    }
+}
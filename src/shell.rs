@@ -0,0 +1,187 @@
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus};
+
+use secret::Secret;
+use super::{ErrorKind, OpSession, Result};
+
+/// A shell to hand a session back to, see `OpSession::shell_export_line`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shell {
+    /// Any POSIX shell, e.g. `sh` or `dash`.
+    Posix,
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl Shell {
+    /// The shell named `name`, e.g. `zsh`, `/usr/bin/fish` or `pwsh.exe`.
+    pub fn from_name(name: &str) -> Option<Shell> {
+        let name = Path::new(name).file_stem()?.to_str()?.to_lowercase();
+        match &name[..] {
+            "sh" | "dash" | "ash" | "ksh" => Some(Shell::Posix),
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "pwsh" | "powershell" => Some(Shell::PowerShell),
+            _ => None,
+        }
+    }
+
+    /// The shell of the user, from `SHELL`, or PowerShell on Windows.
+    pub fn from_env() -> Option<Shell> {
+        match env::var("SHELL") {
+            Ok(shell) => Shell::from_name(&shell),
+            Err(_) if cfg!(windows) => Some(Shell::PowerShell),
+            Err(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Shell::Posix => "sh",
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::PowerShell => "pwsh",
+        }
+    }
+
+    /// A command that sets `name` to `value` in the environment of this shell.
+    pub fn export_line(&self, name: &str, value: &str) -> String {
+        match *self {
+            Shell::Posix | Shell::Bash | Shell::Zsh => {
+                format!("export {}='{}'", name, value.replace('\'', r"'\''"))
+            },
+            Shell::Fish => {
+                format!("set -gx {} '{}'", name, value.replace('\\', r"\\").replace('\'', r"\'"))
+            },
+            Shell::PowerShell => format!("$env:{} = '{}'", name, value.replace('\'', "''")),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl OpSession {
+    /// Name of the `OP_SESSION_*` variable `op` reads the token of this
+    /// session from and the token, like `op signin` prints them.
+    ///
+    /// `None` for service accounts and for sessions made with `Op::session`
+    /// without `with_env_var`, whose account isn't known.
+    pub fn export_env(&self) -> Option<(String, Secret<String>)> {
+        match (self.env_var.as_ref(), self.token()) {
+            (Some(name), Some(token)) => Some((name.clone(), token)),
+            _ => None,
+        }
+    }
+
+    /// A line that makes `shell` use this session when evaluated, like the
+    /// output of `eval $(op signin)`, e.g. `export OP_SESSION_my='…'`.
+    ///
+    /// The line holds the session token, so only print it to a terminal or pipe
+    /// it straight into the shell.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::{Op, Shell};
+    ///
+    /// let session = Op::which().unwrap().signin_subdomain("my", "password").unwrap();
+    /// if let Some(line) = session.shell_export_line(Shell::from_env().unwrap_or(Shell::Posix)) {
+    ///     println!("{}", line);
+    /// }
+    /// ```
+    pub fn shell_export_line(&self, shell: Shell) -> Option<String> {
+        self.export_env().map(|(name, token)| shell.export_line(&name, token.expose()))
+    }
+
+    /// Spawn `command` signed in with this session, so `op` run by it needs
+    /// no sign in of its own.
+    ///
+    /// The `OP_SESSION_*` variable is only set for the child. Other
+    /// `OP_SESSION_*` variables are removed from its environment so `op` can't
+    /// pick the wrong account. Service accounts pass their token on through the
+    /// environment already.
+    pub fn spawn_command(&self, command: &mut Command) -> Result<Child> {
+        if self.auth.is_some() {
+            let (name, token) = self.export_env().ok_or(ErrorKind::UnknownSessionVariable)?;
+            for (other, _) in env::vars_os() {
+                if other.to_str().is_some_and(|o| o.starts_with("OP_SESSION_") && o != name) {
+                    command.env_remove(other);
+                }
+            }
+            command.env(name, token.expose());
+        }
+        Ok(command.spawn()?)
+    }
+
+    /// Start an interactive shell signed in with this session, and wait for
+    /// the user to leave it.
+    ///
+    /// The shell is the one in `SHELL`, or `COMSPEC` on Windows.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate one_password;
+    /// use one_password::Op;
+    ///
+    /// let session = Op::which().unwrap().signin_subdomain("my", "password").unwrap();
+    /// let status = session.spawn_shell().unwrap();
+    /// std::process::exit(status.code().unwrap_or(1));
+    /// ```
+    pub fn spawn_shell(&self) -> Result<ExitStatus> {
+        let shell = env::var_os("SHELL")
+            .or_else(|| if cfg!(windows) { env::var_os("COMSPEC") } else { None })
+            .unwrap_or_else(|| OsString::from(if cfg!(windows) { "cmd.exe" } else { "/bin/sh" }));
+        Ok(self.spawn_command(&mut Command::new(shell))?.wait()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Op;
+
+    #[test]
+    fn exports_sessions() {
+        assert_eq!(Shell::from_name("/usr/local/bin/fish"), Some(Shell::Fish));
+        assert_eq!(Shell::from_name("pwsh.exe"), Some(Shell::PowerShell));
+        assert_eq!(Shell::from_name("cmd"), None);
+
+        let op = Op::new("op");
+        assert_eq!(op.session("token").export_env(), None);
+        let session = op.session("it's").with_env_var("OP_SESSION_my");
+        let (name, token) = session.export_env().unwrap();
+        assert_eq!((&name[..], &token.expose()[..]), ("OP_SESSION_my", "it's"));
+        assert_eq!(session.shell_export_line(Shell::Bash).unwrap(), r"export OP_SESSION_my='it'\''s'");
+        assert_eq!(session.shell_export_line(Shell::Fish).unwrap(), r"set -gx OP_SESSION_my 'it\'s'");
+        assert_eq!(session.shell_export_line(Shell::PowerShell).unwrap(), "$env:OP_SESSION_my = 'it''s'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn spawns_with_session() {
+        let op = Op::new("op");
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("printf %s \"$OP_SESSION_my\"").stdout(::std::process::Stdio::piped());
+        let output = op.session("token").with_env_var("OP_SESSION_my")
+            .spawn_command(&mut command).unwrap()
+            .wait_with_output().unwrap();
+        assert_eq!(output.stdout, b"token");
+        match *op.session("token").spawn_command(&mut Command::new("true")).unwrap_err().kind() {
+            ErrorKind::UnknownSessionVariable => {},
+            ref other => panic!("unexpected error {:?}", other),
+        }
+    }
+}
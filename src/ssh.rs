@@ -0,0 +1,283 @@
+//! Run `op` on another host over SSH, e.g. a bastion where it is signed in,
+//! so no credentials have to be installed locally.
+//!
+//! `Op::ambient_session` gives a session that passes no credentials, leaving
+//! the remote `op` to use its own sign-in, such as a service account token
+//! in the remote environment.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use one_password::Op;
+//! use one_password::ssh::RemoteOp;
+//!
+//! // Authenticates with the local ssh-agent and checks ~/.ssh/known_hosts.
+//! let remote = RemoteOp::connect("bastion.example.com:22", "deploy").unwrap();
+//! let op = Op::new("/usr/local/bin/op").with_backend(remote);
+//! let session = op.ambient_session();
+//! println!("{}", session.read("op://Ops/DB/password").unwrap().expose());
+//! ```
+use std::env;
+use std::ffi::OsStr;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::Output;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ssh2::{Channel, CheckResult, KnownHostFileKind, Session};
+
+use backend::{Backend, Invocation};
+use cassette::exit_status;
+use super::{ErrorKind, Result};
+
+/// Backend that runs `op` on a remote host through an SSH session.
+///
+/// The program of the `Op` is run on the remote host, with the remote
+/// environment: `OpBuilder::env_policy` has no effect, but the locale and
+/// default account are passed on. Invocations share the session and run one
+/// at a time, with their input and output copied concurrently. Commands that
+/// prompt on the terminal are not supported.
+///
+/// The session token is written to the channel ahead of the stdin of `op` and
+/// read into its environment by the remote shell, so it never shows up on
+/// the remote command line.
+pub struct RemoteOp {
+    session: Mutex<Session>,
+}
+
+impl RemoteOp {
+    /// Run `op` through `session`, which has to be connected and authenticated.
+    pub fn new(session: Session) -> RemoteOp {
+        RemoteOp { session: Mutex::new(session) }
+    }
+
+    /// Connect to `addr`, e.g. `bastion.example.com` or `[::1]:2222`, check
+    /// its host key against `~/.ssh/known_hosts` and authenticate as `user`
+    /// with the local ssh-agent.
+    ///
+    /// Fails with `ErrorKind::UnknownHostKey` if the host is not known or
+    /// its key doesn't match. Use `new` for other ways to authenticate.
+    pub fn connect(addr: &str, user: &str) -> Result<RemoteOp> {
+        let (host, port) = host_port(addr).ok_or_else(|| ErrorKind::UnknownHostKey(addr.to_owned()))?;
+        let tcp = TcpStream::connect((host, port))?;
+        let mut session = Session::new().map_err(io::Error::from)?;
+        session.set_tcp_stream(tcp);
+        session.handshake().map_err(io::Error::from)?;
+        check_host_key(&session, host, port)?;
+        session.userauth_agent(user).map_err(io::Error::from)?;
+        Ok(RemoteOp::new(session))
+    }
+
+    fn lock(&self) -> ::std::sync::MutexGuard<'_, Session> {
+        self.session.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    /// Run `invocation`, writing `stdin` to `op` while copying its stdout
+    /// into `stdout`.
+    fn exec(&self, invocation: &Invocation, stdin: Option<&mut dyn Read>, stdout: &mut dyn Write) -> io::Result<Output> {
+        let command = remote_command(invocation)?;
+        let session = self.lock();
+        // 0 disables the timeout.
+        let timeout = invocation.timeout().map_or(0, |t| t.as_millis().min(u128::from(u32::MAX)) as u32);
+        let deadline = invocation.timeout().map(|t| Instant::now() + t);
+        session.set_timeout(timeout);
+        let mut channel = session.channel_session()?;
+        channel.exec(&command)?;
+        let token = invocation.session_var().map(|(_, token)| format!("{}\n", token.expose()).into_bytes());
+        session.set_blocking(false);
+        let stderr = pump(&mut channel, token.unwrap_or_default(), stdin, stdout, deadline);
+        session.set_blocking(true);
+        let stderr = stderr?;
+        channel.wait_close()?;
+        Ok(Output { status: exit_status(channel.exit_status()?), stdout: Vec::new(), stderr })
+    }
+}
+
+/// Write `pending` and then `stdin` to the non-blocking `channel` while
+/// copying its stdout into `stdout`, so neither side waits for the other
+/// with a full window, and return its stderr.
+fn pump(channel: &mut Channel, mut pending: Vec<u8>, mut stdin: Option<&mut dyn Read>, stdout: &mut dyn Write,
+    deadline: Option<Instant>) -> io::Result<Vec<u8>>
+{
+    let would_block = |err: &io::Error| err.kind() == io::ErrorKind::WouldBlock;
+    let mut buf = vec![0; 32 * 1024];
+    let mut stderr = Vec::new();
+    let (mut written, mut eof_sent, mut out_done, mut err_done) = (0, false, false, false);
+    while !(out_done && err_done) {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "op timed out"));
+        }
+        let mut progress = false;
+        if !eof_sent && written == pending.len() {
+            match stdin {
+                Some(ref mut input) => {
+                    let n = input.read(&mut buf)?;
+                    pending.clear();
+                    pending.extend_from_slice(&buf[..n]);
+                    written = 0;
+                    progress = true;
+                },
+                None => match channel.send_eof().map_err(io::Error::from) {
+                    Ok(()) => {
+                        eof_sent = true;
+                        progress = true;
+                    },
+                    Err(ref err) if would_block(err) => (),
+                    Err(err) => return Err(err),
+                },
+            }
+            if pending.is_empty() {
+                stdin = None;
+            }
+        } else if !eof_sent {
+            match channel.write(&pending[written..]) {
+                Ok(n) => {
+                    written += n;
+                    progress = n > 0;
+                },
+                Err(ref err) if would_block(err) => (),
+                // `op` may exit without reading all of its input.
+                Err(_) => {
+                    stdin = None;
+                    eof_sent = true;
+                },
+            }
+        }
+        if !out_done {
+            match channel.read(&mut buf) {
+                Ok(0) => out_done = true,
+                Ok(n) => {
+                    stdout.write_all(&buf[..n])?;
+                    progress = true;
+                },
+                Err(ref err) if would_block(err) => (),
+                Err(err) => return Err(err),
+            }
+        }
+        if !err_done {
+            match channel.stderr().read(&mut buf) {
+                Ok(0) => err_done = true,
+                Ok(n) => {
+                    stderr.extend_from_slice(&buf[..n]);
+                    progress = true;
+                },
+                Err(ref err) if would_block(err) => (),
+                Err(err) => return Err(err),
+            }
+        }
+        if !progress {
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+    Ok(stderr)
+}
+
+impl Backend for RemoteOp {
+    fn run(&self, invocation: &Invocation) -> io::Result<Output> {
+        let mut stdout = Vec::new();
+        let output = self.run_to(invocation, &mut stdout)?;
+        Ok(Output { stdout, ..output })
+    }
+
+    fn run_interactive(&self, _invocation: &Invocation) -> io::Result<Output> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "can't prompt on the terminal over SSH"))
+    }
+
+    fn run_to(&self, invocation: &Invocation, stdout: &mut dyn Write) -> io::Result<Output> {
+        let mut data = invocation.stdin();
+        self.exec(invocation, data.as_mut().map(|d| d as &mut dyn Read), stdout)
+    }
+
+    fn run_from(&self, invocation: &Invocation, stdin: &mut (dyn Read + Send)) -> io::Result<Output> {
+        let mut stdout = Vec::new();
+        let output = self.exec(invocation, Some(stdin), &mut stdout)?;
+        Ok(Output { stdout, ..output })
+    }
+}
+
+/// Host and port of `addr`, the port defaulting to 22.
+fn host_port(addr: &str) -> Option<(&str, u16)> {
+    match addr.rsplit_once(':') {
+        Some((host, port)) if !host.ends_with(':') => {
+            Some((host.trim_start_matches('[').trim_end_matches(']'), port.parse().ok()?))
+        },
+        _ => Some((addr, 22)),
+    }
+}
+
+/// Fail unless the key of `host` is in `~/.ssh/known_hosts`.
+fn check_host_key(session: &Session, host: &str, port: u16) -> Result<()> {
+    let unknown = || ErrorKind::UnknownHostKey(host.to_owned());
+    let mut known_hosts = session.known_hosts().map_err(io::Error::from)?;
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).ok_or_else(unknown)?;
+    let file = PathBuf::from(home).join(".ssh").join("known_hosts");
+    known_hosts.read_file(&file, KnownHostFileKind::OpenSSH).map_err(|_| unknown())?;
+    let (key, _) = session.host_key().ok_or_else(unknown)?;
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        _ => Err(unknown().into()),
+    }
+}
+
+/// Quote `arg` for a POSIX shell.
+fn quote(arg: &OsStr) -> io::Result<String> {
+    let arg = arg.to_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "argument is not valid UTF-8"))?;
+    Ok(format!("'{}'", arg.replace('\'', r"'\''")))
+}
+
+/// Shell command line running `invocation` with its locale and account, and
+/// reading its session token, if any, from the first line of stdin.
+fn remote_command(invocation: &Invocation) -> io::Result<String> {
+    let mut command = String::new();
+    if let Some((name, _)) = invocation.session_var() {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid session variable"));
+        }
+        command.push_str(&format!("IFS= read -r {0} && export {0} && exec ", name));
+    }
+    command.push_str("env");
+    if let Some(locale) = invocation.locale() {
+        let locale = quote(locale)?;
+        command.push_str(&format!(" LC_ALL={} LANG={}", locale, locale));
+    }
+    if let Some(account) = invocation.account() {
+        command.push_str(&format!(" OP_ACCOUNT={}", quote(OsStr::new(account))?));
+    }
+    command.push(' ');
+    command.push_str(&quote(invocation.program().as_os_str())?);
+    for arg in invocation.args() {
+        command.push(' ');
+        command.push_str(&quote(arg)?);
+    }
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::*;
+    use {CommandInfo, Op, Secret};
+
+    #[test]
+    fn quotes_remote_command() {
+        let info = CommandInfo { subcommand: "read".to_owned(), args: Vec::new(), targets: Vec::new(), reason: None };
+        let op = Op::builder().binary("/usr/local/bin/op").account("my").build().unwrap();
+        let args = vec![OsString::from("read"), OsString::from("op://Ops/Bob's DB/password")];
+        let invocation = Invocation::new(&op, args, None, &info);
+        assert_eq!(remote_command(&invocation).unwrap(),
+            r"env LC_ALL='C' LANG='C' OP_ACCOUNT='my' '/usr/local/bin/op' 'read' 'op://Ops/Bob'\''s DB/password'");
+        let invocation = Invocation::new(&op, vec![OsString::from("whoami")], None, &info)
+            .with_session("OP_SESSION_my", Secret::new("TOKEN".to_owned()));
+        assert_eq!(remote_command(&invocation).unwrap(),
+            r"IFS= read -r OP_SESSION_my && export OP_SESSION_my && exec env LC_ALL='C' LANG='C' OP_ACCOUNT='my' '/usr/local/bin/op' 'whoami'");
+        assert_eq!(host_port("bastion.example.com"), Some(("bastion.example.com", 22)));
+        assert_eq!(host_port("[::1]:2222"), Some(("::1", 2222)));
+        assert_eq!(host_port("bastion:ssh"), None);
+    }
+}
@@ -0,0 +1,175 @@
+use std::io::{self, Write};
+use std::mem;
+
+use super::{Error, ErrorKind, Result};
+
+/// Splits a JSON array written to it in arbitrary chunks into its elements,
+/// calling `element` with the bytes of each as soon as it is complete.
+///
+/// Only the element being written is buffered, so the output of `op item list`
+/// for a large account never has to be held in memory at once.
+pub(crate) struct JsonArray<F> {
+    subcommand: String,
+    element: F,
+    buf: Vec<u8>,
+    /// Nesting depth, 1 inside the outer array.
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    done: bool,
+    error: Option<Error>,
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> JsonArray<F> {
+    /// Split the output of `subcommand`, which is named in errors.
+    pub fn new(subcommand: String, element: F) -> JsonArray<F> {
+        JsonArray {
+            subcommand,
+            element,
+            buf: Vec::new(),
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            done: false,
+            error: None,
+        }
+    }
+
+    /// Take the error that made a write fail, if it wasn't an IO error.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    /// Check that a whole array was written.
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some(err) = self.take_error() {
+            return Err(err);
+        }
+        if !self.done {
+            let err = io::Error::new(io::ErrorKind::UnexpectedEof, "op output ended inside a JSON array");
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    fn flush_element(&mut self) -> Result<()> {
+        let element = mem::take(&mut self.buf);
+        if element.iter().any(|b| !b.is_ascii_whitespace()) {
+            (self.element)(&element)?;
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, b: u8) -> Result<()> {
+        if self.in_string {
+            self.buf.push(b);
+            if self.escaped {
+                self.escaped = false;
+            } else if b == b'\\' {
+                self.escaped = true;
+            } else if b == b'"' {
+                self.in_string = false;
+            }
+            return Ok(());
+        }
+        if self.depth == 0 {
+            return match b {
+                _ if b.is_ascii_whitespace() => Ok(()),
+                b'[' if !self.done => {
+                    self.depth = 1;
+                    Ok(())
+                },
+                b'{' if !self.done => {
+                    let err = io::Error::new(io::ErrorKind::InvalidData, "expected a JSON array");
+                    Err(err.into())
+                },
+                _ if self.done => {
+                    let err = io::Error::new(io::ErrorKind::InvalidData, "trailing data after JSON array");
+                    Err(err.into())
+                },
+                _ => Err(ErrorKind::UnexpectedHumanOutput(self.subcommand.clone()).into()),
+            };
+        }
+        match b {
+            b'"' => {
+                self.in_string = true;
+                self.buf.push(b);
+            },
+            b'{' | b'[' => {
+                self.depth += 1;
+                self.buf.push(b);
+            },
+            b'}' | b']' => {
+                self.depth -= 1;
+                if self.depth == 0 {
+                    self.done = true;
+                    self.flush_element()?;
+                } else {
+                    self.buf.push(b);
+                }
+            },
+            b',' if self.depth == 1 => self.flush_element()?,
+            _ => self.buf.push(b),
+        }
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> Write for JsonArray<F> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.error.is_some() {
+            return Err(io::Error::other("JSON array already failed"));
+        }
+        for &b in data {
+            if let Err(err) = self.push(b) {
+                let message = err.to_string();
+                self.error = Some(err);
+                return Err(io::Error::other(message));
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(chunks: &[&str]) -> Result<Vec<String>> {
+        let mut elements = Vec::new();
+        {
+            let mut array = JsonArray::new("item list".to_owned(), |e: &[u8]| {
+                elements.push(String::from_utf8(e.to_vec()).unwrap().trim().to_owned());
+                Ok(())
+            });
+            for chunk in chunks {
+                if array.write_all(chunk.as_bytes()).is_err() {
+                    break;
+                }
+            }
+            array.finish()?;
+        }
+        Ok(elements)
+    }
+
+    #[test]
+    fn splits_elements_across_chunks() {
+        let elements = split(&["[{\"id\": \"a\", \"tags\": [\"x,]\"]}", ",\n  {\"id\": \"b\\\"}\"", ", \"n\": {}}, 3", "]\n"]).unwrap();
+        assert_eq!(elements, vec![r#"{"id": "a", "tags": ["x,]"]}"#, r#"{"id": "b\"}", "n": {}}"#, "3"]);
+        assert!(split(&["[", "]"]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_other_output() {
+        match *split(&["ID    TITLE\n"]).unwrap_err().kind() {
+            ErrorKind::UnexpectedHumanOutput(ref subcommand) => assert_eq!(subcommand, "item list"),
+            ref other => panic!("unexpected error {:?}", other),
+        }
+        assert!(split(&["[{\"id\": \"a\"}"]).is_err());
+        assert!(split(&["[] x"]).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Audit password strength and reuse.
+//!
+//! Unlike the rest of the crate, this reads the password of every login and
+//! password item, which is why `StrengthOptions` can only be created with
+//! `StrengthOptions::read_passwords`. Passwords are scored with zxcvbn and
+//! hashed with a key that is random for every audit, then dropped; the report
+//! names items but holds neither passwords nor hashes.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use one_password::Op;
+//! use one_password::strength::StrengthOptions;
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let options = StrengthOptions::read_passwords().vault("Shared").concurrency(4);
+//! let report = session.audit_passwords(&options).unwrap();
+//! print!("{}", report);
+//! ```
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::BuildHasher;
+
+use zxcvbn::zxcvbn;
+
+use diagnostics::not_found;
+use duplicates::{groups, CATEGORIES};
+use pool;
+use super::{DuplicateGroup, ItemSummary, OpSession, Result};
+
+/// Options for `OpSession::audit_passwords`.
+#[derive(Debug, Clone)]
+pub struct StrengthOptions {
+    vaults: Vec<String>,
+    min_score: u8,
+    concurrency: usize,
+}
+
+impl StrengthOptions {
+    /// Opt in to reading the password of every login and password item.
+    pub fn read_passwords() -> StrengthOptions {
+        StrengthOptions { vaults: Vec::new(), min_score: 3, concurrency: 1 }
+    }
+
+    /// Audit `vault`. Without any vault, the default vault of the session is audited.
+    pub fn vault(mut self, vault: &str) -> StrengthOptions {
+        self.vaults.push(vault.to_owned());
+        self
+    }
+
+    /// Lowest zxcvbn score, from 0 to 4, that isn't reported as weak. Defaults to 3.
+    pub fn min_score(mut self, min_score: u8) -> StrengthOptions {
+        self.min_score = min_score.min(4);
+        self
+    }
+
+    /// Number of passwords to read at the same time. Defaults to 1.
+    ///
+    /// The total number of `op` processes is still limited by `set_max_concurrency`.
+    pub fn concurrency(mut self, concurrency: usize) -> StrengthOptions {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+}
+
+/// An item with a password scoring below `StrengthOptions::min_score`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WeakPassword {
+    pub item: ItemSummary,
+    /// zxcvbn score from 0, too guessable, to 4, very unguessable.
+    pub score: u8,
+    /// Why the password is weak, e.g. `This is a top-10 common password.`,
+    /// when zxcvbn can tell.
+    pub warning: Option<String>,
+}
+
+/// Result of `OpSession::audit_passwords`.
+///
+/// Its `Display` lists weak and reused passwords by item title and id.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct StrengthReport {
+    /// Number of items whose password was read.
+    pub scanned: usize,
+    /// Weak passwords, weakest first.
+    pub weak: Vec<WeakPassword>,
+    /// Groups of two or more items with the same password, largest first.
+    pub reused: Vec<DuplicateGroup>,
+}
+
+impl StrengthReport {
+    pub fn is_clean(&self) -> bool {
+        self.weak.is_empty() && self.reused.is_empty()
+    }
+}
+
+impl fmt::Display for StrengthReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} passwords audited, {} weak, {} reused", self.scanned, self.weak.len(),
+            self.reused.iter().map(|g| g.items.len()).sum::<usize>())?;
+        for weak in &self.weak {
+            write!(f, "weak ({}/4): {} ({})", weak.score, weak.item.title, weak.item.id)?;
+            match weak.warning {
+                Some(ref warning) => writeln!(f, ": {}", warning)?,
+                None => writeln!(f)?,
+            }
+        }
+        for group in &self.reused {
+            let items : Vec<String> = group.items.iter().map(|i| format!("{} ({})", i.title, i.id)).collect();
+            writeln!(f, "reused: {}", items.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl OpSession {
+    /// Score the passwords of the login and password items in the vaults of
+    /// `options` and find those used by more than one item.
+    ///
+    /// Items without a password field or with an empty password are skipped.
+    pub fn audit_passwords(&self, options: &StrengthOptions) -> Result<StrengthReport> {
+        let mut items = Vec::new();
+        let mut keep = |item: ItemSummary| if CATEGORIES.contains(&&item.category[..]) {
+            items.push(item);
+        };
+        if options.vaults.is_empty() {
+            self.for_each_item_in(None, &mut keep)?;
+        }
+        for vault in &options.vaults {
+            self.for_each_item_in(Some(vault), &mut keep)?;
+        }
+
+        let hasher = RandomState::new();
+        let scored = pool::map_concurrent(&items, options.concurrency, |_, item| {
+            let password = match not_found(self.get_field_revealed_in(&item.id, "password", Some(&item.vault_id)))? {
+                Some(password) => password,
+                None => return Ok(None),
+            };
+            if password.expose().is_empty() {
+                return Ok(None);
+            }
+            let entropy = zxcvbn(password.expose(), &[&item.title]);
+            let warning = entropy.feedback().and_then(|f| f.warning()).map(|w| w.to_string());
+            Ok(Some((u8::from(entropy.score()), warning, hasher.hash_one(password.expose()))))
+        }).into_iter().collect::<Result<Vec<_>>>()?;
+
+        let mut report = StrengthReport::default();
+        let mut by_hash : HashMap<u64, Vec<ItemSummary>> = HashMap::new();
+        for (item, scored) in items.into_iter().zip(scored) {
+            let (score, warning, hash) = match scored {
+                Some(scored) => scored,
+                None => continue,
+            };
+            report.scanned += 1;
+            if score < options.min_score {
+                report.weak.push(WeakPassword { item: item.clone(), score, warning });
+            }
+            by_hash.entry(hash).or_default().push(item);
+        }
+        report.weak.sort_by_key(|weak| weak.score);
+        report.reused = groups(by_hash);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{interaction, replay_op};
+
+    #[test]
+    fn reports_weak_and_reused_passwords() {
+        let list = r#"[
+            {"id": "a", "title": "Mail", "vault": {"id": "v"}, "category": "LOGIN"},
+            {"id": "b", "title": "Wiki", "vault": {"id": "v"}, "category": "LOGIN"},
+            {"id": "c", "title": "Root", "vault": {"id": "v"}, "category": "PASSWORD"},
+            {"id": "d", "title": "API", "vault": {"id": "v"}, "category": "LOGIN"},
+            {"id": "e", "title": "Notes", "vault": {"id": "v"}, "category": "SECURE_NOTE"}
+        ]"#;
+        let field = |id: &str, password: &str| interaction("item get",
+            &["--session=⟨redacted⟩", "--vault", "v", id, "--fields", "label=password", "--reveal"], password);
+        let op = replay_op(vec![
+            interaction("item list", &["--session=⟨redacted⟩", "--vault", "v", "--format", "json", "--no-color", "--iso-timestamps"],
+                list),
+            field("a", "password\n"),
+            field("b", "correct horse battery staple 1923\n"),
+            field("c", "correct horse battery staple 1923\n"),
+            field("d", "").failing("[ERROR] field password not found", 1),
+        ]);
+        let report = op.session("token").audit_passwords(&StrengthOptions::read_passwords().vault("v")).unwrap();
+        assert_eq!(report.scanned, 3);
+        assert_eq!(report.weak.len(), 1);
+        assert_eq!((&report.weak[0].item.id[..], report.weak[0].score), ("a", 0));
+        assert_eq!(report.reused.len(), 1);
+        let ids : Vec<&str> = report.reused[0].items.iter().map(|i| &i.id[..]).collect();
+        assert_eq!(ids, vec!["b", "c"]);
+
+        let printed = report.to_string();
+        assert!(printed.starts_with("3 passwords audited, 1 weak, 2 reused\n"));
+        assert!(printed.contains("weak (0/4): Mail (a)"));
+        assert!(printed.contains("reused: Wiki (b), Root (c)\n"));
+        assert!(!printed.contains("password\n") && !printed.contains("horse"));
+        assert!(!::serde_json::to_string(&report).unwrap().contains("horse"));
+    }
+}
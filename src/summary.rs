@@ -0,0 +1,366 @@
+#[cfg(feature = "process")]
+use serde_json;
+
+#[cfg(feature = "process")]
+use command::OpCommand;
+#[cfg(feature = "process")]
+use stream::JsonArray;
+#[cfg(feature = "process")]
+use super::{ErrorKind, Result};
+
+/// Overview of an item as returned by `OpSession::list_items`, without any secret values.
+///
+/// `op` 1.x and 2.x list items in different shapes; both are mapped onto this.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ItemSummary {
+    pub id: String,
+    pub title: String,
+    pub vault_id: String,
+    /// Name of the vault, only listed by `op` 2.x.
+    pub vault_name: Option<String>,
+    /// Category in `op` 2.x spelling, e.g. `LOGIN` or `SECURE_NOTE`.
+    pub category: String,
+    pub tags: Vec<String>,
+    pub favorite: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    /// Incremented by every edit.
+    pub version: Option<u64>,
+    /// UUID of the user who last edited the item, see `OpSession::resolve_user`.
+    pub last_edited_by: Option<String>,
+    /// Websites the item fills in, e.g. the login page of a login.
+    pub urls: Vec<ItemUrl>,
+}
+
+/// A website of an item.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ItemUrl {
+    pub href: String,
+    /// E.g. `website`. May be empty.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    /// Whether this is the URL shown for the item and opened from it. At
+    /// most one URL of an item is primary.
+    #[serde(default)]
+    pub primary: bool,
+}
+
+impl ItemUrl {
+    pub fn new(href: &str) -> ItemUrl {
+        ItemUrl { href: href.to_owned(), label: String::new(), primary: false }
+    }
+}
+
+#[derive(Deserialize)]
+struct V2Vault {
+    id: String,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct V2Summary {
+    id: String,
+    #[serde(default)]
+    title: String,
+    vault: V2Vault,
+    #[serde(default)]
+    category: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    favorite: bool,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    version: Option<u64>,
+    last_edited_by: Option<String>,
+    #[serde(default)]
+    urls: Vec<ItemUrl>,
+}
+
+#[derive(Deserialize)]
+struct V1Url {
+    #[serde(default)]
+    l: String,
+    u: String,
+}
+
+#[derive(Deserialize)]
+struct V1Overview {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// The primary URL.
+    #[serde(default)]
+    url: String,
+    #[serde(default, rename = "URLs")]
+    urls: Vec<V1Url>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct V1Summary {
+    uuid: String,
+    vault_uuid: String,
+    #[serde(default)]
+    template_uuid: String,
+    /// Position among the favorites, only present for favorite items.
+    fav_index: Option<i64>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    item_version: Option<u64>,
+    changer_uuid: Option<String>,
+    overview: V1Overview,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSummary {
+    V2(V2Summary),
+    V1(V1Summary),
+}
+
+/// `op` 1.x template UUIDs and the matching `op` 2.x category.
+const TEMPLATES: &[(&str, &str)] = &[
+    ("001", "LOGIN"),
+    ("002", "CREDIT_CARD"),
+    ("003", "SECURE_NOTE"),
+    ("004", "IDENTITY"),
+    ("005", "PASSWORD"),
+    ("006", "DOCUMENT"),
+    ("100", "SOFTWARE_LICENSE"),
+    ("101", "BANK_ACCOUNT"),
+    ("102", "DATABASE"),
+    ("103", "DRIVER_LICENSE"),
+    ("104", "OUTDOOR_LICENSE"),
+    ("105", "MEMBERSHIP"),
+    ("106", "PASSPORT"),
+    ("107", "REWARD_PROGRAM"),
+    ("108", "SOCIAL_SECURITY_NUMBER"),
+    ("109", "WIRELESS_ROUTER"),
+    ("110", "SERVER"),
+    ("111", "EMAIL_ACCOUNT"),
+    ("112", "API_CREDENTIAL"),
+];
+
+/// The `op` 1.x template UUID for a category, or the category itself if it is unknown.
+pub(crate) fn template_uuid(category: &str) -> String {
+    TEMPLATES.iter()
+        .find(|&&(_, c)| c == category)
+        .map_or(category, |&(template, _)| template)
+        .to_owned()
+}
+
+/// The `op` 2.x category for a template UUID, or the UUID itself if it is unknown.
+pub(crate) fn category(template_uuid: String) -> String {
+    TEMPLATES.iter()
+        .find(|&&(template, _)| template == template_uuid)
+        .map(|&(_, category)| category.to_owned())
+        .unwrap_or(template_uuid)
+}
+
+impl From<RawSummary> for ItemSummary {
+    fn from(raw: RawSummary) -> ItemSummary {
+        match raw {
+            RawSummary::V2(s) => ItemSummary {
+                id: s.id,
+                title: s.title,
+                vault_id: s.vault.id,
+                vault_name: s.vault.name,
+                category: s.category,
+                tags: s.tags,
+                favorite: s.favorite,
+                created_at: s.created_at,
+                updated_at: s.updated_at,
+                version: s.version,
+                last_edited_by: s.last_edited_by,
+                urls: s.urls,
+            },
+            RawSummary::V1(s) => ItemSummary {
+                urls: s.overview.urls.iter()
+                    .map(|u| ItemUrl { href: u.u.clone(), label: u.l.clone(), primary: u.u == s.overview.url })
+                    .collect(),
+                category: category(s.template_uuid),
+                id: s.uuid,
+                title: s.overview.title,
+                vault_id: s.vault_uuid,
+                vault_name: None,
+                tags: s.overview.tags,
+                favorite: s.fav_index.is_some(),
+                created_at: s.created_at,
+                updated_at: s.updated_at,
+                version: s.item_version,
+                last_edited_by: s.changer_uuid,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar, after
+/// Howard Hinnant's `days_from_civil`.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(any(feature = "process", feature = "onepux", feature = "opvault"))]
+/// Date in the proleptic Gregorian calendar of days since 1970-01-01, after
+/// Howard Hinnant's `civil_from_days`.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month as u32, day as u32)
+}
+
+#[cfg(feature = "process")]
+/// Parse an RFC 3339 timestamp such as `2023-01-01T12:00:00.5+01:00` into
+/// seconds since the Unix epoch.
+pub(crate) fn parse_rfc3339(timestamp: &str) -> Option<i64> {
+    let timestamp = timestamp.trim();
+    let field = |range: ::std::ops::Range<usize>| timestamp.get(range).and_then(|s| s.parse::<u32>().ok());
+    let separators = timestamp.as_bytes();
+    if separators.len() < 20 || separators[4] != b'-' || separators[7] != b'-'
+        || !(separators[10] == b'T' || separators[10] == b't' || separators[10] == b' ')
+        || separators[13] != b':' || separators[16] != b':'
+    {
+        return None;
+    }
+    let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+    let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+    if month == 0 || month > 12 || day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let mut rest = &timestamp[19..];
+    if rest.starts_with('.') {
+        let digits = rest[1..].bytes().take_while(u8::is_ascii_digit).count();
+        rest = &rest[1 + digits..];
+    }
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+            let hours = rest.get(1..3)?.parse::<i64>().ok()?;
+            let minutes = rest.get(4..6)?.parse::<i64>().ok()?;
+            let offset = hours * 3600 + minutes * 60;
+            match rest.as_bytes()[0] {
+                b'+' => offset,
+                b'-' => -offset,
+                _ => return None,
+            }
+        },
+        _ => return None,
+    };
+    let days = days_from_civil(i64::from(year), month, day);
+    Some(days * 86_400 + i64::from(hour) * 3600 + i64::from(minute) * 60 + i64::from(second) - offset)
+}
+
+#[cfg(any(feature = "onepux", feature = "opvault"))]
+/// Format seconds since the Unix epoch as an RFC 3339 timestamp in UTC.
+pub(crate) fn rfc3339(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86_400);
+    let seconds = timestamp.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+#[cfg(feature = "process")]
+/// Parse a single item printed by `command`, e.g. `op item create --format json`.
+pub(crate) fn parse(command: &OpCommand, json: &[u8]) -> Result<ItemSummary> {
+    let raw : RawSummary = command.parse_json(json)?;
+    Ok(raw.into())
+}
+
+#[cfg(feature = "process")]
+/// Parse one element of the output of `op list items` or `op item list --format json`.
+fn parse_element(json: &[u8]) -> Result<ItemSummary> {
+    let raw : RawSummary = serde_json::from_slice(json)?;
+    Ok(raw.into())
+}
+
+#[cfg(feature = "process")]
+/// Run `command`, `op list items` or `op item list --format json`, and call
+/// `item` with each listed item as soon as it has been read, so neither the
+/// output nor a parsed document of it is held in memory.
+pub(crate) fn stream_list<F: FnMut(ItemSummary)>(command: &OpCommand, mut item: F) -> Result<()> {
+    let mut array = JsonArray::new(command.subcommand(), |element: &[u8]| {
+        item(parse_element(element)?);
+        Ok(())
+    });
+    let result = command.run_to(&mut array, ErrorKind::CommandFailed);
+    if let Some(err) = array.take_error() {
+        return Err(err);
+    }
+    result?;
+    array.finish()
+}
+
+#[cfg(all(test, feature = "process"))]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn parses_both_versions() {
+        let v1 = br#"[{"uuid": "abc", "templateUuid": "001", "vaultUuid": "v1", "favIndex": 1,
+            "itemVersion": 1, "changerUuid": "U1",
+            "createdAt": "2018-09-24T10:00:00Z", "updatedAt": "2018-09-25T10:00:00Z",
+            "overview": {"title": "Example", "ainfo": "user", "tags": ["web"], "url": "https://example.com",
+                "URLs": [{"l": "website", "u": "https://example.com"}, {"l": "", "u": "https://example.org"}]}}]"#;
+        let v2 = br#"[{"id": "abc", "title": "Example", "vault": {"id": "v1", "name": "Private"},
+            "category": "LOGIN", "tags": ["web"], "version": 1, "favorite": true, "last_edited_by": "U1",
+            "created_at": "2018-09-24T10:00:00Z", "updated_at": "2018-09-25T10:00:00Z",
+            "urls": [{"label": "website", "primary": true, "href": "https://example.com"}, {"href": "https://example.org"}]}]"#;
+        let parse_list = |json: &[u8]| {
+            let mut items = Vec::new();
+            {
+                let mut array = JsonArray::new("item list".to_owned(), |e: &[u8]| {
+                    items.push(parse_element(e)?);
+                    Ok(())
+                });
+                array.write_all(json).unwrap();
+                array.finish().unwrap();
+            }
+            items
+        };
+        let mut from_v1 = parse_list(v1);
+        let from_v2 = parse_list(v2);
+        assert_eq!(from_v1[0].category, "LOGIN");
+        assert!(from_v1[0].favorite);
+        assert_eq!(from_v2[0].vault_name, Some("Private".to_owned()));
+        assert!(from_v1[0].urls[0].primary && !from_v1[0].urls[1].primary);
+        from_v1[0].vault_name = Some("Private".to_owned());
+        assert_eq!(from_v1, from_v2);
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_rfc3339("2000-02-29T00:00:00Z"), Some(951_782_400));
+        assert_eq!(parse_rfc3339("2000-02-29T01:30:00.123456+01:30"), Some(951_782_400));
+        assert_eq!(parse_rfc3339("1969-12-31T23:59:59Z"), Some(-1));
+        assert_eq!(parse_rfc3339("2000-02-29"), None);
+        assert_eq!(parse_rfc3339("29/02/2000 00:00:00"), None);
+    }
+
+    #[cfg(any(feature = "onepux", feature = "opvault"))]
+    #[test]
+    fn formats_timestamps() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(-1), "1969-12-31T23:59:59Z");
+    }
+}
@@ -0,0 +1,392 @@
+//! Mirroring the documents of a vault in a local directory.
+//!
+//! `OpSession::sync_documents` downloads every document of a vault into a
+//! directory and remembers what it downloaded in a manifest file there, so
+//! later runs only download documents that changed in 1Password. With
+//! `SyncOptions::upload`, files edited locally since the last run are
+//! uploaded back instead.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use one_password::Op;
+//! use one_password::sync::SyncOptions;
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let report = session.sync_documents("Ops", "/srv/ops-documents", &SyncOptions::new().concurrency(4)).unwrap();
+//! for failure in &report.failed {
+//!     eprintln!("{}: {}", failure.title, failure.error);
+//! }
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, DirBuilder, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde_json;
+
+use document::DocumentMeta;
+use pool;
+use super::{Error, ItemSummary, OpSession, Result};
+
+/// Name of the manifest kept in the target directory.
+pub const MANIFEST: &str = ".1password-sync.json";
+
+/// What the last sync knew about a document and its local file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    id: String,
+    file_name: String,
+    updated_at: Option<String>,
+    size: u64,
+    /// Modification time of the local file in nanoseconds since the Unix epoch.
+    modified: u64,
+}
+
+/// Size and modification time of `path`, if it exists.
+/// Create `dir` and any missing parents, readable only by the current user.
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)
+}
+
+/// Create the temporary file `path`, readable only by the current user, in
+/// place of any left over from an interrupted sync.
+fn create_part(path: &Path) -> io::Result<File> {
+    match fs::remove_file(path) {
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {},
+        result => result?,
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+fn stat(path: &Path) -> io::Result<Option<(u64, u64)>> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+    Ok(Some((metadata.len(), modified)))
+}
+
+/// A file name for a document that can't escape the target directory.
+fn safe_file_name(name: Option<&str>, id: &str) -> String {
+    match name.and_then(|n| Path::new(n).file_name()).and_then(|n| n.to_str()) {
+        Some(name) if name != MANIFEST && !name.starts_with('.') => name.to_owned(),
+        _ => id.to_owned(),
+    }
+}
+
+/// Options for `OpSession::sync_documents`.
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    concurrency: usize,
+    upload: bool,
+}
+
+impl Default for SyncOptions {
+    fn default() -> SyncOptions {
+        SyncOptions { concurrency: 1, upload: false }
+    }
+}
+
+impl SyncOptions {
+    pub fn new() -> SyncOptions {
+        SyncOptions::default()
+    }
+
+    /// Number of documents to transfer at the same time. Defaults to 1.
+    ///
+    /// The total number of `op` processes is still limited by `set_max_concurrency`.
+    pub fn concurrency(mut self, concurrency: usize) -> SyncOptions {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Upload files changed locally since the last sync instead of replacing
+    /// them with the version in 1Password. Defaults to false. Needs `op` 2.x.
+    pub fn upload(mut self, upload: bool) -> SyncOptions {
+        self.upload = upload;
+        self
+    }
+}
+
+/// A document that could not be synced.
+#[derive(Debug)]
+pub struct SyncFailure {
+    pub id: String,
+    pub title: String,
+    pub error: Error,
+}
+
+/// Result of `OpSession::sync_documents`. Paths are in the target directory.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub downloaded: Vec<PathBuf>,
+    pub uploaded: Vec<PathBuf>,
+    pub unchanged: Vec<PathBuf>,
+    pub failed: Vec<SyncFailure>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Download,
+    Upload,
+    Keep,
+    Conflict,
+}
+
+/// What to do with a document, decided before anything is transferred.
+struct Planned<'a> {
+    document: &'a ItemSummary,
+    /// Entry of the last sync, if the document was synced before.
+    previous: Option<Entry>,
+    action: Action,
+    file_name: String,
+    meta: DocumentMeta,
+}
+
+impl OpSession {
+    /// Download all documents of `vault` into `target_dir`, creating it if
+    /// needed. New directories and files are only readable by the current user.
+    ///
+    /// Documents whose item hasn't changed since the last sync and whose local
+    /// file is untouched are skipped. Files edited locally are replaced,
+    /// unless `options` enables uploading. A document changed both locally and
+    /// in 1Password is left alone and reported as failed. Files of documents
+    /// deleted from the vault are kept.
+    ///
+    /// Downloads go to a temporary file that replaces the old one only after
+    /// its size was verified. A failing document doesn't stop the sync; it is
+    /// listed in the report instead.
+    pub fn sync_documents<P: AsRef<Path>>(&self, vault: &str, target_dir: P, options: &SyncOptions) -> Result<SyncReport> {
+        let dir = target_dir.as_ref();
+        create_private_dir(dir)?;
+        let manifest_path = dir.join(MANIFEST);
+        let mut known : HashMap<String, Entry> = match File::open(&manifest_path) {
+            Ok(file) => {
+                let entries : Vec<Entry> = serde_json::from_reader(file)?;
+                entries.into_iter().map(|e| (e.id.clone(), e)).collect()
+            },
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let mut documents = self.list_items_in(Some(vault))?;
+        documents.retain(|d| d.category == "DOCUMENT");
+
+        let mut decided = Vec::with_capacity(documents.len());
+        for document in &documents {
+            let previous = known.remove(&document.id);
+            let action = match previous {
+                None => Action::Download,
+                Some(ref entry) => {
+                    let local = stat(&dir.join(&entry.file_name))?;
+                    let edited = local.is_some_and(|l| l != (entry.size, entry.modified));
+                    let remote_changed = document.updated_at != entry.updated_at;
+                    match (edited, remote_changed) {
+                        (true, true) if options.upload => Action::Conflict,
+                        (true, false) if options.upload => Action::Upload,
+                        (false, false) if local.is_some() => Action::Keep,
+                        _ => Action::Download,
+                    }
+                },
+            };
+            decided.push((document, previous, action));
+        }
+
+        let metas = pool::map_concurrent(&decided, options.concurrency, |_, &(document, _, action)| {
+            if action == Action::Download {
+                self.document_meta(&document.id, Some(vault))
+            } else {
+                Ok(DocumentMeta::default())
+            }
+        });
+
+        let mut report = SyncReport::default();
+        let mut entries = Vec::with_capacity(documents.len());
+        // Names are assigned before anything is downloaded, so two documents
+        // with the same name don't overwrite each other, nor the files of
+        // documents deleted from the vault.
+        let mut taken : HashSet<String> = decided.iter()
+            .filter_map(|(_, previous, _)| previous.as_ref())
+            .chain(known.values())
+            .map(|e| e.file_name.clone())
+            .collect();
+        let mut plan = Vec::with_capacity(documents.len());
+        for ((document, previous, action), meta) in decided.into_iter().zip(metas) {
+            let meta = match meta {
+                Ok(meta) => meta,
+                Err(error) => {
+                    report.failed.push(SyncFailure { id: document.id.clone(), title: document.title.clone(), error });
+                    entries.extend(previous);
+                    continue;
+                },
+            };
+            let file_name = match previous {
+                Some(ref entry) => entry.file_name.clone(),
+                None => {
+                    let mut name = safe_file_name(meta.file_name.as_ref().map(|n| &n[..]), &document.id);
+                    if !taken.insert(name.clone()) {
+                        name = format!("{}-{}", document.id, name);
+                        taken.insert(name.clone());
+                    }
+                    name
+                },
+            };
+            plan.push(Planned { document, previous, action, file_name, meta });
+        }
+
+        let results = pool::map_concurrent(&plan, options.concurrency, |_, planned| self.sync_document(planned, vault, dir));
+        for (result, planned) in results.into_iter().zip(plan) {
+            match result {
+                Ok(entry) => {
+                    let path = dir.join(&entry.file_name);
+                    match planned.action {
+                        Action::Download => report.downloaded.push(path),
+                        Action::Upload => report.uploaded.push(path),
+                        Action::Keep | Action::Conflict => report.unchanged.push(path),
+                    }
+                    entries.push(entry);
+                },
+                Err(error) => {
+                    report.failed.push(SyncFailure {
+                        id: planned.document.id.clone(),
+                        title: planned.document.title.clone(),
+                        error,
+                    });
+                    // Keep the entry so the document is retried under the same name.
+                    entries.extend(planned.previous);
+                },
+            }
+        }
+
+        // Uploading changed the items, so record their new update times.
+        if !report.uploaded.is_empty() {
+            let updated : HashMap<String, Option<String>> = self.list_items_in(Some(vault))?
+                .into_iter().map(|d| (d.id, d.updated_at)).collect();
+            for entry in &mut entries {
+                if let Some(updated_at) = updated.get(&entry.id) {
+                    entry.updated_at = updated_at.clone();
+                }
+            }
+        }
+        // Files of deleted documents stay, so keep their names reserved.
+        entries.extend(known.into_values());
+        let tmp = dir.join(format!("{}.part", MANIFEST));
+        serde_json::to_writer_pretty(create_part(&tmp)?, &entries)?;
+        fs::rename(&tmp, &manifest_path)?;
+        Ok(report)
+    }
+
+    /// Carry out `planned` and return the new manifest entry of the document.
+    fn sync_document(&self, planned: &Planned, vault: &str, dir: &Path) -> Result<Entry> {
+        let document = planned.document;
+        let path = dir.join(&planned.file_name);
+        match planned.action {
+            Action::Keep => return Ok(planned.previous.clone().expect("kept documents were synced before")),
+            Action::Conflict => bail!("{} was changed both locally and in 1Password", planned.file_name),
+            Action::Upload => {
+                self.update_document(&document.id, &planned.file_name, File::open(&path)?, Some(vault))?;
+            },
+            Action::Download => {
+                let part = dir.join(format!(".{}.part", planned.file_name));
+                let result = create_part(&part).map_err(Error::from)
+                    .and_then(|file| self.download_verified(&document.id, Some(vault), &planned.meta, file, |_| ()));
+                if let Err(error) = result {
+                    let _ = fs::remove_file(&part);
+                    return Err(error);
+                }
+                fs::rename(&part, &path)?;
+            },
+        }
+        let (size, modified) = stat(&path)?.unwrap_or((0, 0));
+        Ok(Entry {
+            id: document.id.clone(),
+            file_name: planned.file_name.clone(),
+            updated_at: document.updated_at.clone(),
+            size,
+            modified,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use cassette::{interaction, replay_op, Interaction};
+    use Op;
+
+    fn op(interactions: Vec<Interaction>) -> Op {
+        replay_op(interactions)
+    }
+
+    const LIST: &str = r#"[
+        {"id": "d1", "title": "Config", "category": "DOCUMENT", "vault": {"id": "v"}, "updated_at": "2024-01-01T00:00:00Z"},
+        {"id": "l1", "title": "Login", "category": "LOGIN", "vault": {"id": "v"}}
+    ]"#;
+
+    #[test]
+    fn syncs_documents() {
+        let dir = env::temp_dir().join(format!("op-sync-{}", ::std::process::id()));
+        let list = || interaction("item list", &["--session=⟨redacted⟩", "--vault", "Ops", "--format", "json", "--no-color", "--iso-timestamps"], LIST);
+
+        let first = op(vec![
+            list(),
+            interaction("item get", &["--session=⟨redacted⟩", "--vault", "Ops", "d1", "--format", "json", "--no-color", "--iso-timestamps"],
+                r#"{"id": "d1", "files": [{"id": "f", "name": "../app.conf", "size": 6}]}"#),
+            interaction("document get", &["--session=⟨redacted⟩", "--vault", "Ops", "d1"], "a = 1\n"),
+        ]);
+        let report = first.session("token").sync_documents("Ops", &dir, &SyncOptions::new()).unwrap();
+        assert_eq!(report.downloaded, vec![dir.join("app.conf")]);
+        assert!(report.failed.is_empty());
+        assert_eq!(fs::read_to_string(dir.join("app.conf")).unwrap(), "a = 1\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&dir), 0o700);
+            assert_eq!(mode(&dir.join("app.conf")), 0o600);
+            assert_eq!(mode(&dir.join(MANIFEST)), 0o600);
+        }
+
+        let second = op(vec![list()]);
+        let report = second.session("token").sync_documents("Ops", &dir, &SyncOptions::new()).unwrap();
+        assert_eq!(report.unchanged, vec![dir.join("app.conf")]);
+
+        fs::write(dir.join("app.conf"), "a = 2\n").unwrap();
+        let third = op(vec![
+            list(),
+            interaction("document edit", &["--session=⟨redacted⟩", "--vault", "Ops", "d1", "-", "--file-name", "app.conf"], ""),
+            list(),
+        ]);
+        let report = third.session("token").sync_documents("Ops", &dir, &SyncOptions::new().upload(true)).unwrap();
+        assert_eq!(report.uploaded, vec![dir.join("app.conf")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sanitizes_file_names() {
+        assert_eq!(safe_file_name(Some("../../etc/passwd"), "id"), "passwd");
+        assert_eq!(safe_file_name(Some(".."), "id"), "id");
+        assert_eq!(safe_file_name(Some(MANIFEST), "id"), "id");
+        assert_eq!(safe_file_name(None, "id"), "id");
+    }
+}
@@ -0,0 +1,140 @@
+//! Credential files for systemd units using `LoadCredential=`.
+//!
+//! Each secret is written to its own file, readable only by the owner, so a
+//! unit can load it with e.g. `LoadCredential=db-password:/run/staging/db-password`
+//! and read it from `$CREDENTIALS_DIRECTORY/db-password`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # extern crate one_password;
+//! use std::collections::BTreeMap;
+//! use one_password::Op;
+//! use one_password::systemd;
+//!
+//! let session = Op::which().unwrap().env_session().unwrap();
+//! let mut references = BTreeMap::new();
+//! references.insert("db-password".to_owned(), "op://Production/Database/password".to_owned());
+//! let files = systemd::write_credentials(&session, "/run/staging", &references).unwrap();
+//! // ... start the unit, then
+//! files.remove().unwrap();
+//! ```
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::{ErrorKind, OpSession, Result, Secret};
+
+/// Files written by `write_credentials`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use = "credential files stay on disk until removed"]
+pub struct CredentialFiles {
+    pub paths: Vec<PathBuf>,
+}
+
+impl CredentialFiles {
+    /// Delete the files. Files that are already gone are ignored.
+    pub fn remove(self) -> Result<()> {
+        remove_all(&self.paths)?;
+        Ok(())
+    }
+}
+
+fn remove_all(paths: &[PathBuf]) -> io::Result<()> {
+    for path in paths {
+        match fs::remove_file(path) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {},
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\', '\0'])
+}
+
+#[cfg(unix)]
+fn create_private(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)
+}
+
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+/// Write `value` to `path` by way of a temporary file in the same directory,
+/// so readers never see a partially written file.
+fn write_atomic(path: &Path, value: &Secret<String>) -> io::Result<()> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("credential");
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name, ::std::process::id()));
+    let result = create_private(&temp).and_then(|mut file| {
+        file.write_all(value.expose().as_bytes())?;
+        file.sync_all()
+    }).and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Read every reference in `references` with `OpSession::read` and write it
+/// to a file in `dir` named after the credential it is mapped from.
+///
+/// All references are read before anything is written. If writing fails,
+/// files written so far are removed again. Existing files are replaced.
+pub fn write_credentials<P: AsRef<Path>>(session: &OpSession, dir: P, references: &BTreeMap<String, String>) -> Result<CredentialFiles> {
+    let mut values = Vec::new();
+    for (name, reference) in references {
+        if !valid_name(name) {
+            return Err(ErrorKind::InvalidCredentialName(name.clone()).into());
+        }
+        values.push((dir.as_ref().join(name), session.read(reference)?));
+    }
+    let mut paths = Vec::new();
+    for (path, value) in values {
+        if let Err(err) = write_atomic(&path, &value) {
+            let _ = remove_all(&paths);
+            return Err(err.into());
+        }
+        paths.push(path);
+    }
+    Ok(CredentialFiles { paths })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use cassette::{read_interaction, replay_op};
+
+    #[test]
+    fn writes_private_files() {
+        let read = read_interaction("op://Prod/DB/password", "hunter2");
+        let op = replay_op(vec![read]);
+        let dir = env::temp_dir().join(format!("op-systemd-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut references = BTreeMap::new();
+        references.insert("db-password".to_owned(), "op://Prod/DB/password".to_owned());
+
+        let files = write_credentials(&op.session("token"), &dir, &references).unwrap();
+        let path = dir.join("db-password");
+        assert_eq!(files.paths, vec![path.clone()]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hunter2");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        files.remove().unwrap();
+        assert!(!path.exists());
+
+        references.insert("../escape".to_owned(), "op://Prod/DB/password".to_owned());
+        assert!(write_credentials(&op.session("token"), &dir, &references).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,202 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use super::{Error, ErrorKind, ItemSummary, OpItem, Result};
+#[cfg(feature = "process")]
+use edit::ItemEdit;
+#[cfg(feature = "process")]
+use pool;
+#[cfg(feature = "process")]
+use super::{max_concurrency, OpSession};
+
+/// Normalize the spelling of a tag: trim each `/`-separated level, collapse
+/// runs of whitespace and drop empty levels.
+fn normalize(tag: &str) -> String {
+    tag.split('/')
+        .map(|level| level.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|level| !level.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// What tags are compared by: 1Password treats tags that only differ in case as one.
+pub(crate) fn key(tag: &str) -> String {
+    normalize(tag).to_lowercase()
+}
+
+/// A tag of an item, e.g. `web` or the nested `Work/AWS`.
+///
+/// Tags are normalized when parsed, so ` Work / AWS ` becomes `Work/AWS`, and
+/// compare without regard to case, so `work/aws` is the same tag. The
+/// spelling is kept for display.
+#[derive(Debug, Clone)]
+pub struct Tag(String);
+
+impl Tag {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether this is `parent` or a tag nested under it, e.g. `Work/AWS`
+    /// under `Work`.
+    pub fn is_within(&self, parent: &Tag) -> bool {
+        let (tag, parent) = (key(&self.0), key(&parent.0));
+        tag == parent || tag.starts_with(&format!("{}/", parent))
+    }
+
+    /// This tag with `from` replaced by `to`, keeping any nested levels, or
+    /// `None` if it isn't within `from`.
+    pub fn renamed(&self, from: &Tag, to: &Tag) -> Option<Tag> {
+        if !self.is_within(from) {
+            return None;
+        }
+        let levels = from.0.split('/').count();
+        let nested : Vec<&str> = self.0.split('/').skip(levels).collect();
+        Some(Tag(if nested.is_empty() { to.0.clone() } else { format!("{}/{}", to.0, nested.join("/")) }))
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Tag {
+    type Err = Error;
+
+    /// Normalize `s`. Fails if nothing is left, or if it contains a comma,
+    /// which `op` would read as a separator between tags.
+    fn from_str(s: &str) -> Result<Tag> {
+        let tag = normalize(s);
+        if tag.is_empty() || tag.contains(',') {
+            bail!(ErrorKind::InvalidTag(s.to_owned()));
+        }
+        Ok(Tag(tag))
+    }
+}
+
+impl PartialEq for Tag {
+    fn eq(&self, other: &Tag) -> bool {
+        key(&self.0) == key(&other.0)
+    }
+}
+
+impl Eq for Tag {}
+
+impl Hash for Tag {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        key(&self.0).hash(state)
+    }
+}
+
+impl PartialOrd for Tag {
+    fn partial_cmp(&self, other: &Tag) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Tag {
+    fn cmp(&self, other: &Tag) -> Ordering {
+        key(&self.0).cmp(&key(&other.0))
+    }
+}
+
+/// Parse `tags` as `op` printed them, skipping any that aren't valid tags.
+fn parse_all<'a, I: IntoIterator<Item = &'a str>>(tags: I) -> Vec<Tag> {
+    tags.into_iter().filter_map(|t| t.parse().ok()).collect()
+}
+
+impl OpItem {
+    /// Tags of the item.
+    pub fn tags(&self) -> Vec<Tag> {
+        let tags = self.overview.extra.get("tags").and_then(|t| t.as_array());
+        parse_all(tags.into_iter().flatten().filter_map(|t| t.as_str()))
+    }
+}
+
+impl ItemSummary {
+    /// Whether the item has `tag` or a tag nested under it.
+    pub fn is_tagged(&self, tag: &Tag) -> bool {
+        parse_all(self.tags.iter().map(|t| &t[..])).iter().any(|t| t.is_within(tag))
+    }
+}
+
+#[cfg(feature = "process")]
+impl OpSession {
+    /// List the items in `vault`, or the default vault if `None`, that have
+    /// `tag` or a tag nested under it.
+    ///
+    /// Items are filtered as `op` lists them, so only matches are kept in memory.
+    pub fn list_items_tagged(&self, tag: &Tag, vault: Option<&str>) -> Result<Vec<ItemSummary>> {
+        let mut items = Vec::new();
+        self.for_each_item_in(vault, |item| if item.is_tagged(tag) {
+            items.push(item);
+        })?;
+        Ok(items)
+    }
+
+    /// Rename the tag `from` to `to` on every item in `vault`, or the default
+    /// vault if `None`, including tags nested under it: renaming `Work` to
+    /// `Job` turns `Work/AWS` into `Job/AWS`.
+    ///
+    /// Items are edited concurrently. Returns the result for each item that
+    /// had the tag; a failure doesn't stop the other edits.
+    pub fn rename_tag(&self, from: &Tag, to: &Tag, vault: Option<&str>) -> Result<Vec<Result<ItemSummary>>> {
+        let items = self.list_items_tagged(from, vault)?;
+        Ok(pool::map_concurrent(&items, max_concurrency(), |_, item| {
+            let mut tags : Vec<Tag> = Vec::new();
+            for tag in parse_all(item.tags.iter().map(|t| &t[..])) {
+                let tag = tag.renamed(from, to).unwrap_or(tag);
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            let tags : Vec<&str> = tags.iter().map(Tag::as_str).collect();
+            self.edit_item_in(&item.id, &ItemEdit::new().tags(&tags), Some(&item.vault_id))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(s: &str) -> Tag {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn normalizes() {
+        assert_eq!(tag("  Work /  AWS  prod/ ").as_str(), "Work/AWS prod");
+        assert_eq!(tag("Work/AWS"), tag("work/aws"));
+        assert!("".parse::<Tag>().is_err());
+        assert!(" / ".parse::<Tag>().is_err());
+        assert!("a,b".parse::<Tag>().is_err());
+        assert!(tag("Work/AWS").is_within(&tag("work")));
+        assert!(!tag("Workshop").is_within(&tag("Work")));
+        assert_eq!(tag("work/AWS").renamed(&tag("Work"), &tag("Job/Old")).unwrap().as_str(), "Job/Old/AWS");
+        assert_eq!(tag("Home").renamed(&tag("Work"), &tag("Job")), None);
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn renames_tag() {
+        use cassette::{interaction, replay_op};
+
+        let list = r#"[{"id": "a", "title": "A", "vault": {"id": "v"}, "tags": ["web", "work/aws"]},
+            {"id": "b", "title": "B", "vault": {"id": "v"}, "tags": ["home"]}]"#;
+        let op = replay_op(vec![
+            interaction("item list", &["--session=⟨redacted⟩", "--vault", "v", "--format", "json", "--no-color", "--iso-timestamps"],
+                list),
+            interaction("item edit", &["--session=⟨redacted⟩", "--vault", "v", "a", "--format", "json", "--no-color", "--iso-timestamps",
+                "--tags=web,Job/aws"],
+                r#"{"id": "a", "title": "A", "vault": {"id": "v"}, "tags": ["web", "Job/aws"]}"#),
+        ]);
+        let renamed = op.session("token").rename_tag(&tag("Work"), &tag("Job"), Some("v")).unwrap();
+        assert_eq!(renamed.len(), 1);
+        assert_eq!(renamed[0].as_ref().unwrap().tags, vec!["web", "Job/aws"]);
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(feature = "process")]
+use super::{ErrorKind, OpSession, Result};
+
+/// A member of the account, as returned by `OpSession::resolve_user`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OpUser {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    /// E.g. `ACTIVE` or `SUSPENDED`. Only reported by `op` 2.x.
+    pub state: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct V2User {
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    email: String,
+    state: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct V1User {
+    uuid: String,
+    #[serde(default)]
+    first_name: String,
+    #[serde(default)]
+    last_name: String,
+    #[serde(default)]
+    email: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawUser {
+    V2(V2User),
+    V1(V1User),
+}
+
+impl From<RawUser> for OpUser {
+    fn from(raw: RawUser) -> OpUser {
+        match raw {
+            RawUser::V2(u) => OpUser { id: u.id, name: u.name, email: u.email, state: u.state },
+            RawUser::V1(u) => OpUser {
+                id: u.uuid,
+                name: format!("{} {}", u.first_name, u.last_name).trim().to_owned(),
+                email: u.email,
+                state: None,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+impl OpSession {
+    /// Look up the user with `uuid`, e.g. the `last_edited_by` of an item, to
+    /// report who last touched it.
+    ///
+    /// This calls `op user get --format json`, or `op get user` with `op` 1.x.
+    pub fn resolve_user(&self, uuid: &str) -> Result<OpUser> {
+        let subcommand : &[&str] = if self.config.cli_version()?.is_v2() {
+            &["user", "get"]
+        } else {
+            &["get", "user"]
+        };
+        let raw : RawUser = self.command(subcommand)
+            .target(uuid).json()?
+            .run_json(|d| ErrorKind::GetCommand(uuid.to_owned(), d))?;
+        Ok(raw.into())
+    }
+}
+
+#[cfg(all(test, feature = "process"))]
+mod tests {
+    use cassette::{interaction, replay_op, Cassette, Replay};
+    use {CliVersion, Op, OpUser};
+
+    #[test]
+    fn resolves_users() {
+        let jane = OpUser {
+            id: "U1".to_owned(),
+            name: "Jane Doe".to_owned(),
+            email: "jane@example.com".to_owned(),
+            state: Some("ACTIVE".to_owned()),
+        };
+
+        let op = replay_op(vec![interaction("user get", &["--session=⟨redacted⟩", "U1", "--format", "json", "--no-color", "--iso-timestamps"],
+            r#"{"id": "U1", "name": "Jane Doe", "email": "jane@example.com", "type": "MEMBER", "state": "ACTIVE"}"#)]);
+        assert_eq!(op.session("token").resolve_user("U1").unwrap(), jane);
+
+        let v1 = Cassette { interactions: vec![interaction("get user", &["--session=⟨redacted⟩", "U1"],
+            r#"{"uuid": "U1", "firstName": "Jane", "lastName": "Doe", "email": "jane@example.com"}"#)] };
+        let op = Op::new("op").with_backend(Replay::new(v1)).with_cli_version(CliVersion::new(1, 12, 0));
+        assert_eq!(op.session("token").resolve_user("U1").unwrap(), OpUser { state: None, ..jane });
+    }
+}
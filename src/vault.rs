@@ -0,0 +1,188 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "process")]
+use super::{ErrorKind, OpSession, Result};
+
+/// Who a vault belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum VaultType {
+    /// The built-in vault of an individual or family account.
+    Personal,
+    /// The built-in vault of a member of a team or business account.
+    Private,
+    /// The built-in vault shared with everyone in a team or business account.
+    Everyone,
+    /// A vault created by a user and shared with whoever was given access.
+    Shared,
+    /// A type this crate doesn't know, as reported by `op`.
+    Other(String),
+}
+
+impl VaultType {
+    fn parse(s: &str) -> VaultType {
+        match s {
+            "PERSONAL" | "P" => VaultType::Personal,
+            "PRIVATE" => VaultType::Private,
+            "EVERYONE" | "E" => VaultType::Everyone,
+            "USER_CREATED" | "U" => VaultType::Shared,
+            other => VaultType::Other(other.to_owned()),
+        }
+    }
+
+    /// The type in `op` 2.x spelling, e.g. `USER_CREATED`.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            VaultType::Personal => "PERSONAL",
+            VaultType::Private => "PRIVATE",
+            VaultType::Everyone => "EVERYONE",
+            VaultType::Shared => "USER_CREATED",
+            VaultType::Other(ref other) => other,
+        }
+    }
+}
+
+impl fmt::Display for VaultType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for VaultType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for VaultType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<VaultType, D::Error> {
+        Ok(VaultType::parse(&String::deserialize(deserializer)?))
+    }
+}
+
+/// A vault, as returned by `OpSession::get_vault`.
+///
+/// `op` 1.x only reports the id, name, description and type.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VaultDetails {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub vault_type: VaultType,
+    /// Number of items in the vault.
+    pub items: Option<u64>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    /// Incremented whenever an item in the vault changes.
+    pub content_version: Option<u64>,
+    /// Incremented whenever the name, description or other attributes of the vault change.
+    pub attribute_version: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct V2Vault {
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(rename = "type")]
+    vault_type: VaultType,
+    items: Option<u64>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    content_version: Option<u64>,
+    attribute_version: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct V1Vault {
+    uuid: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    desc: String,
+    #[serde(rename = "type")]
+    vault_type: VaultType,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawVault {
+    V2(V2Vault),
+    V1(V1Vault),
+}
+
+impl From<RawVault> for VaultDetails {
+    fn from(raw: RawVault) -> VaultDetails {
+        match raw {
+            RawVault::V2(v) => VaultDetails {
+                id: v.id,
+                name: v.name,
+                description: v.description,
+                vault_type: v.vault_type,
+                items: v.items,
+                created_at: v.created_at,
+                updated_at: v.updated_at,
+                content_version: v.content_version,
+                attribute_version: v.attribute_version,
+            },
+            RawVault::V1(v) => VaultDetails {
+                id: v.uuid,
+                name: v.name,
+                description: v.desc,
+                vault_type: v.vault_type,
+                items: None,
+                created_at: None,
+                updated_at: None,
+                content_version: None,
+                attribute_version: None,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "process")]
+impl OpSession {
+    /// Get the details of `vault`, by name or id.
+    ///
+    /// This calls `op vault get --format json`, or `op get vault` with `op` 1.x.
+    pub fn get_vault(&self, vault: &str) -> Result<VaultDetails> {
+        let subcommand : &[&str] = if self.config.cli_version()?.is_v2() {
+            &["vault", "get"]
+        } else {
+            &["get", "vault"]
+        };
+        let raw : RawVault = self.command(subcommand)
+            .target(vault).json()?
+            .run_json(|d| ErrorKind::GetCommand(vault.to_owned(), d))?;
+        Ok(raw.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    #[test]
+    fn parses_both_versions() {
+        let v2 : RawVault = serde_json::from_str(r#"{"id": "v1", "name": "Ops", "content_version": 42,
+            "attribute_version": 2, "items": 12, "type": "USER_CREATED",
+            "created_at": "2021-02-26T00:22:36Z", "updated_at": "2023-01-01T00:00:00Z"}"#).unwrap();
+        let v2 = VaultDetails::from(v2);
+        assert_eq!(v2.vault_type, VaultType::Shared);
+        assert_eq!(v2.items, Some(12));
+        assert_eq!(v2.description, "");
+
+        let v1 : RawVault = serde_json::from_str(r#"{"uuid": "v1", "name": "Ops", "desc": "Operations",
+            "avatar": "", "type": "U"}"#).unwrap();
+        let v1 = VaultDetails::from(v1);
+        assert_eq!(v1.id, "v1");
+        assert_eq!(v1.vault_type, VaultType::Shared);
+        assert_eq!(v1.items, None);
+        assert_eq!(VaultType::parse("FUTURE").as_str(), "FUTURE");
+    }
+}
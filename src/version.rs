@@ -0,0 +1,67 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Error, ErrorKind};
+
+/// Version of the `op` command line tool, used to pick between the syntax of
+/// different major versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CliVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl CliVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> CliVersion {
+        CliVersion { major, minor, patch }
+    }
+
+    /// Whether this is `op` 2.x or later, which uses `op item get` style commands
+    /// instead of `op get item`.
+    pub fn is_v2(&self) -> bool {
+        self.major >= 2
+    }
+}
+
+impl fmt::Display for CliVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for CliVersion {
+    type Err = Error;
+
+    /// Parse the output of `op --version`, e.g. `2.24.0` or `1.12.4-beta.1`.
+    fn from_str(s: &str) -> Result<CliVersion, Error> {
+        let s = s.trim();
+        let core = s.trim_start_matches('v').split(['-', '+']).next().unwrap_or("");
+        let mut parts = core.split('.').map(|p| p.parse::<u32>());
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), patch) => {
+                let patch = match patch {
+                    Some(Ok(p)) => p,
+                    None => 0,
+                    Some(Err(_)) => return Err(ErrorKind::InvalidVersion(s.to_owned()).into()),
+                };
+                Ok(CliVersion::new(major, minor, patch))
+            },
+            _ => Err(ErrorKind::InvalidVersion(s.to_owned()).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("2.24.0\n".parse::<CliVersion>().unwrap(), CliVersion::new(2, 24, 0));
+        assert_eq!("1.12.4-beta.1".parse::<CliVersion>().unwrap(), CliVersion::new(1, 12, 4));
+        assert_eq!("0.5".parse::<CliVersion>().unwrap(), CliVersion::new(0, 5, 0));
+        assert!("op".parse::<CliVersion>().is_err());
+        assert!(CliVersion::new(2, 0, 0) > CliVersion::new(1, 12, 4));
+    }
+}
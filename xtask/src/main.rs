@@ -0,0 +1,443 @@
+//! Development tasks for `one_password`.
+//!
+//! ```text
+//! cargo xtask codegen          regenerate src/models.rs from samples/op
+//! cargo xtask codegen --check  fail if src/models.rs is out of date
+//! ```
+//!
+//! `samples/op` has a directory per `op` version, e.g. `samples/op/2.24.0`,
+//! holding items as printed by `op item get --format json` (or `op get item`
+//! for 1.x). Replace secrets with made up values before adding a sample.
+extern crate serde_json;
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use serde_json::Value;
+
+/// Where the samples are, relative to the workspace root.
+const SAMPLES: &str = "samples/op";
+
+/// Where the models are written, relative to the workspace root.
+const OUTPUT: &str = "src/models.rs";
+
+/// Keys whose values are secrets, hidden by the `Debug` impls of the models.
+const SECRET_KEYS: &[&str] = &["value", "password", "v", "totp", "notesPlain"];
+
+/// Constants listing the distinct values seen at a path, e.g. the categories.
+const VALUE_SETS: &[(&str, &str, &str)] = &[
+    ("CATEGORIES", "category", "Categories of the sampled items."),
+    ("TEMPLATE_UUIDS", "templateUuid", "Template UUIDs of the sampled items."),
+    ("FIELD_TYPES", "fields[].type", "Types of the fields of the sampled items."),
+    ("FIELD_PURPOSES", "fields[].purpose", "Purposes of the fields of the sampled items."),
+];
+
+/// Keys modeled with a type of the crate instead of the inferred one, by
+/// struct and key.
+const TYPED_KEYS: &[(&str, &str, &str)] = &[
+    ("Field", "purpose", "::FieldPurpose"),
+];
+
+const KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for", "if",
+    "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "static",
+    "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+];
+
+/// Shape of the JSON values seen at one place in the samples.
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    /// Only `null` was seen.
+    Null,
+    Bool,
+    Int,
+    Float,
+    Str,
+    Array(Box<Shape>),
+    Object(Object),
+    /// Conflicting shapes, kept as a `serde_json::Value`.
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Object {
+    /// How many objects were merged into this one.
+    seen: usize,
+    keys: Vec<(String, Key)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Key {
+    shape: Shape,
+    /// In how many of the objects the key was present.
+    present: usize,
+    nullable: bool,
+}
+
+fn infer(value: &Value) -> Shape {
+    match *value {
+        Value::Null => Shape::Null,
+        Value::Bool(_) => Shape::Bool,
+        Value::Number(ref n) if n.is_f64() => Shape::Float,
+        Value::Number(_) => Shape::Int,
+        Value::String(_) => Shape::Str,
+        Value::Array(ref items) => Shape::Array(Box::new(items.iter().map(infer).fold(Shape::Null, merge))),
+        Value::Object(ref map) => {
+            let keys = map.iter()
+                .map(|(k, v)| (k.clone(), Key { shape: infer(v), present: 1, nullable: v.is_null() }))
+                .collect();
+            Shape::Object(Object { seen: 1, keys })
+        },
+    }
+}
+
+fn merge(a: Shape, b: Shape) -> Shape {
+    match (a, b) {
+        (Shape::Null, other) | (other, Shape::Null) => other,
+        (Shape::Int, Shape::Float) | (Shape::Float, Shape::Int) => Shape::Float,
+        (Shape::Array(a), Shape::Array(b)) => Shape::Array(Box::new(merge(*a, *b))),
+        (Shape::Object(a), Shape::Object(b)) => Shape::Object(merge_objects(a, b)),
+        (a, b) => if a == b { a } else { Shape::Any },
+    }
+}
+
+fn merge_objects(mut a: Object, b: Object) -> Object {
+    a.seen += b.seen;
+    for (name, key) in b.keys {
+        match a.keys.iter().position(|(ref n, _)| *n == name) {
+            Some(i) => {
+                let existing = a.keys.remove(i).1;
+                a.keys.insert(i, (name, Key {
+                    shape: merge(existing.shape, key.shape),
+                    present: existing.present + key.present,
+                    nullable: existing.nullable || key.nullable,
+                }));
+            },
+            None => a.keys.push((name, key)),
+        }
+    }
+    a
+}
+
+/// `fieldType` or `field type` to `field_type`, keeping Rust keywords usable.
+fn snake_case(key: &str) -> String {
+    let mut ident = String::new();
+    let mut previous = '_';
+    for c in key.chars() {
+        if c.is_uppercase() && (previous.is_lowercase() || previous.is_ascii_digit()) {
+            ident.push('_');
+        }
+        ident.push(if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' });
+        previous = c;
+    }
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&&ident[..]) {
+        ident.insert_str(0, "r#");
+    }
+    ident
+}
+
+/// `password_details` to `PasswordDetails`, keeping inner capitals.
+fn pascal_case(key: &str) -> String {
+    key.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            let mut word : String = Some(first).into_iter().chain(chars).collect();
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                word.insert(0, '_');
+            }
+            word
+        })
+        .collect()
+}
+
+/// Name of an array element, e.g. `Field` for `fields`.
+fn singular(key: &str) -> &str {
+    if key.len() > 1 && key.ends_with('s') { &key[..key.len() - 1] } else { key }
+}
+
+/// A struct to emit, named after its path from the item.
+struct Model {
+    name: String,
+    object: Object,
+}
+
+fn collect(name: &str, object: &Object, models: &mut Vec<Model>) {
+    models.push(Model { name: name.to_owned(), object: object.clone() });
+    for (key, value) in &object.keys {
+        if let Some((child, object)) = nested(name, key, &value.shape) {
+            collect(&child, object, models);
+        }
+    }
+}
+
+/// The struct for `key` of the struct `parent`, if it holds objects.
+fn nested<'a>(parent: &str, key: &str, shape: &'a Shape) -> Option<(String, &'a Object)> {
+    let prefix = if parent == "Item" { "" } else { parent };
+    match *shape {
+        Shape::Object(ref object) => Some((format!("{}{}", prefix, pascal_case(key)), object)),
+        Shape::Array(ref element) => match **element {
+            Shape::Object(ref object) => Some((format!("{}{}", prefix, pascal_case(singular(key))), object)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn rust_type(parent: &str, key: &str, shape: &Shape) -> String {
+    if let Some((name, _)) = nested(parent, key, shape) {
+        return match *shape {
+            Shape::Array(_) => format!("Vec<{}>", name),
+            _ => name,
+        };
+    }
+    match *shape {
+        Shape::Bool => "bool".to_owned(),
+        Shape::Int => "i64".to_owned(),
+        Shape::Float => "f64".to_owned(),
+        Shape::Str => "String".to_owned(),
+        Shape::Array(ref element) => format!("Vec<{}>", rust_type(parent, key, element)),
+        Shape::Null | Shape::Object(_) | Shape::Any => "Value".to_owned(),
+    }
+}
+
+/// Distinct string values at `path`, e.g. `fields[].type`.
+fn values_at(item: &Value, path: &str, values: &mut BTreeSet<String>) {
+    let (head, rest) = match path.find('.') {
+        Some(i) => (&path[..i], Some(&path[i + 1..])),
+        None => (path, None),
+    };
+    let (key, array) = match head.strip_suffix("[]") {
+        Some(key) => (key, true),
+        None => (head, false),
+    };
+    let value = match item.get(key) {
+        Some(value) => value,
+        None => return,
+    };
+    let children : Vec<&Value> = if array {
+        value.as_array().map(|a| a.iter().collect()).unwrap_or_default()
+    } else {
+        vec![value]
+    };
+    for child in children {
+        match rest {
+            Some(rest) => values_at(child, rest, values),
+            None => if let Some(s) = child.as_str() {
+                values.insert(s.to_owned());
+            },
+        }
+    }
+}
+
+/// Models of the items sampled from one `op` version.
+fn emit_version(out: &mut String, version: &str, samples: &[(PathBuf, Value)]) {
+    let shape = samples.iter().map(|(_, s)| infer(s)).fold(Shape::Null, merge);
+    let object = match shape {
+        Shape::Object(object) => object,
+        _ => panic!("samples for op {} are not objects", version),
+    };
+    let mut models = Vec::new();
+    collect("Item", &object, &mut models);
+    let redacted = models.iter().any(|m| m.object.keys.iter().any(|(k, _)| SECRET_KEYS.contains(&&k[..])));
+
+    writeln!(out, "/// Items as printed by `op` {}.", version).unwrap();
+    writeln!(out, "pub mod v{} {{", version.replace('.', "_")).unwrap();
+    if redacted {
+        writeln!(out, "    use std::fmt;\n").unwrap();
+    }
+    writeln!(out, "    use serde_json::{{Map, Value}};").unwrap();
+    if redacted {
+        writeln!(out, "\n    use secret::Redacted;").unwrap();
+    }
+    for &(name, path, doc) in VALUE_SETS {
+        let mut values = BTreeSet::new();
+        for (_, sample) in samples {
+            values_at(sample, path, &mut values);
+        }
+        if values.is_empty() {
+            continue;
+        }
+        let list : Vec<String> = values.iter().map(|v| format!("{:?}", v)).collect();
+        writeln!(out, "\n    /// {}\n    pub const {}: &[&str] = &[{}];", doc, name, list.join(", ")).unwrap();
+    }
+    for model in &models {
+        emit_model(out, model);
+    }
+    writeln!(out, "}}").unwrap();
+}
+
+fn emit_model(out: &mut String, model: &Model) {
+    let secret = model.object.keys.iter().any(|(k, _)| SECRET_KEYS.contains(&&k[..]));
+    writeln!(out).unwrap();
+    if secret {
+        writeln!(out, "    /// Shows secret values only if `set_debug_reveal` was called.").unwrap();
+        writeln!(out, "    #[derive(Serialize, Deserialize, Clone, PartialEq)]").unwrap();
+    } else {
+        writeln!(out, "    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]").unwrap();
+    }
+    writeln!(out, "    pub struct {} {{", model.name).unwrap();
+    for (key, value) in &model.object.keys {
+        let ident = snake_case(key);
+        let optional = value.present < model.object.seen || value.nullable || value.shape == Shape::Null;
+        let mut attributes = Vec::new();
+        if ident.trim_start_matches("r#") != key {
+            attributes.push(format!("rename = {:?}", key));
+        }
+        if optional {
+            attributes.push("default".to_owned());
+            attributes.push("skip_serializing_if = \"Option::is_none\"".to_owned());
+        }
+        if !attributes.is_empty() {
+            writeln!(out, "        #[serde({})]", attributes.join(", ")).unwrap();
+        }
+        let ty = TYPED_KEYS.iter()
+            .find(|&&(name, k, _)| name == model.name && k == key)
+            .map_or_else(|| rust_type(&model.name, key, &value.shape), |&(_, _, ty)| ty.to_owned());
+        let ty = if optional { format!("Option<{}>", ty) } else { ty };
+        writeln!(out, "        pub {}: {},", ident, ty).unwrap();
+    }
+    writeln!(out, "        /// Properties missing from the samples.").unwrap();
+    writeln!(out, "        #[serde(flatten)]").unwrap();
+    writeln!(out, "        pub extra: Map<String, Value>,").unwrap();
+    writeln!(out, "    }}").unwrap();
+    if !secret {
+        return;
+    }
+    writeln!(out, "\n    impl fmt::Debug for {} {{", model.name).unwrap();
+    writeln!(out, "        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {{").unwrap();
+    writeln!(out, "            f.debug_struct({:?})", model.name).unwrap();
+    for (key, _) in &model.object.keys {
+        let ident = snake_case(key);
+        let field = ident.trim_start_matches("r#");
+        if SECRET_KEYS.contains(&&key[..]) {
+            writeln!(out, "                .field({:?}, &Redacted(&self.{}))", field, ident).unwrap();
+        } else {
+            writeln!(out, "                .field({:?}, &self.{})", field, ident).unwrap();
+        }
+    }
+    writeln!(out, "                .field(\"extra\", &Redacted(&self.extra))").unwrap();
+    writeln!(out, "                .finish()\n        }}\n    }}").unwrap();
+}
+
+fn emit_tests(out: &mut String, versions: &[(String, Vec<(PathBuf, Value)>)], root: &Path) {
+    writeln!(out, "\n#[cfg(test)]\nmod tests {{").unwrap();
+    writeln!(out, "    use serde_json;\n").unwrap();
+    writeln!(out, "    #[test]\n    fn parses_samples() {{").unwrap();
+    for (version, samples) in versions {
+        let module = format!("v{}", version.replace('.', "_"));
+        for (path, _) in samples {
+            let relative = path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/");
+            writeln!(out, "        serde_json::from_str::<super::{}::Item>(include_str!(\"../{}\")).unwrap();", module, relative)
+                .unwrap();
+        }
+    }
+    writeln!(out, "    }}\n}}").unwrap();
+}
+
+/// Samples per version, in version order.
+fn read_samples(root: &Path) -> Vec<(String, Vec<(PathBuf, Value)>)> {
+    let mut versions = Vec::new();
+    let mut dirs : Vec<PathBuf> = fs::read_dir(root.join(SAMPLES))
+        .expect("samples/op exists")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort_by_key(|dir| {
+        let name = dir.file_name().unwrap().to_string_lossy().into_owned();
+        name.split('.').map(|part| part.parse::<u32>().unwrap_or(0)).collect::<Vec<_>>()
+    });
+    for dir in dirs {
+        let mut files : Vec<PathBuf> = fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|e| e == "json"))
+            .collect();
+        files.sort();
+        let samples = files.into_iter().map(|path| {
+            let json = fs::read_to_string(&path).unwrap();
+            let value = serde_json::from_str(&json).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+            (path, value)
+        }).collect();
+        versions.push((dir.file_name().unwrap().to_string_lossy().into_owned(), samples));
+    }
+    versions
+}
+
+fn generate(root: &Path) -> String {
+    let versions = read_samples(root);
+    let mut out = String::new();
+    writeln!(out, "//! Serde models of items, one module per `op` version, generated from the").unwrap();
+    writeln!(out, "//! JSON samples in `samples/op`.").unwrap();
+    writeln!(out, "//!").unwrap();
+    writeln!(out, "//! Don't edit this file, add a sample and run `cargo xtask codegen` instead.").unwrap();
+    writeln!(out, "//! Keys missing from some samples are optional and keys not in any sample").unwrap();
+    writeln!(out, "//! end up in `extra`.").unwrap();
+    for (version, samples) in &versions {
+        writeln!(out).unwrap();
+        emit_version(&mut out, version, samples);
+    }
+    emit_tests(&mut out, &versions, root);
+    out
+}
+
+fn main() {
+    let args : Vec<String> = env::args().skip(1).collect();
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap().to_owned();
+    match args.iter().map(|a| &a[..]).collect::<Vec<_>>()[..] {
+        ["codegen"] => {
+            fs::write(root.join(OUTPUT), generate(&root)).unwrap();
+        },
+        ["codegen", "--check"] => {
+            let current = fs::read_to_string(root.join(OUTPUT)).unwrap_or_default();
+            if current != generate(&root) {
+                eprintln!("{} is out of date, run `cargo xtask codegen`", OUTPUT);
+                process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("usage: cargo xtask codegen [--check]");
+            process::exit(2);
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infers_shapes() {
+        let a = serde_json::json!({"id": "a", "version": 1, "fields": [{"type": "STRING"}]});
+        let b = serde_json::json!({"id": "b", "version": 1.5, "fields": [{"type": "CONCEALED", "purpose": "PASSWORD"}]});
+        let object = match merge(infer(&a), infer(&b)) {
+            Shape::Object(object) => object,
+            other => panic!("unexpected shape {:?}", other),
+        };
+        assert_eq!(object.keys[1].1.shape, Shape::Float);
+        let mut models = Vec::new();
+        collect("Item", &object, &mut models);
+        assert_eq!(models[1].name, "Field");
+        assert_eq!(models[1].object.keys[1].1.present, 1);
+        assert_eq!(rust_type("Item", "fields", &object.keys[2].1.shape), "Vec<Field>");
+        let mut out = String::new();
+        emit_model(&mut out, &models[1]);
+        assert!(out.contains("pub purpose: Option<::FieldPurpose>,"));
+    }
+
+    #[test]
+    fn names_identifiers() {
+        assert_eq!(snake_case("notesPlain"), "notes_plain");
+        assert_eq!(snake_case("URLs"), "urls");
+        assert_eq!(snake_case("type"), "r#type");
+        assert_eq!(pascal_case("password_details"), "PasswordDetails");
+        assert_eq!(pascal_case(singular("fields")), "Field");
+    }
+}